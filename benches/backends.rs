@@ -0,0 +1,43 @@
+use boxscript::{Backend, Interpreter};
+use std::time::{Duration, Instant};
+
+const COUNT: &str = "╔═◇▀▨▀▀▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄╗
+║▀◈◇▀▐▀▀                   ║
+╚══════════════════════════╝
+";
+const BESIDE: &str = "╔═◇▀▨▀▀▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄▄═══╗
+║▀◈◇▀▐▀▀                    ║
+║┌─────────┐   ┌───────────┐║
+║│▀▀◈◇▀▀▐▀▀│   │▀▀▀◈◇▀▀▀▐▀▀│║
+║└─────────┘   └───────────┘║
+╚═══════════════════════════╝
+";
+const RUNS: u32 = 5;
+
+fn time(source: &str, backend: Backend) -> Duration {
+    let mut fastest = Duration::MAX;
+
+    for _ in 0..RUNS {
+        let mut interpreter = Interpreter::<i64>::new();
+        interpreter.set_backend(backend);
+        let start = Instant::now();
+        interpreter.run_program(source).unwrap();
+        fastest = fastest.min(start.elapsed());
+    }
+
+    fastest
+}
+
+fn main() {
+    for (name, source) in &[("count", COUNT), ("beside", BESIDE)] {
+        let tree = time(source, Backend::Tree);
+        let vm = time(source, Backend::Vm);
+        println!(
+            "{:<8} tree {:>8.1?}  vm {:>8.1?}  {:.2}x",
+            name,
+            tree,
+            vm,
+            tree.as_secs_f64() / vm.as_secs_f64()
+        );
+    }
+}
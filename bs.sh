@@ -14,7 +14,7 @@ if [ "$1" = "docker" ]; then
     fi
 elif [ "$1" = "nix" ]; then
     if hash nix-shell 2>/dev/null; then
-        nix-shell --run "cargo build --release; cargo run \"$2\""
+        nix-shell --run "cargo build --release --features cli; cargo run --features cli \"$2\""
     else
         echo "Nix not installed. Install here: https://nixos.org/download.html"
         exit 1
@@ -22,7 +22,7 @@ elif [ "$1" = "nix" ]; then
 elif [ "$1" = "rust" ]; then
     if hash cargo 2>/dev/null; then
         if hash llvm-config 2>/dev/null; then
-            cargo build --release 2>/dev/null
+            cargo build --release --features cli 2>/dev/null
             ./target/release/boxscript "$2"
         else
             echo "LLVM not installed. Install here: https://releases.llvm.org/download.html"
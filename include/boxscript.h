@@ -0,0 +1,38 @@
+#ifndef BOXSCRIPT_H
+#define BOXSCRIPT_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define BS_OK 0
+#define BS_INVALID_ARGUMENT 1
+#define BS_PARSE_ERROR 2
+#define BS_VALIDATION_ERROR 3
+#define BS_RUNTIME_ERROR 4
+#define BS_IO_ERROR 5
+#define BS_NOT_LOADED 6
+#define BS_PANIC 7
+
+typedef struct Handle bs_interpreter;
+
+bs_interpreter *bs_new(void);
+void bs_free(bs_interpreter *interpreter);
+
+int bs_load(bs_interpreter *interpreter, const char *source);
+int bs_set_input(bs_interpreter *interpreter, const char *input);
+int bs_run(bs_interpreter *interpreter);
+
+const char *bs_output(const bs_interpreter *interpreter);
+const char *bs_error(const bs_interpreter *interpreter);
+
+int bs_get(const bs_interpreter *interpreter, int64_t address, int64_t *value);
+int bs_set(bs_interpreter *interpreter, int64_t address, int64_t value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,233 @@
+#![allow(clippy::missing_safety_doc)]
+
+use crate::lang::error::BsError;
+use crate::lang::interpreter::Interpreter;
+use std::collections::VecDeque;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+pub const BS_OK: c_int = 0;
+pub const BS_INVALID_ARGUMENT: c_int = 1;
+pub const BS_PARSE_ERROR: c_int = 2;
+pub const BS_VALIDATION_ERROR: c_int = 3;
+pub const BS_RUNTIME_ERROR: c_int = 4;
+pub const BS_IO_ERROR: c_int = 5;
+pub const BS_NOT_LOADED: c_int = 6;
+pub const BS_PANIC: c_int = 7;
+
+pub struct Handle {
+    interpreter: Interpreter<i64>,
+    source: Option<String>,
+    output: CString,
+    error: CString,
+}
+
+impl Handle {
+    fn fail(&mut self, code: c_int, message: &str) -> c_int {
+        self.error = CString::new(message.replace('\0', "")).unwrap();
+        code
+    }
+
+    fn report(&mut self, error: &BsError) -> c_int {
+        let code = match error {
+            BsError::ParseError(..) => BS_PARSE_ERROR,
+            BsError::ValidationError(..) => BS_VALIDATION_ERROR,
+            BsError::RuntimeError(..) => BS_RUNTIME_ERROR,
+            _ => BS_IO_ERROR,
+        };
+        self.fail(code, &error.to_string())
+    }
+}
+
+fn guard<R>(fallback: R, call: impl FnOnce() -> R) -> R {
+    panic::catch_unwind(AssertUnwindSafe(call)).unwrap_or(fallback)
+}
+
+unsafe fn text<'a>(handle: &mut Handle, text: *const c_char) -> Result<&'a str, c_int> {
+    if text.is_null() {
+        return Err(handle.fail(BS_INVALID_ARGUMENT, "Text is null"));
+    }
+    CStr::from_ptr(text)
+        .to_str()
+        .map_err(|_| handle.fail(BS_INVALID_ARGUMENT, "Text is not valid UTF-8"))
+}
+
+#[no_mangle]
+pub extern "C" fn bs_new() -> *mut Handle {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(Handle {
+            interpreter: Interpreter::new(),
+            source: None,
+            output: CString::default(),
+            error: CString::default(),
+        }))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_free(handle: *mut Handle) {
+    guard((), || {
+        if !handle.is_null() {
+            drop(Box::from_raw(handle));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_load(handle: *mut Handle, source: *const c_char) -> c_int {
+    guard(BS_PANIC, || {
+        let handle = match handle.as_mut() {
+            Some(handle) => handle,
+            None => return BS_INVALID_ARGUMENT,
+        };
+        let source = match text(handle, source) {
+            Ok(source) => source,
+            Err(code) => return code,
+        };
+
+        match handle.interpreter.check(source).first() {
+            Some(error) => handle.report(error),
+            None => {
+                handle.source = Some(source.to_string());
+                BS_OK
+            }
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_set_input(handle: *mut Handle, input: *const c_char) -> c_int {
+    guard(BS_PANIC, || {
+        let handle = match handle.as_mut() {
+            Some(handle) => handle,
+            None => return BS_INVALID_ARGUMENT,
+        };
+        let input = match text(handle, input) {
+            Ok(input) => input,
+            Err(code) => return code,
+        };
+
+        let input = input.chars().collect::<VecDeque<char>>();
+        handle.interpreter.set_input(Box::new(input));
+        BS_OK
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_run(handle: *mut Handle) -> c_int {
+    guard(BS_PANIC, || {
+        let handle = match handle.as_mut() {
+            Some(handle) => handle,
+            None => return BS_INVALID_ARGUMENT,
+        };
+        let source = match handle.source.clone() {
+            Some(source) => source,
+            None => return handle.fail(BS_NOT_LOADED, "No program is loaded"),
+        };
+
+        match handle.interpreter.run_program(&source) {
+            Ok(output) => {
+                handle.output = CString::new(output.replace('\0', "")).unwrap();
+                BS_OK
+            }
+            Err(error) => handle.report(&error),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_output(handle: *const Handle) -> *const c_char {
+    guard(ptr::null(), || {
+        handle
+            .as_ref()
+            .map_or(ptr::null(), |handle| handle.output.as_ptr())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_error(handle: *const Handle) -> *const c_char {
+    guard(ptr::null(), || {
+        handle
+            .as_ref()
+            .map_or(ptr::null(), |handle| handle.error.as_ptr())
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_get(handle: *const Handle, address: i64, value: *mut i64) -> c_int {
+    guard(BS_PANIC, || match (handle.as_ref(), value.as_mut()) {
+        (Some(handle), Some(value)) => {
+            *value = handle.interpreter.memory().get(address);
+            BS_OK
+        }
+        _ => BS_INVALID_ARGUMENT,
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bs_set(handle: *mut Handle, address: i64, value: i64) -> c_int {
+    guard(BS_PANIC, || match handle.as_mut() {
+        Some(handle) => {
+            handle.interpreter.memory_mut().set(address, value);
+            BS_OK
+        }
+        None => BS_INVALID_ARGUMENT,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(text: &str) -> CString {
+        CString::new(text).unwrap()
+    }
+
+    #[test]
+    fn it_runs_through_the_c_api() {
+        unsafe {
+            let handle = bs_new();
+            assert_eq!(bs_run(handle), BS_NOT_LOADED);
+            assert_eq!(bs_set(handle, 0, 5), BS_OK);
+            assert_eq!(
+                bs_load(
+                    handle,
+                    c("┌─────────┐\n│▀◈◇▀▐▀▀  │\n│▭▀▀▄▄▄▄▄▀│\n└─────────┘").as_ptr()
+                ),
+                BS_OK
+            );
+            assert_eq!(bs_run(handle), BS_OK);
+            assert_eq!(CStr::from_ptr(bs_output(handle)).to_str(), Ok("A"));
+
+            let mut value = 0;
+            assert_eq!(bs_get(handle, 0, &mut value), BS_OK);
+            assert_eq!(value, 6);
+            bs_free(handle);
+        }
+    }
+
+    #[test]
+    fn it_reports_errors() {
+        unsafe {
+            let handle = bs_new();
+            assert_eq!(bs_load(handle, c("┌──┐\n│▀").as_ptr()), BS_PARSE_ERROR);
+            assert!(!CStr::from_ptr(bs_error(handle)).to_bytes().is_empty());
+            assert_eq!(
+                bs_load(handle, c("┌────┐\n│▬◉7 │\n└────┘").as_ptr()),
+                BS_PARSE_ERROR
+            );
+            assert_eq!(bs_load(handle, ptr::null()), BS_INVALID_ARGUMENT);
+            assert_eq!(bs_get(handle, 0, ptr::null_mut()), BS_INVALID_ARGUMENT);
+            assert_eq!(bs_run(ptr::null_mut()), BS_INVALID_ARGUMENT);
+            bs_free(handle);
+        }
+    }
+
+    #[test]
+    fn it_turns_panics_into_error_codes() {
+        assert_eq!(guard(BS_PANIC, || panic!("Interpreter bug")), BS_PANIC);
+        assert_eq!(guard(BS_PANIC, || BS_OK), BS_OK);
+    }
+}
@@ -0,0 +1,545 @@
+use super::block::{Block, Node};
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Atom, Molecule};
+use super::r#box::{Box, Genus};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Reason {
+    ConstantCondition,
+    Unreachable,
+}
+
+impl Reason {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Reason::ConstantCondition => "its condition is always zero",
+            Reason::Unreachable => "it is unreachable",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeadBox {
+    pub r#box: Box,
+    pub reason: Reason,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cells<T: BoxInt> {
+    pub reads: BTreeSet<T>,
+    pub writes: BTreeSet<T>,
+    pub reads_any: bool,
+    pub writes_any: bool,
+    pub calls: BTreeSet<T>,
+    pub calls_any: bool,
+}
+
+impl<T: BoxInt> Cells<T> {
+    fn new() -> Cells<T> {
+        Cells {
+            reads: BTreeSet::new(),
+            writes: BTreeSet::new(),
+            reads_any: false,
+            writes_any: false,
+            calls: BTreeSet::new(),
+            calls_any: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct State<T: BoxInt> {
+    known: HashMap<T, Option<T>>,
+    zeroed: bool,
+}
+
+impl<T: BoxInt> State<T> {
+    fn new() -> State<T> {
+        State {
+            known: HashMap::new(),
+            zeroed: true,
+        }
+    }
+
+    fn get(&self, address: &T) -> Option<T> {
+        match self.known.get(address) {
+            Some(value) => *value,
+            None if self.zeroed => Some(T::zero()),
+            None => None,
+        }
+    }
+
+    fn havoc(&mut self) {
+        self.known.clear();
+        self.zeroed = false;
+    }
+
+    fn merge(&self, other: &State<T>) -> State<T> {
+        let mut merged = State {
+            known: HashMap::new(),
+            zeroed: self.zeroed && other.zeroed,
+        };
+
+        for address in self.known.keys().chain(other.known.keys()) {
+            let value = self.get(address);
+            merged.known.insert(
+                *address,
+                if value == other.get(address) {
+                    value
+                } else {
+                    None
+                },
+            );
+        }
+
+        merged
+    }
+}
+
+pub fn check<T: BoxInt>(code: &str) -> Vec<BsError> {
+    let boxes = match Box::find_all(code) {
+        Ok(boxes) => boxes,
+        Err(error) => return vec![error],
+    };
+    let mut errors: Vec<BsError> = Vec::new();
+
+    for r#box in boxes.iter() {
+        match Block::<T>::shallow(code, r#box, &boxes) {
+            Ok(mut block) => {
+                let molecules = block
+                    .guard
+                    .iter_mut()
+                    .chain(block.molecules.iter_mut().map(|(_, molecule)| molecule));
+                for molecule in molecules {
+                    if let Err(error) = molecule.compile() {
+                        errors.push(error);
+                    }
+                }
+            }
+            Err(error) => errors.push(error),
+        }
+    }
+
+    errors.sort_by_key(|error| error.location());
+    errors
+}
+
+pub fn fold<T: BoxInt>(molecule: &Molecule<T>) -> Option<T> {
+    molecule.clone().peek(&HashMap::new()).ok()
+}
+
+fn evaluate<T: BoxInt>(
+    molecule: &Molecule<T>,
+    state: &mut State<T>,
+    mut cells: Option<&mut Cells<T>>,
+) -> Option<T> {
+    let atoms = match molecule.clone().postfix() {
+        Ok(atoms) => atoms,
+        Err(_) => {
+            state.havoc();
+            return None;
+        }
+    };
+    let mut stack: Vec<Option<T>> = Vec::new();
+
+    for atom in atoms {
+        match atom {
+            Atom::Data(num) => stack.push(Some(num)),
+            Atom::Memory => {
+                let a = stack.pop()?;
+                if let Some(cells) = cells.as_deref_mut() {
+                    match a {
+                        Some(a) => {
+                            cells.reads.insert(a);
+                        }
+                        None => cells.reads_any = true,
+                    };
+                }
+                stack.push(a.and_then(|a| state.get(&a)));
+            }
+            Atom::Not | Atom::Negate | Atom::Abs => {
+                let a = stack.pop()?;
+                stack.push(a.and_then(|a| atom.unary(a).ok()));
+            }
+            Atom::Copy | Atom::Fill => {
+                stack.pop()?;
+                if let Some(cells) = cells.as_deref_mut() {
+                    cells.reads_any = true;
+                    cells.writes_any = true;
+                }
+                state.havoc();
+                stack.push(None);
+            }
+            Atom::Output | Atom::OutputNumber => {}
+            Atom::PollKey | Atom::Argument => stack.push(None),
+            Atom::Input | Atom::Local => {
+                stack.pop()?;
+                stack.push(None);
+            }
+            _ => {
+                let b = stack.pop()?;
+                let a = stack.pop()?;
+
+                if let Atom::SetIfZero = atom {
+                    if let Some(cells) = cells.as_deref_mut() {
+                        match a {
+                            Some(a) => {
+                                cells.reads.insert(a);
+                                cells.writes.insert(a);
+                            }
+                            None => {
+                                cells.reads_any = true;
+                                cells.writes_any = true;
+                            }
+                        };
+                    }
+                    let a = match a {
+                        Some(a) => a,
+                        None => {
+                            state.havoc();
+                            stack.push(None);
+                            continue;
+                        }
+                    };
+                    stack.push(match state.get(&a) {
+                        Some(current) if current.is_zero() => {
+                            state.known.insert(a, b);
+                            Some(T::one())
+                        }
+                        Some(_) => Some(T::zero()),
+                        None => {
+                            state.known.insert(a, None);
+                            None
+                        }
+                    });
+                    continue;
+                }
+
+                if let Atom::Call = atom {
+                    if let Some(cells) = cells.as_deref_mut() {
+                        cells.reads_any = true;
+                        cells.writes_any = true;
+                        match a {
+                            Some(a) => {
+                                cells.calls.insert(a);
+                            }
+                            None => cells.calls_any = true,
+                        };
+                    }
+                    state.havoc();
+                    stack.push(None);
+                    continue;
+                }
+
+                if let Atom::LocalAssign = atom {
+                    stack.push(b);
+                    continue;
+                }
+
+                if let Atom::IndirectAssign = atom {
+                    let target = a.and_then(|a| state.get(&a));
+                    if let Some(cells) = cells.as_deref_mut() {
+                        match a {
+                            Some(a) => {
+                                cells.reads.insert(a);
+                            }
+                            None => cells.reads_any = true,
+                        };
+                        match target {
+                            Some(target) => {
+                                cells.writes.insert(target);
+                            }
+                            None => cells.writes_any = true,
+                        };
+                    }
+                    match target {
+                        Some(target) => {
+                            state.known.insert(target, b);
+                        }
+                        None => state.havoc(),
+                    }
+                    stack.push(b);
+                    continue;
+                }
+
+                if let Atom::Assign | Atom::SilentAssign = atom {
+                    if let Some(cells) = cells.as_deref_mut() {
+                        match a {
+                            Some(a) => {
+                                cells.writes.insert(a);
+                            }
+                            None => cells.writes_any = true,
+                        };
+                    }
+                    match a {
+                        Some(a) => {
+                            state.known.insert(a, b);
+                        }
+                        None => state.havoc(),
+                    }
+                }
+
+                stack.push(match (a, b) {
+                    (Some(a), Some(b)) => atom.binary(a, b).ok(),
+                    _ => None,
+                });
+            }
+        }
+    }
+
+    stack.pop().unwrap_or_else(|| Some(T::zero()))
+}
+
+fn bury<T: BoxInt>(blocks: &[Block<T>], reason: Reason, dead: &mut Vec<DeadBox>) {
+    for block in blocks {
+        dead.push(DeadBox {
+            r#box: block.r#box.clone(),
+            reason,
+        });
+        bury(&block.children, reason, dead);
+    }
+}
+
+fn visit<T: BoxInt>(block: &Block<T>, state: &mut State<T>, dead: &mut Vec<DeadBox>) -> bool {
+    let guard = block
+        .guard
+        .as_ref()
+        .map(|guard| evaluate(guard, state, None));
+
+    match (block.r#box.genus, guard) {
+        (Genus::NoOp, _) | (Genus::Function, _) => true,
+        (_, Some(Some(value))) if value.is_zero() => {
+            dead.push(DeadBox {
+                r#box: block.r#box.clone(),
+                reason: Reason::ConstantCondition,
+            });
+            bury(&block.children, Reason::Unreachable, dead);
+            true
+        }
+        (Genus::Condition, Some(None)) => {
+            let mut branch = state.clone();
+            if visit_body(block, &mut branch, dead) {
+                *state = state.merge(&branch);
+            }
+            true
+        }
+        (Genus::Loop, Some(value)) => {
+            let constant = block
+                .guard
+                .as_ref()
+                .is_some_and(|guard| !guard.atoms().contains(&Atom::Memory));
+
+            state.havoc();
+            visit_body(block, state, dead);
+            state.havoc();
+            !(constant && value.is_some())
+        }
+        _ => visit_body(block, state, dead),
+    }
+}
+
+fn visit_body<T: BoxInt>(block: &Block<T>, state: &mut State<T>, dead: &mut Vec<DeadBox>) -> bool {
+    let mut reachable = true;
+
+    for node in block.schedule() {
+        match node {
+            Node::Molecule(i) => {
+                if reachable {
+                    evaluate(&block.molecules[i].1, state, None);
+                }
+            }
+            Node::Block(i) => {
+                let child = &block.children[i];
+                if reachable {
+                    reachable = visit(child, state, dead);
+                } else {
+                    bury(std::slice::from_ref(child), Reason::Unreachable, dead);
+                }
+            }
+        }
+    }
+
+    reachable
+}
+
+fn touch<T: BoxInt>(block: &Block<T>, cells: &mut Cells<T>) {
+    if block.include.is_some() {
+        cells.reads_any = true;
+        cells.writes_any = true;
+    }
+
+    let molecules = block
+        .guard
+        .iter()
+        .chain(block.molecules.iter().map(|(_, molecule)| molecule));
+    for molecule in molecules {
+        let mut state = State::new();
+        state.havoc();
+        evaluate(molecule, &mut state, Some(cells));
+    }
+}
+
+pub fn shallow_cells<T: BoxInt>(block: &Block<T>) -> Cells<T> {
+    let mut cells = Cells::new();
+    touch(block, &mut cells);
+    cells
+}
+
+pub fn cells<T: BoxInt>(block: &Block<T>) -> Cells<T> {
+    let mut cells = Cells::new();
+    for block in Block::walk(std::slice::from_ref(block)) {
+        touch(block, &mut cells);
+    }
+
+    cells
+}
+
+pub fn dead_boxes<T: BoxInt>(source: &str) -> Result<Vec<DeadBox>, BsError> {
+    let mut state = State::<T>::new();
+    let mut dead: Vec<DeadBox> = Vec::new();
+    let mut reachable = true;
+
+    for block in Block::<T>::build(source)? {
+        if reachable {
+            reachable = visit(&block, &mut state, &mut dead);
+        } else {
+            bury(std::slice::from_ref(&block), Reason::Unreachable, &mut dead);
+        }
+    }
+
+    dead.sort_by_key(|d| d.r#box.top_left);
+
+    Ok(dead)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(code: &str) -> Vec<([usize; 2], Reason)> {
+        dead_boxes::<i8>(code)
+            .unwrap()
+            .iter()
+            .map(|d| (d.r#box.top_left, d.reason))
+            .collect()
+    }
+
+    #[test]
+    fn it_folds_constants() {
+        assert_eq!(
+            fold(&Molecule::<i8>::new(vec![
+                Atom::Data(2),
+                Atom::Add,
+                Atom::Data(3)
+            ])),
+            Some(5)
+        );
+        assert_eq!(
+            fold(&Molecule::<i8>::new(vec![Atom::Memory, Atom::Data(3)])),
+            Some(0)
+        );
+        assert_eq!(
+            fold(&Molecule::<i8>::new(vec![
+                Atom::Data(1),
+                Atom::Divide,
+                Atom::Data(0)
+            ])),
+            None
+        );
+    }
+
+    #[test]
+    fn it_finds_constant_conditions() {
+        assert_eq!(
+            report("┏━▀▄━━━┓\n┃┌────┐┃\n┃│▀◈▀▀│┃\n┃└────┘┃\n┗━━━━━━┛"),
+            vec![
+                ([0, 0], Reason::ConstantCondition),
+                ([1, 1], Reason::Unreachable)
+            ]
+        );
+        assert_eq!(report("┏━▀▀━━┓\n┃▀◈▀▀ ┃\n┗━━━━━┛"), vec![]);
+    }
+
+    #[test]
+    fn it_propagates_memory() {
+        assert_eq!(
+            report("┌────┐\n│▀◈▀▀│\n└────┘\n┏━◇▀▤▀▄━┓\n┃▭▀▀    ┃\n┗━━━━━━━┛"),
+            vec![([3, 0], Reason::ConstantCondition)]
+        );
+        assert_eq!(
+            report("╔═◇▀▨▀▀▀═╗\n║▀◈◇▀▐▀▀ ║\n╚════════╝\n┏━◇▀▤▀▄━┓\n┃▭▀▀    ┃\n┗━━━━━━━┛"),
+            vec![]
+        );
+        assert_eq!(
+            report("┏━◇▀▀━━━┓\n┃▀◈▀▀   ┃\n┗━━━━━━━┛\n┏━◇▀▤▀▄━┓\n┃▭▀▀    ┃\n┗━━━━━━━┛"),
+            vec![([0, 0], Reason::ConstantCondition)]
+        );
+    }
+
+    #[test]
+    fn it_checks_every_box() {
+        assert_eq!(
+            check::<i8>(
+                "┌─────┐\n│▀▀x  │\n│┌──┐ │\n││▀▝│ │\n│└──┘ │\n└─────┘\n╔══╗\n║  ║\n╚══╝\n┌──┐\n│▝▀│\n└──┘"
+            ),
+            vec![
+                BsError::ParseError("Invalid character".to_string(), Some([1, 3])),
+                BsError::ValidationError("Malformed expression".to_string(), Some([3, 2])),
+                BsError::ParseError("Missing condition".to_string(), Some([6, 0])),
+                BsError::ValidationError("Malformed expression".to_string(), Some([10, 1])),
+            ]
+        );
+        assert_eq!(check::<i8>("┌──┐\n│▀▀│\n└──┘"), vec![]);
+        assert_eq!(
+            check::<i8>("┌──┐\n│▀▀│"),
+            vec![BsError::ParseError(
+                "Unclosed box".to_string(),
+                Some([0, 0])
+            )]
+        );
+    }
+
+    #[test]
+    fn it_collects_cells() {
+        let blocks = Block::<i8>::build(
+            "╔═◇▀▨▀▀▄═════╗\n║▀▀◈◇▀▐▀▀    ║\n║┏━◇◇▀▀━━━━┓ ║\n║┃▀▀▄◈▀▐▀▀ ┃ ║\n║┗━━━━━━━━━┛ ║\n╚════════════╝",
+        )
+        .unwrap();
+        let cells = cells(&blocks[0]);
+
+        assert_eq!(cells.reads, [0, 1].iter().cloned().collect());
+        assert_eq!(cells.writes, [1, 2].iter().cloned().collect());
+        assert!(cells.reads_any);
+        assert!(!cells.writes_any);
+
+        let own = shallow_cells(&blocks[0]);
+        assert_eq!(own.writes, [1].iter().cloned().collect());
+        assert!(!own.reads_any);
+    }
+
+    #[test]
+    fn it_follows_indirect_writes() {
+        let blocks = Block::<i8>::build("┌──────┐\n│▀◈▀▀▀ │\n│▀◙▀▀  │\n└──────┘").unwrap();
+        let found = cells(&blocks[0]);
+
+        assert_eq!(found.reads, [0].iter().cloned().collect());
+        assert_eq!(found.writes, [0].iter().cloned().collect());
+        assert!(found.writes_any);
+
+        assert_eq!(
+            report("┌─────┐\n│▀◈▀▀▀│\n│▀◙▀▀ │\n└─────┘\n┏━◇▀▀▀▤▀▄━┓\n┃▭▀▀      ┃\n┗━━━━━━━━━┛"),
+            vec![([4, 0], Reason::ConstantCondition)]
+        );
+    }
+
+    #[test]
+    fn it_finds_unreachable_boxes() {
+        assert_eq!(
+            report("╔═▀▀═══╗\n║▭▀▀▀▀ ║\n╚══════╝\n┌────┐\n│▀◈▀▀│\n└────┘"),
+            vec![([3, 0], Reason::Unreachable)]
+        );
+    }
+}
@@ -0,0 +1,389 @@
+use super::block::{Block, Node};
+use super::canon;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Dialect, Literal, Molecule};
+use super::r#box::{Box, Genus};
+
+const INDENT: &str = "    ";
+
+enum Entry {
+    Line(String),
+    Boxes(Vec<Vec<String>>),
+}
+
+fn keyword(genus: Genus) -> &'static str {
+    match genus {
+        Genus::Execution => "exec",
+        Genus::Loop => "loop",
+        Genus::Condition => "if",
+        Genus::Function => "fn",
+        Genus::NoOp => "note",
+    }
+}
+
+fn from_keyword(word: &str) -> Option<Genus> {
+    [
+        Genus::Execution,
+        Genus::Loop,
+        Genus::Condition,
+        Genus::Function,
+        Genus::NoOp,
+    ]
+    .iter()
+    .find(|genus| keyword(**genus) == word)
+    .cloned()
+}
+
+fn write_run<T: BoxInt>(blocks: &[&Block<T>], code: &str, depth: usize, lines: &mut Vec<String>) {
+    let boxes: Vec<&Box> = blocks.iter().map(|block| &block.r#box).collect();
+
+    for group in canon::groups(&boxes) {
+        if group.len() == 1 {
+            write(blocks[group[0]], code, depth, lines);
+            continue;
+        }
+
+        lines.push(format!("{}row {{", INDENT.repeat(depth)));
+        for i in group {
+            write(blocks[i], code, depth + 1, lines);
+        }
+        lines.push(format!("{}}}", INDENT.repeat(depth)));
+    }
+}
+
+fn write<T: BoxInt>(block: &Block<T>, code: &str, depth: usize, lines: &mut Vec<String>) {
+    let indent = INDENT.repeat(depth);
+
+    if let Some(include) = &block.include {
+        lines.push(format!("{}{}", indent, include.header()));
+        return;
+    }
+
+    let header = match &block.guard {
+        Some(guard) => format!("{} {}", keyword(block.r#box.genus), guard.ascii()),
+        None => keyword(block.r#box.genus).to_string(),
+    };
+    lines.push(format!("{}{} {{", indent, header));
+
+    if block.r#box.genus == Genus::NoOp {
+        let mut text: Vec<String> = block
+            .r#box
+            .content(code, &[])
+            .iter()
+            .map(|line| line.trim_end().to_string())
+            .collect();
+        if block.r#box.has_marker(code) {
+            text[0] = text[0].chars().skip(1).collect();
+        }
+        while text.last().is_some_and(|line| line.is_empty()) {
+            text.pop();
+        }
+        while text.first().is_some_and(|line| line.is_empty()) {
+            text.remove(0);
+        }
+        for line in text {
+            lines.push(
+                format!("{}{}{}", indent, INDENT, line)
+                    .trim_end()
+                    .to_string(),
+            );
+        }
+    } else {
+        let schedule = block.schedule();
+        let mut i = 0;
+        while i < schedule.len() {
+            match schedule[i] {
+                Node::Molecule(j) => {
+                    lines.push(format!(
+                        "{}{}{}",
+                        indent,
+                        INDENT,
+                        block.molecules[j].1.ascii()
+                    ));
+                    i += 1;
+                }
+                Node::Block(_) => {
+                    let mut run: Vec<&Block<T>> = Vec::new();
+                    while let Some(Node::Block(j)) = schedule.get(i) {
+                        run.push(&block.children[*j]);
+                        i += 1;
+                    }
+                    write_run(&run, code, depth + 1, lines);
+                }
+            }
+        }
+    }
+
+    lines.push(format!("{}}}", indent));
+}
+
+pub fn to_ascii<T: BoxInt>(code: &str) -> Result<String, BsError> {
+    let code = code.replace("\r\n", "\n").replace('\t', " ");
+    let blocks = Block::<T>::build(&code)?;
+    let boxes: Vec<&Box> = blocks.iter().map(|block| &block.r#box).collect();
+    let mut lines: Vec<String> = Vec::new();
+
+    for group in canon::groups(&boxes) {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+        let run: Vec<&Block<T>> = group.iter().map(|i| &blocks[*i]).collect();
+        write_run(&run, &code, 0, &mut lines);
+    }
+
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+struct Reader<'a> {
+    lines: Vec<&'a str>,
+    row: usize,
+    dialect: Dialect,
+}
+
+impl<'a> Reader<'a> {
+    fn entries<T: BoxInt>(&mut self, opened: Option<[usize; 2]>) -> Result<Vec<Entry>, BsError> {
+        let mut entries: Vec<Entry> = Vec::new();
+
+        while self.row < self.lines.len() {
+            let row = self.row;
+            let line = self.lines[row];
+            let column = line.chars().take_while(|c| c.is_whitespace()).count();
+            let text = line.trim();
+            self.row += 1;
+
+            if text.is_empty() {
+                continue;
+            }
+            if text == "}" {
+                if opened.is_none() {
+                    return Err(BsError::ParseError(
+                        "Unmatched }".to_string(),
+                        Some([row, column]),
+                    ));
+                }
+                return Ok(entries);
+            }
+
+            if text.starts_with("include ") && !text.ends_with('{') {
+                let edge = "─".repeat(text.chars().count());
+                entries.push(Entry::Boxes(vec![vec![
+                    format!("┌{}┐", text),
+                    format!("└{}┘", edge),
+                ]]));
+                continue;
+            }
+
+            let header = match text.strip_suffix('{') {
+                Some(header) => header.trim_end(),
+                None if opened.is_none() => {
+                    return Err(BsError::ParseError(
+                        "Code outside of a box".to_string(),
+                        Some([row, column]),
+                    ))
+                }
+                None => {
+                    let molecule = Molecule::<T>::read_ascii(text, [row, column])?;
+                    self.dialect.check(&molecule)?;
+                    entries.push(Entry::Line(molecule.source_as(Literal::Original)));
+                    continue;
+                }
+            };
+            let (word, guard) = match header.find(' ') {
+                Some(space) => (&header[..space], Some(header[space..].trim())),
+                None => (header, None),
+            };
+
+            if word == "row" {
+                let mut parts: Vec<Vec<String>> = Vec::new();
+                for entry in self.entries::<T>(Some([row, column]))? {
+                    match entry {
+                        Entry::Boxes(boxes) => parts.extend(boxes),
+                        Entry::Line(_) => {
+                            return Err(BsError::ParseError(
+                                "A row can only hold boxes".to_string(),
+                                Some([row, column]),
+                            ))
+                        }
+                    }
+                }
+                entries.push(Entry::Boxes(vec![canon::beside(&parts)]));
+                continue;
+            }
+
+            let genus = from_keyword(word).ok_or_else(|| {
+                BsError::ParseError(format!("Unknown box kind {}", word), Some([row, column]))
+            })?;
+            let needs_guard = matches!(genus, Genus::Loop | Genus::Condition | Genus::Function);
+            if needs_guard != guard.is_some() {
+                return Err(BsError::ParseError(
+                    if needs_guard {
+                        format!("Missing {} guard", word)
+                    } else {
+                        format!("A {} box takes no guard", word)
+                    },
+                    Some([row, column]),
+                ));
+            }
+
+            let mut lines: Vec<String> = Vec::new();
+            if let Some(guard) = guard {
+                let at = [row, column + header.chars().count() - guard.chars().count()];
+                let molecule = Molecule::<T>::read_ascii(guard, at)?;
+                self.dialect.check(&molecule)?;
+                lines.push(molecule.source_as(Literal::Original));
+            }
+            if genus == Genus::Function {
+                let needed = Dialect::introducing('◴');
+                if needed > self.dialect {
+                    return Err(BsError::ParseError(
+                        format!("Glyph ◴ needs dialect {} or later", needed.number()),
+                        Some([row, column]),
+                    ));
+                }
+                lines[0].insert(0, '◴');
+            }
+
+            if genus == Genus::NoOp {
+                let indent = format!(
+                    "{}{}",
+                    &line[..line.len() - line.trim_start().len()],
+                    INDENT
+                );
+                let mut text: Vec<String> = Vec::new();
+                while self.row < self.lines.len() && self.lines[self.row].trim() != "}" {
+                    let line = self.lines[self.row].trim_end();
+                    text.push(
+                        line.strip_prefix(&indent)
+                            .unwrap_or(line.trim_start())
+                            .to_string(),
+                    );
+                    self.row += 1;
+                }
+                if self.row == self.lines.len() {
+                    return Err(BsError::ParseError(
+                        "Unclosed box".to_string(),
+                        Some([row, column]),
+                    ));
+                }
+                self.row += 1;
+                entries.push(Entry::Boxes(vec![canon::frame(genus, &text)]));
+                continue;
+            }
+
+            for entry in self.entries::<T>(Some([row, column]))? {
+                match entry {
+                    Entry::Line(line) => lines.push(line),
+                    Entry::Boxes(boxes) => lines.extend(boxes.into_iter().flatten()),
+                }
+            }
+            entries.push(Entry::Boxes(vec![canon::frame(genus, &lines)]));
+        }
+
+        match opened {
+            Some(at) => Err(BsError::ParseError("Unclosed box".to_string(), Some(at))),
+            None => Ok(entries),
+        }
+    }
+}
+
+pub fn to_glyphs<T: BoxInt>(ascii: &str, dialect: Dialect) -> Result<String, BsError> {
+    let ascii = ascii.replace("\r\n", "\n").replace('\t', INDENT);
+    let mut reader = Reader {
+        lines: ascii.lines().collect(),
+        row: 0,
+        dialect,
+    };
+    let mut lines: Vec<String> = Vec::new();
+
+    for entry in reader.entries::<T>(None)? {
+        if let Entry::Boxes(boxes) = entry {
+            for part in boxes {
+                if !lines.is_empty() {
+                    lines.push(String::new());
+                }
+                lines.extend(part.iter().map(|line| line.trim_end().to_string()));
+            }
+        }
+    }
+
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::Interpreter;
+    use super::*;
+
+    const ASCII: &str = "note {\n    Counts from 0 to 3\n}\n\nloop @ 0 < 4 {\n    out @ 0 + 48\n    0 := @ 0 + 1\n}\n\nrow {\n    exec {\n        out 'a'\n    }\n    if 1 {\n        print -31\n        out \"hi\"\n    }\n}\n";
+    const GLYPHS: &str = "╭┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄╮\n┆Counts from 0 to 3┆\n╰┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄┄╯\n\n╔═══════════╗\n║◇▀▨▀▀▄▄    ║\n║▭◇▀▐▀▀▀▄▄▄▄║\n║▀◈◇▀▐▀▀    ║\n╚═══════════╝\n\n┌────┐ ┏━━━━━━━┓\n│▭'a'│ ┃▀▀     ┃\n└────┘ ┃▬▄▀▀▀▀▀┃\n       ┃▭\"hi\"  ┃\n       ┗━━━━━━━┛\n";
+
+    #[test]
+    fn it_transpiles_both_ways() {
+        assert_eq!(to_glyphs::<i64>(ASCII, Dialect::V3), Ok(GLYPHS.to_string()));
+        assert_eq!(to_ascii::<i64>(GLYPHS), Ok(ASCII.to_string()));
+
+        let mut interpreter = Interpreter::<i64>::new();
+        interpreter.set_dialect(Dialect::V3);
+        assert_eq!(
+            interpreter.run_program(GLYPHS),
+            Ok("0123a-31hi".to_string())
+        );
+    }
+
+    #[test]
+    fn it_round_trips_glyph_programs() {
+        let code = "╔═◇▀▨▀▀▄▀═════╗\n║┏━◇▀▖▀▀▄▤▀━━┓║\n║┃▭◇▀▐▀▀▀▄▄▄▄┃║\n║┗━━━━━━━━━━━┛║\n║▀◈◇▀▐▀▀      ║\n╚═════════════╝\n┌───┐\n│◴▀▀│\n│◶▘◶│\n└───┘\n┌────────┐\n│◰▬▀▀◵▀▀▀│\n└────────┘";
+        let ascii = to_ascii::<i64>(code).unwrap();
+
+        assert!(ascii.contains("fn 1 {\n    arg * arg\n}\n\nexec {\n    print 1 call 3\n}"));
+        assert_eq!(
+            to_glyphs::<i64>(&ascii, Dialect::V3),
+            canon::format::<i64>(code)
+        );
+    }
+
+    #[test]
+    fn it_rejects_bad_ascii() {
+        let error = |ascii: &str| to_glyphs::<i64>(ascii, Dialect::V3).unwrap_err();
+
+        assert_eq!(
+            error("out 1"),
+            BsError::ParseError("Code outside of a box".to_string(), Some([0, 0]))
+        );
+        assert_eq!(
+            error("exec {\n  out 1\n"),
+            BsError::ParseError("Unclosed box".to_string(), Some([0, 0]))
+        );
+        assert_eq!(
+            error("loop {\n}"),
+            BsError::ParseError("Missing loop guard".to_string(), Some([0, 0]))
+        );
+        assert_eq!(
+            error("exec {\n}\n  box {\n}"),
+            BsError::ParseError("Unknown box kind box".to_string(), Some([2, 2]))
+        );
+        assert_eq!(
+            error("exec {\n    out 1 plus 2\n}"),
+            BsError::ParseError("Unknown word plus".to_string(), Some([1, 10]))
+        );
+    }
+
+    #[test]
+    fn it_rejects_glyphs_the_dialect_cannot_run() {
+        let error = |ascii: &str| to_glyphs::<i64>(ascii, Dialect::STABLE).unwrap_err();
+
+        assert!(matches!(
+            error("exec {\n    out \"hi\"\n}"),
+            BsError::ParseError(message, _) if message == "Glyph \" needs dialect 3 or later"
+        ));
+        assert_eq!(
+            error("fn 1 {\n    arg\n}"),
+            BsError::ParseError("Glyph ◴ needs dialect 3 or later".to_string(), Some([0, 0]))
+        );
+        assert_eq!(
+            to_glyphs::<i64>("exec {\n    out 1\n}", Dialect::STABLE),
+            Ok("┌───┐\n│▭▀▀│\n└───┘\n".to_string())
+        );
+    }
+}
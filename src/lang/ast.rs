@@ -0,0 +1,521 @@
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::Atom;
+use super::function::{self, Functions};
+use super::interpreter::{ArithMode, ExecContext};
+use super::memory::Memory;
+use super::terminal;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Expr<T: BoxInt> {
+    Number(T),
+    PollKey(Option<[usize; 2]>),
+    Argument(Option<[usize; 2]>),
+    Unary(Atom<T>, Box<Expr<T>>, Option<[usize; 2]>),
+    Binary(Atom<T>, Box<Expr<T>>, Box<Expr<T>>, Option<[usize; 2]>),
+}
+
+pub const MAX_DEPTH: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kind {
+    Value,
+    Text,
+    Silent,
+}
+
+pub enum Access<'a, 'b, T: BoxInt> {
+    Read(&'a dyn Memory<T>),
+    Write(&'a mut ExecContext<'b, T>),
+}
+
+fn locate(error: BsError, at: Option<[usize; 2]>) -> BsError {
+    match at {
+        Some(at) => error.at(at),
+        None => error,
+    }
+}
+
+fn impure(what: &str, at: Option<[usize; 2]>) -> BsError {
+    locate(
+        BsError::ValidationError(
+            format!("{} is not allowed in a pure expression", what),
+            None,
+        ),
+        at,
+    )
+}
+
+impl<T: BoxInt> Expr<T> {
+    pub fn lower(postfix: &[(Atom<T>, Option<[usize; 2]>)]) -> Result<Expr<T>, BsError> {
+        let malformed = |at| {
+            locate(
+                BsError::ValidationError("Malformed expression".to_string(), None),
+                at,
+            )
+        };
+        let unprinted = |at| {
+            locate(
+                BsError::ValidationError("Text can only be printed".to_string(), None),
+                at,
+            )
+        };
+        let unusable = |kind, at| match kind {
+            Kind::Silent => locate(
+                BsError::ValidationError("Silent assignment has no value to use".to_string(), None),
+                at,
+            ),
+            _ => unprinted(at),
+        };
+        let mut stack: Vec<(Expr<T>, usize, Kind)> = Vec::new();
+
+        for (atom, at) in postfix.iter().cloned() {
+            let node = match atom {
+                Atom::Data(num) => (Expr::Number(num), 1, Kind::Value),
+                Atom::PollKey => (Expr::PollKey(at), 1, Kind::Value),
+                Atom::Argument => (Expr::Argument(at), 1, Kind::Value),
+                Atom::Output => {
+                    let (a, depth, kind) = stack.pop().ok_or_else(|| malformed(at))?;
+                    if kind == Kind::Silent {
+                        return Err(unusable(kind, at));
+                    }
+                    (print(a, at), depth + 1, Kind::Value)
+                }
+                Atom::Memory
+                | Atom::Not
+                | Atom::Negate
+                | Atom::Abs
+                | Atom::Copy
+                | Atom::Fill
+                | Atom::Local
+                | Atom::Input
+                | Atom::OutputNumber => {
+                    let (a, depth, kind) = stack.pop().ok_or_else(|| malformed(at))?;
+                    if kind != Kind::Value {
+                        return Err(unusable(kind, at));
+                    }
+                    (Expr::Unary(atom, Box::new(a), at), depth + 1, Kind::Value)
+                }
+                _ => {
+                    let (b, right, kind) = stack.pop().ok_or_else(|| malformed(at))?;
+                    let (a, left, joined) = stack.pop().ok_or_else(|| malformed(at))?;
+                    if kind != Kind::Value {
+                        return Err(unusable(kind, at));
+                    }
+                    if joined == Kind::Silent || (joined == Kind::Text && atom != Atom::Concat) {
+                        return Err(unusable(joined, at));
+                    }
+                    let kind = match atom {
+                        Atom::Concat => Kind::Text,
+                        Atom::SilentAssign => Kind::Silent,
+                        _ => Kind::Value,
+                    };
+                    (
+                        Expr::Binary(atom, Box::new(a), Box::new(b), at),
+                        left.max(right) + 1,
+                        kind,
+                    )
+                }
+            };
+
+            if node.1 > MAX_DEPTH {
+                return Err(locate(
+                    BsError::ValidationError("Expression is nested too deeply".to_string(), None),
+                    at,
+                ));
+            }
+            stack.push(node);
+        }
+
+        match stack.len() {
+            0 => Ok(Expr::Number(T::zero())),
+            1 if stack[0].2 == Kind::Text => {
+                Err(unprinted(postfix.first().and_then(|(_, at)| *at)))
+            }
+            1 => Ok(stack.pop().unwrap().0),
+            _ => Err(malformed(postfix.first().and_then(|(_, at)| *at))),
+        }
+    }
+
+    pub fn eval(&self, context: &mut ExecContext<T>, mode: ArithMode) -> Result<T, BsError> {
+        self.evaluate(&mut Access::Write(context), mode)
+    }
+
+    pub fn evaluate(&self, access: &mut Access<T>, mode: ArithMode) -> Result<T, BsError> {
+        match self {
+            Expr::Number(num) => Ok(*num),
+            Expr::PollKey(at) => poll_key(access, *at),
+            Expr::Argument(at) => argument(access, *at),
+            Expr::Unary(atom, operand, at) => {
+                let a = operand.evaluate(access, mode)?;
+                unary(*atom, a, access, mode, *at)
+            }
+            Expr::Binary(atom, left, right, at) => {
+                let a = left.evaluate(access, mode)?;
+                let b = right.evaluate(access, mode)?;
+                binary(*atom, a, b, access, mode, *at)
+            }
+        }
+    }
+}
+
+fn print<T: BoxInt>(expr: Expr<T>, at: Option<[usize; 2]>) -> Expr<T> {
+    match expr {
+        Expr::Binary(Atom::Concat, a, b, joined) => Expr::Binary(
+            Atom::Concat,
+            Box::new(print(*a, at)),
+            Box::new(print(*b, at)),
+            joined,
+        ),
+        _ => Expr::Unary(Atom::Output, Box::new(expr), at),
+    }
+}
+
+fn symbol<T: BoxInt>(atom: Atom<T>) -> &'static str {
+    atom.mnemonic().unwrap_or("?")
+}
+
+impl<T: BoxInt> fmt::Display for Expr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let operand = |expr: &Expr<T>| match expr {
+            Expr::Binary(..) => format!("({})", expr),
+            _ => expr.to_string(),
+        };
+
+        match self {
+            Expr::Number(num) => write!(f, "{}", num),
+            Expr::PollKey(_) => write!(f, "key"),
+            Expr::Argument(_) => write!(f, "arg"),
+            Expr::Unary(Atom::Memory, a, _) => write!(f, "mem[{}]", a),
+            Expr::Unary(Atom::Not, a, _) => write!(f, "!{}", operand(a)),
+            Expr::Unary(Atom::Negate, a, _) => write!(f, "-{}", operand(a)),
+            Expr::Unary(Atom::Abs, a, _) => write!(f, "abs({})", a),
+            Expr::Unary(Atom::Copy, a, _) => write!(f, "copy({})", a),
+            Expr::Unary(Atom::Fill, a, _) => write!(f, "fill({})", a),
+            Expr::Unary(Atom::Local, a, _) => write!(f, "local[{}]", a),
+            Expr::Unary(Atom::Output, a, _) => write!(f, "out({})", a),
+            Expr::Unary(Atom::OutputNumber, a, _) => write!(f, "print({})", a),
+            Expr::Unary(Atom::Input, a, _) => write!(f, "in({})", a),
+            Expr::Unary(atom, a, _) => write!(f, "{:?}({})", atom, a),
+            Expr::Binary(Atom::Concat, a, b, _) => write!(f, "{}; {}", a, b),
+            Expr::Binary(Atom::Min, a, b, _) => write!(f, "min({}, {})", a, b),
+            Expr::Binary(Atom::Max, a, b, _) => write!(f, "max({}, {})", a, b),
+            Expr::Binary(Atom::Call, a, b, _) => write!(f, "call({}, {})", a, b),
+            Expr::Binary(Atom::IndirectAssign, a, b, _) => {
+                write!(f, "mem[{}] := {}", a, operand(b))
+            }
+            Expr::Binary(Atom::LocalAssign, a, b, _) => {
+                write!(f, "local[{}] := {}", a, operand(b))
+            }
+            Expr::Binary(atom, a, b, _) => {
+                write!(f, "{} {} {}", operand(a), symbol(*atom), operand(b))
+            }
+        }
+    }
+}
+
+pub fn poll_key<T: BoxInt>(access: &mut Access<T>, at: Option<[usize; 2]>) -> Result<T, BsError> {
+    match access {
+        Access::Read(_) => Err(impure("Key input", at)),
+        Access::Write(context) => {
+            context.stdout.flush().map_err(|error| locate(error, at))?;
+            Ok(context
+                .input
+                .poll_key()
+                .and_then(T::from_char)
+                .unwrap_or_else(T::zero))
+        }
+    }
+}
+
+pub fn argument<T: BoxInt>(access: &mut Access<T>, at: Option<[usize; 2]>) -> Result<T, BsError> {
+    match access {
+        Access::Read(_) => Err(impure("Reading the argument", at)),
+        Access::Write(context) => context.argument().ok_or_else(|| {
+            locate(
+                BsError::RuntimeError("Argument used outside of a function".to_string(), None),
+                at,
+            )
+        }),
+    }
+}
+
+fn scratch<'c, T: BoxInt>(
+    context: &'c mut ExecContext<T>,
+    at: Option<[usize; 2]>,
+) -> Result<&'c mut HashMap<T, T>, BsError> {
+    context
+        .functions
+        .as_deref_mut()
+        .and_then(Functions::scratch)
+        .ok_or_else(|| {
+            locate(
+                BsError::RuntimeError("Local memory used outside of a function".to_string(), None),
+                at,
+            )
+        })
+}
+
+fn bulk<T: BoxInt>(
+    atom: Atom<T>,
+    a: T,
+    context: &mut ExecContext<T>,
+    at: Option<[usize; 2]>,
+) -> Result<T, BsError> {
+    let out_of_bounds = || {
+        locate(
+            BsError::RuntimeError("Memory range is out of bounds".to_string(), None),
+            at,
+        )
+    };
+    let span = |start: T, len: T| {
+        len.to_usize()
+            .filter(|len| {
+                *len == 0
+                    || T::from(len - 1)
+                        .and_then(|last| start.checked_add(&last))
+                        .is_some()
+            })
+            .ok_or_else(out_of_bounds)
+    };
+
+    let mut parameters = [T::zero(); 3];
+    for (i, parameter) in parameters.iter_mut().enumerate() {
+        let address = T::from(i)
+            .and_then(|i| a.checked_add(&i))
+            .ok_or_else(out_of_bounds)?;
+        *parameter = context.memory.get(address);
+    }
+
+    let (start, len) = match atom {
+        Atom::Copy => {
+            let [from, to, len] = parameters;
+            let len = span(from, len).and(span(to, len))?;
+            context.spend(len, at)?;
+            context.memory.copy(from, to, len);
+            (to, len)
+        }
+        _ => {
+            let [start, len, value] = parameters;
+            let len = span(start, len)?;
+            context.spend(len, at)?;
+            context.memory.fill(start, len, value);
+            (start, len)
+        }
+    };
+
+    if let Some(hook) = context.hook.as_mut() {
+        for i in 0..len {
+            let address = start + T::from(i).unwrap();
+            hook.wrote(address, context.memory.get(address));
+        }
+    }
+
+    Ok(T::from(len).unwrap())
+}
+
+pub fn unary<T: BoxInt>(
+    atom: Atom<T>,
+    a: T,
+    access: &mut Access<T>,
+    mode: ArithMode,
+    at: Option<[usize; 2]>,
+) -> Result<T, BsError> {
+    Ok(match (atom, access) {
+        (Atom::Memory, Access::Read(memory)) => memory.get(a),
+        (Atom::Memory, Access::Write(context)) => context.memory.get(a),
+        (Atom::Not, _) | (Atom::Negate, _) | (Atom::Abs, _) => {
+            atom.unary_in(mode, a).map_err(|error| locate(error, at))?
+        }
+        (Atom::Output, Access::Read(_)) => return Err(impure("Output", at)),
+        (Atom::Output, Access::Write(context)) => {
+            terminal::encode(a, context.stdout).map_err(|error| locate(error, at))?;
+            a
+        }
+        (Atom::OutputNumber, Access::Read(_)) => return Err(impure("Output", at)),
+        (Atom::OutputNumber, Access::Write(context)) => {
+            context
+                .stdout
+                .write(&a.to_string())
+                .map_err(|error| locate(error, at))?;
+            a
+        }
+        (Atom::Copy, Access::Read(_)) => return Err(impure("Copying memory", at)),
+        (Atom::Fill, Access::Read(_)) => return Err(impure("Filling memory", at)),
+        (Atom::Copy, Access::Write(context)) | (Atom::Fill, Access::Write(context)) => {
+            bulk(atom, a, context, at)?
+        }
+        (Atom::Local, Access::Read(_)) => return Err(impure("Local memory", at)),
+        (Atom::Local, Access::Write(context)) => scratch(context, at)?
+            .get(&a)
+            .cloned()
+            .unwrap_or_else(T::zero),
+        (Atom::Input, Access::Read(_)) => return Err(impure("Input", at)),
+        (Atom::Input, Access::Write(context)) => {
+            let invalid =
+                |message: &str| locate(BsError::RuntimeError(message.to_string(), None), at);
+            context.stdout.flush().map_err(|error| locate(error, at))?;
+
+            if !a.is_zero() {
+                context
+                    .input
+                    .read_int()
+                    .and_then(T::from)
+                    .ok_or_else(|| invalid("Expected an integer on input"))?
+            } else if let Some(c) = context.input.read_char() {
+                T::from_char(c).ok_or_else(|| invalid("Input character is out of range"))?
+            } else {
+                -T::one()
+            }
+        }
+        _ => unreachable!(),
+    })
+}
+
+pub fn binary<T: BoxInt>(
+    atom: Atom<T>,
+    a: T,
+    b: T,
+    access: &mut Access<T>,
+    mode: ArithMode,
+    at: Option<[usize; 2]>,
+) -> Result<T, BsError> {
+    if let Atom::SetIfZero = atom {
+        return match access {
+            Access::Read(_) => Err(impure("Set-if-zero", at)),
+            Access::Write(context) => {
+                if !context.memory.compare_and_assign(a, T::zero(), b) {
+                    return Ok(T::zero());
+                }
+                if let Some(hook) = context.hook.as_mut() {
+                    hook.wrote(a, b);
+                }
+                Ok(T::one())
+            }
+        };
+    }
+
+    if let Atom::Call = atom {
+        return match access {
+            Access::Read(_) => Err(impure("Calling a function", at)),
+            Access::Write(context) => {
+                function::call(context, a, b, mode).map_err(|error| locate(error, at))
+            }
+        };
+    }
+
+    if let Atom::LocalAssign = atom {
+        match access {
+            Access::Read(_) => return Err(impure("Local memory", at)),
+            Access::Write(context) => {
+                scratch(context, at)?.insert(a, b);
+            }
+        }
+    }
+
+    if let Atom::Assign | Atom::SilentAssign | Atom::IndirectAssign = atom {
+        match access {
+            Access::Read(_) => return Err(impure("Assignment", at)),
+            Access::Write(context) => {
+                let address = match atom {
+                    Atom::IndirectAssign => context.memory.get(a),
+                    _ => a,
+                };
+                context.memory.set(address, b);
+                if let Some(hook) = context.hook.as_mut() {
+                    hook.wrote(address, b);
+                }
+            }
+        }
+    }
+
+    atom.binary_in(mode, a, b)
+        .map_err(|error| locate(error, at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::input::NoInput;
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_lowers_postfix_into_trees() {
+        let expr = Expr::<i8>::lower(&[
+            (Atom::Data(2), None),
+            (Atom::Data(1), None),
+            (Atom::Add, Some([0, 1])),
+            (Atom::Output, Some([0, 0])),
+        ])
+        .unwrap();
+        assert_eq!(
+            expr,
+            Expr::Unary(
+                Atom::Output,
+                Box::new(Expr::Binary(
+                    Atom::Add,
+                    Box::new(Expr::Number(2)),
+                    Box::new(Expr::Number(1)),
+                    Some([0, 1])
+                )),
+                Some([0, 0])
+            )
+        );
+
+        let mut stdout = String::new();
+        let mut memory = HashMap::new();
+        assert_eq!(
+            expr.eval(
+                &mut ExecContext::new(&mut memory, &mut stdout, &mut NoInput),
+                ArithMode::Checked
+            ),
+            Ok(3)
+        );
+        assert_eq!(stdout, "\u{3}");
+
+        assert_eq!(Expr::<i8>::lower(&[]), Ok(Expr::Number(0)));
+        assert_eq!(
+            Expr::<i8>::lower(&[
+                (Atom::Data(1), None),
+                (Atom::Output, Some([2, 3])),
+                (Atom::Add, Some([2, 4]))
+            ]),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                Some([2, 4])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_limits_depth() {
+        let chain = |length: usize| {
+            let mut postfix = vec![(Atom::Data(1), None)];
+            for _ in 1..length {
+                postfix.push((Atom::Data(1), None));
+                postfix.push((Atom::Add, Some([0, 1])));
+            }
+            Expr::<i64>::lower(&postfix)
+        };
+
+        let expr = chain(MAX_DEPTH).unwrap();
+        let value = std::thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(move || expr.evaluate(&mut Access::Read(&HashMap::new()), ArithMode::Checked))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(value, Ok(MAX_DEPTH as i64));
+
+        assert_eq!(
+            chain(MAX_DEPTH + 1),
+            Err(BsError::ValidationError(
+                "Expression is nested too deeply".to_string(),
+                Some([0, 1])
+            ))
+        );
+    }
+}
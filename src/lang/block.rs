@@ -0,0 +1,531 @@
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::Molecule;
+use super::include::Include;
+use super::interpreter::{ArithMode, ExecContext, Runnable};
+use super::memory::Memory;
+use super::r#box::{Box, Genus, Relation};
+use super::scheduler::{self, Step};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Node {
+    Molecule(usize),
+    Block(usize),
+}
+
+pub struct Stop<'a, T: BoxInt> {
+    pub at: [usize; 2],
+    pub molecule: &'a Molecule<T>,
+    pub memory: &'a mut dyn Memory<T>,
+    pub stdout: &'a str,
+    pub reloads: &'a mut Vec<Block<T>>,
+}
+
+pub const MAX_NESTING: usize = 256;
+
+pub type Hook<'a, T> = dyn FnMut(Stop<T>) -> Result<(), BsError> + 'a;
+
+pub struct Walk<'a, T: BoxInt> {
+    pending: Vec<&'a Block<T>>,
+}
+
+impl<'a, T: BoxInt> Iterator for Walk<'a, T> {
+    type Item = &'a Block<T>;
+
+    fn next(&mut self) -> Option<&'a Block<T>> {
+        let block = self.pending.pop()?;
+        self.pending.extend(block.children.iter().rev());
+        Some(block)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Block<T: BoxInt> {
+    pub r#box: Box,
+    pub guard: Option<Molecule<T>>,
+    pub molecules: Vec<(usize, Molecule<T>)>,
+    pub children: Vec<Block<T>>,
+    pub include: Option<Include>,
+    pub arith_mode: ArithMode,
+}
+
+impl<T: BoxInt> Block<T> {
+    pub fn build(code: &str) -> Result<Vec<Block<T>>, BsError> {
+        let boxes = Box::find_all(code)?;
+        let parents = Block::<T>::parents(&boxes)?;
+        let roots: Vec<usize> = (0..boxes.len()).filter(|i| parents[*i].is_none()).collect();
+
+        Block::assemble(code, &boxes, &parents, &roots)
+    }
+
+    pub fn walk(blocks: &[Block<T>]) -> Walk<'_, T> {
+        Walk {
+            pending: blocks.iter().rev().collect(),
+        }
+    }
+
+    pub fn replace(blocks: &mut [Block<T>], block: Block<T>) -> bool {
+        let mut pending: Vec<&mut Block<T>> = blocks.iter_mut().collect();
+
+        while let Some(target) = pending.pop() {
+            if target.r#box == block.r#box {
+                *target = block;
+                return true;
+            }
+            pending.extend(target.children.iter_mut());
+        }
+
+        false
+    }
+
+    pub fn expressions(&self) -> impl Iterator<Item = &Molecule<T>> {
+        self.guard
+            .iter()
+            .chain(self.molecules.iter().map(|(_, molecule)| molecule))
+    }
+
+    pub fn new(code: &str, r#box: &Box, boxes: &[Box]) -> Result<Block<T>, BsError> {
+        let mut all = boxes.to_vec();
+        let root = match boxes.iter().position(|b| b == r#box) {
+            Some(root) => root,
+            None => {
+                all.push(r#box.clone());
+                all.len() - 1
+            }
+        };
+        let parents = Block::<T>::parents(&all)?;
+
+        Ok(Block::assemble(code, &all, &parents, &[root])?.remove(0))
+    }
+
+    fn parents(boxes: &[Box]) -> Result<Vec<Option<usize>>, BsError> {
+        let depths: Vec<usize> = boxes
+            .iter()
+            .map(|b| boxes.iter().filter(|other| other.contains(b)).count())
+            .collect();
+
+        boxes
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                if depths[i] >= MAX_NESTING {
+                    return Err(BsError::ParseError(
+                        "Boxes are nested too deeply".to_string(),
+                        Some(b.top_left),
+                    ));
+                }
+
+                Ok((0..boxes.len()).find(|j| depths[*j] + 1 == depths[i] && boxes[*j].contains(b)))
+            })
+            .collect()
+    }
+
+    fn assemble(
+        code: &str,
+        boxes: &[Box],
+        parents: &[Option<usize>],
+        roots: &[usize],
+    ) -> Result<Vec<Block<T>>, BsError> {
+        let mut built: Vec<Option<Block<T>>> = boxes.iter().map(|_| None).collect();
+        let mut order: Vec<usize> = Vec::new();
+        let mut pending: Vec<usize> = roots.iter().rev().cloned().collect();
+
+        while let Some(i) = pending.pop() {
+            built[i] = Some(Block::shallow(code, &boxes[i], boxes)?);
+            order.push(i);
+            pending.extend((0..boxes.len()).rev().filter(|j| parents[*j] == Some(i)));
+        }
+
+        for i in order.into_iter().rev() {
+            built[i].as_mut().unwrap().children.reverse();
+            if let Some(parent) = parents[i].filter(|_| !roots.contains(&i)) {
+                let block = built[i].take().unwrap();
+                built[parent].as_mut().unwrap().children.push(block);
+            }
+        }
+
+        Ok(roots.iter().map(|i| built[*i].take().unwrap()).collect())
+    }
+
+    fn nested<'a>(r#box: &'a Box, boxes: &'a [Box]) -> impl Iterator<Item = &'a Box> + 'a {
+        boxes.iter().filter(move |b| {
+            r#box.relationship(b) == Relation::Parent
+                && !boxes
+                    .iter()
+                    .any(|other| r#box.contains(other) && other.contains(b))
+        })
+    }
+
+    pub fn shallow(code: &str, r#box: &Box, boxes: &[Box]) -> Result<Block<T>, BsError> {
+        let mut guard: Option<Molecule<T>> = None;
+        let mut molecules: Vec<(usize, Molecule<T>)> = Vec::new();
+        let mut include: Option<Include> = None;
+
+        if let Some((position, header)) = r#box.header_at(code) {
+            match r#box.genus {
+                Genus::Loop | Genus::Condition | Genus::Function => {
+                    guard = Some(Molecule::read(&header, position)?);
+                }
+                Genus::Execution => {
+                    include = Include::parse(&header, position)?;
+                    if include.is_none() {
+                        return Err(BsError::ParseError(
+                            "Unexpected header".to_string(),
+                            Some(r#box.top_left),
+                        ));
+                    }
+                }
+                Genus::NoOp => {}
+            }
+        }
+
+        for (i, line) in r#box.content(code, boxes).iter().enumerate() {
+            if line.trim().is_empty() || r#box.genus == Genus::NoOp {
+                continue;
+            }
+
+            let molecule =
+                Molecule::read(line, [r#box.top_left[0] + 1 + i, r#box.top_left[1] + 1])?;
+
+            if guard.is_none() && r#box.genus != Genus::Execution {
+                guard = Some(molecule);
+            } else {
+                molecules.push((r#box.top_left[0] + 1 + i, molecule));
+            }
+        }
+
+        if guard.is_none() && (r#box.genus == Genus::Loop || r#box.genus == Genus::Condition) {
+            return Err(BsError::ParseError(
+                "Missing condition".to_string(),
+                Some(r#box.top_left),
+            ));
+        }
+
+        if guard.is_none() && r#box.genus == Genus::Function {
+            return Err(BsError::ParseError(
+                "Missing function number".to_string(),
+                Some(r#box.top_left),
+            ));
+        }
+
+        if include.is_some()
+            && (!molecules.is_empty() || Block::<T>::nested(r#box, boxes).next().is_some())
+        {
+            return Err(BsError::ParseError(
+                "Unexpected code in include".to_string(),
+                Some(r#box.top_left),
+            ));
+        }
+
+        Ok(Block {
+            r#box: r#box.clone(),
+            guard,
+            molecules,
+            children: Vec::new(),
+            include,
+            arith_mode: ArithMode::Checked,
+        })
+    }
+
+    pub fn set_arith_mode(&mut self, arith_mode: ArithMode) {
+        let mut pending: Vec<&mut Block<T>> = vec![self];
+
+        while let Some(block) = pending.pop() {
+            block.arith_mode = arith_mode;
+
+            if let Some(guard) = block.guard.as_mut() {
+                guard.set_arith_mode(arith_mode);
+            }
+            for (_, molecule) in block.molecules.iter_mut() {
+                molecule.set_arith_mode(arith_mode);
+            }
+            pending.extend(block.children.iter_mut());
+        }
+    }
+
+    pub fn silence_assignments(&mut self) {
+        let mut pending: Vec<&mut Block<T>> = vec![self];
+
+        while let Some(block) = pending.pop() {
+            if let Some(guard) = block.guard.as_mut() {
+                guard.silence_assignments();
+            }
+            for (_, molecule) in block.molecules.iter_mut() {
+                molecule.silence_assignments();
+            }
+            pending.extend(block.children.iter_mut());
+        }
+    }
+
+    pub fn evaluate(
+        molecule: &mut Molecule<T>,
+        at: [usize; 2],
+        context: &mut ExecContext<T>,
+    ) -> Result<T, BsError> {
+        if let Some(stop) = context.stop.as_mut() {
+            stop(Stop {
+                at: molecule.positions().first().cloned().unwrap_or(at),
+                molecule,
+                memory: &mut *context.memory,
+                stdout: context.stdout.captured(),
+                reloads: &mut context.reloads,
+            })?;
+        }
+
+        molecule.run(context)
+    }
+
+    fn check(&mut self, context: &mut ExecContext<T>) -> Result<bool, BsError> {
+        match self.guard.as_mut() {
+            Some(guard) => Ok(!Block::evaluate(guard, self.r#box.top_left, context)?.is_zero()),
+            None => Ok(true),
+        }
+    }
+
+    pub fn schedule(&self) -> Vec<Node> {
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut molecules = self.molecules.iter().enumerate().peekable();
+        let mut children = self.children.iter().enumerate().peekable();
+
+        loop {
+            let next_molecule = molecules.peek().map(|(_, (row, _))| *row);
+            let next_child = children.peek().map(|(_, child)| child.r#box.top_left[0]);
+
+            nodes.push(match (next_molecule, next_child) {
+                (Some(row), Some(top)) if row < top => Node::Molecule(molecules.next().unwrap().0),
+                (Some(_), None) => Node::Molecule(molecules.next().unwrap().0),
+                (_, Some(_)) => Node::Block(children.next().unwrap().0),
+                (None, None) => break,
+            });
+        }
+
+        nodes
+    }
+
+    fn run_body(&mut self, context: &mut ExecContext<T>) -> Result<T, BsError> {
+        let mut value = T::zero();
+
+        for step in scheduler::plan(self) {
+            value = match step {
+                Step::Molecule(i) => {
+                    let (row, molecule) = &mut self.molecules[i];
+                    let at = [*row, self.r#box.top_left[1] + 1];
+                    Block::evaluate(molecule, at, context)?
+                }
+                Step::Block(i) => self.children[i].run(context)?,
+                Step::Simultaneous(range) => scheduler::run(&mut self.children[range], context)?,
+            };
+        }
+
+        Ok(value)
+    }
+
+    pub fn call(&mut self, context: &mut ExecContext<T>) -> Result<T, BsError> {
+        self.run_body(context)
+    }
+}
+
+impl<T: BoxInt> Runnable<T> for Block<T> {
+    fn run(&mut self, context: &mut ExecContext<T>) -> Result<T, BsError> {
+        let mut value = T::zero();
+
+        match self.r#box.genus {
+            Genus::Execution => value = self.run_body(context)?,
+            Genus::Condition => {
+                if self.check(context)? {
+                    value = self.run_body(context)?;
+                }
+            }
+            Genus::Loop => {
+                let mut iterations: usize = 0;
+
+                while self.check(context)? {
+                    if Some(iterations) == context.max_iterations {
+                        return Err(BsError::RuntimeError(
+                            format!("Loop exceeded {} iterations", iterations),
+                            Some(self.r#box.top_left),
+                        ));
+                    }
+
+                    value = self.run_body(context)?;
+                    iterations += 1;
+                }
+            }
+            Genus::NoOp | Genus::Function => {}
+        }
+
+        Ok(value)
+    }
+}
+
+#[allow(unused_must_use)]
+#[cfg(test)]
+mod tests {
+    use super::super::input::NoInput;
+    use super::*;
+    use std::collections::HashMap;
+
+    fn run(code: &str) -> Result<(HashMap<i8, i8>, String), BsError> {
+        let mut memory = HashMap::new();
+        let mut stdout = String::new();
+
+        for mut block in Block::<i8>::build(code)? {
+            block.run(&mut ExecContext::new(
+                &mut memory,
+                &mut stdout,
+                &mut NoInput,
+            ))?;
+        }
+
+        Ok((memory, stdout))
+    }
+
+    #[test]
+    fn it_builds_trees() {
+        let code = "┌──────┐\n│▀◈▀▀  │\n│┏━━━┓ │\n│┃▀▀ ┃ │\n│┗━━━┛ │\n└──────┘\n╭┄┄╮\n╰┄┄╯";
+        let blocks = Block::<i8>::build(code).unwrap();
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].molecules.len(), 1);
+        assert_eq!(blocks[0].children.len(), 1);
+        assert_eq!(blocks[0].children[0].r#box.genus, Genus::Condition);
+        assert!(blocks[0].children[0].guard.is_some());
+        assert!(blocks[0].children[0].molecules.is_empty());
+        assert_eq!(blocks[1].r#box.genus, Genus::NoOp);
+    }
+
+    #[test]
+    fn it_walks_blocks() {
+        let code = "┌──────────┐\n│▀◈▀▀      │\n│┏━▀▀━━━━┓ │\n│┃▭◇▀    ┃ │\n│┗━━━━━━━┛ │\n└──────────┘\n┌──────────┐\n│▀▀◈▀▀▄    │\n└──────────┘";
+        let blocks = Block::<i8>::build(code).unwrap();
+
+        assert_eq!(
+            Block::walk(&blocks)
+                .map(|block| block.r#box.top_left)
+                .collect::<Vec<[usize; 2]>>(),
+            vec![[0, 0], [2, 1], [6, 0]]
+        );
+        assert_eq!(
+            Block::walk(&blocks)
+                .flat_map(|block| block.expressions())
+                .map(|molecule| molecule.source())
+                .collect::<Vec<String>>(),
+            vec!["▀◈▀▀", "▀▀", "▭◇▀", "▀▀◈▀▀▄"]
+        );
+    }
+
+    #[test]
+    fn it_limits_nesting() {
+        let nest = |depth: usize| {
+            let width = 2 * depth - 1;
+            let mut rows = Vec::new();
+            for i in 0..depth {
+                rows.push(format!(
+                    "{}┌{}┐{}",
+                    "│".repeat(i),
+                    "─".repeat(width - 2 * i),
+                    "│".repeat(i)
+                ));
+            }
+            rows.push(format!("{} {}", "│".repeat(depth), "│".repeat(depth)));
+            for i in (0..depth).rev() {
+                rows.push(format!(
+                    "{}└{}┘{}",
+                    "│".repeat(i),
+                    "─".repeat(width - 2 * i),
+                    "│".repeat(i)
+                ));
+            }
+            rows.join("\n")
+        };
+
+        assert_eq!(Block::<i8>::build(&nest(64)).unwrap()[0].children.len(), 1);
+        assert_eq!(
+            Block::<i8>::build(&nest(MAX_NESTING + 1)).err(),
+            Some(BsError::ParseError(
+                "Boxes are nested too deeply".to_string(),
+                Some([MAX_NESTING, MAX_NESTING])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_runs_in_order() {
+        let code = "┌──────────┐\n│▀◈▀▀      │\n│┌────────┐│\n││▀◈◇▀▘▀▀▄││\n│└────────┘│\n│▀◈◇▀▐▀▀   │\n└──────────┘";
+        assert_eq!(run(code).unwrap().0, [(0, 3)].iter().cloned().collect());
+    }
+
+    #[test]
+    fn it_runs_conditions_and_loops() {
+        let code = "┏━━━━━━┓\n┃▀▄    ┃\n┃▀◈▀▀  ┃\n┗━━━━━━┛\n┏━━━━━━┓\n┃▀▀    ┃\n┃▀▀◈▀▀ ┃\n┗━━━━━━┛";
+        assert_eq!(run(code).unwrap().0, [(1, 1)].iter().cloned().collect());
+
+        let code = "╔════════════╗\n║◇▀▨▀▀▄▀     ║\n║▀◈◇▀▐▀▀     ║\n║▭◇▀▐▀▀▀▄▄▄▄ ║\n╚════════════╝";
+        assert_eq!(run(code).unwrap().1, "12345".to_string());
+    }
+
+    #[test]
+    fn it_checks_conditions_once() {
+        let code = "┏━◇▀▤▀▄━━━━━━━┓\n┃▀◈▀▀         ┃\n┃▭▀▀▀▀▄▄▄▄    ┃\n┗━━━━━━━━━━━━━┛";
+        assert_eq!(run(code).unwrap().1, "p".to_string());
+
+        let code = "╔═◇▀▨▀▀▀▀═════╗\n║▀◈◇▀▐▀▀      ║\n║┏━◇▀▖▀▀▄▤▀▄━┓║\n║┃▭◇▀▐▀▀▀▄▄▄▄┃║\n║┗━━━━━━━━━━━┛║\n╚═════════════╝";
+        assert_eq!(run(code).unwrap().1, "246".to_string());
+    }
+
+    #[test]
+    fn it_limits_loops() {
+        let mut block = Block::<i8>::build("╔═▀▀══╗\n║▭▀▀▀▀║\n╚═════╝")
+            .unwrap()
+            .remove(0);
+
+        let (mut memory, mut stdout, mut input) = (HashMap::new(), String::new(), NoInput);
+        let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+        context.max_iterations = Some(3);
+        assert_eq!(
+            block.run(&mut context),
+            Err(BsError::RuntimeError(
+                "Loop exceeded 3 iterations".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(stdout, "\u{7}\u{7}\u{7}".to_string());
+
+        let mut block = Block::<i8>::build("╔═▀▄══╗\n║▭▀▀▀▀║\n╚═════╝")
+            .unwrap()
+            .remove(0);
+
+        let (mut memory, mut stdout, mut input) = (HashMap::new(), String::new(), NoInput);
+        let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+        context.max_iterations = Some(0);
+        assert_eq!(block.run(&mut context), Ok(0));
+    }
+
+    #[test]
+    fn it_runs_headers() {
+        let code = "╔═◇▀▨▀▀▄▀════╗\n║▀◈◇▀▐▀▀     ║\n║▭◇▀▐▀▀▀▄▄▄▄ ║\n╚════════════╝";
+        assert_eq!(run(code).unwrap().1, "12345".to_string());
+
+        let code = "┏━▀▄━━━┓\n┃▀◈▀▀  ┃\n┗━━━━━━┛\n╭┄note┄╮\n┆text  ┆\n╰┄┄┄┄┄┄╯";
+        assert_eq!(run(code).unwrap().0, HashMap::new());
+
+        assert_eq!(
+            run("┌─▀─┐\n│   │\n└───┘"),
+            Err(BsError::ParseError(
+                "Unexpected header".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_requires_conditions() {
+        assert_eq!(
+            run("╔══╗\n║  ║\n╚══╝"),
+            Err(BsError::ParseError(
+                "Missing condition".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+}
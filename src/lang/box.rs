@@ -0,0 +1,649 @@
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::Molecule;
+use super::matrix;
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Genus {
+    Execution,
+    Loop,
+    Condition,
+    NoOp,
+    Function,
+}
+
+impl fmt::Display for Genus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Corner {
+    Light,
+    Double,
+    Heavy,
+}
+
+impl Genus {
+    pub fn from_marker(c: char) -> Option<Genus> {
+        match c {
+            '◰' => Some(Genus::Execution),
+            '◱' => Some(Genus::Loop),
+            '◲' => Some(Genus::Condition),
+            '◳' => Some(Genus::NoOp),
+            '◴' => Some(Genus::Function),
+            _ => None,
+        }
+    }
+
+    pub fn from_edge(c: char) -> Option<Genus> {
+        match c {
+            '─' | '│' => Some(Genus::Execution),
+            '═' | '║' => Some(Genus::Loop),
+            '━' | '┃' => Some(Genus::Condition),
+            '┄' | '┅' | '┈' | '┉' | '╌' | '╍' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' => {
+                Some(Genus::NoOp)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Genus::Execution => "execution",
+            Genus::Loop => "loop",
+            Genus::Condition => "condition",
+            Genus::NoOp => "no-op",
+            Genus::Function => "function",
+        }
+    }
+
+    fn from_corner(corner: Corner) -> Genus {
+        match corner {
+            Corner::Light => Genus::Execution,
+            Corner::Double => Genus::Loop,
+            Corner::Heavy => Genus::Condition,
+        }
+    }
+
+    fn accepts(&self, corner: Corner) -> bool {
+        match self {
+            Genus::Execution => corner == Corner::Light,
+            Genus::Loop => corner == Corner::Double,
+            Genus::Condition => corner == Corner::Heavy,
+            Genus::NoOp => corner != Corner::Double,
+            Genus::Function => true,
+        }
+    }
+
+    pub fn infer(
+        code: &str,
+        top_left: &[usize; 2],
+        bottom_right: &[usize; 2],
+    ) -> Result<Genus, BsError> {
+        Genus::infer_in(&matrix::chars(code), top_left, bottom_right)
+    }
+
+    fn infer_in(
+        grid: &[Vec<char>],
+        top_left: &[usize; 2],
+        bottom_right: &[usize; 2],
+    ) -> Result<Genus, BsError> {
+        let at = |row: usize, col: usize| {
+            grid.get(row)
+                .and_then(|line| line.get(col))
+                .cloned()
+                .unwrap_or('\0')
+        };
+        let [top, left] = *top_left;
+        let [bottom, right] = *bottom_right;
+
+        let mut corners: Vec<Corner> = Vec::new();
+        for (row, col) in &[(top, left), (top, right), (bottom, left), (bottom, right)] {
+            corners.push(match at(*row, *col) {
+                '┌' | '┐' | '└' | '┘' | '╭' | '╮' | '╰' | '╯' => Corner::Light,
+                '╔' | '╗' | '╚' | '╝' => Corner::Double,
+                '┏' | '┓' | '┗' | '┛' => Corner::Heavy,
+                _ => return Err(broken([*row, *col])),
+            });
+        }
+
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for col in left + 1..right {
+            if !is_header(at(top, col)) {
+                edges.push((top, col));
+            }
+            edges.push((bottom, col));
+        }
+        for row in top + 1..bottom {
+            edges.push((row, left));
+            edges.push((row, right));
+        }
+
+        let mut genera: Vec<Genus> = Vec::new();
+        for (row, col) in edges {
+            match Genus::from_edge(at(row, col)) {
+                Some(genus) => {
+                    if !genera.contains(&genus) {
+                        genera.push(genus);
+                    }
+                }
+                None => return Err(broken([row, col])),
+            }
+        }
+
+        if top < bottom && left < right {
+            if let Some(genus) = Genus::from_marker(at(top + 1, left + 1)) {
+                return Ok(genus);
+            }
+        }
+
+        let genus = match genera.len() {
+            0 => {
+                if corners.iter().any(|corner| *corner != corners[0]) {
+                    None
+                } else {
+                    Some(Genus::from_corner(corners[0]))
+                }
+            }
+            1 => {
+                if corners.iter().all(|corner| genera[0].accepts(*corner)) {
+                    Some(genera[0])
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+
+        genus.ok_or_else(|| {
+            BsError::ParseError("Ambiguous box border".to_string(), Some([top, left]))
+        })
+    }
+}
+
+impl fmt::Display for Box {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} box {}:{} to {}:{}",
+            self.genus,
+            self.top_left[0] + 1,
+            self.top_left[1] + 1,
+            self.bottom_right[0] + 1,
+            self.bottom_right[1] + 1
+        )
+    }
+}
+
+fn broken(location: [usize; 2]) -> BsError {
+    BsError::ParseError("Broken box border".to_string(), Some(location))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Relation {
+    Parent,
+    Child,
+    Before,
+    After,
+    Simultaneous,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Box {
+    pub top_left: [usize; 2],
+    pub bottom_right: [usize; 2],
+    pub genus: Genus,
+}
+
+impl Box {
+    pub fn new(top_left: [usize; 2], bottom_right: [usize; 2], genus: Genus) -> Box {
+        Box {
+            top_left,
+            bottom_right,
+            genus,
+        }
+    }
+
+    pub fn find_all(code: &str) -> Result<Vec<Box>, BsError> {
+        let grid = matrix::chars(code);
+        let at = |row: usize, col: usize| {
+            grid.get(row)
+                .and_then(|line| line.get(col))
+                .cloned()
+                .unwrap_or('\0')
+        };
+        let mut boxes: Vec<Box> = Vec::new();
+
+        for (top, line) in grid.iter().enumerate() {
+            for (left, c) in line.iter().enumerate() {
+                if let '┌' | '╔' | '┏' | '╭' = c {
+                } else {
+                    continue;
+                }
+
+                if boxes
+                    .iter()
+                    .any(|b| b.genus == Genus::NoOp && b.covers(&[top, left]))
+                {
+                    continue;
+                }
+
+                let mut right = left + 1;
+                while is_horizontal(at(top, right)) || is_header(at(top, right)) {
+                    right += 1;
+                }
+                if let '┐' | '╗' | '┓' | '╮' = at(top, right) {
+                } else {
+                    return Err(BsError::ParseError(
+                        "Unclosed box".to_string(),
+                        Some([top, left]),
+                    ));
+                }
+
+                let mut bottom = top + 1;
+                while is_vertical(at(bottom, left)) {
+                    bottom += 1;
+                }
+                if let '└' | '╚' | '┗' | '╰' = at(bottom, left) {
+                } else {
+                    return Err(BsError::ParseError(
+                        "Unclosed box".to_string(),
+                        Some([top, left]),
+                    ));
+                }
+
+                let genus = Genus::infer_in(&grid, &[top, left], &[bottom, right])?;
+                boxes.push(Box::new([top, left], [bottom, right], genus));
+            }
+        }
+
+        for (i, a) in boxes.iter().enumerate() {
+            for b in &boxes[i + 1..] {
+                if a.intersects(b) && !a.contains(b) && !b.contains(a) {
+                    return Err(BsError::ParseError(
+                        "Overlapping boxes".to_string(),
+                        Some(b.top_left),
+                    ));
+                }
+            }
+        }
+
+        for (row, line) in grid.iter().enumerate() {
+            for (col, c) in line.iter().enumerate() {
+                if *c != '\0' && !c.is_whitespace() && !boxes.iter().any(|b| b.covers(&[row, col]))
+                {
+                    return Err(BsError::ParseError(
+                        "Code outside of a box".to_string(),
+                        Some([row, col]),
+                    ));
+                }
+            }
+        }
+
+        Ok(boxes)
+    }
+
+    pub fn covers(&self, loc: &[usize; 2]) -> bool {
+        (self.top_left[0]..=self.bottom_right[0]).contains(&loc[0])
+            && (self.top_left[1]..=self.bottom_right[1]).contains(&loc[1])
+    }
+
+    pub fn contains(&self, other: &Box) -> bool {
+        self != other
+            && self.top_left[0] < other.top_left[0]
+            && self.top_left[1] < other.top_left[1]
+            && self.bottom_right[0] > other.bottom_right[0]
+            && self.bottom_right[1] > other.bottom_right[1]
+    }
+
+    pub fn intersects(&self, other: &Box) -> bool {
+        self.top_left[0] <= other.bottom_right[0]
+            && other.top_left[0] <= self.bottom_right[0]
+            && self.top_left[1] <= other.bottom_right[1]
+            && other.top_left[1] <= self.bottom_right[1]
+    }
+
+    pub fn before(&self, other: &Box) -> bool {
+        self.bottom_right[0] < other.top_left[0]
+    }
+
+    pub fn after(&self, other: &Box) -> bool {
+        other.before(self)
+    }
+
+    pub fn simultaneous(&self, other: &Box) -> bool {
+        !self.before(other) && !self.after(other)
+    }
+
+    pub fn relationship(&self, other: &Box) -> Relation {
+        if self.contains(other) {
+            Relation::Parent
+        } else if other.contains(self) {
+            Relation::Child
+        } else if self.before(other) {
+            Relation::Before
+        } else if self.after(other) {
+            Relation::After
+        } else {
+            Relation::Simultaneous
+        }
+    }
+
+    pub fn content(&self, code: &str, boxes: &[Box]) -> Vec<String> {
+        let grid = matrix::chars(code);
+        let inner: Vec<&Box> = boxes.iter().filter(|b| self.contains(b)).collect();
+        let marker = self.has_marker(code);
+
+        (self.top_left[0] + 1..self.bottom_right[0])
+            .map(|row| {
+                (self.top_left[1] + 1..self.bottom_right[1])
+                    .map(|col| {
+                        let c = grid[row][col];
+                        if c == '\0'
+                            || marker && [row, col] == [self.top_left[0] + 1, self.top_left[1] + 1]
+                            || inner.iter().any(|b| b.covers(&[row, col]))
+                        {
+                            ' '
+                        } else {
+                            c
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn molecules<T: BoxInt>(
+        &self,
+        code: &str,
+        boxes: &[Box],
+    ) -> Result<Vec<Molecule<T>>, BsError> {
+        let mut molecules: Vec<Molecule<T>> = Vec::new();
+
+        for (i, line) in self.content(code, boxes).iter().enumerate() {
+            if !line.trim().is_empty() {
+                molecules.push(Molecule::read(
+                    line,
+                    [self.top_left[0] + 1 + i, self.top_left[1] + 1],
+                )?);
+            }
+        }
+
+        Ok(molecules)
+    }
+
+    pub fn header(&self, code: &str) -> Option<String> {
+        self.header_at(code).map(|(_, header)| header)
+    }
+
+    pub fn header_at(&self, code: &str) -> Option<([usize; 2], String)> {
+        let line: Vec<char> = matrix::chars(code)
+            .get(self.top_left[0])?
+            .iter()
+            .skip(self.top_left[1] + 1)
+            .take(self.bottom_right[1] - self.top_left[1] - 1)
+            .cloned()
+            .collect();
+        let start = line
+            .iter()
+            .position(|c| is_header(*c) && !c.is_whitespace())?;
+        let end = line
+            .iter()
+            .rposition(|c| is_header(*c) && !c.is_whitespace())?;
+
+        Some((
+            [self.top_left[0], self.top_left[1] + 1 + start],
+            line[start..=end].iter().collect(),
+        ))
+    }
+
+    pub fn has_marker(&self, code: &str) -> bool {
+        matrix::chars(code)
+            .get(self.top_left[0] + 1)
+            .and_then(|line| line.get(self.top_left[1] + 1))
+            .and_then(|c| Genus::from_marker(*c))
+            .is_some()
+    }
+}
+
+fn is_horizontal(c: char) -> bool {
+    matches!(c, '─' | '═' | '━' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍')
+}
+
+fn is_header(c: char) -> bool {
+    c != '\0' && !('\u{2500}'..='\u{257f}').contains(&c)
+}
+
+fn is_vertical(c: char) -> bool {
+    matches!(c, '│' | '║' | '┃' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏')
+}
+
+#[allow(unused_must_use)]
+#[cfg(test)]
+mod tests {
+    use super::super::input::NoInput;
+    use super::super::interpreter::{ExecContext, Runnable};
+    use super::*;
+
+    #[test]
+    fn it_infers_genus_from_borders() {
+        assert_eq!(
+            Genus::infer("┌─┐\n│ │\n└─┘", &[0, 0], &[2, 2]),
+            Ok(Genus::Execution)
+        );
+        assert_eq!(
+            Genus::infer("╔═╗\n║ ║\n╚═╝", &[0, 0], &[2, 2]),
+            Ok(Genus::Loop)
+        );
+        assert_eq!(
+            Genus::infer("┏━┓\n┃ ┃\n┗━┛", &[0, 0], &[2, 2]),
+            Ok(Genus::Condition)
+        );
+        assert_eq!(
+            Genus::infer("╭┄╮\n┆ ┆\n╰┄╯", &[0, 0], &[2, 2]),
+            Ok(Genus::NoOp)
+        );
+        assert_eq!(
+            Genus::infer("┏┅┓\n┇ ┇\n┗┅┛", &[0, 0], &[2, 2]),
+            Ok(Genus::NoOp)
+        );
+        assert_eq!(Genus::infer("╔╗\n╚╝", &[0, 0], &[1, 1]), Ok(Genus::Loop));
+    }
+
+    #[test]
+    fn it_reports_ambiguous_borders() {
+        assert_eq!(
+            Genus::infer("┌═┐\n│ │\n└─┘", &[0, 0], &[2, 2]),
+            Err(BsError::ParseError(
+                "Ambiguous box border".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(
+            Genus::infer("╔─╗\n│ │\n╚─╝", &[0, 0], &[2, 2]),
+            Err(BsError::ParseError(
+                "Ambiguous box border".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(
+            Genus::infer("┌┐\n╚╝", &[0, 0], &[1, 1]),
+            Err(BsError::ParseError(
+                "Ambiguous box border".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(
+            Genus::infer("┌─┐\n│ │\n└a┘", &[0, 0], &[2, 2]),
+            Err(BsError::ParseError(
+                "Broken box border".to_string(),
+                Some([2, 1])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_respects_markers() {
+        assert_eq!(
+            Genus::infer("┌──┐\n│◱ │\n└──┘", &[0, 0], &[2, 3]),
+            Ok(Genus::Loop)
+        );
+        assert_eq!(
+            Genus::infer("┌═─┐\n│◳ │\n└──┘", &[0, 0], &[2, 3]),
+            Ok(Genus::NoOp)
+        );
+        assert!(Box::new([0, 0], [2, 3], Genus::Loop).has_marker("┌──┐\n│◱ │\n└──┘"));
+        assert!(!Box::new([0, 0], [2, 3], Genus::Loop).has_marker("╔══╗\n║  ║\n╚══╝"));
+    }
+
+    #[test]
+    fn it_finds_boxes() {
+        let code =
+            "┌───────┐\n│▭▀▀▀▀▀▀│ ╔══╗\n│┏━━┓   │ ║  ║\n│┃▀ ┃   │ ╚══╝\n│┗━━┛   │\n└───────┘";
+        assert_eq!(
+            Box::find_all(code),
+            Ok(vec![
+                Box::new([0, 0], [5, 8], Genus::Execution),
+                Box::new([1, 10], [3, 13], Genus::Loop),
+                Box::new([2, 1], [4, 4], Genus::Condition),
+            ])
+        );
+
+        assert_eq!(Box::find_all(""), Ok(vec![]));
+    }
+
+    #[test]
+    fn it_detects_bad_boxes() {
+        assert_eq!(
+            Box::find_all("┌──\n│ │\n└─┘"),
+            Err(BsError::ParseError(
+                "Unclosed box".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(
+            Box::find_all("┌─┐\n│ │\n  ┘"),
+            Err(BsError::ParseError(
+                "Unclosed box".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(
+            Box::find_all("┌─┐\n│ │\n└─┘ ▀"),
+            Err(BsError::ParseError(
+                "Code outside of a box".to_string(),
+                Some([2, 4])
+            ))
+        );
+        assert_eq!(
+            Box::find_all("┌──┐\n│┌─┼┐\n└┼─┘│\n └──┘"),
+            Err(BsError::ParseError(
+                "Broken box border".to_string(),
+                Some([2, 1])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_reads_headers() {
+        let code = "╔═◇▀▨▀▀═╗ ┏━━━━┓\n║       ║ ┃    ┃\n╚═══════╝ ┗━━━━┛";
+        let boxes = Box::find_all(code).unwrap();
+        assert_eq!(
+            boxes,
+            vec![
+                Box::new([0, 0], [2, 8], Genus::Loop),
+                Box::new([0, 10], [2, 15], Genus::Condition),
+            ]
+        );
+        assert_eq!(boxes[0].header(code), Some("◇▀▨▀▀".to_string()));
+        assert_eq!(boxes[1].header(code), None);
+
+        assert_eq!(
+            Box::find_all("┏━ ▀▀ ┏━┓\n┃    ┃ ┃\n┗━━━━┛━┛"),
+            Err(BsError::ParseError(
+                "Unclosed box".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_masks_comments() {
+        let code = "╭┄┄┄┄┄┄┄┄╮\n┆ note ┌ ┆\n┆┌──┐    ┆\n┆└──┘ a  ┆\n╰┄┄┄┄┄┄┄┄╯";
+        assert_eq!(
+            Box::find_all(code),
+            Ok(vec![Box::new([0, 0], [4, 9], Genus::NoOp)])
+        );
+    }
+
+    #[test]
+    fn it_relates_boxes() {
+        let outer = Box::new([0, 0], [5, 8], Genus::Execution);
+        let inner = Box::new([2, 1], [4, 4], Genus::Condition);
+        let side = Box::new([1, 10], [3, 13], Genus::Loop);
+        let below = Box::new([6, 0], [7, 1], Genus::Execution);
+
+        assert_eq!(outer.relationship(&inner), Relation::Parent);
+        assert_eq!(inner.relationship(&outer), Relation::Child);
+        assert_eq!(outer.relationship(&side), Relation::Simultaneous);
+        assert_eq!(outer.relationship(&below), Relation::Before);
+        assert_eq!(below.relationship(&side), Relation::After);
+    }
+
+    #[test]
+    fn it_displays_boxes() {
+        assert_eq!(
+            Box::new([2, 1], [4, 4], Genus::Condition).to_string(),
+            "condition box 3:2 to 5:5"
+        );
+        assert_eq!(Genus::NoOp.to_string(), "no-op");
+    }
+
+    #[test]
+    fn it_extracts_content() {
+        let code = "┌─────┐\n│▭▀▀▀▀│\n│┌─┐▀ │\n│└─┘  │\n└─────┘";
+        let boxes = Box::find_all(code).unwrap();
+        assert_eq!(
+            boxes[0].content(code, &boxes),
+            vec!["▭▀▀▀▀", "   ▀ ", "     "]
+        );
+
+        let code = "╔═══╗\n║◰▀ ║\n╚═══╝";
+        let boxes = Box::find_all(code).unwrap();
+        assert_eq!(boxes[0].genus, Genus::Execution);
+        assert_eq!(boxes[0].content(code, &boxes), vec![" ▀ "]);
+    }
+
+    #[test]
+    fn it_stacks_molecules() {
+        let code = "┌───────────┐\n│▀◈▀▀▀▄▄▀▄  │\n│           │\n│▭◇▀▐▀▀     │\n└───────────┘";
+        let boxes = Box::find_all(code).unwrap();
+        let mut molecules = boxes[0].molecules::<i8>(code, &boxes).unwrap();
+        assert_eq!(molecules.len(), 2);
+
+        let mut memory = std::collections::HashMap::new();
+        let mut stdout = String::new();
+        for molecule in molecules.iter_mut() {
+            molecule.run(&mut ExecContext::new(
+                &mut memory,
+                &mut stdout,
+                &mut NoInput,
+            ));
+        }
+        assert_eq!(stdout, "3".to_string());
+
+        let code = "┌──┐\n│▀a│\n└──┘";
+        let boxes = Box::find_all(code).unwrap();
+        assert_eq!(
+            boxes[0].molecules::<i8>(code, &boxes),
+            Err(BsError::ParseError(
+                "Invalid character".to_string(),
+                Some([1, 2])
+            ))
+        );
+    }
+}
@@ -0,0 +1,380 @@
+use super::block::{Block, Node};
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Dialect, Literal, Molecule};
+use super::r#box::{Box, Genus};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub literal: Literal,
+    pub dialect: Dialect,
+    pub fold: bool,
+}
+
+impl Default for Style {
+    fn default() -> Style {
+        Style {
+            literal: Literal::Binary,
+            dialect: Dialect::LATEST,
+            fold: false,
+        }
+    }
+}
+
+fn border(genus: Genus) -> [char; 6] {
+    match genus {
+        Genus::Execution | Genus::Function => ['┌', '─', '┐', '│', '└', '┘'],
+        Genus::Loop => ['╔', '═', '╗', '║', '╚', '╝'],
+        Genus::Condition => ['┏', '━', '┓', '┃', '┗', '┛'],
+        Genus::NoOp => ['╭', '┄', '╮', '┆', '╰', '╯'],
+    }
+}
+
+fn width(lines: &[String]) -> usize {
+    lines
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+fn pad(line: &str, width: usize) -> String {
+    format!("{}{}", line, " ".repeat(width - line.chars().count()))
+}
+
+pub fn frame(genus: Genus, lines: &[String]) -> Vec<String> {
+    let [top_left, horizontal, top_right, vertical, bottom_left, bottom_right] = border(genus);
+    let inner = width(lines);
+    let edge: String = std::iter::repeat_n(horizontal, inner).collect();
+
+    let mut framed = vec![format!("{}{}{}", top_left, edge, top_right)];
+    for line in lines {
+        framed.push(format!("{}{}{}", vertical, pad(line, inner), vertical));
+    }
+    framed.push(format!("{}{}{}", bottom_left, edge, bottom_right));
+
+    framed
+}
+
+pub fn beside(parts: &[Vec<String>]) -> Vec<String> {
+    let height = parts.iter().map(|part| part.len()).max().unwrap_or(0);
+
+    (0..height)
+        .map(|row| {
+            parts
+                .iter()
+                .map(|part| pad(part.get(row).map_or("", |line| line), width(part)))
+                .collect::<Vec<String>>()
+                .join(" ")
+        })
+        .collect()
+}
+
+pub fn groups(boxes: &[&Box]) -> Vec<Vec<usize>> {
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut bottom = 0;
+
+    for (i, r#box) in boxes.iter().enumerate() {
+        match groups.last_mut() {
+            Some(group) if r#box.top_left[0] <= bottom => {
+                group.push(i);
+                bottom = bottom.max(r#box.bottom_right[0]);
+            }
+            _ => {
+                groups.push(vec![i]);
+                bottom = r#box.bottom_right[0];
+            }
+        }
+    }
+
+    groups
+}
+
+fn line<T: BoxInt>(molecule: &Molecule<T>, style: Style) -> Result<String, BsError> {
+    let written = if style.fold {
+        molecule.folded()
+    } else {
+        molecule.clone()
+    };
+    let source = written.source_as(style.literal.within(style.dialect));
+
+    match Molecule::<T>::read(&source, [0, 0]) {
+        Ok(read) if read.atoms() == written.atoms() => Ok(source),
+        _ => Err(BsError::ParseError(
+            "Formatting would change the meaning of this expression".to_string(),
+            molecule.positions().first().cloned(),
+        )),
+    }
+}
+
+fn render<T: BoxInt>(block: &Block<T>, code: &str, style: Style) -> Result<Vec<String>, BsError> {
+    let mut lines: Vec<String> = Vec::new();
+
+    if block.r#box.genus == Genus::NoOp {
+        lines = block
+            .r#box
+            .content(code, &[])
+            .iter()
+            .map(|line| line.trim_end().to_string())
+            .collect();
+        if block.r#box.has_marker(code) {
+            lines[0] = lines[0].chars().skip(1).collect();
+        }
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        while lines.first().is_some_and(|line| line.is_empty()) {
+            lines.remove(0);
+        }
+
+        return Ok(frame(Genus::NoOp, &lines));
+    }
+
+    if let Some(include) = &block.include {
+        let header = include.header();
+        let edge = "─".repeat(header.chars().count());
+        return Ok(vec![format!("┌{}┐", header), format!("└{}┘", edge)]);
+    }
+
+    if let Some(guard) = &block.guard {
+        lines.push(line(guard, style)?);
+    }
+    if block.r#box.genus == Genus::Function {
+        lines[0].insert(0, '◴');
+    }
+
+    let schedule = block.schedule();
+    let mut i = 0;
+    while i < schedule.len() {
+        match schedule[i] {
+            Node::Molecule(j) => {
+                lines.push(line(&block.molecules[j].1, style)?);
+                i += 1;
+            }
+            Node::Block(_) => {
+                let mut run: Vec<&Block<T>> = Vec::new();
+                while let Some(Node::Block(j)) = schedule.get(i) {
+                    run.push(&block.children[*j]);
+                    i += 1;
+                }
+                lines.extend(arrange(&run, code, style)?);
+            }
+        }
+    }
+
+    Ok(frame(block.r#box.genus, &lines))
+}
+
+fn arrange<T: BoxInt>(
+    blocks: &[&Block<T>],
+    code: &str,
+    style: Style,
+) -> Result<Vec<String>, BsError> {
+    let boxes: Vec<&Box> = blocks.iter().map(|block| &block.r#box).collect();
+    let mut lines: Vec<String> = Vec::new();
+
+    for group in groups(&boxes) {
+        let parts = group
+            .iter()
+            .map(|i| render(blocks[*i], code, style))
+            .collect::<Result<Vec<Vec<String>>, BsError>>()?;
+        lines.extend(beside(&parts));
+    }
+
+    Ok(lines)
+}
+
+pub fn canonicalize<T: BoxInt>(code: &str) -> Result<String, BsError> {
+    canonicalize_as::<T>(code, Style::default())
+}
+
+pub fn canonicalize_as<T: BoxInt>(code: &str, style: Style) -> Result<String, BsError> {
+    if let Some(prefix) = style.literal.prefix() {
+        let needed = Dialect::introducing(prefix);
+        if needed > style.dialect {
+            return Err(BsError::ValidationError(
+                format!(
+                    "Glyph {} needs dialect {} or later",
+                    prefix,
+                    needed.number()
+                ),
+                None,
+            ));
+        }
+    }
+
+    let code = code.replace("\r\n", "\n").replace('\t', " ");
+    let blocks = Block::<T>::build(&code)?;
+    let boxes: Vec<&Box> = blocks.iter().map(|block| &block.r#box).collect();
+    let mut lines: Vec<String> = Vec::new();
+
+    for group in groups(&boxes) {
+        if !lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        let parts = group
+            .iter()
+            .map(|i| render(&blocks[*i], &code, style))
+            .collect::<Result<Vec<Vec<String>>, BsError>>()?;
+        lines.extend(
+            beside(&parts)
+                .iter()
+                .map(|line| line.trim_end().to_string()),
+        );
+    }
+
+    Ok(lines.iter().map(|line| format!("{}\n", line)).collect())
+}
+
+pub fn format<T: BoxInt>(code: &str) -> Result<String, BsError> {
+    canonicalize_as::<T>(
+        code,
+        Style {
+            literal: Literal::Original,
+            ..Style::default()
+        },
+    )
+}
+
+pub fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::expression::Atom;
+    use super::super::interpreter::Interpreter;
+    use super::*;
+
+    #[test]
+    fn it_canonicalizes_programs() {
+        assert_eq!(
+            canonicalize::<i8>("\n\n  ┌──────────┐   \n  │◰ ▀ ◈ ▀▄▀▀│\n  └──────────┘\n"),
+            Ok("┌─────┐\n│▀◈▀▀▀│\n└─────┘\n".to_string())
+        );
+
+        assert_eq!(
+            canonicalize::<i8>("╔═◇▀▨▀▀═╗\n║▀◈▀▀   ║\n║┌──┐   ║\n║│▭▀│   ║\n║└──┘   ║\n╚═══════╝"),
+            Ok("╔═════╗\n║◇▀▨▀▀║\n║▀◈▀▀ ║\n║┌──┐ ║\n║│▭▀│ ║\n║└──┘ ║\n╚═════╝\n".to_string())
+        );
+    }
+
+    #[test]
+    fn it_converts_literals() {
+        let code = "┌──────────┐\n│▀▀◈▀▀▄▀▄▀▄│\n└──────────┘";
+        let decimal = canonicalize_as::<i8>(
+            code,
+            Style {
+                literal: Literal::Decimal,
+                ..Style::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(decimal, "┌──────┐\n│◉1◈◉42│\n└──────┘\n");
+        assert_eq!(
+            canonicalize_as::<i8>(
+                &decimal,
+                Style {
+                    literal: Literal::Hex,
+                    ..Style::default()
+                }
+            ),
+            Ok("┌──────┐\n│◎1◈◎2a│\n└──────┘\n".to_string())
+        );
+        assert_eq!(
+            hash(&canonicalize::<i8>(&decimal).unwrap()),
+            hash(&canonicalize::<i8>(code).unwrap())
+        );
+        assert_eq!(
+            canonicalize_as::<i8>(
+                code,
+                Style {
+                    literal: Literal::Hex,
+                    dialect: Dialect::V2,
+                    fold: false,
+                }
+            ),
+            Err(BsError::ValidationError(
+                "Glyph ◎ needs dialect 3 or later".to_string(),
+                None
+            ))
+        );
+    }
+
+    #[test]
+    fn it_folds_and_shortens_literals() {
+        let code = "┌───────────────────┐\n│▀◈▕▀▀▀▀▀▐▀▀▀▀▀▏▘◇▀▀│\n└───────────────────┘";
+        let style = Style {
+            literal: Literal::Shortest,
+            fold: true,
+            ..Style::default()
+        };
+
+        assert_eq!(
+            canonicalize_as::<i16>(code, style),
+            Ok("┌─────────┐\n│▀◈◉30▘◇▀▀│\n└─────────┘\n".to_string())
+        );
+        assert_eq!(
+            canonicalize_as::<i16>(
+                code,
+                Style {
+                    dialect: Dialect::V2,
+                    ..style
+                }
+            ),
+            Ok("┌────────────┐\n│▀◈▀▀▀▀▀▄▘◇▀▀│\n└────────────┘\n".to_string())
+        );
+    }
+
+    #[test]
+    fn it_refuses_to_change_meaning() {
+        let molecule = Molecule::<i32>::new(vec![Atom::Data(72), Atom::Concat, Atom::Data(-1)]);
+
+        assert_eq!(
+            line(&molecule, Style::default()).unwrap_err().message(),
+            "Formatting would change the meaning of this expression"
+        );
+    }
+
+    #[test]
+    fn it_keeps_simultaneous_boxes_together() {
+        assert_eq!(
+            canonicalize::<i8>("┌──┐\n│▭▀│     ┌───┐\n└──┘     │▭▀▀│\n         └───┘\n\n\n╭┄┄┄┄┄┄╮\n┆ note ┆\n╰┄┄┄┄┄┄╯"),
+            Ok("┌──┐ ┌───┐\n│▭▀│ │▭▀▀│\n└──┘ └───┘\n\n╭┄┄┄┄┄╮\n┆ note┆\n╰┄┄┄┄┄╯\n".to_string())
+        );
+    }
+
+    #[test]
+    fn it_formats_without_changing_meaning() {
+        let run = |code: &str| {
+            let mut interpreter = Interpreter::<i64>::new();
+            interpreter.set_dialect(Dialect::V3);
+            interpreter.run_program(code)
+        };
+        let code = "╭┄┄┄┄┄┄┄┄┄┄┄┄┄╮\n┆  counts up  ┆\n┆             ┆\n╰┄┄┄┄┄┄┄┄┄┄┄┄┄╯\n\n╔═◇▀▨▀▀▄▄════╗   \n║ ▭◇▀▐◉48    ║   \n║  ▀◈◇▀▐▀▀   ║   \n╚════════════╝\n┌────────┐\n│◰ ▭'a'  │\n│▬◎-1f   │\n│▭\"hi\"   │\n└────────┘\n";
+        let formatted = format::<i64>(code).unwrap();
+
+        assert_eq!(
+            formatted,
+            "╭┄┄┄┄┄┄┄┄┄┄┄╮\n┆  counts up┆\n╰┄┄┄┄┄┄┄┄┄┄┄╯\n\n╔═══════╗\n║◇▀▨▀▀▄▄║\n║▭◇▀▐◉48║\n║▀◈◇▀▐▀▀║\n╚═══════╝\n\n┌─────┐\n│▭'a' │\n│▬◎-1f│\n│▭\"hi\"│\n└─────┘\n"
+        );
+        assert_eq!(run(&formatted), run(code));
+        assert_eq!(run(code), Ok("0123a-31hi".to_string()));
+        assert_eq!(format::<i64>(&formatted), Ok(formatted));
+    }
+
+    #[test]
+    fn it_hashes_canonical_forms() {
+        let a = canonicalize::<i8>("┌────┐\n│▀◈▀▀│\n└────┘").unwrap();
+        let b = canonicalize::<i8>("  ┌─────┐\n  │▀◈ ▀▀│\n  └─────┘  ").unwrap();
+        let c = canonicalize::<i8>("┌────┐\n│▀◈▀▄│\n└────┘").unwrap();
+
+        assert_eq!(hash(&a), hash(&b));
+        assert_ne!(hash(&a), hash(&c));
+        assert_eq!(hash(""), 0xcbf2_9ce4_8422_2325);
+    }
+}
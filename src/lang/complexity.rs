@@ -0,0 +1,158 @@
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::Molecule;
+use super::r#box::{Box, Genus};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complexity {
+    pub bytes: Option<usize>,
+    pub boxes: Option<usize>,
+    pub depth: Option<usize>,
+    pub tokens: Option<usize>,
+}
+
+fn exceeded(message: String, at: Option<[usize; 2]>) -> BsError {
+    BsError::ParseError(message, at)
+}
+
+impl Complexity {
+    pub fn is_unlimited(&self) -> bool {
+        *self == Complexity::default()
+    }
+
+    pub fn check<T: BoxInt>(&self, source: &str) -> Result<(), BsError> {
+        if let Some(bytes) = self.bytes.filter(|bytes| source.len() > *bytes) {
+            return Err(exceeded(
+                format!("Program is larger than {} bytes", bytes),
+                None,
+            ));
+        }
+
+        if let Some(limit) = self.boxes {
+            let mut corners = source.lines().enumerate().flat_map(|(row, line)| {
+                line.chars()
+                    .enumerate()
+                    .filter(|(_, c)| matches!(c, '┌' | '╔' | '┏' | '╭'))
+                    .map(move |(col, _)| [row, col])
+            });
+            if let Some(at) = corners.nth(limit) {
+                return Err(exceeded(
+                    format!("Program has more than {} boxes", limit),
+                    Some(at),
+                ));
+            }
+        }
+
+        if self.depth.is_none() && self.tokens.is_none() {
+            return Ok(());
+        }
+        let boxes = Box::find_all(source)?;
+
+        if let Some(limit) = self.depth {
+            for r#box in boxes.iter() {
+                if boxes.iter().filter(|other| other.contains(r#box)).count() >= limit {
+                    return Err(exceeded(
+                        format!("Boxes are nested more than {} deep", limit),
+                        Some(r#box.top_left),
+                    ));
+                }
+            }
+        }
+
+        if let Some(limit) = self.tokens {
+            for r#box in boxes.iter().filter(|b| b.genus != Genus::NoOp) {
+                let mut lines: Vec<([usize; 2], String)> = r#box
+                    .content(source, &boxes)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, line)| ([r#box.top_left[0] + 1 + i, r#box.top_left[1] + 1], line))
+                    .collect();
+                if r#box.genus != Genus::Execution {
+                    lines.extend(r#box.header_at(source));
+                }
+
+                for (at, line) in lines {
+                    let molecule = Molecule::<T>::read(&line, at)?;
+                    if molecule.atoms().len() > limit {
+                        return Err(exceeded(
+                            format!("Expression has more than {} tokens", limit),
+                            molecule.positions().first().cloned(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NESTED: &str =
+        "┌──────────┐\n│▀◈▀▀      │\n│┏━▀▀━━━━┓ │\n│┃┌─────┐┃ │\n│┃│▭▀▐▀▀│┃ │\n│┃└─────┘┃ │\n│┗━━━━━━━┛ │\n└──────────┘";
+
+    #[test]
+    fn it_allows_programs_within_limits() {
+        assert!(Complexity::default().is_unlimited());
+        assert_eq!(Complexity::default().check::<i8>(NESTED), Ok(()));
+        assert_eq!(
+            Complexity {
+                bytes: Some(NESTED.len()),
+                boxes: Some(3),
+                depth: Some(3),
+                tokens: Some(4),
+            }
+            .check::<i8>(NESTED),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn it_rejects_complex_programs() {
+        let check = |complexity: Complexity| complexity.check::<i8>(NESTED);
+
+        assert_eq!(
+            check(Complexity {
+                bytes: Some(10),
+                ..Complexity::default()
+            }),
+            Err(BsError::ParseError(
+                "Program is larger than 10 bytes".to_string(),
+                None
+            ))
+        );
+        assert_eq!(
+            check(Complexity {
+                boxes: Some(2),
+                ..Complexity::default()
+            }),
+            Err(BsError::ParseError(
+                "Program has more than 2 boxes".to_string(),
+                Some([3, 2])
+            ))
+        );
+        assert_eq!(
+            check(Complexity {
+                depth: Some(2),
+                ..Complexity::default()
+            }),
+            Err(BsError::ParseError(
+                "Boxes are nested more than 2 deep".to_string(),
+                Some([3, 2])
+            ))
+        );
+        assert_eq!(
+            check(Complexity {
+                tokens: Some(3),
+                ..Complexity::default()
+            }),
+            Err(BsError::ParseError(
+                "Expression has more than 3 tokens".to_string(),
+                Some([4, 3])
+            ))
+        );
+    }
+}
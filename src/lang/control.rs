@@ -0,0 +1,237 @@
+use super::datatype::BoxInt;
+use super::input::InputSource;
+use super::memory::Memory;
+use std::collections::VecDeque;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Request<T: BoxInt> {
+    Print(T),
+    Set(T, T),
+    Memory,
+    Input(String),
+}
+
+impl<T: BoxInt> Request<T> {
+    pub fn parse(line: &str) -> Result<Request<T>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| {
+            T::from_str_radix(word, 10).map_err(|_| format!("invalid number: {}", word))
+        };
+
+        Ok(match words.as_slice() {
+            ["print", address] => Request::Print(number(address)?),
+            ["set", address, value] => Request::Set(number(address)?, number(value)?),
+            ["memory"] => Request::Memory,
+            ["input", ..] => Request::Input(
+                line.trim_start()["input".len()..]
+                    .strip_prefix(' ')
+                    .unwrap_or_default()
+                    .replace("\\n", "\n"),
+            ),
+            _ => return Err(format!("unknown command: {}", line.trim())),
+        })
+    }
+}
+
+type Reply = Sender<String>;
+
+#[derive(Clone)]
+pub struct Handle<T: BoxInt> {
+    requests: Sender<(Request<T>, Reply)>,
+    input: Arc<Mutex<VecDeque<char>>>,
+}
+
+impl<T: BoxInt> Handle<T> {
+    pub fn submit(&self, line: &str) -> Receiver<String> {
+        let (reply, response) = channel();
+
+        match Request::parse(line) {
+            Ok(Request::Input(text)) => {
+                self.input.lock().unwrap().extend(text.chars());
+                reply.send("ok".to_string()).unwrap();
+            }
+            Ok(request) => {
+                if let Err(error) = self.requests.send((request, reply)) {
+                    (error.0)
+                        .1
+                        .send("error: program is not running".to_string())
+                        .unwrap();
+                }
+            }
+            Err(message) => reply.send(format!("error: {}", message)).unwrap(),
+        }
+
+        response
+    }
+
+    pub fn send(&self, line: &str) -> String {
+        self.submit(line)
+            .recv()
+            .unwrap_or_else(|_| "error: program is not running".to_string())
+    }
+}
+
+pub struct Bridge<T: BoxInt> {
+    requests: Receiver<(Request<T>, Reply)>,
+    input: Arc<Mutex<VecDeque<char>>>,
+}
+
+impl<T: BoxInt> Bridge<T> {
+    pub fn new() -> (Bridge<T>, Handle<T>) {
+        let (sender, requests) = channel();
+        let input = Arc::new(Mutex::new(VecDeque::new()));
+
+        (
+            Bridge {
+                requests,
+                input: input.clone(),
+            },
+            Handle {
+                requests: sender,
+                input,
+            },
+        )
+    }
+
+    pub fn input(&self, fallback: Box<dyn InputSource>) -> Injected {
+        Injected {
+            injected: self.input.clone(),
+            fallback,
+        }
+    }
+
+    pub fn serve(&self, memory: &mut dyn Memory<T>) {
+        while let Ok((request, reply)) = self.requests.try_recv() {
+            let response = match request {
+                Request::Print(address) => memory.get(address).to_string(),
+                Request::Set(address, value) => {
+                    memory.set(address, value);
+                    "ok".to_string()
+                }
+                Request::Memory => memory
+                    .cells()
+                    .iter()
+                    .map(|(address, value)| format!("{}={}", address, value))
+                    .collect::<Vec<String>>()
+                    .join(" "),
+                Request::Input(_) => unreachable!(),
+            };
+
+            let _ = reply.send(response);
+        }
+    }
+}
+
+pub struct Injected {
+    injected: Arc<Mutex<VecDeque<char>>>,
+    fallback: Box<dyn InputSource>,
+}
+
+impl InputSource for Injected {
+    fn read_char(&mut self) -> Option<char> {
+        let injected = self.injected.lock().unwrap().pop_front();
+        injected.or_else(|| self.fallback.read_char())
+    }
+
+    fn poll_key(&mut self) -> Option<char> {
+        let injected = self.injected.lock().unwrap().pop_front();
+        injected.or_else(|| self.fallback.poll_key())
+    }
+}
+
+#[cfg(unix)]
+pub fn listen<T: BoxInt + Send + 'static>(
+    path: &std::path::Path,
+    handle: Handle<T>,
+) -> std::io::Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    let listener = UnixListener::bind(path)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().filter_map(Result::ok) {
+            let handle = handle.clone();
+            thread::spawn(move || {
+                let mut writer = match stream.try_clone() {
+                    Ok(writer) => writer,
+                    Err(_) => return,
+                };
+                for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    if writeln!(writer, "{}", handle.send(&line)).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::input::Reader;
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_parses_requests() {
+        assert_eq!(Request::<i8>::parse("print 3"), Ok(Request::Print(3)));
+        assert_eq!(Request::<i8>::parse("set -1 7"), Ok(Request::Set(-1, 7)));
+        assert_eq!(Request::<i8>::parse(" memory "), Ok(Request::Memory));
+        assert_eq!(
+            Request::<i8>::parse("input a b\\n"),
+            Ok(Request::Input("a b\n".to_string()))
+        );
+        assert_eq!(
+            Request::<i8>::parse("set 0 x"),
+            Err("invalid number: x".to_string())
+        );
+        assert_eq!(
+            Request::<i8>::parse("jump"),
+            Err("unknown command: jump".to_string())
+        );
+    }
+
+    #[test]
+    fn it_serves_requests_at_stops() {
+        let (bridge, handle) = Bridge::<i64>::new();
+        let mut memory = HashMap::new();
+        memory.insert(1, 5);
+
+        let set = handle.submit("set 0 72");
+        let print = handle.submit("print 1");
+        let listing = handle.submit("memory");
+        assert!(set.try_recv().is_err());
+
+        bridge.serve(&mut memory);
+        assert_eq!(set.recv(), Ok("ok".to_string()));
+        assert_eq!(print.recv(), Ok("5".to_string()));
+        assert_eq!(listing.recv(), Ok("0=72 1=5".to_string()));
+        assert_eq!(memory.get(&0), Some(&72));
+
+        assert_eq!(handle.send("print"), "error: unknown command: print");
+        drop(bridge);
+        assert_eq!(handle.send("print 0"), "error: program is not running");
+    }
+
+    #[test]
+    fn it_injects_input() {
+        let (bridge, handle) = Bridge::<i64>::new();
+        let mut input = bridge.input(Box::new(Reader::new("z".as_bytes())));
+
+        assert_eq!(handle.send("input hi"), "ok");
+        assert_eq!(input.read_char(), Some('h'));
+        assert_eq!(input.poll_key(), Some('i'));
+        assert_eq!(input.read_char(), Some('z'));
+        assert_eq!(input.read_char(), None);
+    }
+}
@@ -0,0 +1,204 @@
+use super::datatype::BoxInt;
+use super::expression::Molecule;
+use super::trace::ExecHook;
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{Arc, Mutex};
+
+pub const KEEP: usize = 32;
+
+type Step = ([usize; 2], i128);
+
+#[derive(Clone, Debug, Default)]
+pub struct Recent {
+    steps: Arc<Mutex<VecDeque<Step>>>,
+}
+
+impl Recent {
+    pub fn new() -> Recent {
+        Recent::default()
+    }
+
+    fn push(&self, at: [usize; 2], value: i128) {
+        if let Ok(mut steps) = self.steps.try_lock() {
+            if steps.len() == KEEP {
+                steps.pop_front();
+            }
+            steps.push_back((at, value));
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        match self.steps.try_lock() {
+            Ok(steps) => steps
+                .iter()
+                .map(|(at, value)| format!("{}:{} = {}", at[0] + 1, at[1] + 1, value))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+pub struct Recorder<T: BoxInt> {
+    recent: Recent,
+    inner: Option<Box<dyn ExecHook<T>>>,
+}
+
+impl<T: BoxInt> Recorder<T> {
+    pub fn new(recent: Recent, inner: Option<Box<dyn ExecHook<T>>>) -> Recorder<T> {
+        Recorder { recent, inner }
+    }
+}
+
+impl<T: BoxInt> ExecHook<T> for Recorder<T> {
+    fn evaluated(&mut self, molecule: &Molecule<T>, value: T) {
+        let at = molecule.positions().first().cloned().unwrap_or([0, 0]);
+        self.recent.push(at, value.to_i128().unwrap_or_default());
+
+        if let Some(inner) = self.inner.as_mut() {
+            inner.evaluated(molecule, value);
+        }
+    }
+
+    fn wrote(&mut self, address: T, value: T) {
+        if let Some(inner) = self.inner.as_mut() {
+            inner.wrote(address, value);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Bundle {
+    pub version: String,
+    pub args: Vec<String>,
+    pub source: String,
+    pub message: String,
+    pub trace: Vec<String>,
+}
+
+impl Bundle {
+    pub fn report(&self) -> String {
+        let mut text = format!(
+            "boxscript {}\ncommand: {}\nseed: none, runs are deterministic\n\n{}\n\nlast {} steps:\n",
+            self.version,
+            self.args.join(" "),
+            self.message.trim_end(),
+            self.trace.len()
+        );
+        for line in &self.trace {
+            text += &format!("  {}\n", line);
+        }
+
+        text
+    }
+
+    pub fn write(&self, dir: &Path) -> io::Result<PathBuf> {
+        let path = dir.join(format!("boxscript-crash-{}", process::id()));
+        fs::create_dir_all(&path)?;
+        fs::write(path.join("program.bs"), &self.source)?;
+        fs::write(path.join("report.txt"), self.report())?;
+
+        Ok(path)
+    }
+}
+
+pub fn instructions(path: &Path) -> String {
+    format!(
+        "BoxScript crashed. This is a bug in the interpreter, not in your program.\nA reproducer was saved to {}\nNothing has been sent anywhere. To report the crash, open an issue at {}/issues and attach program.bs and report.txt from that directory.",
+        path.display(),
+        env!("CARGO_PKG_REPOSITORY")
+    )
+}
+
+pub fn install(source: String, recent: Recent) {
+    let default = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default(info);
+
+        let bundle = Bundle {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            args: env::args().collect(),
+            source: source.clone(),
+            message: info.to_string(),
+            trace: recent.lines(),
+        };
+        match bundle.write(&env::temp_dir()) {
+            Ok(path) => eprintln!("{}", instructions(&path)),
+            Err(error) => eprintln!(
+                "BoxScript crashed and could not save a reproducer: {}",
+                error
+            ),
+        }
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::block::Block;
+    use super::super::input::NoInput;
+    use super::super::interpreter::{ExecContext, Runnable};
+    use super::super::trace::Tracer;
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn it_keeps_the_last_steps() {
+        let recent = Recent::new();
+        let mut recorder = Recorder::new(recent.clone(), None);
+        let (mut memory, mut stdout, mut input) = (HashMap::new(), String::new(), NoInput);
+        let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+        context.hook = Some(&mut recorder);
+
+        let code = "╔═◇▀▨▀▀▀▀▀▀══╗\n║▀◈◇▀▐▀▀     ║\n╚════════════╝";
+        for mut block in Block::<i8>::build(code).unwrap() {
+            block.run(&mut context).unwrap();
+        }
+
+        let lines = recent.lines();
+        assert_eq!(lines.len(), KEEP);
+        assert_eq!(lines[0], "2:2 = 16");
+        assert_eq!(lines[KEEP - 2], "2:2 = 31");
+        assert_eq!(lines[KEEP - 1], "1:3 = 0");
+    }
+
+    #[test]
+    fn it_forwards_to_the_inner_hook() {
+        let mut recorder =
+            Recorder::new(Recent::new(), Some(Box::new(Tracer::new(io::sink(), None))));
+        let molecule = Molecule::<i8>::read("▀◈▀▀", [1, 2]).unwrap();
+        recorder.wrote(0, 1);
+        recorder.evaluated(&molecule, 1);
+
+        assert_eq!(recorder.recent.lines(), vec!["2:3 = 1"]);
+    }
+
+    #[test]
+    fn it_writes_bundles() {
+        let bundle = Bundle {
+            version: "0.1.0".to_string(),
+            args: vec!["boxscript".to_string(), "count.bs".to_string()],
+            source: "┌──┐\n│▀▀│\n└──┘".to_string(),
+            message: "panicked at src/lang/vm.rs:1:1:\nexplicit panic".to_string(),
+            trace: vec!["2:2 = 1".to_string()],
+        };
+        assert_eq!(
+            bundle.report(),
+            "boxscript 0.1.0\ncommand: boxscript count.bs\nseed: none, runs are deterministic\n\npanicked at src/lang/vm.rs:1:1:\nexplicit panic\n\nlast 1 steps:\n  2:2 = 1\n"
+        );
+
+        let dir = env::temp_dir().join(format!("boxscript-bundle-{}", process::id()));
+        let path = bundle.write(&dir).unwrap();
+        let source = fs::read_to_string(path.join("program.bs")).unwrap();
+        let report = fs::read_to_string(path.join("report.txt")).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(source, bundle.source);
+        assert_eq!(report, bundle.report());
+        assert!(instructions(&path).contains(&path.display().to_string()));
+    }
+}
@@ -0,0 +1,51 @@
+use num_traits::{
+    CheckedRem, CheckedShl, CheckedShr, PrimInt, SaturatingMul, Signed, ToPrimitive, WrappingAdd,
+    WrappingMul, WrappingShl, WrappingShr, WrappingSub,
+};
+
+pub trait BoxInt:
+    PrimInt
+    + Signed
+    + ToPrimitive
+    + CheckedRem
+    + CheckedShl
+    + CheckedShr
+    + WrappingAdd
+    + WrappingSub
+    + WrappingMul
+    + WrappingShl
+    + WrappingShr
+    + SaturatingMul
+    + std::hash::Hash
+    + std::fmt::Debug
+    + std::fmt::Display
+    + 'static
+{
+    fn from_char(c: char) -> Option<Self> {
+        Self::from(c as u32)
+    }
+
+    fn to_char(self) -> Option<char> {
+        self.to_u32().and_then(std::char::from_u32)
+    }
+}
+
+impl BoxInt for i8 {}
+impl BoxInt for i16 {}
+impl BoxInt for i32 {}
+impl BoxInt for i64 {}
+impl BoxInt for i128 {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_converts_characters() {
+        assert_eq!(i64::from_char('é'), Some(233));
+        assert_eq!(i8::from_char('é'), None);
+        assert_eq!(72i16.to_char(), Some('H'));
+        assert_eq!((-1i64).to_char(), None);
+        assert_eq!(0xd800i32.to_char(), None);
+    }
+}
@@ -0,0 +1,575 @@
+use super::block::{Block, Stop};
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Dialect, Molecule};
+use super::include;
+use super::interpreter::{ArithMode, Interpreter};
+use super::memory::Memory;
+use super::r#box::{Box, Genus};
+use super::scheduler;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Command<T: BoxInt> {
+    Break(usize),
+    Delete(usize),
+    BreakBox([usize; 2]),
+    DeleteBox([usize; 2]),
+    Continue,
+    Step,
+    Print(T),
+    Set(T, T),
+    Memory,
+    Where,
+    Watch(String),
+    Unwatch(String),
+    Reload([usize; 2]),
+    Quit,
+}
+
+impl<T: BoxInt> Command<T> {
+    pub fn parse(line: &str) -> Result<Option<Command<T>>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |word: &str| {
+            T::from_str_radix(word, 10).map_err(|_| format!("invalid number: {}", word))
+        };
+        let line_number = |word: &str| match word.parse::<usize>() {
+            Ok(line) if line > 0 => Ok(line - 1),
+            _ => Err(format!("invalid line: {}", word)),
+        };
+        let corner = |word: &str| {
+            let invalid = || format!("invalid box: {}", word);
+            let (row, col) = word.split_once(':').ok_or_else(invalid)?;
+            match (row.parse::<usize>(), col.parse::<usize>()) {
+                (Ok(row), Ok(col)) if row > 0 && col > 0 => Ok([row - 1, col - 1]),
+                _ => Err(invalid()),
+            }
+        };
+        let rest = || line.trim()[words[0].len()..].trim().to_string();
+
+        Ok(Some(match words.as_slice() {
+            [] => return Ok(None),
+            [word, ..] if word.starts_with('#') => return Ok(None),
+            ["break", at] if at.contains(':') => Command::BreakBox(corner(at)?),
+            ["delete", at] if at.contains(':') => Command::DeleteBox(corner(at)?),
+            ["reload", at] => Command::Reload(corner(at)?),
+            ["break", line] => Command::Break(line_number(line)?),
+            ["delete", line] => Command::Delete(line_number(line)?),
+            ["run"] | ["continue"] => Command::Continue,
+            ["step"] => Command::Step,
+            ["print", address] => Command::Print(number(address)?),
+            ["set", address, value] => Command::Set(number(address)?, number(value)?),
+            ["memory"] => Command::Memory,
+            ["where"] => Command::Where,
+            ["watch", _, ..] => Command::Watch(rest()),
+            ["unwatch", _, ..] => Command::Unwatch(rest()),
+            ["quit"] | ["abort"] => Command::Quit,
+            _ => return Err(format!("unknown command: {}", line.trim())),
+        }))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Event<T: BoxInt> {
+    Breakpoint(usize),
+    Deleted(usize),
+    BoxBreakpoint([usize; 2]),
+    BoxDeleted([usize; 2]),
+    Stopped([usize; 2], String),
+    Output(String),
+    Value(T, T),
+    Memory(Vec<(T, T)>),
+    Watch(String, Result<T, BsError>),
+    Unwatched(String),
+    Reloaded([usize; 2]),
+    Finished,
+    Failed(BsError),
+    Aborted,
+    NotRunning,
+    Invalid(String),
+}
+
+impl<T: BoxInt> Event<T> {
+    pub fn describe(&self) -> String {
+        match self {
+            Event::Breakpoint(line) => format!("breakpoint at line {}", line + 1),
+            Event::Deleted(line) => format!("deleted breakpoint at line {}", line + 1),
+            Event::BoxBreakpoint([row, col]) => {
+                format!("breakpoint at box {}:{}", row + 1, col + 1)
+            }
+            Event::BoxDeleted([row, col]) => {
+                format!("deleted breakpoint at box {}:{}", row + 1, col + 1)
+            }
+            Event::Stopped([row, col], source) => {
+                format!("stopped at {}:{}: {}", row + 1, col + 1, source)
+            }
+            Event::Output(text) => format!("output: {:?}", text),
+            Event::Value(address, value) => format!("memory[{}] = {}", address, value),
+            Event::Memory(cells) if cells.is_empty() => "memory is empty".to_string(),
+            Event::Memory(cells) => cells
+                .iter()
+                .map(|(address, value)| format!("memory[{}] = {}", address, value))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Event::Watch(expr, Ok(value)) => format!("watch {} = {}", expr, value),
+            Event::Watch(expr, Err(error)) => format!("watch {}: error: {}", expr, error),
+            Event::Unwatched(expr) => format!("removed watch {}", expr),
+            Event::Reloaded([row, col]) => format!("reloaded box {}:{}", row + 1, col + 1),
+            Event::Finished => "program finished".to_string(),
+            Event::Failed(error) => format!("error: {}", error),
+            Event::Aborted => "program stopped".to_string(),
+            Event::NotRunning => "program is not running".to_string(),
+            Event::Invalid(message) => message.clone(),
+        }
+    }
+}
+
+enum Resume {
+    Continue,
+    Step,
+    Quit,
+}
+
+pub struct Debugger<'a, T: BoxInt> {
+    commands: &'a mut dyn Iterator<Item = Result<Command<T>, String>>,
+    emit: &'a mut dyn FnMut(Event<T>),
+    breakpoints: Vec<usize>,
+    box_breakpoints: Vec<[usize; 2]>,
+    boxes: Vec<Box>,
+    watches: Vec<(String, Molecule<T>)>,
+    arith_mode: ArithMode,
+    dialect: Dialect,
+    silent_assign: bool,
+    include_dir: PathBuf,
+    path: Option<PathBuf>,
+    reloads: Vec<Block<T>>,
+    stepping: bool,
+    reported: usize,
+    position: Option<([usize; 2], String)>,
+}
+
+impl<'a, T: BoxInt> Debugger<'a, T> {
+    pub fn new(
+        commands: &'a mut dyn Iterator<Item = Result<Command<T>, String>>,
+        emit: &'a mut dyn FnMut(Event<T>),
+    ) -> Debugger<'a, T> {
+        Debugger {
+            commands,
+            emit,
+            breakpoints: Vec::new(),
+            box_breakpoints: Vec::new(),
+            boxes: Vec::new(),
+            watches: Vec::new(),
+            arith_mode: ArithMode::Checked,
+            dialect: Dialect::STABLE,
+            silent_assign: false,
+            include_dir: PathBuf::from("."),
+            path: None,
+            reloads: Vec::new(),
+            stepping: false,
+            reported: 0,
+            position: None,
+        }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = Some(path);
+    }
+
+    fn prompt(&mut self, memory: &mut dyn Memory<T>) -> Resume {
+        while let Some(command) = self.commands.next() {
+            let command = match command {
+                Ok(command) => command,
+                Err(message) => {
+                    (self.emit)(Event::Invalid(message));
+                    continue;
+                }
+            };
+
+            match command {
+                Command::Break(line) => {
+                    if !self.breakpoints.contains(&line) {
+                        self.breakpoints.push(line);
+                    }
+                    (self.emit)(Event::Breakpoint(line));
+                }
+                Command::Delete(line) => {
+                    self.breakpoints.retain(|breakpoint| *breakpoint != line);
+                    (self.emit)(Event::Deleted(line));
+                }
+                Command::BreakBox(at) => {
+                    if !self.boxes.iter().any(|b| b.top_left == at) {
+                        (self.emit)(Event::Invalid(format!(
+                            "no box at {}:{}",
+                            at[0] + 1,
+                            at[1] + 1
+                        )));
+                    } else {
+                        if !self.box_breakpoints.contains(&at) {
+                            self.box_breakpoints.push(at);
+                        }
+                        (self.emit)(Event::BoxBreakpoint(at));
+                    }
+                }
+                Command::DeleteBox(at) => {
+                    self.box_breakpoints.retain(|breakpoint| *breakpoint != at);
+                    (self.emit)(Event::BoxDeleted(at));
+                }
+                Command::Continue => return Resume::Continue,
+                Command::Step => return Resume::Step,
+                Command::Print(address) => {
+                    let value = memory.get(address);
+                    (self.emit)(Event::Value(address, value));
+                }
+                Command::Set(address, value) => {
+                    memory.set(address, value);
+                    (self.emit)(Event::Value(address, value));
+                }
+                Command::Memory => {
+                    (self.emit)(Event::Memory(memory.cells()));
+                }
+                Command::Where => match &self.position {
+                    Some((at, source)) => (self.emit)(Event::Stopped(*at, source.clone())),
+                    None => (self.emit)(Event::NotRunning),
+                },
+                Command::Watch(expr) => {
+                    let (watches, arith_mode) = (&mut self.watches, self.arith_mode);
+                    let value = Molecule::read(&expr, [0, 0]).and_then(|mut molecule| {
+                        molecule.set_arith_mode(arith_mode);
+                        let value = molecule.peek(memory);
+                        if let Ok(_) | Err(BsError::RuntimeError(..)) = value {
+                            watches.push((expr.clone(), molecule));
+                        }
+                        value
+                    });
+                    (self.emit)(Event::Watch(expr, value));
+                }
+                Command::Unwatch(expr) => {
+                    self.watches.retain(|(watched, _)| *watched != expr);
+                    (self.emit)(Event::Unwatched(expr));
+                }
+                Command::Reload(at) => match self.reload(at) {
+                    Ok(block) => {
+                        self.reloads.push(block);
+                        (self.emit)(Event::Reloaded(at));
+                    }
+                    Err(message) => (self.emit)(Event::Invalid(message)),
+                },
+                Command::Quit => return Resume::Quit,
+            }
+        }
+
+        Resume::Quit
+    }
+
+    fn reload(&self, at: [usize; 2]) -> Result<Block<T>, String> {
+        let name = format!("box {}:{}", at[0] + 1, at[1] + 1);
+        let (stopped, _) = self.position.as_ref().ok_or("program is not running")?;
+        let path = self.path.as_ref().ok_or("no file to reload")?;
+        let r#box = self
+            .boxes
+            .iter()
+            .find(|b| b.top_left == at)
+            .ok_or_else(|| format!("no box at {}:{}", at[0] + 1, at[1] + 1))?;
+
+        let roots: Vec<&Box> = self
+            .boxes
+            .iter()
+            .filter(|b| b.genus != Genus::Function && !self.boxes.iter().any(|o| o.contains(b)))
+            .collect();
+        let batches = scheduler::batches(roots.iter().cloned());
+        let batch = |root: Option<usize>| {
+            root.and_then(|root| batches.iter().position(|batch| batch.contains(&root)))
+        };
+        let target = batch(roots.iter().position(|b| *b == r#box || b.contains(r#box)));
+        let current = batch(roots.iter().position(|b| b.covers(stopped)));
+        match (target, current) {
+            (None, _) => return Err(format!("{} is in a function", name)),
+            (Some(target), Some(current)) if target > current => {}
+            _ => return Err(format!("{} has already started", name)),
+        }
+
+        let failed = |error: BsError| format!("cannot reload {}: {}", name, error);
+        let code = fs::read_to_string(path).map_err(|error| failed(error.into()))?;
+        let boxes = Box::find_all(&code).map_err(failed)?;
+        if !boxes.contains(r#box) {
+            return Err(format!("{} changed shape", name));
+        }
+
+        let mut blocks = vec![Block::new(&code, r#box, &boxes).map_err(failed)?];
+        include::resolve(&mut blocks, &self.include_dir).map_err(failed)?;
+        for block in Block::walk(&blocks) {
+            for molecule in block.expressions() {
+                self.dialect.check(molecule).map_err(failed)?;
+            }
+        }
+
+        let mut block = blocks.remove(0);
+        block.set_arith_mode(self.arith_mode);
+        if self.silent_assign {
+            block.silence_assignments();
+        }
+        Ok(block)
+    }
+
+    fn watch(&mut self, memory: &dyn Memory<T>) {
+        for (expr, molecule) in &mut self.watches {
+            (self.emit)(Event::Watch(expr.clone(), molecule.peek(memory)));
+        }
+    }
+
+    fn report(&mut self, stdout: &str) {
+        if stdout.len() > self.reported {
+            (self.emit)(Event::Output(stdout[self.reported..].to_string()));
+            self.reported = stdout.len();
+        }
+    }
+
+    fn innermost(&self, at: [usize; 2]) -> Option<[usize; 2]> {
+        self.boxes
+            .iter()
+            .filter(|b| b.covers(&at))
+            .min_by_key(|b| {
+                (b.bottom_right[0] - b.top_left[0]) * (b.bottom_right[1] - b.top_left[1])
+            })
+            .map(|b| b.top_left)
+    }
+
+    fn pause(&mut self, stop: Stop<T>) -> Result<(), BsError> {
+        if !self.stepping
+            && !self.breakpoints.contains(&stop.at[0])
+            && !self
+                .innermost(stop.at)
+                .is_some_and(|corner| self.box_breakpoints.contains(&corner))
+        {
+            return Ok(());
+        }
+
+        self.report(stop.stdout);
+        let source = stop.molecule.source();
+        (self.emit)(Event::Stopped(stop.at, source.clone()));
+        self.watch(stop.memory);
+        self.position = Some((stop.at, source));
+
+        let resume = self.prompt(stop.memory);
+        stop.reloads.append(&mut self.reloads);
+        match resume {
+            Resume::Continue => self.stepping = false,
+            Resume::Step => self.stepping = true,
+            Resume::Quit => {
+                return Err(BsError::RuntimeError(
+                    "Stopped by debugger".to_string(),
+                    Some(stop.at),
+                ))
+            }
+        }
+
+        self.position = None;
+        Ok(())
+    }
+
+    pub fn run(&mut self, interpreter: &mut Interpreter<T>, source: &str) {
+        self.arith_mode = interpreter.arith_mode();
+        self.dialect = interpreter.dialect();
+        self.silent_assign = interpreter.silent_assign();
+        self.include_dir = interpreter.include_dir().to_path_buf();
+        self.boxes = Box::find_all(source).unwrap_or_default();
+        match self.prompt(interpreter.memory_mut()) {
+            Resume::Continue => self.stepping = false,
+            Resume::Step => self.stepping = true,
+            Resume::Quit => return,
+        }
+
+        let mut quit = false;
+        let result = interpreter.run_program_with(source, &mut |stop| {
+            let result = self.pause(stop);
+            quit = result.is_err();
+            result
+        });
+
+        match result {
+            Ok(stdout) => {
+                self.report(&stdout);
+                (self.emit)(Event::Finished);
+            }
+            Err(_) if quit => (self.emit)(Event::Aborted),
+            Err(error) => (self.emit)(Event::Failed(error)),
+        }
+
+        loop {
+            match self.prompt(interpreter.memory_mut()) {
+                Resume::Quit => return,
+                _ => (self.emit)(Event::NotRunning),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn debug(code: &str, script: &str) -> Vec<String> {
+        let mut events: Vec<String> = Vec::new();
+        let mut commands = script
+            .lines()
+            .filter_map(|line| Command::parse(line).transpose());
+        let mut emit = |event: Event<i8>| events.push(event.describe());
+
+        Debugger::new(&mut commands, &mut emit).run(&mut Interpreter::new(), code);
+
+        events
+    }
+
+    #[test]
+    fn it_parses_commands() {
+        assert_eq!(Command::<i8>::parse("break 3"), Ok(Some(Command::Break(2))));
+        assert_eq!(
+            Command::<i8>::parse("set -1 5"),
+            Ok(Some(Command::Set(-1, 5)))
+        );
+        assert_eq!(Command::<i8>::parse("  # note"), Ok(None));
+        assert_eq!(
+            Command::<i8>::parse("break 0"),
+            Err("invalid line: 0".to_string())
+        );
+        assert_eq!(
+            Command::<i8>::parse("jump"),
+            Err("unknown command: jump".to_string())
+        );
+    }
+
+    #[test]
+    fn it_stops_at_breakpoints() {
+        let code = "╔═◇▀▨▀▀▀═╗\n║▀◈◇▀▐▀▀ ║\n║▭◇▀▐▀▀▀▄║\n╚════════╝";
+
+        assert_eq!(
+            debug(
+                code,
+                "break 3\nrun\nprint 0\ncontinue\ndelete 3\nset 0 9\ncontinue\nmemory"
+            ),
+            vec![
+                "breakpoint at line 3",
+                "stopped at 3:2: ▭◇▀▐▀▀▀▄",
+                "memory[0] = 1",
+                "output: \"\\u{7}\"",
+                "stopped at 3:2: ▭◇▀▐▀▀▀▄",
+                "deleted breakpoint at line 3",
+                "memory[0] = 9",
+                "output: \"\\u{f}\"",
+                "program finished",
+                "memory[0] = 9",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_stops_in_boxes() {
+        let code = "┌─────────┐\n│▀◈▀▀     │\n│┏━◇▀━━━┓ │\n│┃▀◈▀▀▄ ┃ │\n│┗━━━━━━┛ │\n│▭◇▀▐▀▀▀▄ │\n└─────────┘";
+
+        assert_eq!(
+            debug(
+                code,
+                "break 3:2\nbreak 2:2\nrun\nmemory\ncontinue\ndelete 3:2\nbreak 0:1\nabort"
+            ),
+            vec![
+                "breakpoint at box 3:2",
+                "no box at 2:2",
+                "stopped at 3:4: ◇▀",
+                "memory[0] = 1",
+                "stopped at 4:3: ▀◈▀▀▄",
+                "deleted breakpoint at box 3:2",
+                "invalid box: 0:1",
+                "program stopped",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_evaluates_watches() {
+        let code = "╔═◇▀▨▀▀▀═╗\n║▀◈◇▀▐▀▀ ║\n╚════════╝";
+
+        assert_eq!(
+            debug(
+                code,
+                "watch ◇▀▐▀▀▄\nbreak 2\nrun\nwatch ▀◈▀▀▀\nwatch ◇▀▝◇▀▀\nwatch ▀x\nprint 0\ncontinue\nunwatch ◇▀▐▀▀▄\ncontinue"
+            ),
+            vec![
+                "watch ◇▀▐▀▀▄ = 2",
+                "breakpoint at line 2",
+                "stopped at 2:2: ▀◈◇▀▐▀▀",
+                "watch ◇▀▐▀▀▄ = 2",
+                "watch ▀◈▀▀▀: error: Assignment is not allowed in a pure expression at 1:2",
+                "watch ◇▀▝◇▀▀: error: Division caused invalid value at 1:3",
+                "watch ▀x: error: Invalid character at 1:2",
+                "memory[0] = 0",
+                "stopped at 2:2: ▀◈◇▀▐▀▀",
+                "watch ◇▀▐▀▀▄ = 3",
+                "watch ◇▀▝◇▀▀: error: Division caused invalid value at 1:3",
+                "removed watch ◇▀▐▀▀▄",
+                "stopped at 2:2: ▀◈◇▀▐▀▀",
+                "watch ◇▀▝◇▀▀: error: Division caused invalid value at 1:3",
+                "program stopped",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reloads_boxes() {
+        let code = "┌────┐\n│▀◈▀▀│\n└────┘\n┌────┐\n│▭◇▀ │\n└────┘";
+        let path = std::env::temp_dir().join(format!("boxscript-reload-{}.bs", std::process::id()));
+        fs::write(&path, code.replace("▭◇▀ ", "▭▀▀▄")).unwrap();
+
+        let mut events: Vec<String> = Vec::new();
+        let mut commands = "reload 4:1\nbreak 2\nrun\nreload 1:1\nreload 2:2\nreload 4:1\ncontinue"
+            .lines()
+            .filter_map(|line| Command::parse(line).transpose());
+        let mut emit = |event: Event<i8>| events.push(event.describe());
+        let mut debugger = Debugger::new(&mut commands, &mut emit);
+        debugger.set_path(path.clone());
+        debugger.run(&mut Interpreter::new(), code);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            events,
+            vec![
+                "program is not running",
+                "breakpoint at line 2",
+                "stopped at 2:2: ▀◈▀▀",
+                "box 1:1 has already started",
+                "no box at 2:2",
+                "reloaded box 4:1",
+                "output: \"\\u{2}\"",
+                "program finished",
+            ]
+        );
+    }
+
+    #[test]
+    fn it_steps_and_quits() {
+        let code = "┌────┐\n│▀◈▀▀│\n│▭◇▀ │\n└────┘";
+
+        assert_eq!(
+            debug(code, "step\nwhere\nstep\nquit\ncontinue"),
+            vec![
+                "stopped at 2:2: ▀◈▀▀",
+                "stopped at 2:2: ▀◈▀▀",
+                "stopped at 3:2: ▭◇▀",
+                "program stopped",
+                "program is not running"
+            ]
+        );
+        assert_eq!(
+            debug(code, "jump\nrun\nstep"),
+            vec![
+                "unknown command: jump",
+                "output: \"\\u{1}\"",
+                "program finished",
+                "program is not running"
+            ]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+pub use super::error::json;
+use super::error::BsError;
+use super::render::Render;
+use ansi_term::Colour::{Blue, Red};
+use ansi_term::Style;
+
+pub fn report(
+    error: &BsError,
+    source: &str,
+    filename: &str,
+    render: Render,
+    colour: bool,
+) -> String {
+    let paint = |style: Style, text: &str| {
+        if colour {
+            style.paint(text).to_string()
+        } else {
+            text.to_string()
+        }
+    };
+    let mut lines = vec![format!(
+        "{} {}",
+        paint(Red.bold(), "error:"),
+        paint(Style::new().bold(), &render.apply(error.message()))
+    )];
+
+    let [row, col] = match error.location() {
+        Some(location) => location,
+        None => return lines.join("\n") + "\n",
+    };
+    let line = match source.lines().nth(row) {
+        Some(line) if col < line.chars().count() => line,
+        _ => {
+            lines.push(format!("  at {}:{}:{}", filename, row + 1, col + 1));
+            return lines.join("\n") + "\n";
+        }
+    };
+
+    let number = (row + 1).to_string();
+    let gutter = " ".repeat(number.len());
+
+    lines.push(format!(
+        "{}{} {}:{}:{}",
+        gutter,
+        paint(Blue.bold(), "-->"),
+        filename,
+        row + 1,
+        col + 1
+    ));
+    lines.push(format!("{} {}", gutter, paint(Blue.bold(), "|")));
+    lines.push(format!(
+        "{} {}",
+        paint(Blue.bold(), &format!("{} |", number)),
+        render.apply(line.trim_end())
+    ));
+    lines.push(format!(
+        "{} {} {}{}",
+        gutter,
+        paint(Blue.bold(), "|"),
+        " ".repeat(col),
+        paint(Red.bold(), "^")
+    ));
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_points_at_errors() {
+        let source = "╔═◇▀▨▀x═╗\n║▀◈▀▀   ║\n╚═══════╝";
+        let error = BsError::ParseError("Invalid character".to_string(), Some([0, 6]));
+
+        assert_eq!(
+            report(&error, source, "main.bs", Render::Unicode, false),
+            [
+                "error: Invalid character",
+                " --> main.bs:1:7",
+                "  |",
+                "1 | ╔═◇▀▨▀x═╗",
+                "  |       ^",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            report(&error, source, "main.bs", Render::Ascii, false)
+                .lines()
+                .nth(3),
+            Some("1 | +=@1<1x=+")
+        );
+    }
+
+    #[test]
+    fn it_reports_errors_without_snippets() {
+        let error = BsError::ValidationError("Malformed expression".to_string(), None);
+        assert_eq!(
+            report(&error, "", "main.bs", Render::Unicode, false),
+            "error: Malformed expression\n"
+        );
+
+        let error =
+            BsError::RuntimeError("Division caused invalid value".to_string(), Some([4, 2]));
+        assert_eq!(
+            report(&error, "┌─┐\n└─┘", "main.bs", Render::Unicode, false),
+            "error: Division caused invalid value\n  at main.bs:5:3\n"
+        );
+    }
+}
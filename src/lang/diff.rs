@@ -0,0 +1,252 @@
+use super::error::BsError;
+use super::r#box::{Box, Genus};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(Box),
+    Removed(Box),
+    Moved(Box, Box),
+    Resized(Box, Box),
+    Guard(Box, Option<String>, Option<String>),
+    Line(Box, usize, Option<String>, Option<String>),
+}
+
+impl Change {
+    pub fn describe(&self) -> String {
+        let show = |text: &Option<String>| text.clone().unwrap_or_else(|| "nothing".to_string());
+
+        match self {
+            Change::Added(new) => format!("added {}", name(new)),
+            Change::Removed(old) => format!("removed {}", name(old)),
+            Change::Moved(old, new) => format!(
+                "moved {} to {}:{}",
+                name(old),
+                new.top_left[0] + 1,
+                new.top_left[1] + 1
+            ),
+            Change::Resized(old, new) => {
+                format!("resized {} from {} to {}", name(old), size(old), size(new))
+            }
+            Change::Guard(new, old_guard, new_guard) => format!(
+                "changed condition of {}: {} -> {}",
+                name(new),
+                show(old_guard),
+                show(new_guard)
+            ),
+            Change::Line(new, line, old_line, new_line) => format!(
+                "changed line {} of {}: {} -> {}",
+                line + 1,
+                name(new),
+                show(old_line),
+                show(new_line)
+            ),
+        }
+    }
+}
+
+fn name(r#box: &Box) -> String {
+    format!(
+        "{:?} box at {}:{}",
+        r#box.genus,
+        r#box.top_left[0] + 1,
+        r#box.top_left[1] + 1
+    )
+}
+
+fn size(r#box: &Box) -> String {
+    format!(
+        "{}x{}",
+        r#box.bottom_right[0] - r#box.top_left[0] + 1,
+        r#box.bottom_right[1] - r#box.top_left[1] + 1
+    )
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Summary {
+    r#box: Box,
+    guard: Option<String>,
+    lines: Vec<String>,
+}
+
+impl Summary {
+    fn all(code: &str) -> Result<Vec<Summary>, BsError> {
+        let boxes = Box::find_all(code)?;
+
+        Ok(boxes
+            .iter()
+            .map(|r#box| {
+                let mut lines: Vec<String> = r#box
+                    .content(code, &boxes)
+                    .iter()
+                    .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+                    .filter(|line: &String| !line.is_empty())
+                    .collect();
+                let mut guard = r#box
+                    .header(code)
+                    .map(|header| header.chars().filter(|c| !c.is_whitespace()).collect());
+
+                if guard.is_none()
+                    && matches!(
+                        r#box.genus,
+                        Genus::Loop | Genus::Condition | Genus::Function
+                    )
+                    && !lines.is_empty()
+                {
+                    guard = Some(lines.remove(0));
+                }
+
+                Summary {
+                    r#box: r#box.clone(),
+                    guard,
+                    lines,
+                }
+            })
+            .collect())
+    }
+
+    fn same_code(&self, other: &Summary) -> bool {
+        self.r#box.genus == other.r#box.genus
+            && self.guard == other.guard
+            && self.lines == other.lines
+    }
+
+    fn same_size(&self, other: &Summary) -> bool {
+        size(&self.r#box) == size(&other.r#box)
+    }
+}
+
+pub fn diff(old: &str, new: &str) -> Result<Vec<Change>, BsError> {
+    let old = Summary::all(old)?;
+    let new = Summary::all(new)?;
+    let mut pairs: Vec<(usize, usize)> = Vec::new();
+    let mut old_used = vec![false; old.len()];
+    let mut new_used = vec![false; new.len()];
+
+    let passes: [fn(&Summary, &Summary) -> bool; 3] = [
+        |a, b| a.same_code(b) && a.r#box.top_left == b.r#box.top_left,
+        |a, b| a.same_code(b),
+        |a, b| a.r#box.genus == b.r#box.genus && a.r#box.top_left == b.r#box.top_left,
+    ];
+
+    for pass in passes.iter() {
+        for (i, a) in old.iter().enumerate() {
+            if old_used[i] {
+                continue;
+            }
+
+            if let Some(j) = (0..new.len()).find(|j| !new_used[*j] && pass(a, &new[*j])) {
+                old_used[i] = true;
+                new_used[j] = true;
+                pairs.push((i, j));
+            }
+        }
+    }
+
+    let mut changes: Vec<Change> = Vec::new();
+
+    for (i, a) in old.iter().enumerate() {
+        if !old_used[i] {
+            changes.push(Change::Removed(a.r#box.clone()));
+        }
+    }
+
+    pairs.sort_by_key(|(_, j)| new[*j].r#box.top_left);
+
+    for (i, j) in pairs {
+        let (a, b) = (&old[i], &new[j]);
+
+        if a.r#box.top_left != b.r#box.top_left {
+            changes.push(Change::Moved(a.r#box.clone(), b.r#box.clone()));
+        }
+        if !a.same_size(b) {
+            changes.push(Change::Resized(a.r#box.clone(), b.r#box.clone()));
+        }
+        if a.guard != b.guard {
+            changes.push(Change::Guard(
+                b.r#box.clone(),
+                a.guard.clone(),
+                b.guard.clone(),
+            ));
+        }
+        for line in 0..a.lines.len().max(b.lines.len()) {
+            let (before, after) = (a.lines.get(line), b.lines.get(line));
+            if before != after {
+                changes.push(Change::Line(
+                    b.r#box.clone(),
+                    line,
+                    before.cloned(),
+                    after.cloned(),
+                ));
+            }
+        }
+    }
+
+    for (j, b) in new.iter().enumerate() {
+        if !new_used[j] {
+            changes.push(Change::Added(b.r#box.clone()));
+        }
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn describe(old: &str, new: &str) -> Vec<String> {
+        diff(old, new)
+            .unwrap()
+            .iter()
+            .map(|change| change.describe())
+            .collect()
+    }
+
+    #[test]
+    fn it_finds_no_changes() {
+        let code = "┌────┐\n│▀◈▀▀│\n└────┘";
+        assert_eq!(describe(code, code), Vec::<String>::new());
+        assert_eq!(
+            describe(code, "┌─────┐\n│▀◈ ▀▀│\n└─────┘"),
+            vec!["resized Execution box at 1:1 from 3x6 to 3x7"]
+        );
+    }
+
+    #[test]
+    fn it_finds_moved_and_resized_boxes() {
+        assert_eq!(
+            describe(
+                "┌────┐\n│▀◈▀▀│\n└────┘",
+                "\n  ┌──────┐\n  │▀◈▀▀  │\n  └──────┘"
+            ),
+            vec![
+                "moved Execution box at 1:1 to 2:3",
+                "resized Execution box at 1:1 from 3x6 to 3x8"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_finds_changed_code() {
+        assert_eq!(
+            describe(
+                "╔═◇▀▨▀▀═╗\n║▭◇▀    ║\n╚═══════╝",
+                "╔═◇▀▨▀▀▀╗\n║▭◇▀▐▀▀ ║\n║▭▀▀▀▀▀ ║\n╚═══════╝"
+            ),
+            vec![
+                "resized Loop box at 1:1 from 3x9 to 4x9",
+                "changed condition of Loop box at 1:1: ◇▀▨▀▀ -> ◇▀▨▀▀▀",
+                "changed line 1 of Loop box at 1:1: ▭◇▀ -> ▭◇▀▐▀▀",
+                "changed line 2 of Loop box at 1:1: nothing -> ▭▀▀▀▀▀"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_finds_added_and_removed_boxes() {
+        assert_eq!(
+            describe("┌──┐\n│▀ │\n└──┘", "┏━━┓\n┃▀ ┃\n┗━━┛"),
+            vec!["removed Execution box at 1:1", "added Condition box at 1:1"]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use super::analysis::{self, Cells};
+use super::block::Block;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::r#box::{Box, Genus};
+use std::collections::BTreeSet;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry<T: BoxInt> {
+    pub r#box: Box,
+    pub depth: usize,
+    pub comment: Vec<String>,
+    pub guard: Option<String>,
+    pub include: Option<String>,
+    pub cells: Cells<T>,
+}
+
+impl<T: BoxInt> Entry<T> {
+    fn title(&self) -> String {
+        let name = self.r#box.genus.name();
+        format!(
+            "{}{} box at {}:{}",
+            name[..1].to_uppercase(),
+            &name[1..],
+            self.r#box.top_left[0] + 1,
+            self.r#box.top_left[1] + 1
+        )
+    }
+
+    fn paragraphs(&self) -> Vec<String> {
+        self.comment
+            .split(|line| line.is_empty())
+            .filter(|lines| !lines.is_empty())
+            .map(|lines| lines.join("\n"))
+            .collect()
+    }
+}
+
+fn comment(source: &str, r#box: &Box, boxes: &[Box]) -> Vec<String> {
+    let mut lines: Vec<String> = r#box.header(source).into_iter().collect();
+    lines.extend(
+        r#box
+            .content(source, boxes)
+            .iter()
+            .map(|line| line.trim().to_string()),
+    );
+
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let start = lines.iter().position(|line| !line.is_empty()).unwrap_or(0);
+    lines.split_off(start)
+}
+
+fn collect<T: BoxInt>(
+    source: &str,
+    boxes: &[Box],
+    blocks: &[Block<T>],
+    depth: usize,
+    entries: &mut Vec<Entry<T>>,
+) {
+    let mut pending: Vec<String> = Vec::new();
+
+    for block in blocks {
+        if block.r#box.genus == Genus::NoOp {
+            if !pending.is_empty() {
+                pending.push(String::new());
+            }
+            pending.extend(comment(source, &block.r#box, boxes));
+            continue;
+        }
+
+        entries.push(Entry {
+            r#box: block.r#box.clone(),
+            depth,
+            comment: std::mem::take(&mut pending),
+            guard: block.guard.as_ref().map(|guard| guard.source()),
+            include: block.include.as_ref().map(|include| include.header()),
+            cells: analysis::cells(block),
+        });
+        collect(source, boxes, &block.children, depth + 1, entries);
+    }
+}
+
+pub fn entries<T: BoxInt>(source: &str) -> Result<Vec<Entry<T>>, BsError> {
+    let boxes = Box::find_all(source)?;
+    let blocks = Block::<T>::build(source)?;
+    let mut entries: Vec<Entry<T>> = Vec::new();
+    collect(source, &boxes, &blocks, 0, &mut entries);
+
+    Ok(entries)
+}
+
+fn addresses<T: BoxInt>(cells: &BTreeSet<T>, any: bool) -> String {
+    let mut parts: Vec<String> = cells.iter().map(|cell| cell.to_string()).collect();
+    if any {
+        parts.push("any cell".to_string());
+    }
+
+    if parts.is_empty() {
+        "none".to_string()
+    } else {
+        parts.join(", ")
+    }
+}
+
+fn facts<T: BoxInt>(entry: &Entry<T>) -> Vec<(&'static str, String, bool)> {
+    let mut facts: Vec<(&'static str, String, bool)> = Vec::new();
+    if let Some(guard) = &entry.guard {
+        let label = match entry.r#box.genus {
+            Genus::Function => "Number",
+            _ => "Guard",
+        };
+        facts.push((label, guard.clone(), true));
+    }
+    if let Some(include) = &entry.include {
+        facts.push(("Includes", include.clone(), true));
+    }
+    facts.push((
+        "Reads",
+        addresses(&entry.cells.reads, entry.cells.reads_any),
+        false,
+    ));
+    facts.push((
+        "Writes",
+        addresses(&entry.cells.writes, entry.cells.writes_any),
+        false,
+    ));
+
+    facts
+}
+
+pub fn markdown<T: BoxInt>(title: &str, entries: &[Entry<T>]) -> String {
+    let mut text = format!("# {}\n", title);
+
+    for entry in entries {
+        text += &format!(
+            "\n{} {}\n\n",
+            "#".repeat((entry.depth + 2).min(6)),
+            entry.title()
+        );
+        for paragraph in entry.paragraphs() {
+            text += &format!("{}\n\n", paragraph);
+        }
+        for (label, value, code) in facts(entry) {
+            if code {
+                text += &format!("- {}: `{}`\n", label, value);
+            } else {
+                text += &format!("- {}: {}\n", label, value);
+            }
+        }
+    }
+
+    text
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn html<T: BoxInt>(title: &str, entries: &[Entry<T>]) -> String {
+    let mut text = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n",
+        escape(title)
+    );
+
+    for entry in entries {
+        let level = (entry.depth + 2).min(6);
+        text += &format!(
+            "<section id=\"box-{}-{}\" class=\"{}\">\n<h{3}>{4}</h{3}>\n",
+            entry.r#box.top_left[0] + 1,
+            entry.r#box.top_left[1] + 1,
+            entry.r#box.genus.name(),
+            level,
+            entry.title()
+        );
+        for paragraph in entry.paragraphs() {
+            text += &format!("<p>{}</p>\n", escape(&paragraph));
+        }
+
+        text += "<dl>\n";
+        for (label, value, code) in facts(entry) {
+            if code {
+                text += &format!(
+                    "<dt>{}</dt><dd><code>{}</code></dd>\n",
+                    label,
+                    escape(&value)
+                );
+            } else {
+                text += &format!("<dt>{}</dt><dd>{}</dd>\n", label, escape(&value));
+            }
+        }
+        text += "</dl>\n</section>\n";
+    }
+
+    text + "</body>\n</html>\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "╭┄┄┄┄┄┄┄┄┄┄┄┄┄╮\n┆Counts to two┆\n┆             ┆\n┆Uses cell 0  ┆\n╰┄┄┄┄┄┄┄┄┄┄┄┄┄╯\n╔═◇▀▨▀▀▄══════╗\n║▀◈◇▀▐▀▀      ║\n║╭┄┄┄┄┄┄┄┄╮   ║\n║┆Print it┆   ║\n║╰┄┄┄┄┄┄┄┄╯   ║\n║┌──────┐     ║\n║│▭◇▀   │     ║\n║└──────┘     ║\n╚═════════════╝";
+
+    #[test]
+    fn it_attaches_comments() {
+        let entries = entries::<i8>(PROGRAM).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].r#box.top_left, [5, 0]);
+        assert_eq!(entries[0].depth, 0);
+        assert_eq!(entries[0].comment, vec!["Counts to two", "", "Uses cell 0"]);
+        assert_eq!(entries[0].guard, Some("◇▀▨▀▀▄".to_string()));
+        assert_eq!(entries[1].r#box.top_left, [10, 1]);
+        assert_eq!(entries[1].depth, 1);
+        assert_eq!(entries[1].comment, vec!["Print it"]);
+        assert_eq!(entries[1].cells.reads, [0].iter().cloned().collect());
+    }
+
+    #[test]
+    fn it_writes_markdown() {
+        assert_eq!(
+            markdown("count.bs", &entries::<i8>(PROGRAM).unwrap()),
+            "# count.bs\n\n## Loop box at 6:1\n\nCounts to two\n\nUses cell 0\n\n- Guard: `◇▀▨▀▀▄`\n- Reads: 0\n- Writes: 0\n\n### Execution box at 11:2\n\nPrint it\n\n- Reads: 0\n- Writes: none\n"
+        );
+    }
+
+    #[test]
+    fn it_writes_html() {
+        let html = html("<count>", &entries::<i8>(PROGRAM).unwrap());
+
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.contains("<title>&lt;count&gt;</title>"));
+        assert!(html.contains(
+            "<section id=\"box-11-2\" class=\"execution\">\n<h3>Execution box at 11:2</h3>\n<p>Print it</p>\n<dl>\n<dt>Reads</dt><dd>0</dd>\n<dt>Writes</dt><dd>none</dd>\n</dl>\n</section>\n"
+        ));
+        assert!(html.ends_with("</body>\n</html>\n"));
+    }
+}
@@ -0,0 +1,147 @@
+use super::block::{Block, Node};
+use super::datatype::BoxInt;
+#[cfg(feature = "json")]
+use super::expression::Atom;
+use super::expression::Molecule;
+#[cfg(feature = "json")]
+use super::mi::{location, number};
+#[cfg(feature = "json")]
+use serde_json::Value;
+
+fn start<T: BoxInt>(molecule: &Molecule<T>, at: [usize; 2]) -> [usize; 2] {
+    molecule.positions().first().cloned().unwrap_or(at)
+}
+
+fn line<T: BoxInt>(label: &str, molecule: &Molecule<T>, at: [usize; 2]) -> String {
+    let at = start(molecule, at);
+    let sources: Vec<String> = molecule.atoms().iter().map(|atom| atom.source()).collect();
+    let names: Vec<String> = molecule
+        .atoms()
+        .iter()
+        .map(|atom| format!("{:?}", atom))
+        .collect();
+
+    format!(
+        "{} {}:{}  {}  [{}]",
+        label,
+        at[0] + 1,
+        at[1] + 1,
+        sources.join(" "),
+        names.join(" ")
+    )
+}
+
+fn write<T: BoxInt>(block: &Block<T>, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(depth);
+    lines.push(format!("{}{}", indent, block.r#box));
+
+    if let Some(include) = &block.include {
+        lines.push(format!("{}  {}", indent, include.header()));
+    }
+    if let Some(guard) = &block.guard {
+        let at = [block.r#box.top_left[0], block.r#box.top_left[1] + 1];
+        lines.push(format!("{}  {}", indent, line("guard", guard, at)));
+    }
+
+    for node in block.schedule() {
+        match node {
+            Node::Molecule(i) => {
+                let (row, molecule) = &block.molecules[i];
+                let at = [*row, block.r#box.top_left[1] + 1];
+                lines.push(format!("{}  {}", indent, line("line", molecule, at)));
+            }
+            Node::Block(i) => write(&block.children[i], depth + 1, lines),
+        }
+    }
+}
+
+pub fn text<T: BoxInt>(blocks: &[Block<T>]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for block in blocks {
+        write(block, 0, &mut lines);
+    }
+
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+#[cfg(feature = "json")]
+fn molecule<T: BoxInt>(molecule: &Molecule<T>, at: [usize; 2]) -> Value {
+    json!({
+        "source": molecule.source(),
+        "location": location(start(molecule, at)),
+        "tokens": molecule
+            .atoms()
+            .iter()
+            .zip(molecule.positions())
+            .map(|(atom, at)| {
+                let mut token = json!({
+                    "atom": format!("{:?}", atom).split('(').next(),
+                    "source": atom.source(),
+                    "location": location(*at),
+                });
+                if let Atom::Data(num) = atom {
+                    token["value"] = number(*num);
+                }
+                token
+            })
+            .collect::<Vec<Value>>(),
+    })
+}
+
+#[cfg(feature = "json")]
+fn node<T: BoxInt>(block: &Block<T>) -> Value {
+    let guard_at = [block.r#box.top_left[0], block.r#box.top_left[1] + 1];
+
+    json!({
+        "genus": block.r#box.genus.name(),
+        "top_left": location(block.r#box.top_left),
+        "bottom_right": location(block.r#box.bottom_right),
+        "include": block.include.as_ref().map(|include| include.header()),
+        "guard": block.guard.as_ref().map(|guard| molecule(guard, guard_at)),
+        "body": block
+            .schedule()
+            .iter()
+            .map(|item| match item {
+                Node::Molecule(i) => {
+                    let (row, inner) = &block.molecules[*i];
+                    json!({ "molecule": molecule(inner, [*row, block.r#box.top_left[1] + 1]) })
+                }
+                Node::Block(i) => json!({ "box": node(&block.children[*i]) }),
+            })
+            .collect::<Vec<Value>>(),
+    })
+}
+
+#[cfg(feature = "json")]
+pub fn json<T: BoxInt>(blocks: &[Block<T>]) -> String {
+    json!({ "boxes": blocks.iter().map(node).collect::<Vec<Value>>() }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_dumps_text() {
+        let blocks = Block::<i8>::build(
+            "╔═◇▀▨▀▀▄═════╗\n║▀◈◇▀▐▀▀     ║\n║┏━◇▀▤▀▀┓    ║\n║┃▭▀▀▀▀ ┃    ║\n║┗━━━━━━┛    ║\n╚════════════╝",
+        )
+        .unwrap();
+
+        assert_eq!(
+            text(&blocks),
+            "loop box 1:1 to 6:14\n  guard 1:3  ◇ ▀ ▨ ▀▀▄  [Memory Data(0) Less Data(2)]\n  line 2:2  ▀ ◈ ◇ ▀ ▐ ▀▀  [Data(0) Assign Memory Data(0) Add Data(1)]\n  condition box 3:2 to 5:9\n    guard 3:4  ◇ ▀ ▤ ▀▀  [Memory Data(0) Equal Data(1)]\n    line 4:3  ▭ ▀▀▀▀  [Output Data(7)]\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn it_dumps_json() {
+        let blocks = Block::<i8>::build("┌────┐\n│▭▀▀ │\n└────┘").unwrap();
+
+        assert_eq!(
+            json(&blocks),
+            "{\"boxes\":[{\"body\":[{\"molecule\":{\"location\":{\"column\":2,\"line\":2},\"source\":\"▭▀▀\",\"tokens\":[{\"atom\":\"Output\",\"location\":{\"column\":2,\"line\":2},\"source\":\"▭\"},{\"atom\":\"Data\",\"location\":{\"column\":3,\"line\":2},\"source\":\"▀▀\",\"value\":1}]}}],\"bottom_right\":{\"column\":6,\"line\":3},\"genus\":\"execution\",\"guard\":null,\"include\":null,\"top_left\":{\"column\":1,\"line\":1}}]}"
+        );
+    }
+}
@@ -0,0 +1,59 @@
+use super::canon;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::Atom;
+use super::r#box::Genus;
+
+pub fn encode<T: BoxInt>(text: &str) -> Result<String, BsError> {
+    let lines = text
+        .chars()
+        .map(|c| {
+            let value = T::from_char(c).ok_or_else(|| {
+                BsError::ValidationError(format!("Character {:?} is out of range", c), None)
+            })?;
+            Ok(format!(
+                "{}{}",
+                Atom::<T>::Output.source(),
+                Atom::Data(value).source()
+            ))
+        })
+        .collect::<Result<Vec<String>, BsError>>()?;
+
+    Ok(canon::frame(Genus::Execution, &lines)
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::Interpreter;
+    use super::*;
+
+    #[test]
+    fn it_encodes_text() {
+        assert_eq!(
+            encode::<i64>("Hi"),
+            Ok("┌─────────┐\n│▭▀▀▄▄▀▄▄▄│\n│▭▀▀▀▄▀▄▄▀│\n└─────────┘\n".to_string())
+        );
+
+        for text in ["Hello, World!\n", "héllo ☃", ""] {
+            let program = encode::<i64>(text).unwrap();
+            assert_eq!(
+                Interpreter::<i64>::new().run_program(&program),
+                Ok(text.to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn it_rejects_characters_out_of_range() {
+        assert_eq!(
+            encode::<i8>("é"),
+            Err(BsError::ValidationError(
+                "Character 'é' is out of range".to_string(),
+                None
+            ))
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum BsError {
+    ParseError(String, Option<[usize; 2]>),
+    ValidationError(String, Option<[usize; 2]>),
+    RuntimeError(String, Option<[usize; 2]>),
+    IoError(String),
+}
+
+impl BsError {
+    pub fn message(&self) -> &str {
+        match self {
+            BsError::ParseError(message, _)
+            | BsError::ValidationError(message, _)
+            | BsError::RuntimeError(message, _)
+            | BsError::IoError(message) => message,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BsError::ParseError(..) => "parse",
+            BsError::ValidationError(..) => "validation",
+            BsError::RuntimeError(..) => "runtime",
+            BsError::IoError(_) => "io",
+        }
+    }
+
+    pub fn location(&self) -> Option<[usize; 2]> {
+        match self {
+            BsError::ParseError(_, location)
+            | BsError::ValidationError(_, location)
+            | BsError::RuntimeError(_, location) => *location,
+            BsError::IoError(_) => None,
+        }
+    }
+
+    pub fn at(self, location: [usize; 2]) -> BsError {
+        match self {
+            BsError::ParseError(message, None) => BsError::ParseError(message, Some(location)),
+            BsError::ValidationError(message, None) => {
+                BsError::ValidationError(message, Some(location))
+            }
+            BsError::RuntimeError(message, None) => BsError::RuntimeError(message, Some(location)),
+            other => other,
+        }
+    }
+}
+
+impl fmt::Display for BsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location() {
+            Some([row, col]) => write!(f, "{} at {}:{}", self.message(), row + 1, col + 1),
+            None => write!(f, "{}", self.message()),
+        }
+    }
+}
+
+impl Error for BsError {}
+
+impl From<io::Error> for BsError {
+    fn from(error: io::Error) -> BsError {
+        BsError::IoError(error.to_string())
+    }
+}
+
+#[cfg(feature = "json")]
+pub fn json(errors: &[BsError]) -> String {
+    json!({
+        "errors": errors
+            .iter()
+            .map(|error| json!({
+                "kind": error.kind(),
+                "message": error.message(),
+                "line": error.location().map(|[row, _]| row + 1),
+                "column": error.location().map(|[_, col]| col + 1),
+            }))
+            .collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn it_reports_errors_as_json() {
+        assert_eq!(
+            json(&[
+                BsError::ParseError("Invalid character".to_string(), Some([0, 6])),
+                BsError::IoError("lib.bs: not found".to_string()),
+            ]),
+            "{\"errors\":[{\"column\":7,\"kind\":\"parse\",\"line\":1,\"message\":\"Invalid character\"},{\"column\":null,\"kind\":\"io\",\"line\":null,\"message\":\"lib.bs: not found\"}]}"
+        );
+        assert_eq!(json(&[]), "{\"errors\":[]}");
+    }
+
+    #[test]
+    fn it_displays_locations() {
+        assert_eq!(
+            BsError::ParseError("Unclosed box".to_string(), Some([0, 4])).to_string(),
+            "Unclosed box at 1:5"
+        );
+        assert_eq!(
+            BsError::ValidationError("Malformed expression".to_string(), None).to_string(),
+            "Malformed expression"
+        );
+    }
+
+    #[test]
+    fn it_keeps_the_first_location() {
+        let error = BsError::RuntimeError("Division caused invalid value".to_string(), None);
+
+        assert_eq!(error.clone().at([1, 2]).location(), Some([1, 2]));
+        assert_eq!(error.at([1, 2]).at([3, 4]).location(), Some([1, 2]));
+        assert_eq!(
+            BsError::IoError("Not found".to_string())
+                .at([0, 0])
+                .location(),
+            None
+        );
+    }
+}
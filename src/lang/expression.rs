@@ -1,15 +1,33 @@
-use super::interpreter::{BoxInt, Parser, Runnable, Validator};
+use super::ast::{Access, Expr};
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::interpreter::{ArithMode, ExecContext, Parser, Runnable, Validator};
 use super::math;
-use regex::Regex;
+use super::memory::Memory;
+use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Atom<T: BoxInt> {
     Greater,
     Less,
+    Min,
+    Max,
     Equal,
     NotEqual,
     Assign,
+    SilentAssign,
+    IndirectAssign,
+    SetIfZero,
     Not,
+    Negate,
+    Abs,
+    Copy,
+    Fill,
+    Call,
+    Local,
+    LocalAssign,
     And,
     Or,
     Xor,
@@ -24,11 +42,253 @@ pub enum Atom<T: BoxInt> {
     LeftParen,
     RightParen,
     Output,
+    OutputNumber,
+    Input,
+    PollKey,
+    Argument,
     Data(T),
     Memory,
+    Concat,
+}
+
+const GLYPHS: [(char, Atom<i8>); 37] = [
+    ('▕', Atom::LeftParen),
+    ('▏', Atom::RightParen),
+    ('▔', Atom::Not),
+    ('▁', Atom::Negate),
+    ('▣', Atom::Abs),
+    ('▖', Atom::Modulo),
+    ('▗', Atom::InverseModulo),
+    ('▘', Atom::Multiply),
+    ('▝', Atom::Divide),
+    ('▚', Atom::LeftShift),
+    ('▞', Atom::RightShift),
+    ('▐', Atom::Add),
+    ('▌', Atom::Subtract),
+    ('▨', Atom::Less),
+    ('▧', Atom::Greater),
+    ('▦', Atom::Min),
+    ('▩', Atom::Max),
+    ('▤', Atom::Equal),
+    ('▥', Atom::NotEqual),
+    ('░', Atom::And),
+    ('▒', Atom::Xor),
+    ('▓', Atom::Or),
+    ('◇', Atom::Memory),
+    ('◈', Atom::Assign),
+    ('◘', Atom::SilentAssign),
+    ('◙', Atom::IndirectAssign),
+    ('◫', Atom::Copy),
+    ('◼', Atom::Fill),
+    ('◵', Atom::Call),
+    ('◶', Atom::Argument),
+    ('◷', Atom::Local),
+    ('◸', Atom::LocalAssign),
+    ('◆', Atom::SetIfZero),
+    ('▭', Atom::Output),
+    ('▬', Atom::OutputNumber),
+    ('▯', Atom::Input),
+    ('▮', Atom::PollKey),
+];
+
+const INTRODUCED: [(char, Dialect); 21] = [
+    ('◆', Dialect::V2),
+    ('▯', Dialect::V2),
+    ('▮', Dialect::V2),
+    ('◉', Dialect::V3),
+    ('◎', Dialect::V3),
+    ('"', Dialect::V3),
+    ('\'', Dialect::V3),
+    ('▬', Dialect::V3),
+    ('▁', Dialect::V3),
+    ('▣', Dialect::V3),
+    ('▦', Dialect::V3),
+    ('▩', Dialect::V3),
+    ('◘', Dialect::V3),
+    ('◙', Dialect::V3),
+    ('◫', Dialect::V3),
+    ('◼', Dialect::V3),
+    ('◴', Dialect::V3),
+    ('◵', Dialect::V3),
+    ('◶', Dialect::V3),
+    ('◷', Dialect::V3),
+    ('◸', Dialect::V3),
+];
+
+type Quoted = (Vec<(char, usize)>, usize);
+
+fn leading(text: &str, matches: impl Fn(char) -> bool) -> usize {
+    text.find(|c: char| !matches(c)).unwrap_or(text.len())
+}
+
+const RESERVED: [char; 6] = ['●', '○', '◐', '◑', '◒', '◓'];
+
+const LOOKALIKES: [(char, char, &str); 23] = [
+    ('▃', '▄', "digit zero"),
+    ('▅', '▄', "digit zero"),
+    ('▂', '▁', "negate"),
+    ('⎹', '▕', "left parenthesis"),
+    ('⎸', '▏', "right parenthesis"),
+    ('▎', '▏', "right parenthesis"),
+    ('▍', '▌', "subtract"),
+    ('▋', '▌', "subtract"),
+    ('‾', '▔', "not"),
+    ('¯', '▔', "not"),
+    ('◊', '◇', "memory"),
+    ('⋄', '◇', "memory"),
+    ('♦', '◆', "set if zero"),
+    ('⬥', '◆', "set if zero"),
+    ('■', '▮', "poll key"),
+    ('⊡', '▣', "absolute value"),
+    ('⦿', '◉', "decimal prefix"),
+    ('⊚', '◎', "hex prefix"),
+    ('“', '"', "quote"),
+    ('”', '"', "quote"),
+    ('‘', '\'', "single quote"),
+    ('’', '\'', "single quote"),
+    ('＇', '\'', "single quote"),
+];
+
+pub fn lookalike(c: char) -> Option<(char, &'static str)> {
+    if let Some((_, glyph, name)) = LOOKALIKES.iter().find(|(other, _, _)| *other == c) {
+        return Some((*glyph, name));
+    }
+
+    let ascii = std::char::from_u32((c as u32).checked_sub(0xfee0)?)
+        .filter(|_| ('！'..='～').contains(&c))?;
+    match ascii {
+        '0'..='9' => Some((ascii, "digit")),
+        'A'..='Z' | 'a'..='z' => Some((ascii, "letter")),
+        '-' => Some((ascii, "minus sign")),
+        '"' => Some((ascii, "quote")),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Literal {
+    Binary,
+    Decimal,
+    Hex,
+    Shortest,
+    Original,
+}
+
+impl Literal {
+    pub fn from_name(name: &str) -> Option<Literal> {
+        match name {
+            "binary" => Some(Literal::Binary),
+            "decimal" => Some(Literal::Decimal),
+            "hex" => Some(Literal::Hex),
+            "shortest" => Some(Literal::Shortest),
+            _ => None,
+        }
+    }
+
+    fn from_prefix(c: char) -> Option<Literal> {
+        match c {
+            '◉' => Some(Literal::Decimal),
+            '◎' => Some(Literal::Hex),
+            _ => None,
+        }
+    }
+
+    pub fn prefix(self) -> Option<char> {
+        match self {
+            Literal::Decimal => Some('◉'),
+            Literal::Hex => Some('◎'),
+            _ => None,
+        }
+    }
+
+    pub fn within(self, dialect: Dialect) -> Literal {
+        match self {
+            Literal::Shortest if dialect < Dialect::V3 => Literal::Binary,
+            _ => self,
+        }
+    }
+
+    fn radix(self) -> u32 {
+        match self {
+            Literal::Binary | Literal::Shortest | Literal::Original => 2,
+            Literal::Decimal => 10,
+            Literal::Hex => 16,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+#[non_exhaustive]
+pub enum Dialect {
+    V1,
+    V2,
+    V3,
+}
+
+impl Dialect {
+    pub const STABLE: Dialect = Dialect::V2;
+    pub const LATEST: Dialect = Dialect::V3;
+
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        match name {
+            "1" => Some(Dialect::V1),
+            "2" => Some(Dialect::V2),
+            "3" => Some(Dialect::V3),
+            _ => None,
+        }
+    }
+
+    pub fn number(self) -> u8 {
+        match self {
+            Dialect::V1 => 1,
+            Dialect::V2 => 2,
+            Dialect::V3 => 3,
+        }
+    }
+
+    pub fn introducing(c: char) -> Dialect {
+        INTRODUCED
+            .iter()
+            .find(|(glyph, _)| *glyph == c)
+            .map_or(Dialect::V1, |(_, dialect)| *dialect)
+    }
+
+    pub fn check<T: BoxInt>(self, molecule: &Molecule<T>) -> Result<(), BsError> {
+        for (i, (atom, position)) in molecule
+            .children
+            .iter()
+            .zip(molecule.positions.iter())
+            .enumerate()
+        {
+            let prefix = molecule
+                .prefixes
+                .iter()
+                .find(|(j, _)| *j == i)
+                .map(|(_, prefix)| *prefix);
+            if let Some(glyph) = atom.glyph().or(prefix) {
+                let needed = Dialect::introducing(glyph);
+                if needed > self {
+                    return Err(BsError::ParseError(
+                        format!("Glyph {} needs dialect {} or later", glyph, needed.number()),
+                        Some(*position),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: BoxInt> fmt::Display for Atom<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:?}", self.source(), self)
+    }
 }
 
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum AtomType {
     Number,
     Binary,
@@ -38,23 +298,280 @@ pub enum AtomType {
 impl<T: BoxInt> Atom<T> {
     pub fn precedence(&self) -> u8 {
         match self {
-            Atom::Output | Atom::Assign => 1,
-            Atom::Less | Atom::Greater | Atom::Equal | Atom::NotEqual => 2,
+            Atom::Output
+            | Atom::OutputNumber
+            | Atom::Assign
+            | Atom::SilentAssign
+            | Atom::IndirectAssign
+            | Atom::LocalAssign
+            | Atom::SetIfZero => 1,
+            Atom::Less | Atom::Greater | Atom::Min | Atom::Max | Atom::Equal | Atom::NotEqual => 2,
             Atom::Or => 3,
             Atom::Xor => 4,
             Atom::And => 5,
             Atom::LeftShift | Atom::RightShift => 6,
             Atom::Add | Atom::Subtract => 7,
             Atom::Multiply | Atom::Divide | Atom::Modulo | Atom::InverseModulo => 8,
-            Atom::Memory | Atom::Not => 9,
+            Atom::Memory
+            | Atom::Not
+            | Atom::Negate
+            | Atom::Abs
+            | Atom::Copy
+            | Atom::Fill
+            | Atom::Call
+            | Atom::Local
+            | Atom::Input => 9,
+            Atom::Concat => 10,
             _ => 0,
         }
     }
 
+    pub fn from_glyph(c: char) -> Option<Atom<T>> {
+        GLYPHS
+            .iter()
+            .find(|(glyph, _)| *glyph == c)
+            .map(|(_, atom)| atom.cast())
+    }
+
+    pub fn glyph(&self) -> Option<char> {
+        GLYPHS
+            .iter()
+            .find(|(_, atom)| atom.cast::<T>() == *self)
+            .map(|(glyph, _)| *glyph)
+    }
+
+    pub fn source(&self) -> String {
+        self.source_as(Literal::Binary)
+    }
+
+    pub fn mnemonic(&self) -> Option<&'static str> {
+        Some(match self {
+            Atom::Greater => ">",
+            Atom::Less => "<",
+            Atom::Min => "min",
+            Atom::Max => "max",
+            Atom::Equal => "==",
+            Atom::NotEqual => "!=",
+            Atom::Assign => ":=",
+            Atom::SilentAssign => "set",
+            Atom::IndirectAssign => "@:=",
+            Atom::SetIfZero => "?=",
+            Atom::Not => "~",
+            Atom::Negate => "neg",
+            Atom::Abs => "abs",
+            Atom::Copy => "copy",
+            Atom::Fill => "fill",
+            Atom::Call => "call",
+            Atom::Local => "local",
+            Atom::LocalAssign => "local:=",
+            Atom::And => "&",
+            Atom::Or => "|",
+            Atom::Xor => "^",
+            Atom::LeftShift => "<<",
+            Atom::RightShift => ">>",
+            Atom::Add => "+",
+            Atom::Subtract => "-",
+            Atom::Multiply => "*",
+            Atom::Divide => "/",
+            Atom::Modulo => "%",
+            Atom::InverseModulo => "rmod",
+            Atom::LeftParen => "(",
+            Atom::RightParen => ")",
+            Atom::Output => "out",
+            Atom::OutputNumber => "print",
+            Atom::Input => "in",
+            Atom::PollKey => "key",
+            Atom::Argument => "arg",
+            Atom::Memory => "@",
+            Atom::Data(_) | Atom::Concat => return None,
+        })
+    }
+
+    pub fn from_mnemonic(word: &str) -> Option<Atom<T>> {
+        GLYPHS
+            .iter()
+            .map(|(_, atom)| atom.cast::<T>())
+            .find(|atom| atom.mnemonic() == Some(word))
+    }
+
+    pub fn source_as(&self, literal: Literal) -> String {
+        match self {
+            Atom::Data(_) if literal == Literal::Shortest => {
+                [Literal::Binary, Literal::Decimal, Literal::Hex]
+                    .iter()
+                    .map(|literal| self.source_as(*literal))
+                    .min_by_key(|source| source.chars().count())
+                    .unwrap()
+            }
+            Atom::Data(num) if literal == Literal::Decimal => format!("◉{}", num),
+            Atom::Data(num) if literal == Literal::Hex => {
+                let value = num.to_i128().unwrap_or_default();
+                let sign = if value < 0 { "-" } else { "" };
+                format!("◎{}{:x}", sign, value.unsigned_abs())
+            }
+            Atom::Data(num) => {
+                let mut digits: Vec<char> = Vec::new();
+                let mut rest = *num;
+                while !rest.is_zero() {
+                    digits.push(if (rest % (T::one() + T::one())).is_zero() {
+                        '▄'
+                    } else {
+                        '▀'
+                    });
+                    rest = rest / (T::one() + T::one());
+                }
+                digits.push(if *num < T::zero() { '▄' } else { '▀' });
+                digits.iter().rev().collect()
+            }
+            _ => self.glyph().map_or(String::new(), |c| c.to_string()),
+        }
+    }
+
+    fn cast<U: BoxInt>(&self) -> Atom<U> {
+        match *self {
+            Atom::Greater => Atom::Greater,
+            Atom::Min => Atom::Min,
+            Atom::Max => Atom::Max,
+            Atom::Less => Atom::Less,
+            Atom::Equal => Atom::Equal,
+            Atom::NotEqual => Atom::NotEqual,
+            Atom::Assign => Atom::Assign,
+            Atom::SilentAssign => Atom::SilentAssign,
+            Atom::IndirectAssign => Atom::IndirectAssign,
+            Atom::SetIfZero => Atom::SetIfZero,
+            Atom::Not => Atom::Not,
+            Atom::Negate => Atom::Negate,
+            Atom::Abs => Atom::Abs,
+            Atom::Copy => Atom::Copy,
+            Atom::Fill => Atom::Fill,
+            Atom::Call => Atom::Call,
+            Atom::Local => Atom::Local,
+            Atom::LocalAssign => Atom::LocalAssign,
+            Atom::And => Atom::And,
+            Atom::Or => Atom::Or,
+            Atom::Xor => Atom::Xor,
+            Atom::LeftShift => Atom::LeftShift,
+            Atom::RightShift => Atom::RightShift,
+            Atom::Add => Atom::Add,
+            Atom::Subtract => Atom::Subtract,
+            Atom::Multiply => Atom::Multiply,
+            Atom::Divide => Atom::Divide,
+            Atom::Modulo => Atom::Modulo,
+            Atom::InverseModulo => Atom::InverseModulo,
+            Atom::LeftParen => Atom::LeftParen,
+            Atom::RightParen => Atom::RightParen,
+            Atom::Output => Atom::Output,
+            Atom::OutputNumber => Atom::OutputNumber,
+            Atom::Input => Atom::Input,
+            Atom::PollKey => Atom::PollKey,
+            Atom::Argument => Atom::Argument,
+            Atom::Memory => Atom::Memory,
+            Atom::Concat => Atom::Concat,
+            Atom::Data(_) => Atom::Data(U::zero()),
+        }
+    }
+
+    pub fn unary(&self, a: T) -> Result<T, BsError> {
+        self.unary_in(ArithMode::Checked, a)
+    }
+
+    pub fn unary_in(&self, mode: ArithMode, a: T) -> Result<T, BsError> {
+        let overflow =
+            || BsError::RuntimeError(format!("Arithmetic overflow: {}{}", self.source(), a), None);
+
+        Ok(match self {
+            Atom::Not => !a,
+            Atom::Negate => math::subtract(mode, T::zero(), a).ok_or_else(overflow)?,
+            Atom::Abs if a < T::zero() => {
+                math::subtract(mode, T::zero(), a).ok_or_else(overflow)?
+            }
+            Atom::Abs => a,
+            _ => unreachable!(),
+        })
+    }
+
+    pub fn binary(&self, a: T, b: T) -> Result<T, BsError> {
+        self.binary_in(ArithMode::Checked, a, b)
+    }
+
+    pub fn binary_in(&self, mode: ArithMode, a: T, b: T) -> Result<T, BsError> {
+        let invalid = |message: &str| BsError::RuntimeError(message.to_string(), None);
+        let overflow = || {
+            BsError::RuntimeError(
+                format!("Arithmetic overflow: {} {} {}", a, self.source(), b),
+                None,
+            )
+        };
+
+        Ok(match self {
+            Atom::Add => math::add(mode, a, b).ok_or_else(overflow)?,
+            Atom::Subtract => math::subtract(mode, a, b).ok_or_else(overflow)?,
+            Atom::Multiply => math::multiply(mode, a, b).ok_or_else(overflow)?,
+            Atom::Divide => {
+                math::divide(mode, a, b).ok_or_else(|| invalid("Division caused invalid value"))?
+            }
+            Atom::Modulo => math::modulo(mode, a, b)?,
+            Atom::InverseModulo => math::inv_modulo(mode, a, b)?,
+            Atom::LeftShift => b
+                .to_u32()
+                .and_then(|b| math::shift_left(mode, a, b))
+                .ok_or_else(|| invalid("Bitwise shift got invalid value"))?,
+            Atom::RightShift => b
+                .to_u32()
+                .and_then(|b| math::shift_right(mode, a, b))
+                .ok_or_else(|| invalid("Bitwise shift got invalid value"))?,
+            Atom::And => a & b,
+            Atom::Or => a | b,
+            Atom::Xor => a ^ b,
+            Atom::Less => {
+                if a < b {
+                    T::one()
+                } else {
+                    T::zero()
+                }
+            }
+            Atom::Greater => {
+                if a > b {
+                    T::one()
+                } else {
+                    T::zero()
+                }
+            }
+            Atom::Min => a.min(b),
+            Atom::Max => a.max(b),
+            Atom::Equal => {
+                if a == b {
+                    T::one()
+                } else {
+                    T::zero()
+                }
+            }
+            Atom::NotEqual => {
+                if a != b {
+                    T::one()
+                } else {
+                    T::zero()
+                }
+            }
+            Atom::Assign | Atom::IndirectAssign | Atom::LocalAssign | Atom::Concat => b,
+            Atom::SilentAssign => T::zero(),
+            _ => unreachable!(),
+        })
+    }
+
     pub fn form(&self) -> AtomType {
         match self {
-            Atom::Output | Atom::Memory | Atom::Not => AtomType::Unary,
-            Atom::Data(_) => AtomType::Number,
+            Atom::Output
+            | Atom::OutputNumber
+            | Atom::Input
+            | Atom::Memory
+            | Atom::Not
+            | Atom::Negate
+            | Atom::Abs
+            | Atom::Copy
+            | Atom::Fill
+            | Atom::Local => AtomType::Unary,
+            Atom::Data(_) | Atom::PollKey | Atom::Argument => AtomType::Number,
             _ => AtomType::Binary,
         }
     }
@@ -63,154 +580,514 @@ impl<T: BoxInt> Atom<T> {
 #[derive(Clone, Debug, PartialEq)]
 pub struct Molecule<T: BoxInt> {
     children: Vec<Atom<T>>,
-    sorted_children: Option<Vec<Atom<T>>>,
+    positions: Vec<[usize; 2]>,
+    widths: Vec<usize>,
+    order: Option<Vec<usize>>,
+    expr: Option<Expr<T>>,
     valid: bool,
+    arith_mode: ArithMode,
+    prefixes: Vec<(usize, char)>,
 }
 
 impl<T: BoxInt> Molecule<T> {
     pub fn new(children: Vec<Atom<T>>) -> Molecule<T> {
         Molecule {
             children,
-            sorted_children: None,
+            positions: Vec::new(),
+            widths: Vec::new(),
+            order: None,
+            expr: None,
             valid: false,
+            arith_mode: ArithMode::Checked,
+            prefixes: Vec::new(),
         }
     }
 
-    pub fn sort(
-        children: &[Atom<T>],
-        sorted: &mut Option<Vec<Atom<T>>>,
-    ) -> Result<Vec<Atom<T>>, String> {
-        if sorted.is_none() {
-            let mut output: Vec<Atom<T>> = Vec::new();
-            let mut stack: Vec<Atom<T>> = Vec::new();
-
-            for child in children {
-                if let Atom::Data(_) = *child {
-                    output.push(*child);
-                } else if let Atom::LeftParen | Atom::Not | Atom::Memory = *child {
-                    stack.push(*child);
-                } else if let Atom::RightParen = *child {
-                    while !stack.is_empty() && stack.last().cloned().unwrap() != Atom::LeftParen {
-                        output.push(stack.pop().unwrap());
+    pub fn read(expr: &str, origin: [usize; 2]) -> Result<Molecule<T>, BsError> {
+        let mut rest = expr;
+        let mut position = origin;
+        let mut molecule = Molecule::new(Vec::new());
+
+        while let Some(c) = rest.chars().next() {
+            let length = if c.is_whitespace() {
+                leading(rest, char::is_whitespace)
+            } else if c == '▄' || c == '▀' {
+                let number = &rest[..leading(rest, |c| c == '▄' || c == '▀')];
+                let val = Molecule::number(number).ok_or_else(|| {
+                    BsError::ParseError("Invalid number".to_string(), Some(position))
+                })?;
+                molecule.push(position, number.chars().count(), Atom::Data(val));
+                number.len()
+            } else if let Some(literal) = Literal::from_prefix(c) {
+                let digits = &rest[c.len_utf8()..];
+                let sign = usize::from(digits.starts_with('-'));
+                let digits =
+                    &digits[..sign + leading(&digits[sign..], |c| c.is_ascii_alphanumeric())];
+                let val = T::from_str_radix(digits, literal.radix()).map_err(|_| {
+                    BsError::ParseError("Invalid number".to_string(), Some(position))
+                })?;
+                molecule.prefixes.push((molecule.children.len(), c));
+                molecule.push(position, digits.chars().count() + 1, Atom::Data(val));
+                c.len_utf8() + digits.len()
+            } else if c == '"' || c == '\'' {
+                let (chars, length) = Molecule::<T>::quoted(rest).map_err(|message| {
+                    let closing =
+                        rest.chars().enumerate().skip(1).find(|(_, other)| {
+                            lookalike(*other).map(|(glyph, _)| glyph) == Some(c)
+                        });
+
+                    match closing {
+                        Some((offset, other)) if message == "Unclosed quote" => {
+                            BsError::ParseError(
+                                format!(
+                                    "Invalid character {}, did you mean {} ({})?",
+                                    other,
+                                    c,
+                                    lookalike(other).unwrap().1
+                                ),
+                                Some([position[0], position[1] + offset]),
+                            )
+                        }
+                        _ => BsError::ParseError(message.to_string(), Some(position)),
                     }
+                })?;
+                if chars.is_empty() || (c == '\'' && chars.len() > 1) {
+                    return Err(BsError::ParseError(
+                        "Quotes must hold text, and single quotes one character".to_string(),
+                        Some(position),
+                    ));
+                }
 
-                    if stack.is_empty() {
-                        return Err("Missing left parenthesis".to_string());
+                let end = rest[..length].chars().count();
+                molecule.prefixes.push((molecule.children.len(), c));
+                for (i, (ch, offset)) in chars.iter().enumerate() {
+                    let at = [position[0], position[1] + offset];
+                    let val = T::from_char(*ch).ok_or_else(|| {
+                        BsError::ParseError("Character is out of range".to_string(), Some(at))
+                    })?;
+                    let start = if i == 0 { position } else { at };
+                    let stop = chars.get(i + 1).map_or(end, |(_, next)| *next);
+
+                    if i > 0 {
+                        molecule.push(at, 0, Atom::Concat);
+                    }
+                    molecule.push(start, position[1] + stop - start[1], Atom::Data(val));
+                }
+                length
+            } else {
+                let atom = Atom::from_glyph(c).ok_or_else(|| {
+                    if RESERVED.contains(&c) {
+                        BsError::ParseError(
+                            format!("Glyph {} is reserved for future use", c),
+                            Some(position),
+                        )
+                    } else if let Some((glyph, name)) = lookalike(c) {
+                        BsError::ParseError(
+                            format!(
+                                "Invalid character {}, did you mean {} ({})?",
+                                c, glyph, name
+                            ),
+                            Some(position),
+                        )
+                    } else {
+                        BsError::ParseError("Invalid character".to_string(), Some(position))
                     }
+                })?;
+                molecule.push(position, 1, atom);
+                c.len_utf8()
+            };
 
-                    stack.pop();
+            position[1] += rest[..length].chars().count();
+            rest = &rest[length..];
+        }
+
+        Ok(molecule)
+    }
+
+    pub fn read_ascii(expr: &str, origin: [usize; 2]) -> Result<Molecule<T>, BsError> {
+        let mut molecule = Molecule::new(Vec::new());
+        let mut rest = expr;
+        let mut position = origin;
+
+        while let Some(c) = rest.chars().next() {
+            let length = if c.is_whitespace() {
+                c.len_utf8()
+            } else if c == '"' || c == '\'' {
+                let length = Molecule::<T>::quoted(rest).map_or(rest.len(), |(_, length)| length);
+                let quoted = Molecule::<T>::read(&rest[..length], position)?;
+                let offset = molecule.children.len();
+                for (i, prefix) in quoted.prefixes.iter() {
+                    molecule.prefixes.push((offset + i, *prefix));
+                }
+                for (atom, start, end) in quoted.spans() {
+                    molecule.push(start, end[1] - start[1], atom);
+                }
+                length
+            } else {
+                let length = if c == '(' || c == ')' {
+                    1
+                } else {
+                    rest.find(|c: char| c.is_whitespace() || c == '(' || c == ')')
+                        .unwrap_or(rest.len())
+                };
+                let word = &rest[..length];
+                let digits = word.strip_prefix('-').unwrap_or(word);
+                let atom = if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                    T::from_str_radix(word, 10).map(Atom::Data).map_err(|_| {
+                        BsError::ParseError("Invalid number".to_string(), Some(position))
+                    })?
                 } else {
-                    let precedence = child.precedence();
+                    Atom::from_mnemonic(word).ok_or_else(|| {
+                        BsError::ParseError(format!("Unknown word {}", word), Some(position))
+                    })?
+                };
+                molecule.push(position, word.chars().count(), atom);
+                length
+            };
+
+            position[1] += rest[..length].chars().count();
+            rest = &rest[length..];
+        }
 
-                    if let Atom::Assign = *child {
-                        while !stack.is_empty()
-                            && stack.last().cloned().unwrap().precedence() > precedence
-                        {
-                            output.push(stack.pop().unwrap());
-                        }
-                    } else {
-                        while !stack.is_empty()
-                            && stack.last().cloned().unwrap().precedence() >= precedence
-                        {
-                            output.push(stack.pop().unwrap());
-                        }
-                    }
+        Ok(molecule)
+    }
 
-                    stack.push(*child);
+    fn quoted(rest: &str) -> Result<Quoted, &'static str> {
+        let quote = rest.chars().next().unwrap();
+        let mut chars: Vec<(char, usize)> = Vec::new();
+        let mut column = 1;
+        let mut rest = rest.char_indices().skip(1);
+
+        while let Some((i, c)) = rest.next() {
+            let start = column;
+            let c = match c {
+                _ if c == quote => return Ok((chars, i + c.len_utf8())),
+                '\\' => {
+                    column += 1;
+                    match rest.next() {
+                        Some((_, 'n')) => '\n',
+                        Some((_, escaped)) => escaped,
+                        None => break,
+                    }
                 }
+                _ => c,
+            };
+            column += 1;
+            chars.push((c, start));
+        }
+
+        Err("Unclosed quote")
+    }
+
+    pub fn set_arith_mode(&mut self, arith_mode: ArithMode) {
+        self.arith_mode = arith_mode;
+    }
+
+    pub fn silence_assignments(&mut self) {
+        for atom in self.children.iter_mut() {
+            if *atom == Atom::Assign {
+                *atom = Atom::SilentAssign;
             }
+        }
+        self.expr = None;
+    }
+
+    fn push(&mut self, position: [usize; 2], width: usize, atom: Atom<T>) {
+        self.positions.push(position);
+        self.widths.push(width);
+        self.children.push(atom);
+    }
+
+    pub fn atoms(&self) -> &[Atom<T>] {
+        &self.children
+    }
+
+    pub fn positions(&self) -> &[[usize; 2]] {
+        &self.positions
+    }
+
+    pub fn prefixes(&self) -> &[(usize, char)] {
+        &self.prefixes
+    }
+
+    pub fn spans(&self) -> impl Iterator<Item = (Atom<T>, [usize; 2], [usize; 2])> + '_ {
+        self.children
+            .iter()
+            .zip(self.positions.iter().zip(self.widths.iter()))
+            .map(|(atom, (start, width))| (*atom, *start, [start[0], start[1] + width]))
+    }
+
+    pub fn source(&self) -> String {
+        self.source_as(Literal::Binary)
+    }
+
+    pub fn source_as(&self, literal: Literal) -> String {
+        self.pieces(literal, |atom, literal| atom.source_as(literal))
+            .concat()
+    }
+
+    pub fn ascii(&self) -> String {
+        self.pieces(Literal::Original, |atom, _| match atom {
+            Atom::Data(num) => num.to_string(),
+            _ => atom.mnemonic().unwrap_or_default().to_string(),
+        })
+        .join(" ")
+    }
 
-            while !stack.is_empty() {
-                if let Atom::LeftParen = stack.last().cloned().unwrap() {
-                    return Err("Missing right parenthesis".to_string());
+    fn pieces<F: Fn(&Atom<T>, Literal) -> String>(
+        &self,
+        literal: Literal,
+        write: F,
+    ) -> Vec<String> {
+        let mut pieces: Vec<String> = Vec::new();
+        let mut i = 0;
+
+        while i < self.children.len() {
+            let prefix = self
+                .prefixes
+                .iter()
+                .find(|(j, _)| *j == i)
+                .map(|(_, prefix)| *prefix)
+                .filter(|_| literal == Literal::Original);
+            let quote = match prefix {
+                Some('\'') => '\'',
+                _ if self.children.get(i + 1) == Some(&Atom::Concat) || prefix == Some('"') => '"',
+                _ => {
+                    let literal = prefix.and_then(Literal::from_prefix).unwrap_or(literal);
+                    pieces.push(write(&self.children[i], literal));
+                    i += 1;
+                    continue;
+                }
+            };
+
+            let mut source = quote.to_string();
+            loop {
+                if let Atom::Data(num) = self.children[i] {
+                    match num.to_u32().and_then(char::from_u32) {
+                        Some(c) if c == quote || c == '\\' => {
+                            source.push('\\');
+                            source.push(c);
+                        }
+                        Some('\n') => source += "\\n",
+                        Some(c) => source.push(c),
+                        None => {}
+                    }
                 }
+                if self.children.get(i + 1) != Some(&Atom::Concat) {
+                    break;
+                }
+                i += 2;
+            }
+            source.push(quote);
+            pieces.push(source);
+            i += 1;
+        }
+
+        pieces
+    }
+
+    pub fn folded(&self) -> Molecule<T> {
+        let mut children = self.children.clone();
+        let mut i = 0;
 
-                output.push(stack.pop().unwrap());
+        if let Some(value) = self.constant(&children) {
+            children = vec![Atom::Data(value)];
+        }
+
+        while i < children.len() {
+            if children[i] == Atom::LeftParen {
+                let mut depth = 0;
+                let end = (i..children.len()).find(|j| {
+                    match children[*j] {
+                        Atom::LeftParen => depth += 1,
+                        Atom::RightParen => depth -= 1,
+                        _ => {}
+                    }
+                    depth == 0
+                });
+
+                if let Some(value) = end.and_then(|end| self.constant(&children[i + 1..end])) {
+                    children.splice(i..=end.unwrap(), vec![Atom::Data(value)]);
+                }
             }
+            i += 1;
+        }
+
+        let mut folded = Molecule::new(children);
+        folded.arith_mode = self.arith_mode;
+        folded
+    }
+
+    fn constant(&self, atoms: &[Atom<T>]) -> Option<T> {
+        let pure = !atoms.iter().any(|atom| {
+            matches!(
+                atom,
+                Atom::Memory
+                    | Atom::Input
+                    | Atom::Output
+                    | Atom::OutputNumber
+                    | Atom::PollKey
+                    | Atom::Argument
+                    | Atom::Call
+                    | Atom::Local
+                    | Atom::LocalAssign
+                    | Atom::Assign
+                    | Atom::SilentAssign
+                    | Atom::IndirectAssign
+                    | Atom::SetIfZero
+                    | Atom::Copy
+                    | Atom::Fill
+                    | Atom::Concat
+            )
+        });
+        if atoms.is_empty() || !pure {
+            return None;
+        }
+
+        let mut molecule = Molecule::new(atoms.to_vec());
+        molecule.arith_mode = self.arith_mode;
+        molecule.peek(&HashMap::<T, T>::new()).ok()
+    }
+
+    pub fn postfix(&mut self) -> Result<Vec<Atom<T>>, BsError> {
+        Ok(self.ordered()?.iter().map(|i| self.children[*i]).collect())
+    }
 
-            *sorted = Some(output);
+    pub fn peek(&mut self, memory: &dyn Memory<T>) -> Result<T, BsError> {
+        self.evaluate(Access::Read(memory))
+    }
+
+    pub fn sort(
+        children: &[Atom<T>],
+        sorted: &mut Option<Vec<Atom<T>>>,
+    ) -> Result<Vec<Atom<T>>, BsError> {
+        if sorted.is_none() {
+            let order = Molecule::order(children).map_err(|(_, error)| error)?;
+            *sorted = Some(order.iter().map(|i| children[*i]).collect());
         }
 
         Ok(sorted.as_ref().unwrap().to_vec())
     }
-}
 
-impl<T: BoxInt> Parser<Atom<T>> for Molecule<T> {
-    fn parse(expr: &str) -> Result<Vec<Atom<T>>, String> {
-        lazy_static! {
-            static ref NUMBER: Regex = Regex::new(r"^[▄▀]+").unwrap();
-            static ref WHITESPACE: Regex = Regex::new(r"^[\s]+").unwrap();
-            static ref OTHER: Regex = Regex::new(r"^.").unwrap();
+    fn ordered(&mut self) -> Result<Vec<usize>, BsError> {
+        if let Err(error) = Molecule::validate(&self.children, &mut self.valid) {
+            return Err(self.locate(error, 0));
         }
 
-        let mut expr_copy = expr.to_string();
-        let mut children: Vec<Atom<T>> = Vec::new();
+        if self.order.is_none() {
+            match Molecule::order(&self.children) {
+                Ok(order) => self.order = Some(order),
+                Err((i, error)) => return Err(self.locate(error, i)),
+            }
+        }
+
+        Ok(self.order.clone().unwrap())
+    }
+
+    fn locate(&self, error: BsError, index: usize) -> BsError {
+        match self.positions.get(index) {
+            Some(position) => error.at(*position),
+            None => error,
+        }
+    }
+
+    fn order(children: &[Atom<T>]) -> Result<Vec<usize>, (usize, BsError)> {
+        let mut output: Vec<usize> = Vec::new();
+        let mut stack: Vec<usize> = Vec::new();
+
+        for (i, child) in children.iter().enumerate() {
+            if let Atom::Data(_) | Atom::PollKey | Atom::Argument = *child {
+                output.push(i);
+            } else if let Atom::LeftParen
+            | Atom::Not
+            | Atom::Negate
+            | Atom::Abs
+            | Atom::Copy
+            | Atom::Fill
+            | Atom::Local
+            | Atom::Memory
+            | Atom::Input = *child
+            {
+                stack.push(i);
+            } else if let Atom::RightParen = *child {
+                while !stack.is_empty() && children[*stack.last().unwrap()] != Atom::LeftParen {
+                    output.push(stack.pop().unwrap());
+                }
 
-        while !expr_copy.is_empty() {
-            if WHITESPACE.is_match(&expr_copy) {
-                expr_copy = WHITESPACE.replace(&expr_copy, "").to_string();
-            } else if NUMBER.is_match(&expr_copy) {
-                let number = NUMBER.find(&expr_copy).unwrap().as_str();
+                if stack.is_empty() {
+                    return Err((
+                        i,
+                        BsError::ValidationError("Missing left parenthesis".to_string(), None),
+                    ));
+                }
 
-                if number.chars().count() == 1 {
-                    children.push(Atom::Data(T::zero()));
+                stack.pop();
+            } else {
+                let precedence = child.precedence();
+
+                if let Atom::Assign
+                | Atom::SilentAssign
+                | Atom::IndirectAssign
+                | Atom::LocalAssign
+                | Atom::SetIfZero = *child
+                {
+                    while !stack.is_empty()
+                        && children[*stack.last().unwrap()].precedence() > precedence
+                    {
+                        output.push(stack.pop().unwrap());
+                    }
                 } else {
-                    let digits: String = number
-                        .chars()
-                        .map(|c| match c {
-                            '▀' => '1',
-                            '▄' => '0',
-                            _ => unreachable!(),
-                        })
-                        .collect();
-                    let val = match T::from_str_radix(&digits[1..], 2) {
-                        Ok(x) => x,
-                        _ => unreachable!(),
-                    };
-                    if number.starts_with('▄') {
-                        children.push(Atom::Data(T::zero() - val));
-                    } else {
-                        children.push(Atom::Data(val));
+                    while !stack.is_empty()
+                        && children[*stack.last().unwrap()].precedence() >= precedence
+                    {
+                        output.push(stack.pop().unwrap());
                     }
                 }
 
-                expr_copy = NUMBER.replace(&expr_copy, "").to_string();
-            } else {
-                children.push(match expr_copy.chars().next().unwrap() {
-                    '▕' => Atom::LeftParen,
-                    '▏' => Atom::RightParen,
-                    '▔' => Atom::Not,
-                    '▖' => Atom::Modulo,
-                    '▗' => Atom::InverseModulo,
-                    '▘' => Atom::Multiply,
-                    '▝' => Atom::Divide,
-                    '▚' => Atom::LeftShift,
-                    '▞' => Atom::RightShift,
-                    '▐' => Atom::Add,
-                    '▌' => Atom::Subtract,
-                    '▨' => Atom::Less,
-                    '▧' => Atom::Greater,
-                    '▤' => Atom::Equal,
-                    '▥' => Atom::NotEqual,
-                    '░' => Atom::And,
-                    '▒' => Atom::Xor,
-                    '▓' => Atom::Or,
-                    '◇' => Atom::Memory,
-                    '◈' => Atom::Assign,
-                    '▭' => Atom::Output,
-                    _ => return Err("Invalid character".to_string()),
-                });
+                stack.push(i);
+            }
+        }
 
-                expr_copy = OTHER.replace(&expr_copy, "").to_string();
+        while let Some(i) = stack.pop() {
+            if let Atom::LeftParen = children[i] {
+                return Err((
+                    i,
+                    BsError::ValidationError("Missing right parenthesis".to_string(), None),
+                ));
             }
+
+            output.push(i);
+        }
+
+        Ok(output)
+    }
+
+    fn number(literal: &str) -> Option<T> {
+        if literal.chars().count() == 1 {
+            return Some(T::zero());
+        }
+
+        let digits: String = literal
+            .chars()
+            .skip(1)
+            .map(|c| if c == '▀' { '1' } else { '0' })
+            .collect();
+        if literal.starts_with('▄') {
+            T::from_str_radix(&format!("-{}", digits), 2).ok()
+        } else {
+            T::from_str_radix(&digits, 2).ok()
         }
+    }
+}
 
-        Ok(children)
+impl<T: BoxInt> Parser<Atom<T>> for Molecule<T> {
+    fn parse(expr: &str) -> Result<Vec<Atom<T>>, BsError> {
+        Ok(Molecule::read(expr, [0, 0])?.children)
     }
 }
 
 impl<T: BoxInt> Validator<Atom<T>> for Molecule<T> {
-    fn validate(children: &[Atom<T>], valid: &mut bool) -> Result<(), String> {
+    fn validate(children: &[Atom<T>], valid: &mut bool) -> Result<(), BsError> {
         if !*valid {
             let mut token_types: Vec<AtomType> = vec![];
             for child in children {
@@ -224,11 +1101,14 @@ impl<T: BoxInt> Validator<Atom<T>> for Molecule<T> {
                 || token_types.len() == 2
                     && (token_types[0] != AtomType::Unary || token_types[1] != AtomType::Number)
             {
-                return Err("Malformed expression".to_string());
+                return Err(BsError::ValidationError(
+                    "Malformed expression".to_string(),
+                    None,
+                ));
             }
             *valid = true;
 
-            if token_types.is_empty() {
+            if token_types.len() < 2 {
                 return Ok(());
             }
 
@@ -261,7 +1141,10 @@ impl<T: BoxInt> Validator<Atom<T>> for Molecule<T> {
             }
 
             if !*valid {
-                return Err("Malformed expression".to_string());
+                return Err(BsError::ValidationError(
+                    "Malformed expression".to_string(),
+                    None,
+                ));
             }
 
             Ok(())
@@ -271,217 +1154,278 @@ impl<T: BoxInt> Validator<Atom<T>> for Molecule<T> {
     }
 }
 
+impl<T: BoxInt> fmt::Display for Molecule<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.clone().compile() {
+            Ok(expr) => write!(f, "{}", expr),
+            Err(_) => write!(f, "{}", self.source()),
+        }
+    }
+}
+
 impl<T: BoxInt> Runnable<T> for Molecule<T> {
-    fn run(
-        &mut self,
-        memory: &mut std::collections::HashMap<T, T>,
-        stdout: &mut String,
-    ) -> Result<(T, String), String> {
-        Molecule::validate(&self.children, &mut self.valid)?;
-
-        let children = Molecule::sort(&self.children, &mut self.sorted_children)?;
-
-        let mut stack: Vec<T> = vec![];
-        for child in children {
-            if let Atom::Data(num) = child {
-                stack.push(num);
-            } else if let Atom::Memory | Atom::Not | Atom::Output = child {
-                let a = stack.pop().unwrap();
-
-                if let Atom::Memory = child {
-                    stack.push(*memory.get(&a).unwrap_or(&T::zero()));
-                } else if let Atom::Not = child {
-                    stack.push(!a);
-                } else if let Atom::Output = child {
-                    stack.push(a);
-
-                    if let Some(val) = a.to_u32() {
-                        if let Some(chr) = std::char::from_u32(val) {
-                            stdout.push(chr);
-                        } else {
-                            stdout.push('\u{ffff}');
-                        }
-                    } else {
-                        stdout.push('\u{ffff}');
-                    }
-                }
-            } else {
-                let b = stack.pop().unwrap();
-                let a = stack.pop().unwrap();
+    fn run(&mut self, context: &mut ExecContext<T>) -> Result<T, BsError> {
+        context.spend(self.children.len(), self.positions.first().cloned())?;
+        let value = self.evaluate(Access::Write(&mut *context))?;
+        if let Some(hook) = context.hook.as_mut() {
+            hook.evaluated(self, value);
+        }
 
-                if let Atom::Assign = child {
-                    memory.insert(a, b);
-                }
+        Ok(value)
+    }
+}
 
-                stack.push(match child {
-                    Atom::Add => a.checked_add(&b).ok_or("Addition caused invalid value")?,
-                    Atom::Subtract => a
-                        .checked_sub(&b)
-                        .ok_or("Subtraction caused invalid value")?,
-                    Atom::Multiply => a
-                        .checked_mul(&b)
-                        .ok_or("Multiplication caused invalid value")?,
-                    Atom::Divide => a.checked_div(&b).ok_or("Division caused invalid value")?,
-                    Atom::Modulo => math::modulo(a, b)?,
-                    Atom::InverseModulo => math::inv_modulo(a, b)?,
-                    Atom::LeftShift => {
-                        a.signed_shl(b.to_u32().ok_or("Bitwise shift got invalid value")?)
-                    }
-                    Atom::RightShift => {
-                        a.signed_shr(b.to_u32().ok_or("Bitwise shift got invalid value")?)
-                    }
-                    Atom::And => a & b,
-                    Atom::Or => a | b,
-                    Atom::Xor => a ^ b,
-                    Atom::Less => {
-                        if a < b {
-                            T::one()
-                        } else {
-                            T::zero()
-                        }
-                    }
-                    Atom::Greater => {
-                        if a > b {
-                            T::one()
-                        } else {
-                            T::zero()
-                        }
-                    }
-                    Atom::Equal => {
-                        if a == b {
-                            T::one()
-                        } else {
-                            T::zero()
-                        }
-                    }
-                    Atom::NotEqual => {
-                        if a != b {
-                            T::one()
-                        } else {
-                            T::zero()
-                        }
-                    }
-                    Atom::Assign => b,
-                    _ => unreachable!(),
-                });
-            }
+impl<T: BoxInt> Molecule<T> {
+    pub fn compile(&mut self) -> Result<&Expr<T>, BsError> {
+        if self.expr.is_none() {
+            let postfix: Vec<(Atom<T>, Option<[usize; 2]>)> = self
+                .ordered()?
+                .iter()
+                .map(|i| (self.children[*i], self.positions.get(*i).cloned()))
+                .collect();
+            self.expr = Some(Expr::lower(&postfix)?);
         }
 
-        Ok((stack.pop().unwrap_or_else(T::zero), stdout.to_string()))
+        Ok(self.expr.as_ref().unwrap())
+    }
+
+    fn evaluate(&mut self, mut access: Access<T>) -> Result<T, BsError> {
+        let arith_mode = self.arith_mode;
+        self.compile()?.evaluate(&mut access, arith_mode)
     }
 }
 
 #[allow(unused_must_use)]
 #[cfg(test)]
 mod tests {
+    use super::super::input::{NoInput, Reader};
     use super::*;
+    use std::collections::HashMap;
 
     #[test]
     fn it_works_simple() {
         assert_eq!(
             Molecule::<i8>::new(vec![Atom::Data(2), Atom::Add, Atom::Data(2)])
-                .run(&mut std::collections::HashMap::new(), &mut String::new())
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                ))
                 .unwrap(),
-            (4, String::new())
+            4
         );
     }
 
     #[test]
     fn it_detects_bad_outputs() {
+        let mut stdout = String::new();
         assert_eq!(
-            Molecule::<i32>::new(vec![Atom::Output, Atom::Data(55296),])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Ok((55296, "\u{ffff}".to_string()))
+            Molecule::<i32>::new(vec![Atom::Output, Atom::Data(55296),]).run(
+                &mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut stdout,
+                    &mut NoInput
+                )
+            ),
+            Ok(55296)
         );
+        assert_eq!(stdout, "\u{ffff}");
     }
 
     #[test]
     fn it_detects_bad_expressions() {
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::Data(0), Atom::Data(0)])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::Data(0), Atom::Data(0)]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::Multiply, Atom::Data(0)])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::Multiply, Atom::Data(0)]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::Subtract, Atom::Not])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::Subtract, Atom::Not]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::Output, Atom::Memory])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::Output, Atom::Memory]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::Not, Atom::Modulo])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::Not, Atom::Modulo]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::Data(0), Atom::Xor])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::Data(0), Atom::Xor]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::And])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::And]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::Data(0), Atom::And, Atom::Divide])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::Data(0), Atom::And, Atom::Divide]).run(
+                &mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )
+            ),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::And, Atom::LeftShift, Atom::Data(0)])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::And, Atom::LeftShift, Atom::Data(0)]).run(
+                &mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )
+            ),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::And, Atom::Not, Atom::Data(0)])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::And, Atom::Not, Atom::Data(0)]).run(
+                &mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )
+            ),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::And, Atom::Data(0), Atom::Greater])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Malformed expression".to_string())
+            Molecule::<i8>::new(vec![Atom::And, Atom::Data(0), Atom::Greater]).run(
+                &mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )
+            ),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                None
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀▐▭◇▄", [0, 0])
+                .unwrap()
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                Some([0, 1])
+            ))
         );
     }
 
     #[test]
     fn it_detects_bad_parentheses() {
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::LeftParen])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Missing right parenthesis".to_string())
+            Molecule::<i8>::new(vec![Atom::LeftParen]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Missing right parenthesis".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::RightParen])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Missing left parenthesis".to_string())
+            Molecule::<i8>::new(vec![Atom::RightParen]).run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Err(BsError::ValidationError(
+                "Missing left parenthesis".to_string(),
+                None
+            ))
         );
 
         assert_eq!(
-            Molecule::<i8>::new(vec![Atom::RightParen, Atom::LeftParen])
-                .run(&mut std::collections::HashMap::new(), &mut String::new()),
-            Err("Missing left parenthesis".to_string())
+            Molecule::<i8>::new(vec![Atom::RightParen, Atom::LeftParen]).run(
+                &mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )
+            ),
+            Err(BsError::ValidationError(
+                "Missing left parenthesis".to_string(),
+                None
+            ))
         );
     }
 
@@ -489,56 +1433,731 @@ mod tests {
     fn it_detects_bad_chars() {
         assert_eq!(
             Molecule::<i8>::parse("a"),
-            Err("Invalid character".to_string())
+            Err(BsError::ParseError(
+                "Invalid character".to_string(),
+                Some([0, 0])
+            ))
         );
     }
 
     #[test]
-    fn it_works_many_times() {
-        let mut mol = Molecule::<i8>::new(vec![Atom::Data(2), Atom::Multiply, Atom::Data(2)]);
-        mol.run(&mut std::collections::HashMap::new(), &mut String::new());
+    fn it_reports_reserved_glyphs() {
         assert_eq!(
-            mol.run(&mut std::collections::HashMap::new(), &mut String::new())
-                .unwrap(),
-            (4, String::new())
+            Molecule::<i8>::read("▀▐●▀", [2, 1]),
+            Err(BsError::ParseError(
+                "Glyph ● is reserved for future use".to_string(),
+                Some([2, 3])
+            ))
         );
+    }
 
+    #[test]
+    fn it_suggests_lookalike_glyphs() {
         assert_eq!(
-            mol.run(&mut std::collections::HashMap::new(), &mut String::new())
-                .unwrap(),
-            mol.run(&mut std::collections::HashMap::new(), &mut String::new())
-                .unwrap()
+            Molecule::<i8>::read("▀▀▃▐▀", [1, 2]),
+            Err(BsError::ParseError(
+                "Invalid character ▃, did you mean ▄ (digit zero)?".to_string(),
+                Some([1, 4])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::parse("◉5▐１"),
+            Err(BsError::ParseError(
+                "Invalid character １, did you mean 1 (digit)?".to_string(),
+                Some([0, 3])
+            ))
         );
+        assert_eq!(lookalike('”'), Some(('"', "quote")));
+        assert_eq!(lookalike('＂'), Some(('"', "quote")));
+        assert_eq!(lookalike('＋'), None);
+        assert_eq!(lookalike('a'), None);
+        assert!(LOOKALIKES
+            .iter()
+            .all(|(c, glyph, _)| Atom::<i8>::from_glyph(*c).is_none()
+                && (Atom::<i8>::from_glyph(*glyph).is_some() || "▄◉◎\"'".contains(*glyph))));
+    }
+
+    #[test]
+    fn it_points_at_lookalike_closing_quotes() {
+        assert_eq!(
+            Molecule::<i8>::read("▭\"Hi” ", [2, 1]),
+            Err(BsError::ParseError(
+                "Invalid character ”, did you mean \" (quote)?".to_string(),
+                Some([2, 5])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▭\"Hi", [2, 1]),
+            Err(BsError::ParseError(
+                "Unclosed quote".to_string(),
+                Some([2, 2])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_gates_glyphs_by_dialect() {
+        let molecule = Molecule::<i8>::read("▀▀◆▀", [0, 4]).unwrap();
+
+        assert_eq!(Dialect::V2.check(&molecule), Ok(()));
+        assert_eq!(
+            Dialect::V1.check(&molecule),
+            Err(BsError::ParseError(
+                "Glyph ◆ needs dialect 2 or later".to_string(),
+                Some([0, 6])
+            ))
+        );
+        for (code, glyph) in [("▭▯▀", '▯'), ("▀◈▮", '▮')] {
+            let molecule = Molecule::<i8>::read(code, [0, 0]).unwrap();
+            assert_eq!(Dialect::V2.check(&molecule), Ok(()));
+            assert_eq!(
+                Dialect::V1.check(&molecule),
+                Err(BsError::ParseError(
+                    format!("Glyph {} needs dialect 2 or later", glyph),
+                    Some([0, code.chars().position(|c| c == glyph).unwrap()])
+                ))
+            );
+        }
+        assert_eq!(Dialect::from_name("1"), Some(Dialect::V1));
+        assert_eq!(Dialect::from_name("4"), None);
+
+        let molecule = Molecule::<i8>::read("▀◈◎7f", [1, 0]).unwrap();
+        assert_eq!(Dialect::V3.check(&molecule), Ok(()));
+        assert_eq!(
+            Dialect::V2.check(&molecule),
+            Err(BsError::ParseError(
+                "Glyph ◎ needs dialect 3 or later".to_string(),
+                Some([1, 2])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_reads_prefixed_literals() {
+        assert_eq!(
+            Molecule::<i16>::parse("◉300▐◎-Ff ◉0"),
+            Ok(vec![
+                Atom::Data(300),
+                Atom::Add,
+                Atom::Data(-255),
+                Atom::Data(0)
+            ])
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀▐◉1a", [0, 0]),
+            Err(BsError::ParseError(
+                "Invalid number".to_string(),
+                Some([0, 2])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("◉200", [0, 0]),
+            Err(BsError::ParseError(
+                "Invalid number".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert!(Molecule::<i8>::read("◎", [0, 0]).is_err());
+    }
+
+    #[test]
+    fn it_reads_quoted_text() {
+        let molecule = Molecule::<i8>::read("▭\"H\\\"i\"", [1, 1]).unwrap();
+
+        assert_eq!(
+            molecule.atoms(),
+            &[
+                Atom::Output,
+                Atom::Data(72),
+                Atom::Concat,
+                Atom::Data(34),
+                Atom::Concat,
+                Atom::Data(105)
+            ]
+        );
+        assert_eq!(
+            molecule
+                .spans()
+                .map(|(_, start, end)| [start[1], end[1]])
+                .collect::<Vec<_>>(),
+            vec![[1, 2], [2, 4], [4, 4], [4, 6], [6, 6], [6, 8]]
+        );
+        assert_eq!(molecule.source(), "▭\"H\\\"i\"");
+        assert_eq!(
+            Molecule::<i8>::parse("'A'▐▀▀"),
+            Ok(vec![Atom::Data(65), Atom::Add, Atom::Data(1)])
+        );
+        assert_eq!(
+            Dialect::V2.check(&molecule).unwrap_err().location(),
+            Some([1, 2])
+        );
+        assert_eq!(Dialect::V3.check(&molecule), Ok(()));
+    }
+
+    #[test]
+    fn it_rejects_bad_quotes() {
+        let error = |expr: &str| Molecule::<i8>::read(expr, [0, 0]).unwrap_err();
+
+        assert_eq!(
+            error("▭\"Hi"),
+            BsError::ParseError("Unclosed quote".to_string(), Some([0, 1]))
+        );
+        assert_eq!(
+            error("▭'Hi'"),
+            BsError::ParseError(
+                "Quotes must hold text, and single quotes one character".to_string(),
+                Some([0, 1])
+            )
+        );
+        assert_eq!(error("▭\"\"").message(), error("▭'Hi'").message());
+        assert_eq!(
+            error("▭\"aé\""),
+            BsError::ParseError("Character is out of range".to_string(), Some([0, 3]))
+        );
+    }
+
+    #[test]
+    fn it_folds_constants() {
+        let folded = |expr: &str| {
+            Molecule::<i8>::read(expr, [0, 0])
+                .unwrap()
+                .folded()
+                .source()
+        };
+
+        assert_eq!(folded("▀◈▕▀▀▐▀▀▄▏▘◇▀"), "▀◈▀▀▀▘◇▀");
+        assert_eq!(folded("▀▀▐▀▀▘▀▀▄"), "▀▀▀");
+        assert_eq!(folded("◇▕▀▀▐▕▀▀▘▀▀▄▏▏"), "◇▀▀▀");
+        assert_eq!(folded("▕◇▀▏▐▕▀▀▐▀▀▏"), "▕◇▀▏▐▀▀▄");
+        assert_eq!(folded("▀◈▕▀▀▀▀▀▀▀▀▘▀▀▄▏"), "▀◈▕▀▀▀▀▀▀▀▀▘▀▀▄▏");
+        assert_eq!(folded("▭\"Hi\""), "▭\"Hi\"");
+    }
+
+    #[test]
+    fn it_picks_the_shortest_literal() {
+        let shortest = |num: i16| Atom::Data(num).source_as(Literal::Shortest);
+
+        assert_eq!(shortest(1000), "◎3e8");
+        assert_eq!(shortest(10), "◎a");
+        assert_eq!(shortest(-5), "◉-5");
+        assert_eq!(shortest(1), "▀▀");
+        assert_eq!(shortest(0), "▀");
+        assert_eq!(Literal::Shortest.within(Dialect::V2), Literal::Binary);
+        assert_eq!(Literal::Hex.within(Dialect::V2), Literal::Hex);
+    }
+
+    #[test]
+    fn it_writes_literals_in_other_bases() {
+        let molecule = Molecule::<i128>::read("◉-1000◈▀▀▀▀", [0, 0]).unwrap();
+
+        assert_eq!(molecule.source(), "▄▀▀▀▀▀▄▀▄▄▄◈▀▀▀▀");
+        assert_eq!(molecule.source_as(Literal::Decimal), "◉-1000◈◉7");
+        assert_eq!(molecule.source_as(Literal::Hex), "◎-3e8◈◎7");
+        assert_eq!(
+            Atom::<i128>::Data(i128::MIN).source_as(Literal::Hex),
+            "◎-80000000000000000000000000000000"
+        );
+    }
+
+    #[test]
+    fn it_locates_errors() {
+        assert_eq!(
+            Molecule::<i8>::read("▀▐ ▀a", [3, 5]),
+            Err(BsError::ParseError(
+                "Invalid character".to_string(),
+                Some([3, 9])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀ ▏", [0, 0]).unwrap().postfix(),
+            Err(BsError::ValidationError(
+                "Missing left parenthesis".to_string(),
+                Some([0, 2])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀▐ ▀▝▄", [3, 5])
+                .unwrap()
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )),
+            Err(BsError::RuntimeError(
+                "Division caused invalid value".to_string(),
+                Some([3, 9])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀▀▄▄▄▄▄▄▄▄", [0, 0]),
+            Err(BsError::ParseError(
+                "Invalid number".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_works_many_times() {
+        let mut mol = Molecule::<i8>::new(vec![Atom::Data(2), Atom::Multiply, Atom::Data(2)]);
+        mol.run(&mut ExecContext::new(
+            &mut std::collections::HashMap::new(),
+            &mut String::new(),
+            &mut NoInput,
+        ));
+        assert_eq!(
+            mol.run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            ))
+            .unwrap(),
+            4
+        );
+
+        assert_eq!(
+            mol.run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            ))
+            .unwrap(),
+            mol.run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            ))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn it_works_with_lone_numbers() {
+        assert_eq!(
+            Molecule::<i8>::new(vec![Atom::Data(3)])
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                ))
+                .unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn it_reports_overflow() {
+        let overflow = |message: &str| Err(BsError::RuntimeError(message.to_string(), None));
+
+        assert_eq!(
+            Atom::<i8>::Add.binary(127, 1),
+            overflow("Arithmetic overflow: 127 ▐ 1")
+        );
+        assert_eq!(
+            Atom::<i8>::Subtract.binary(-128, 1),
+            overflow("Arithmetic overflow: -128 ▌ 1")
+        );
+        assert_eq!(
+            Atom::<i8>::Multiply.binary(64, -3),
+            overflow("Arithmetic overflow: 64 ▘ -3")
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀▀▀▀▀▀▀▀▐▀▀", [1, 1])
+                .unwrap()
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                )),
+            Err(BsError::RuntimeError(
+                "Arithmetic overflow: 127 ▐ 1".to_string(),
+                Some([1, 9])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_applies_arith_modes() {
+        let modes = |atom: Atom<i8>, a: i8, b: i8| {
+            [
+                ArithMode::Checked,
+                ArithMode::Wrapping,
+                ArithMode::Saturating,
+            ]
+            .iter()
+            .map(|mode| atom.binary_in(*mode, a, b).ok())
+            .collect::<Vec<Option<i8>>>()
+        };
+
+        assert_eq!(modes(Atom::Add, 127, 1), vec![None, Some(-128), Some(127)]);
+        assert_eq!(
+            modes(Atom::Subtract, -128, 1),
+            vec![None, Some(127), Some(-128)]
+        );
+        assert_eq!(
+            modes(Atom::Multiply, 64, -3),
+            vec![None, Some(64), Some(-128)]
+        );
+        assert_eq!(
+            modes(Atom::Divide, -128, -1),
+            vec![None, Some(-128), Some(127)]
+        );
+        assert_eq!(modes(Atom::Divide, 1, 0), vec![None, None, None]);
+        assert_eq!(modes(Atom::Modulo, -128, -1), vec![None, Some(0), Some(0)]);
+        assert_eq!(modes(Atom::LeftShift, 1, 9), vec![None, Some(2), Some(0)]);
+        assert_eq!(
+            modes(Atom::RightShift, -4, 8),
+            vec![None, Some(-4), Some(-1)]
+        );
+        assert_eq!(modes(Atom::Add, 1, 2), vec![Some(3), Some(3), Some(3)]);
+
+        let mut molecule = Molecule::<i8>::read("▀▀▀▀▀▀▀▀▐▀▀", [0, 0]).unwrap();
+        molecule.set_arith_mode(ArithMode::Wrapping);
+        assert_eq!(
+            molecule.run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Ok(-128)
+        );
+    }
+
+    #[test]
+    fn it_negates_and_takes_absolute_values() {
+        let run = |source: &str, mode: ArithMode| {
+            let mut molecule = Molecule::<i8>::read(source, [0, 0]).unwrap();
+            molecule.set_arith_mode(mode);
+            molecule.run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput,
+            ))
+        };
+        let min = Atom::Data(i8::MIN).source();
+
+        assert_eq!(run("▁▀▀▀", ArithMode::Checked), Ok(-3));
+        assert_eq!(run("▣▄▀▀▐▀▀", ArithMode::Checked), Ok(4));
+        assert_eq!(run("▣▁▀▀▀", ArithMode::Checked), Ok(3));
+        assert_eq!(
+            run(&format!("▁{}", min), ArithMode::Checked),
+            Err(BsError::RuntimeError(
+                "Arithmetic overflow: ▁-128".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(run(&format!("▁{}", min), ArithMode::Wrapping), Ok(-128));
+        assert_eq!(run(&format!("▣{}", min), ArithMode::Saturating), Ok(127));
+        assert_eq!(
+            Molecule::<i8>::read("▣▁▀▀▀▐▀▀", [0, 0])
+                .unwrap()
+                .compile()
+                .unwrap()
+                .to_string(),
+            "abs(-3) + 1"
+        );
+    }
+
+    #[test]
+    fn it_takes_minimum_and_maximum() {
+        let mut molecule = Molecule::<i8>::read("▀▀▀▐▀▀▦▀▀▀▀", [0, 0]).unwrap();
+        assert_eq!(
+            molecule.atoms(),
+            &[
+                Atom::Data(3),
+                Atom::Add,
+                Atom::Data(1),
+                Atom::Min,
+                Atom::Data(7)
+            ]
+        );
+        assert_eq!(molecule.compile().unwrap().to_string(), "min(3 + 1, 7)");
+
+        let run = |source: &str| {
+            Molecule::<i8>::read(source, [0, 0])
+                .unwrap()
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput,
+                ))
+        };
+
+        assert_eq!(run("▀▀▀▐▀▀▦▀▀▀▀"), Ok(4));
+        assert_eq!(run("▀▀▀▐▀▀▩▀▀▀▀"), Ok(7));
+        assert_eq!(run("▄▀▀▩▀▀▦▀▀▄"), Ok(1));
+        assert_eq!(run("▕▄▀▀▩▄▀▄▏▘▀▀▄"), Ok(-4));
+    }
+
+    #[test]
+    fn it_assigns_silently() {
+        let mut hm = std::collections::HashMap::<i8, i8>::new();
+        let mut molecule = Molecule::<i8>::read("▀▀◘▀▀▀", [0, 0]).unwrap();
+
+        assert_eq!(
+            molecule.run(&mut ExecContext::new(
+                &mut hm,
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Ok(0)
+        );
+        assert_eq!(hm.get(&1), Some(&3));
+        assert_eq!(molecule.compile().unwrap().to_string(), "1 set 3");
+        assert_eq!(
+            Molecule::<i8>::read("▕▀▀◘▀▀▏▐▀▀", [2, 1])
+                .unwrap()
+                .compile(),
+            Err(BsError::ValidationError(
+                "Silent assignment has no value to use".to_string(),
+                Some([2, 8])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀▀◈▀▀◘▀▀▀", [0, 0]).unwrap().compile(),
+            Err(BsError::ValidationError(
+                "Silent assignment has no value to use".to_string(),
+                Some([0, 2])
+            ))
+        );
+
+        let mut molecule = Molecule::<i8>::read("▀◈▀▀◈▀▀▀", [0, 0]).unwrap();
+        molecule.silence_assignments();
+        assert_eq!(molecule.atoms()[1], Atom::SilentAssign);
+        assert!(molecule.compile().is_err());
+    }
+
+    #[test]
+    fn it_writes_through_pointers() {
+        let mut hm = std::collections::HashMap::<i8, i8>::new();
+        hm.insert(1, 5);
+        let mut molecule = Molecule::<i8>::read("▀▀◙▀▀▀", [0, 0]).unwrap();
+
+        assert_eq!(
+            molecule.run(&mut ExecContext::new(
+                &mut hm,
+                &mut String::new(),
+                &mut NoInput
+            )),
+            Ok(3)
+        );
+        assert_eq!(hm.get(&5), Some(&3));
+        assert_eq!(hm.get(&1), Some(&5));
+        assert_eq!(molecule.compile().unwrap().to_string(), "mem[1] := 3");
+        assert_eq!(
+            molecule.peek(&hm),
+            Err(BsError::ValidationError(
+                "Assignment is not allowed in a pure expression".to_string(),
+                Some([0, 2])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_copies_and_fills_memory() {
+        let mut hm = std::collections::HashMap::<i8, i8>::new();
+        hm.extend(vec![(0, 10), (1, 3), (2, 9), (20, 10), (21, 30), (22, 3)]);
+        let run = |source: &str, hm: &mut std::collections::HashMap<i8, i8>| {
+            Molecule::<i8>::read(source, [0, 0])
+                .unwrap()
+                .run(&mut ExecContext::new(hm, &mut String::new(), &mut NoInput))
+        };
+
+        assert_eq!(run("◼▀", &mut hm), Ok(3));
+        assert_eq!(run("◫▀▀▄▀▄▄", &mut hm), Ok(3));
+        assert_eq!(
+            (10..13)
+                .chain(30..33)
+                .map(|address| hm[&address])
+                .collect::<Vec<i8>>(),
+            vec![9; 6]
+        );
+
+        hm.insert(0, 126);
+        assert_eq!(
+            run("▀▀▐◼▀", &mut hm),
+            Err(BsError::RuntimeError(
+                "Memory range is out of bounds".to_string(),
+                Some([0, 3])
+            ))
+        );
+        hm.insert(1, -1);
+        assert!(run("◼▀", &mut hm).is_err());
+        assert_eq!(
+            Molecule::<i8>::read("◫▀", [0, 0]).unwrap().peek(&hm),
+            Err(BsError::ValidationError(
+                "Copying memory is not allowed in a pure expression".to_string(),
+                Some([0, 0])
+            ))
+        );
+
+        let mut hm = std::collections::HashMap::<i64, i64>::new();
+        hm.extend(vec![(0, 10), (1, 1 << 62), (2, 9)]);
+        let (mut stdout, mut input) = (String::new(), NoInput);
+        let mut context = ExecContext::new(&mut hm, &mut stdout, &mut input);
+        context.max_steps = Some(1000);
+        assert_eq!(
+            Molecule::<i64>::read("◼▀", [0, 0])
+                .unwrap()
+                .run(&mut context),
+            Err(BsError::RuntimeError(
+                "Execution budget exceeded after 2 steps".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
+    fn edges<T: BoxInt + std::fmt::Debug>() {
+        let bits = <T as num_traits::NumCast>::from(std::mem::size_of::<T>() * 8).unwrap();
+        let one = T::one();
+        let three = one + one + one;
+
+        assert!(Atom::Divide.binary(T::min_value(), -one).is_err());
+        assert!(Atom::Modulo.binary(T::min_value(), -one).is_err());
+        assert_eq!(Atom::Modulo.binary(T::max_value(), three), Ok(one));
+        assert_eq!(Atom::Modulo.binary(-one, three), Ok(one + one));
+        assert!(Atom::LeftShift.binary(one, bits).is_err());
+        assert!(Atom::RightShift.binary(-one, bits).is_err());
+        assert_eq!(Atom::LeftShift.binary(one, bits - one), Ok(T::min_value()));
+
+        let min = Atom::Data(T::min_value()).source();
+        assert_eq!(
+            Molecule::<T>::read(&min, [0, 0]).unwrap().atoms(),
+            &[Atom::Data(T::min_value())]
+        );
+    }
+
+    #[test]
+    fn it_overflows_the_same_at_every_width() {
+        edges::<i8>();
+        edges::<i16>();
+        edges::<i32>();
+        edges::<i64>();
+        edges::<i128>();
+    }
+
+    #[test]
+    fn it_writes_source() {
+        let source = "▭▕▀▀▄▐▄▀▀▏▘◇▀▀";
+        assert_eq!(
+            Molecule::<i8>::new(Molecule::parse(source).unwrap()).source(),
+            source
+        );
+        assert_eq!(
+            Molecule::<i8>::new(Molecule::parse("▀ ▐ ▄▄▄▀").unwrap()).source(),
+            "▀▐▄▀"
+        );
+        assert_eq!(Atom::Data(-128i8).source(), "▄▀▄▄▄▄▄▄▄");
+    }
+
+    #[test]
+    fn it_reads_and_writes_ascii() {
+        for (_, atom) in GLYPHS.iter() {
+            let name = atom.mnemonic().unwrap();
+            assert_eq!(Atom::<i8>::from_mnemonic(name), Some(*atom));
+        }
+
+        let molecule = Molecule::<i8>::read_ascii("out (@ 1 + -3) * 'a'", [2, 4]).unwrap();
+        assert_eq!(molecule.source_as(Literal::Original), "▭▕◇▀▀▐▄▀▀▏▘'a'");
+        assert_eq!(molecule.ascii(), "out ( @ 1 + -3 ) * 'a'");
+        assert_eq!(molecule.positions()[4], [2, 13]);
+        assert_eq!(
+            Molecule::<i8>::read("▭\"a b\"▐◇▀", [0, 0]).unwrap().ascii(),
+            "out \"a b\" + @ 0"
+        );
+        assert_eq!(
+            Molecule::<i8>::read_ascii("1 plus 2", [0, 0]).map(|_| ()),
+            Err(BsError::ParseError(
+                "Unknown word plus".to_string(),
+                Some([0, 2])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_reports_spans() {
+        let molecule = Molecule::<i8>::read("▀◈ ▀▄▀▀ ▐▀▀", [2, 1]).unwrap();
+
+        assert_eq!(
+            molecule.spans().collect::<Vec<_>>(),
+            vec![
+                (Atom::Data(0), [2, 1], [2, 2]),
+                (Atom::Assign, [2, 2], [2, 3]),
+                (Atom::Data(3), [2, 4], [2, 8]),
+                (Atom::Add, [2, 9], [2, 10]),
+                (Atom::Data(1), [2, 10], [2, 12]),
+            ]
+        );
+        assert_eq!(Molecule::<i8>::new(vec![Atom::Data(1)]).spans().count(), 0);
     }
 
     #[test]
     fn it_outputs() {
+        let mut stdout = String::new();
         assert_eq!(
             Molecule::<i8>::new(vec![Atom::Output, Atom::Data(48)])
-                .run(&mut std::collections::HashMap::new(), &mut String::new())
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut stdout,
+                    &mut NoInput
+                ))
                 .unwrap(),
-            (48, "0".to_string())
+            48
+        );
+        assert_eq!(stdout, "0");
+    }
+
+    #[test]
+    fn it_outputs_numbers() {
+        let mut stdout = String::new();
+        let mut molecule = Molecule::<i16>::read("▬▄▀▀▀▀▀▀▀▀▘▀▀▄", [0, 0]).unwrap();
+
+        assert_eq!(
+            molecule
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut stdout,
+                    &mut NoInput
+                ))
+                .unwrap(),
+            -510
+        );
+        assert_eq!(stdout, "-510");
+        assert_eq!(
+            molecule.peek(&std::collections::HashMap::new()),
+            Err(BsError::ValidationError(
+                "Output is not allowed in a pure expression".to_string(),
+                Some([0, 0])
+            ))
         );
+        assert_eq!(molecule.compile().unwrap().to_string(), "print(-255 * 2)");
     }
 
     #[test]
     fn it_works_with_memory() {
         let mut hm = std::collections::HashMap::<i8, i8>::new();
         hm.insert(0, 48);
+        let mut stdout = String::new();
         assert_eq!(
             Molecule::<i8>::new(vec![Atom::Output, Atom::Memory, Atom::Data(0)])
-                .run(&mut hm, &mut String::new())
+                .run(&mut ExecContext::new(&mut hm, &mut stdout, &mut NoInput))
                 .unwrap(),
-            (48, "0".to_string())
+            48
+        );
+        assert_eq!(stdout, "0");
+        Molecule::<i8>::new(vec![Atom::Data(0), Atom::Assign, Atom::Data(13)]).run(
+            &mut ExecContext::new(&mut hm, &mut String::new(), &mut NoInput),
         );
-        Molecule::<i8>::new(vec![Atom::Data(0), Atom::Assign, Atom::Data(13)])
-            .run(&mut hm, &mut String::new());
         assert_eq!(hm, [(0, 13)].iter().cloned().collect());
         assert_eq!(
             Molecule::<i8>::new(vec![Atom::Memory, Atom::Data(13)])
-                .run(&mut std::collections::HashMap::new(), &mut String::new())
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                ))
                 .unwrap(),
-            (0, String::new())
+            0
         );
         hm.insert(0, 48);
         Molecule::<i8>::new(vec![
@@ -552,31 +2171,209 @@ mod tests {
             Atom::Assign,
             Atom::Data(1),
         ])
-        .run(&mut hm, &mut String::new());
+        .run(&mut ExecContext::new(
+            &mut hm,
+            &mut String::new(),
+            &mut NoInput,
+        ));
         assert_eq!(hm, [(0, 1), (1, 1), (3, 1)].iter().cloned().collect());
     }
 
+    #[test]
+    fn it_sets_if_zero() {
+        let mut hm = std::collections::HashMap::<i8, i8>::new();
+        let mut claim = Molecule::<i8>::read("▀▀◆▀▀▀▐▀◆▀▀", [0, 0]).unwrap();
+        let mut same = Molecule::<i8>::read("▀▀◆▀▀", [0, 0]).unwrap();
+        let (mut stdout, mut input) = (String::new(), NoInput);
+        let mut context = ExecContext::new(&mut hm, &mut stdout, &mut input);
+
+        assert_eq!(claim.run(&mut context), Ok(1));
+        assert_eq!(claim.run(&mut context), Ok(0));
+        assert_eq!(same.run(&mut context), Ok(0));
+        context.memory.set(1, 0);
+        assert_eq!(same.run(&mut context), Ok(1));
+        assert_eq!(hm, [(1, 1), (3, 1)].iter().cloned().collect());
+        assert_eq!(
+            claim.peek(&hm),
+            Err(BsError::ValidationError(
+                "Set-if-zero is not allowed in a pure expression".to_string(),
+                Some([0, 8])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_peeks_purely() {
+        let mut hm = std::collections::HashMap::<i8, i8>::new();
+        hm.insert(0, 48);
+        assert_eq!(
+            Molecule::<i8>::new(vec![Atom::Memory, Atom::Data(0), Atom::Add, Atom::Data(1)])
+                .peek(&hm),
+            Ok(49)
+        );
+        assert_eq!(
+            Molecule::<i8>::new(vec![Atom::Data(0), Atom::Assign, Atom::Data(13)]).peek(&hm),
+            Err(BsError::ValidationError(
+                "Assignment is not allowed in a pure expression".to_string(),
+                None
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▭◇▄▐▀", [2, 3]).unwrap().peek(&hm),
+            Err(BsError::ValidationError(
+                "Output is not allowed in a pure expression".to_string(),
+                Some([2, 3])
+            ))
+        );
+        assert_eq!(hm, [(0, 48)].iter().cloned().collect());
+    }
+
+    #[test]
+    fn it_polls_keys() {
+        let mut hm = std::collections::HashMap::<i64, i64>::new();
+        let mut keys: std::collections::VecDeque<char> = "aé".chars().collect();
+        let mut molecule = Molecule::<i64>::read("▀◈▮▐▀▀", [0, 0]).unwrap();
+        assert_eq!(
+            molecule.run(&mut ExecContext::new(
+                &mut hm,
+                &mut String::new(),
+                &mut keys
+            )),
+            Ok(98)
+        );
+        assert_eq!(
+            molecule.run(&mut ExecContext::new(
+                &mut hm,
+                &mut String::new(),
+                &mut keys
+            )),
+            Ok(234)
+        );
+        assert_eq!(
+            molecule.run(&mut ExecContext::new(
+                &mut hm,
+                &mut String::new(),
+                &mut keys
+            )),
+            Ok(1)
+        );
+        assert_eq!(hm, [(0, 1)].iter().cloned().collect());
+
+        let mut keys: std::collections::VecDeque<char> = "é".chars().collect();
+        assert_eq!(
+            Molecule::<i8>::read("▮", [0, 0])
+                .unwrap()
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut keys
+                )),
+            Ok(0)
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▀▐▮", [1, 1])
+                .unwrap()
+                .peek(&HashMap::new()),
+            Err(BsError::ValidationError(
+                "Key input is not allowed in a pure expression".to_string(),
+                Some([1, 3])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_displays_atoms_and_molecules() {
+        assert_eq!(Atom::<i8>::Add.to_string(), "▐ Add");
+        assert_eq!(Atom::<i8>::Data(2).to_string(), "▀▀▄ Data(2)");
+
+        let display = |expr: &str| Molecule::<i8>::read(expr, [0, 0]).unwrap().to_string();
+        assert_eq!(display("▀◈◇▀▐▀▀"), "0 := (mem[0] + 1)");
+        assert_eq!(display("▀◘▀▀▗▀▀▄"), "0 set (1 rmod 2)");
+        assert_eq!(display("▭◇▀▀▌▀▀▘▀▀▄"), "out(mem[1] - (1 * 2))");
+        assert_eq!(display("▔▮▤▯▀"), "!key == in(0)");
+        assert_eq!(display("▐▀▀"), "▐▀▀");
+    }
+
+    #[test]
+    fn it_reads_input() {
+        let mut hm = std::collections::HashMap::<i64, i64>::new();
+        let mut input = Reader::new("hé 42\nx".as_bytes());
+        let mut read = |expr: &str, input: &mut Reader<&[u8]>| {
+            Molecule::<i64>::read(expr, [0, 0])
+                .unwrap()
+                .run(&mut ExecContext::new(&mut hm, &mut String::new(), input))
+        };
+        assert_eq!(read("▯▀", &mut input), Ok(104));
+        assert_eq!(read("▀◈▯▀", &mut input), Ok(233));
+        assert_eq!(read("▯▀▀▐▀▀", &mut input), Ok(43));
+        assert_eq!(
+            read("▀▐▯▀▀", &mut input),
+            Err(BsError::RuntimeError(
+                "Expected an integer on input".to_string(),
+                Some([0, 2])
+            ))
+        );
+        assert_eq!(read("▯▀", &mut input), Ok(-1));
+        assert_eq!(hm, [(0, 233)].iter().cloned().collect());
+
+        assert_eq!(
+            Molecule::<i8>::read("▯▀", [0, 0])
+                .unwrap()
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut Reader::new("é".as_bytes())
+                )),
+            Err(BsError::RuntimeError(
+                "Input character is out of range".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(
+            Molecule::<i8>::read("▯▀", [0, 0])
+                .unwrap()
+                .peek(&HashMap::new()),
+            Err(BsError::ValidationError(
+                "Input is not allowed in a pure expression".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
     #[test]
     fn it_works_with_memory_with_parsing() {
         let mut hm = std::collections::HashMap::<i8, i8>::new();
         hm.insert(0, 48);
+        let mut stdout = String::new();
         assert_eq!(
             Molecule::<i8>::new(Molecule::parse("▭◇▀").unwrap())
-                .run(&mut hm, &mut String::new())
+                .run(&mut ExecContext::new(&mut hm, &mut stdout, &mut NoInput))
                 .unwrap(),
-            (48, "0".to_string())
+            48
         );
-        Molecule::<i8>::new(Molecule::parse("▀◈▀▀▀▄▀").unwrap()).run(&mut hm, &mut String::new());
+        assert_eq!(stdout, "0");
+        Molecule::<i8>::new(Molecule::parse("▀◈▀▀▀▄▀").unwrap()).run(&mut ExecContext::new(
+            &mut hm,
+            &mut String::new(),
+            &mut NoInput,
+        ));
         assert_eq!(hm, [(0, 13)].iter().cloned().collect());
         assert_eq!(
             Molecule::<i8>::new(Molecule::parse("◇▀▀▀▄▀").unwrap())
-                .run(&mut std::collections::HashMap::new(), &mut String::new())
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                ))
                 .unwrap(),
-            (0, String::new())
+            0
         );
         hm.insert(0, 48);
-        Molecule::<i8>::new(Molecule::parse("▀▀▄▐▀▀◈▀▀◈▀◈▀▀").unwrap())
-            .run(&mut hm, &mut String::new());
+        Molecule::<i8>::new(Molecule::parse("▀▀▄▐▀▀◈▀▀◈▀◈▀▀").unwrap()).run(&mut ExecContext::new(
+            &mut hm,
+            &mut String::new(),
+            &mut NoInput,
+        ));
         assert_eq!(hm, [(0, 1), (1, 1), (3, 1)].iter().cloned().collect());
     }
 
@@ -609,9 +2406,13 @@ mod tests {
                 Atom::Modulo,
                 Atom::Data(11),
             ])
-            .run(&mut std::collections::HashMap::new(), &mut String::new())
+            .run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            ))
             .unwrap(),
-            (-1, String::new())
+            -1
         );
 
         assert_eq!(
@@ -626,9 +2427,13 @@ mod tests {
                 Atom::NotEqual,
                 Atom::Data(-1),
             ])
-            .run(&mut std::collections::HashMap::new(), &mut String::new())
+            .run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            ))
             .unwrap(),
-            (1, String::new())
+            1
         );
 
         assert_eq!(
@@ -647,9 +2452,13 @@ mod tests {
                 Atom::Data(8),
                 Atom::RightParen,
             ])
-            .run(&mut std::collections::HashMap::new(), &mut String::new())
+            .run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            ))
             .unwrap(),
-            (-8, String::new())
+            -8
         );
     }
 
@@ -660,23 +2469,35 @@ mod tests {
                 Molecule::parse("▔▄▐▀▀▌▀▀▄▘▀▀▀▝▀▀▄▄▗▀▀▄▀▚▀▀▀▄▞▀▀▀▀▒▀▀▄▄▄▓▀▀▄▄▀░▀▀▄▀▄▖▀▀▄▀▀ ")
                     .unwrap()
             )
-            .run(&mut std::collections::HashMap::new(), &mut String::new())
+            .run(&mut ExecContext::new(
+                &mut std::collections::HashMap::new(),
+                &mut String::new(),
+                &mut NoInput
+            ))
             .unwrap(),
-            (-1, String::new())
+            -1
         );
 
         assert_eq!(
             Molecule::<i8>::new(Molecule::parse("▀▄▨▀▀▧▀▀▄▤▀▄▥▄▀").unwrap())
-                .run(&mut std::collections::HashMap::new(), &mut String::new())
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                ))
                 .unwrap(),
-            (1, String::new())
+            1
         );
 
         assert_eq!(
             Molecule::<i8>::new(Molecule::parse("▕▕▀▄▐▀▀▄▏▖▀▀▄▖▀▀▄▌▀▀▄▄▄▏").unwrap())
-                .run(&mut std::collections::HashMap::new(), &mut String::new())
+                .run(&mut ExecContext::new(
+                    &mut std::collections::HashMap::new(),
+                    &mut String::new(),
+                    &mut NoInput
+                ))
                 .unwrap(),
-            (-8, String::new())
+            -8
         );
     }
 }
@@ -0,0 +1,106 @@
+use super::analysis;
+use super::datatype::BoxInt;
+use super::expression::{self, Dialect};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Fix {
+    pub at: [usize; 2],
+    pub found: char,
+    pub glyph: char,
+    pub name: &'static str,
+}
+
+impl Fix {
+    pub fn describe(&self) -> String {
+        format!(
+            "{}:{}: replaced {} with {} ({})",
+            self.at[0] + 1,
+            self.at[1] + 1,
+            self.found,
+            self.glyph,
+            self.name
+        )
+    }
+}
+
+fn join(lines: &[Vec<char>]) -> String {
+    lines
+        .iter()
+        .map(|line| line.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn fix<T: BoxInt>(code: &str, dialect: Dialect) -> (String, Vec<Fix>) {
+    let mut lines: Vec<Vec<char>> = code
+        .split('\n')
+        .map(|line| line.chars().collect())
+        .collect();
+    let mut fixes: Vec<Fix> = Vec::new();
+
+    loop {
+        let mut found: Vec<Fix> = analysis::check::<T>(&join(&lines))
+            .iter()
+            .filter_map(|error| {
+                let at = error.location()?;
+                let c = *lines.get(at[0])?.get(at[1])?;
+                let (glyph, name) = expression::lookalike(c)?;
+                if Dialect::introducing(glyph) > dialect {
+                    return None;
+                }
+                Some(Fix {
+                    at,
+                    found: c,
+                    glyph,
+                    name,
+                })
+            })
+            .collect();
+        found.dedup_by_key(|fix| fix.at);
+
+        if found.is_empty() {
+            fixes.sort_by_key(|fix| fix.at);
+            return (join(&lines), fixes);
+        }
+
+        for fix in &found {
+            lines[fix.at[0]][fix.at[1]] = fix.glyph;
+        }
+        fixes.extend(found);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_fixes_lookalike_glyphs() {
+        let code = "┌──────┐\n│▀▃◈◊▀ │\n│▭“Hi” │\n└──────┘\n";
+        let (fixed, fixes) = fix::<i64>(code, Dialect::V3);
+
+        assert_eq!(fixed, "┌──────┐\n│▀▄◈◇▀ │\n│▭\"Hi\" │\n└──────┘\n");
+        assert_eq!(
+            fixes.iter().map(Fix::describe).collect::<Vec<String>>(),
+            vec![
+                "2:3: replaced ▃ with ▄ (digit zero)",
+                "2:5: replaced ◊ with ◇ (memory)",
+                "3:3: replaced “ with \" (quote)",
+                "3:6: replaced ” with \" (quote)",
+            ]
+        );
+
+        let (fixed, fixes) = fix::<i64>(code, Dialect::V2);
+        assert_eq!(fixed, "┌──────┐\n│▀▄◈◇▀ │\n│▭“Hi” │\n└──────┘\n");
+        assert_eq!(fixes.len(), 2);
+    }
+
+    #[test]
+    fn it_leaves_other_errors_alone() {
+        let code = "┌────┐\n│▀▐a │\n└────┘";
+        assert_eq!(
+            fix::<i64>(code, Dialect::LATEST),
+            (code.to_string(), Vec::new())
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use super::block::Block;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Atom, Dialect};
+use super::interpreter::{ArithMode, ExecContext};
+use super::r#box::Genus;
+use super::stdlib::Builtin;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+pub const MAX_CALL_DEPTH: usize = 64;
+pub const CALL_DEPTH_LIMIT: usize = 4096;
+pub const CALL_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame<T: BoxInt> {
+    pub number: T,
+    pub argument: T,
+    pub scratch: HashMap<T, T>,
+}
+
+pub type Host<T> = dyn Fn(&mut ExecContext<T>) -> Result<T, BsError>;
+
+#[derive(Clone)]
+pub struct Functions<T: BoxInt> {
+    bodies: HashMap<T, Block<T>>,
+    idle: HashMap<T, Vec<Block<T>>>,
+    hosts: HashMap<T, Rc<Host<T>>>,
+    frames: Vec<Frame<T>>,
+}
+
+impl<T: BoxInt> Default for Functions<T> {
+    fn default() -> Functions<T> {
+        Functions {
+            bodies: HashMap::new(),
+            idle: HashMap::new(),
+            hosts: HashMap::new(),
+            frames: Vec::new(),
+        }
+    }
+}
+
+impl<T: BoxInt> Functions<T> {
+    pub fn new() -> Functions<T> {
+        Functions::default()
+    }
+
+    pub fn define(&mut self, blocks: &mut Vec<Block<T>>, dialect: Dialect) -> Result<(), BsError> {
+        for block in Block::walk(blocks) {
+            for child in block.children.iter() {
+                if child.r#box.genus == Genus::Function {
+                    return Err(BsError::ParseError(
+                        "Function boxes must be at the top level".to_string(),
+                        Some(child.r#box.top_left),
+                    ));
+                }
+            }
+        }
+
+        let mut bodies: HashMap<T, Block<T>> = HashMap::new();
+        let mut i = 0;
+        while i < blocks.len() {
+            if blocks[i].r#box.genus != Genus::Function {
+                i += 1;
+                continue;
+            }
+
+            let block = blocks.remove(i);
+            let at = block.r#box.top_left;
+            let needed = Dialect::introducing('◴');
+            if needed > dialect {
+                return Err(BsError::ParseError(
+                    format!("Glyph ◴ needs dialect {} or later", needed.number()),
+                    Some([at[0] + 1, at[1] + 1]),
+                ));
+            }
+
+            let folded = block.guard.as_ref().map(|guard| guard.folded());
+            let number = match folded.as_ref().map(|guard| guard.atoms()) {
+                Some([Atom::Data(number)]) => *number,
+                _ => {
+                    return Err(BsError::ParseError(
+                        "Function number must be a constant".to_string(),
+                        Some(at),
+                    ))
+                }
+            };
+            if bodies.insert(number, block).is_some() {
+                return Err(BsError::ParseError(
+                    format!("Function {} is defined more than once", number),
+                    Some(at),
+                ));
+            }
+        }
+
+        self.bodies = bodies;
+        self.idle.clear();
+        Ok(())
+    }
+
+    pub fn register(&mut self, number: T, host: Rc<Host<T>>) {
+        self.hosts.insert(number, host);
+    }
+
+    pub fn bodies(&self) -> &HashMap<T, Block<T>> {
+        &self.bodies
+    }
+
+    pub fn frames(&self) -> &[Frame<T>] {
+        &self.frames
+    }
+
+    pub fn argument(&self) -> Option<T> {
+        self.frames.last().map(|frame| frame.argument)
+    }
+
+    pub fn scratch(&mut self) -> Option<&mut HashMap<T, T>> {
+        self.frames.last_mut().map(|frame| &mut frame.scratch)
+    }
+
+    fn checkout(&mut self, number: T) -> Option<Block<T>> {
+        self.idle
+            .get_mut(&number)
+            .and_then(Vec::pop)
+            .or_else(|| self.bodies.get(&number).cloned())
+    }
+}
+
+pub fn call<T: BoxInt>(
+    context: &mut ExecContext<T>,
+    number: T,
+    argument: T,
+    mode: ArithMode,
+) -> Result<T, BsError> {
+    let functions = match context.functions.as_mut() {
+        Some(functions)
+            if functions.bodies.contains_key(&number) || functions.hosts.contains_key(&number) =>
+        {
+            functions
+        }
+        _ => {
+            return match Builtin::from_number(number) {
+                Some(builtin) => builtin.run(context, argument, mode),
+                None => Err(BsError::RuntimeError(
+                    format!("No function numbered {}", number),
+                    None,
+                )),
+            }
+        }
+    };
+    if functions.frames.len() >= context.max_call_depth.min(CALL_DEPTH_LIMIT) {
+        return Err(BsError::RuntimeError(
+            format!("Call stack exceeded {} frames", functions.frames.len()),
+            None,
+        ));
+    }
+    let body = functions.checkout(number);
+    let host = match body {
+        Some(_) => None,
+        None => functions.hosts.get(&number).cloned(),
+    };
+    functions.frames.push(Frame {
+        number,
+        argument,
+        scratch: HashMap::new(),
+    });
+
+    let value = match (body, host) {
+        (Some(mut body), _) => {
+            let value = body.call(context);
+            let functions = context.functions.as_mut().unwrap();
+            functions.idle.entry(number).or_default().push(body);
+            value
+        }
+        (None, host) => host.unwrap()(context),
+    };
+
+    context.functions.as_mut().unwrap().frames.pop();
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::interpreter::Interpreter;
+    use super::*;
+
+    fn run(code: &str) -> Result<String, BsError> {
+        let mut interpreter = Interpreter::<i64>::new();
+        interpreter.set_dialect(Dialect::V3);
+        interpreter.run_program(code)
+    }
+
+    #[test]
+    fn it_calls_functions() {
+        let code = "┌────────────┐\n│◴▀▀         │\n│▀◈◶▐◶       │\n│◇▀          │\n└────────────┘\n┌────────────┐\n│▬▀▀◵▀▀▀     │\n└────────────┘";
+        assert_eq!(run(code), Ok("6".to_string()));
+    }
+
+    #[test]
+    fn it_stops_inside_functions() {
+        let code = "┌────────────┐\n│◴▀▀         │\n│▀◈◶▐◶       │\n│◇▀          │\n└────────────┘\n┌────────────┐\n│▬▀▀◵▀▀▀     │\n└────────────┘";
+        let mut interpreter = Interpreter::<i64>::new();
+        interpreter.set_dialect(Dialect::V3);
+        let mut stops: Vec<[usize; 2]> = Vec::new();
+
+        interpreter
+            .run_program_with(code, &mut |stop| {
+                stops.push(stop.at);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(stops, vec![[6, 1], [2, 1], [3, 1]]);
+    }
+
+    #[test]
+    fn it_recurses() {
+        let code = "┌────────────────┐\n│◴▀▀             │\n│▀◈▀▀            │\n│┏━━━━━━━━━━━━━┓ │\n│┃◶▧▀▀         ┃ │\n│┃▀◈◶▘▀▀◵▕◶▌▀▀▏┃ │\n│┗━━━━━━━━━━━━━┛ │\n│◇▀              │\n└────────────────┘\n┌────────────────┐\n│▬▀▀◵▀▀▄▀        │\n└────────────────┘";
+        assert_eq!(run(code), Ok("120".to_string()));
+    }
+
+    #[test]
+    fn it_keeps_locals_per_frame() {
+        let code = "┌──────────────┐\n│◴▀▀           │\n│▀◸◶▘◶         │\n│▀◈▀           │\n│┏━━━━━━━━━━━━┓│\n│┃◶           ┃│\n│┃▀▀◈▀▀◵▕◶▌▀▀▏┃│\n│┃▀◈◇▀▀▐◷▀    ┃│\n│┗━━━━━━━━━━━━┛│\n│◇▀            │\n└──────────────┘\n┌───────┐\n│▬▀▀◵▀▀▀│\n└───────┘";
+        assert_eq!(run(code), Ok("14".to_string()));
+        assert_eq!(
+            run("┌────┐\n│▀◸▀▀│\n└────┘"),
+            Err(BsError::RuntimeError(
+                "Local memory used outside of a function".to_string(),
+                Some([1, 2])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_limits_call_depth() {
+        let code = "┌────┐\n│◴▀▀ │\n│▀▀◵◶│\n└────┘\n┌────┐\n│▀▀◵▀│\n└────┘";
+        let mut interpreter = Interpreter::<i64>::new();
+        interpreter.set_dialect(Dialect::V3);
+
+        interpreter.set_max_call_depth(3);
+        assert_eq!(
+            interpreter.run_program(code),
+            Err(BsError::RuntimeError(
+                "Call stack exceeded 3 frames".to_string(),
+                Some([2, 3])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_caps_call_depth() {
+        let code = "┌────────────────────┐\n│◴▀▀                 │\n│┌──────────────────┐│\n││▀▀                ││\n││┏━━━━━━━━━━━━━━━━┓││\n││┃▀▀◵▕◶▐▀▀▏▐▀▀    ┃││\n││┗━━━━━━━━━━━━━━━━┛││\n│└──────────────────┘│\n└────────────────────┘\n┌────────────────────┐\n│▬▀▀◵▀▀▀             │\n└────────────────────┘";
+        let (depth, result) = std::thread::Builder::new()
+            .stack_size(CALL_STACK_SIZE)
+            .spawn(move || {
+                let mut interpreter = Interpreter::<i64>::new();
+                interpreter.set_dialect(Dialect::V3);
+                interpreter.set_max_call_depth(1_000_000);
+                (interpreter.max_call_depth(), interpreter.run_program(code))
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        assert_eq!(depth, CALL_DEPTH_LIMIT);
+        assert_eq!(
+            result.map_err(|error| error.message().to_string()),
+            Err(format!("Call stack exceeded {} frames", CALL_DEPTH_LIMIT))
+        );
+    }
+
+    #[test]
+    fn it_rejects_bad_functions() {
+        assert_eq!(
+            run("┌─────┐\n│▬▀▀◵▀│\n└─────┘"),
+            Err(BsError::RuntimeError(
+                "No function numbered 1".to_string(),
+                Some([1, 4])
+            ))
+        );
+        assert_eq!(
+            run("┌────┐\n│◴◇▀ │\n└────┘"),
+            Err(BsError::ParseError(
+                "Function number must be a constant".to_string(),
+                Some([0, 0])
+            ))
+        );
+        assert_eq!(
+            run("┌───┐\n│◴▀▀│\n└───┘\n┌───┐\n│◴▀▀│\n└───┘"),
+            Err(BsError::ParseError(
+                "Function 1 is defined more than once".to_string(),
+                Some([3, 0])
+            ))
+        );
+        assert_eq!(
+            run("┌───┐\n│▬◶ │\n└───┘"),
+            Err(BsError::RuntimeError(
+                "Argument used outside of a function".to_string(),
+                Some([1, 2])
+            ))
+        );
+        assert_eq!(
+            run("┌──────┐\n│◴▀▀   │\n│▀▀◵◶  │\n└──────┘\n┌──────┐\n│▀▀◵▀  │\n└──────┘"),
+            Err(BsError::RuntimeError(
+                "Call stack exceeded 64 frames".to_string(),
+                Some([2, 3])
+            ))
+        );
+    }
+}
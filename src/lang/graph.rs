@@ -0,0 +1,157 @@
+use super::analysis;
+use super::block::Block;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::r#box::Genus;
+use super::stdlib::Builtin;
+use super::xref::name;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Nesting,
+    Calls,
+}
+
+impl Kind {
+    pub fn from_name(name: &str) -> Option<Kind> {
+        match name {
+            "nesting" => Some(Kind::Nesting),
+            "calls" => Some(Kind::Calls),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Kind::Nesting => "nesting",
+            Kind::Calls => "calls",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Graph {
+    pub kind: Kind,
+    pub nodes: Vec<String>,
+    pub edges: Vec<(String, String)>,
+}
+
+impl Graph {
+    fn new(kind: Kind) -> Graph {
+        Graph {
+            kind,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+        }
+    }
+
+    fn node(&mut self, node: String) {
+        if !self.nodes.contains(&node) {
+            self.nodes.push(node);
+        }
+    }
+
+    fn edge(&mut self, from: String, to: String) {
+        self.node(from.clone());
+        self.node(to.clone());
+        self.edges.push((from, to));
+    }
+
+    pub fn dot(&self) -> String {
+        let mut lines = vec![format!("digraph {} {{", self.kind.name())];
+        lines.extend(self.nodes.iter().map(|node| format!("    \"{}\";", node)));
+        lines.extend(
+            self.edges
+                .iter()
+                .map(|(from, to)| format!("    \"{}\" -> \"{}\";", from, to)),
+        );
+        lines.push("}".to_string());
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+}
+
+pub fn graph<T: BoxInt>(source: &str, kind: Kind) -> Result<Graph, BsError> {
+    let blocks = Block::<T>::build(source)?;
+    let mut graph = Graph::new(kind);
+
+    match kind {
+        Kind::Nesting => {
+            for block in Block::walk(&blocks) {
+                graph.node(name(&block.r#box));
+                for child in block.children.iter() {
+                    graph.edge(name(&block.r#box), name(&child.r#box));
+                }
+            }
+        }
+        Kind::Calls => {
+            let functions: BTreeMap<T, String> = blocks
+                .iter()
+                .filter(|block| block.r#box.genus == Genus::Function)
+                .filter_map(|block| {
+                    let number = block.guard.as_ref().and_then(analysis::fold)?;
+                    Some((number, name(&block.r#box)))
+                })
+                .collect();
+            let callee = |number: &T| match functions.get(number) {
+                Some(function) => function.clone(),
+                None => match Builtin::from_number(*number) {
+                    Some(builtin) => format!("builtin {}", builtin.name()),
+                    None => format!("function {}", number),
+                },
+            };
+
+            for block in blocks.iter() {
+                if block.r#box.genus == Genus::NoOp {
+                    continue;
+                }
+
+                let cells = analysis::cells(block);
+                graph.node(name(&block.r#box));
+                for number in cells.calls.iter() {
+                    graph.edge(name(&block.r#box), callee(number));
+                }
+                if cells.calls_any {
+                    graph.edge(name(&block.r#box), "any function".to_string());
+                }
+            }
+        }
+    }
+
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "┌───────────┐\n│◴▀▀        │\n│▀▀◵◶       │\n└───────────┘\n┌───────────┐\n│▭▀▀◵▀▀▀    │\n│┌────────┐ │\n││▭◇▀◵▀   │ │\n│└────────┘ │\n└───────────┘";
+
+    #[test]
+    fn it_graphs_nesting() {
+        assert_eq!(
+            graph::<i8>(PROGRAM, Kind::Nesting).unwrap().dot(),
+            "digraph nesting {\n    \"function 1:1\";\n    \"execution 5:1\";\n    \"execution 7:2\";\n    \"execution 5:1\" -> \"execution 7:2\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn it_graphs_calls() {
+        let calls = graph::<i8>(PROGRAM, Kind::Calls).unwrap();
+        assert_eq!(
+            calls.edges,
+            vec![
+                ("function 1:1".to_string(), "function 1:1".to_string()),
+                ("execution 5:1".to_string(), "function 1:1".to_string()),
+                ("execution 5:1".to_string(), "any function".to_string()),
+            ]
+        );
+
+        let builtin = "┌──────────┐\n│▭▁◉3◵▀    │\n└──────────┘";
+        assert_eq!(
+            graph::<i8>(builtin, Kind::Calls).unwrap().edges,
+            vec![("execution 1:1".to_string(), "builtin abs".to_string())]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use super::block::Block;
+use super::canon;
+use super::datatype::BoxInt;
+use super::error::BsError;
+#[cfg(feature = "fs")]
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Include {
+    pub path: String,
+    pub hash: u64,
+}
+
+impl Include {
+    pub fn parse(header: &str, at: [usize; 2]) -> Result<Option<Include>, BsError> {
+        let mut words = header.split_whitespace();
+        if words.next() != Some("include") {
+            return Ok(None);
+        }
+
+        let malformed = || BsError::ParseError("Malformed include".to_string(), Some(at));
+        let path = words.next().ok_or_else(malformed)?;
+        let hash = words
+            .next()
+            .filter(|hash| hash.len() == 16)
+            .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+            .ok_or_else(malformed)?;
+
+        if words.next().is_some() {
+            return Err(malformed());
+        }
+
+        Ok(Some(Include {
+            path: path.to_string(),
+            hash,
+        }))
+    }
+
+    pub fn header(&self) -> String {
+        format!("include {} {:016x}", self.path, self.hash)
+    }
+}
+
+#[cfg(feature = "fs")]
+fn read(path: &Path, include: &Include) -> Result<String, BsError> {
+    fs::read_to_string(path)
+        .map_err(|error| BsError::IoError(format!("{}: {}", include.path, error)))
+}
+
+#[cfg(not(feature = "fs"))]
+fn read(_: &Path, include: &Include) -> Result<String, BsError> {
+    Err(BsError::IoError(format!(
+        "{}: file access needs the fs feature",
+        include.path
+    )))
+}
+
+pub fn resolve<T: BoxInt>(blocks: &mut [Block<T>], dir: &Path) -> Result<(), BsError> {
+    let mut pending: Vec<(&mut Block<T>, PathBuf)> = blocks
+        .iter_mut()
+        .rev()
+        .map(|block| (block, dir.to_path_buf()))
+        .collect();
+
+    while let Some((block, dir)) = pending.pop() {
+        let include = match &block.include {
+            Some(include) => include.clone(),
+            None => {
+                pending.extend(
+                    block
+                        .children
+                        .iter_mut()
+                        .rev()
+                        .map(|child| (child, dir.clone())),
+                );
+                continue;
+            }
+        };
+        let at = Some(block.r#box.top_left);
+
+        let path = dir.join(&include.path);
+        let source = read(&path, &include)?;
+        let inside =
+            |error: BsError| BsError::ParseError(format!("{} in {}", error, include.path), at);
+        let found = canon::hash(&canon::canonicalize::<T>(&source).map_err(inside)?);
+        if found != include.hash {
+            return Err(BsError::ValidationError(
+                format!(
+                    "Include hash mismatch for {} (expected {:016x}, found {:016x})",
+                    include.path, include.hash, found
+                ),
+                at,
+            ));
+        }
+
+        block.children = Block::build(&source).map_err(inside)?;
+        let inner = path.parent().map_or(dir, Path::to_path_buf);
+        pending.extend(
+            block
+                .children
+                .iter_mut()
+                .rev()
+                .map(|child| (child, inner.clone())),
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "fs")]
+    fn scratch(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("boxscript-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    fn pin(path: &str, source: &str) -> String {
+        Include {
+            path: path.to_string(),
+            hash: canon::hash(&canon::canonicalize::<i8>(source).unwrap()),
+        }
+        .header()
+    }
+
+    fn including(header: &str) -> String {
+        let width = header.chars().count();
+        format!("┌{}┐\n└{}┘", header, "─".repeat(width))
+    }
+
+    #[test]
+    fn it_parses_includes() {
+        assert_eq!(
+            Include::parse("include lib.bs 00000000000000ff", [0, 0]),
+            Ok(Some(Include {
+                path: "lib.bs".to_string(),
+                hash: 255
+            }))
+        );
+        assert_eq!(Include::parse("◇▀▨▀▀", [0, 0]), Ok(None));
+        assert_eq!(
+            Include::parse("include lib.bs", [2, 3]),
+            Err(BsError::ParseError(
+                "Malformed include".to_string(),
+                Some([2, 3])
+            ))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn it_runs_pinned_includes() {
+        use crate::lang::input::NoInput;
+        use crate::lang::interpreter::{ExecContext, Runnable};
+        use std::collections::HashMap;
+
+        let library = "┌──────┐\n│▭▀▀▄▄▀│\n└──────┘";
+        let dir = scratch("pinned", &[("lib.bs", library)]);
+
+        let mut blocks = Block::<i8>::build(&including(&pin("lib.bs", library))).unwrap();
+        resolve(&mut blocks, &dir).unwrap();
+
+        let mut stdout = String::new();
+        blocks[0]
+            .run(&mut ExecContext::new(
+                &mut HashMap::new(),
+                &mut stdout,
+                &mut NoInput,
+            ))
+            .unwrap();
+        assert_eq!(stdout, "\t");
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn it_rejects_changed_includes() {
+        let library = "┌──────┐\n│▭▀▀▄▄▀│\n└──────┘";
+        let dir = scratch("changed", &[("lib.bs", "┌──────┐\n│▭▀▀▄▄▄│\n└──────┘")]);
+
+        let header = pin("lib.bs", library);
+        let mut blocks = Block::<i8>::build(&including(&header)).unwrap();
+        let error = resolve(&mut blocks, &dir).unwrap_err();
+
+        assert!(matches!(error, BsError::ValidationError(_, Some([0, 0]))));
+        assert!(error
+            .message()
+            .starts_with("Include hash mismatch for lib.bs"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "fs"))]
+    fn it_needs_file_access() {
+        let library = "┌──────┐\n│▭▀▀▄▄▀│\n└──────┘";
+        let mut blocks = Block::<i8>::build(&including(&pin("lib.bs", library))).unwrap();
+
+        assert_eq!(
+            resolve(&mut blocks, Path::new(".")),
+            Err(BsError::IoError(
+                "lib.bs: file access needs the fs feature".to_string()
+            ))
+        );
+    }
+}
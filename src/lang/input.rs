@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::io::Read;
+
+pub trait InputSource {
+    fn read_char(&mut self) -> Option<char>;
+
+    fn poll_key(&mut self) -> Option<char> {
+        None
+    }
+
+    fn read_int(&mut self) -> Option<i128> {
+        let mut c = self.read_char()?;
+        while c.is_whitespace() {
+            c = self.read_char()?;
+        }
+
+        let mut digits = String::new();
+        if c == '-' || c == '+' {
+            digits.push(c);
+            c = self.read_char()?;
+        }
+        while c.is_ascii_digit() {
+            digits.push(c);
+            match self.read_char() {
+                Some(next) => c = next,
+                None => break,
+            }
+        }
+
+        digits.parse().ok()
+    }
+}
+
+pub struct NoInput;
+
+impl InputSource for NoInput {
+    fn read_char(&mut self) -> Option<char> {
+        None
+    }
+}
+
+impl InputSource for VecDeque<char> {
+    fn read_char(&mut self) -> Option<char> {
+        self.pop_front()
+    }
+
+    fn poll_key(&mut self) -> Option<char> {
+        self.pop_front()
+    }
+}
+
+pub struct Reader<R: Read> {
+    bytes: std::io::Bytes<std::io::BufReader<R>>,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(reader: R) -> Reader<R> {
+        Reader {
+            bytes: std::io::BufReader::new(reader).bytes(),
+        }
+    }
+}
+
+impl<R: Read> InputSource for Reader<R> {
+    fn read_char(&mut self) -> Option<char> {
+        let first = self.bytes.next()?.ok()?;
+        let length = match first {
+            0x00..=0x7f => return Some(first as char),
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Some('\u{fffd}'),
+        };
+
+        let mut buffer = vec![first];
+        for _ in 1..length {
+            buffer.push(self.bytes.next()?.ok()?);
+        }
+
+        Some(
+            std::str::from_utf8(&buffer)
+                .ok()
+                .and_then(|s| s.chars().next())
+                .unwrap_or('\u{fffd}'),
+        )
+    }
+}
+
+#[cfg(all(unix, feature = "raw-keys"))]
+pub struct RawKeyboard {
+    original: libc::termios,
+    pending: VecDeque<char>,
+}
+
+#[cfg(all(unix, feature = "raw-keys"))]
+impl RawKeyboard {
+    pub fn new() -> std::io::Result<RawKeyboard> {
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 0;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(RawKeyboard {
+            original,
+            pending: VecDeque::new(),
+        })
+    }
+}
+
+#[cfg(all(unix, feature = "raw-keys"))]
+impl InputSource for RawKeyboard {
+    fn read_char(&mut self) -> Option<char> {
+        loop {
+            if let Some(key) = self.poll_key() {
+                return Some(key);
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+    }
+
+    fn poll_key(&mut self) -> Option<char> {
+        if self.pending.is_empty() {
+            let mut buffer = [0u8; 64];
+            let read = unsafe {
+                libc::read(
+                    libc::STDIN_FILENO,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+
+            if read > 0 {
+                self.pending
+                    .extend(String::from_utf8_lossy(&buffer[..read as usize]).chars());
+            }
+        }
+
+        self.pending.pop_front()
+    }
+}
+
+#[cfg(all(unix, feature = "raw-keys"))]
+impl Drop for RawKeyboard {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_polls_keys() {
+        assert_eq!(NoInput.poll_key(), None);
+
+        let mut keys: VecDeque<char> = "ab".chars().collect();
+        assert_eq!(keys.poll_key(), Some('a'));
+        assert_eq!(keys.poll_key(), Some('b'));
+        assert_eq!(keys.poll_key(), None);
+    }
+
+    #[test]
+    fn it_reads_characters() {
+        let mut input = Reader::new("hé\u{1F4E6}".as_bytes());
+        assert_eq!(input.read_char(), Some('h'));
+        assert_eq!(input.read_char(), Some('é'));
+        assert_eq!(input.read_char(), Some('\u{1F4E6}'));
+        assert_eq!(input.read_char(), None);
+        assert_eq!(input.poll_key(), None);
+
+        assert_eq!(Reader::new(&[0xff, b'a'][..]).read_char(), Some('\u{fffd}'));
+    }
+
+    #[test]
+    fn it_reads_integers() {
+        let mut input = Reader::new("  42\n-7 x +3".as_bytes());
+        assert_eq!(input.read_int(), Some(42));
+        assert_eq!(input.read_int(), Some(-7));
+        assert_eq!(input.read_int(), None);
+        assert_eq!(input.read_int(), Some(3));
+        assert_eq!(input.read_int(), None);
+        assert_eq!(NoInput.read_int(), None);
+    }
+}
@@ -1,25 +1,932 @@
-use num_traits::{PrimInt, Signed, ToPrimitive};
+use super::analysis;
+use super::block::{Block, Hook};
+use super::complexity::Complexity;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Dialect, Molecule};
+use super::function::{Functions, CALL_DEPTH_LIMIT, MAX_CALL_DEPTH};
+use super::include;
+use super::input::{InputSource, NoInput};
+use super::memory::Memory;
+#[cfg(feature = "json")]
+use super::mi::number;
+use super::output::Output;
+use super::replay::Schedule;
+use super::scheduler;
+use super::trace::ExecHook;
+use super::vm::Program;
+#[cfg(feature = "json")]
+use serde_json::Value;
+use std::collections::HashMap;
+#[cfg(all(feature = "fs", feature = "json"))]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-pub trait BoxInt: PrimInt + Signed + ToPrimitive + std::hash::Hash + std::fmt::Display {}
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ArithMode {
+    Checked,
+    Wrapping,
+    Saturating,
+}
 
-impl BoxInt for i8 {}
-impl BoxInt for i16 {}
-impl BoxInt for i32 {}
-impl BoxInt for i64 {}
-impl BoxInt for i128 {}
+impl ArithMode {
+    pub fn from_name(name: &str) -> Option<ArithMode> {
+        match name {
+            "checked" => Some(ArithMode::Checked),
+            "wrapping" => Some(ArithMode::Wrapping),
+            "saturating" => Some(ArithMode::Saturating),
+            _ => None,
+        }
+    }
+}
 
-pub trait Runnable<T> {
-    fn run(
-        &mut self,
-        memory: &mut std::collections::HashMap<T, T>,
-        stdout: &mut String,
-    ) -> Result<(T, String), String>;
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Backend {
+    Tree,
+    Vm,
+}
+
+impl Backend {
+    pub fn from_name(name: &str) -> Option<Backend> {
+        match name {
+            "tree" => Some(Backend::Tree),
+            "vm" => Some(Backend::Vm),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateSnapshot<T: BoxInt> {
+    pub memory: Vec<(T, T)>,
+    pub next_box: usize,
+}
+
+#[cfg(feature = "json")]
+impl<T: BoxInt> StateSnapshot<T> {
+    pub fn to_json(&self) -> String {
+        json!({
+            "memory": self
+                .memory
+                .iter()
+                .map(|(address, value)| json!([number(*address), number(*value)]))
+                .collect::<Vec<Value>>(),
+            "next_box": self.next_box,
+        })
+        .to_string()
+    }
+
+    pub fn from_json(text: &str) -> Result<StateSnapshot<T>, BsError> {
+        let invalid = || BsError::ParseError("Invalid saved state".to_string(), None);
+        let number = |value: &Value| match value {
+            Value::Number(number) => number.as_i64().and_then(T::from),
+            Value::String(text) => T::from_str_radix(text, 10).ok(),
+            _ => None,
+        };
+
+        let state: Value = serde_json::from_str(text).map_err(|_| invalid())?;
+        let memory = state["memory"]
+            .as_array()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|cell| match cell.as_array().map(Vec::as_slice) {
+                Some([address, value]) => Some((number(address)?, number(value)?)),
+                _ => None,
+            })
+            .collect::<Option<Vec<(T, T)>>>()
+            .ok_or_else(invalid)?;
+        let next_box = state["next_box"].as_u64().ok_or_else(invalid)? as usize;
+
+        Ok(StateSnapshot { memory, next_box })
+    }
+}
+
+pub struct ExecContext<'a, T: BoxInt> {
+    pub memory: &'a mut dyn Memory<T>,
+    pub stdout: &'a mut dyn Output,
+    pub input: &'a mut dyn InputSource,
+    pub max_iterations: Option<usize>,
+    pub max_steps: Option<usize>,
+    pub steps: usize,
+    pub timeout: Option<Duration>,
+    pub started: Option<Instant>,
+    pub sequential: bool,
+    pub hook: Option<&'a mut dyn ExecHook<T>>,
+    pub stop: Option<&'a mut Hook<'a, T>>,
+    pub schedule: Option<&'a mut Schedule>,
+    pub functions: Option<&'a mut Functions<T>>,
+    pub max_call_depth: usize,
+    pub next_box: usize,
+    pub reloads: Vec<Block<T>>,
+}
+
+impl<'a, T: BoxInt> ExecContext<'a, T> {
+    pub fn new(
+        memory: &'a mut dyn Memory<T>,
+        stdout: &'a mut dyn Output,
+        input: &'a mut dyn InputSource,
+    ) -> ExecContext<'a, T> {
+        ExecContext {
+            memory,
+            stdout,
+            input,
+            max_iterations: None,
+            max_steps: None,
+            steps: 0,
+            timeout: None,
+            started: None,
+            sequential: false,
+            hook: None,
+            stop: None,
+            schedule: None,
+            functions: None,
+            max_call_depth: MAX_CALL_DEPTH,
+            next_box: 0,
+            reloads: Vec::new(),
+        }
+    }
+
+    pub fn spend(&mut self, atoms: usize, at: Option<[usize; 2]>) -> Result<(), BsError> {
+        if self
+            .max_steps
+            .is_some_and(|max| self.steps.saturating_add(atoms) > max)
+        {
+            return Err(BsError::RuntimeError(
+                format!("Execution budget exceeded after {} steps", self.steps),
+                at,
+            ));
+        }
+
+        if let Some(timeout) = self.timeout {
+            if self.started.get_or_insert_with(Instant::now).elapsed() >= timeout {
+                return Err(BsError::RuntimeError(
+                    format!("Timed out after {} seconds", timeout.as_secs_f64()),
+                    at,
+                ));
+            }
+        }
+
+        self.steps = self.steps.saturating_add(atoms);
+        Ok(())
+    }
+
+    pub fn argument(&self) -> Option<T> {
+        self.functions
+            .as_ref()
+            .and_then(|functions| functions.argument())
+    }
+
+    pub fn snapshot(&self) -> StateSnapshot<T> {
+        StateSnapshot {
+            memory: self.memory.cells(),
+            next_box: self.next_box,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &StateSnapshot<T>) {
+        self.memory.clear();
+        for (address, value) in snapshot.memory.iter() {
+            self.memory.set(*address, *value);
+        }
+        self.next_box = snapshot.next_box;
+    }
+}
+
+pub trait Runnable<T: BoxInt> {
+    fn run(&mut self, context: &mut ExecContext<T>) -> Result<T, BsError>;
 }
 
 pub trait Parser<T> {
-    fn parse(expr: &str) -> Result<Vec<T>, String>;
+    fn parse(expr: &str) -> Result<Vec<T>, BsError>;
 }
 
 pub trait Validator<T> {
-    fn validate(children: &[T], valid: &mut bool) -> Result<(), String>;
+    fn validate(children: &[T], valid: &mut bool) -> Result<(), BsError>;
+}
+
+pub struct Interpreter<T: BoxInt> {
+    memory: Box<dyn Memory<T>>,
+    max_iterations: Option<usize>,
+    max_steps: Option<usize>,
+    max_call_depth: usize,
+    timeout: Option<Duration>,
+    sequential: bool,
+    arith_mode: ArithMode,
+    dialect: Dialect,
+    silent_assign: bool,
+    backend: Backend,
+    complexity: Complexity,
+    include_dir: PathBuf,
+    input: Box<dyn InputSource>,
+    exec_hook: Option<Box<dyn ExecHook<T>>>,
+    schedule: Option<Schedule>,
+    functions: Functions<T>,
+    #[cfg(all(feature = "fs", feature = "json"))]
+    checkpoint: Option<PathBuf>,
+    resume_at: usize,
+}
+
+impl<T: BoxInt> Default for Interpreter<T> {
+    fn default() -> Interpreter<T> {
+        Interpreter::new()
+    }
+}
+
+impl<T: BoxInt> Interpreter<T> {
+    pub fn new() -> Interpreter<T> {
+        Interpreter {
+            memory: Box::new(HashMap::new()),
+            max_iterations: None,
+            max_steps: None,
+            max_call_depth: MAX_CALL_DEPTH,
+            timeout: None,
+            sequential: false,
+            arith_mode: ArithMode::Checked,
+            dialect: Dialect::STABLE,
+            silent_assign: false,
+            backend: Backend::Tree,
+            complexity: Complexity::default(),
+            include_dir: PathBuf::from("."),
+            input: Box::new(NoInput),
+            exec_hook: None,
+            schedule: None,
+            functions: Functions::new(),
+            #[cfg(all(feature = "fs", feature = "json"))]
+            checkpoint: None,
+            resume_at: 0,
+        }
+    }
+
+    pub fn set_max_iterations(&mut self, max_iterations: Option<usize>) {
+        self.max_iterations = max_iterations;
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: Option<usize>) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth.min(CALL_DEPTH_LIMIT);
+    }
+
+    pub fn max_call_depth(&self) -> usize {
+        self.max_call_depth
+    }
+
+    pub fn register_builtin<F>(&mut self, id: T, callback: F)
+    where
+        F: Fn(&mut ExecContext<T>) -> Result<T, BsError> + 'static,
+    {
+        self.functions.register(id, Rc::new(callback));
+    }
+
+    pub fn set_sequential(&mut self, sequential: bool) {
+        self.sequential = sequential;
+    }
+
+    pub fn sequential(&self) -> bool {
+        self.sequential
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_arith_mode(&mut self, arith_mode: ArithMode) {
+        self.arith_mode = arith_mode;
+    }
+
+    pub fn arith_mode(&self) -> ArithMode {
+        self.arith_mode
+    }
+
+    pub fn set_dialect(&mut self, dialect: Dialect) {
+        self.dialect = dialect;
+    }
+
+    pub fn dialect(&self) -> Dialect {
+        self.dialect
+    }
+
+    pub fn set_silent_assign(&mut self, silent_assign: bool) {
+        self.silent_assign = silent_assign;
+    }
+
+    pub fn silent_assign(&self) -> bool {
+        self.silent_assign
+    }
+
+    pub fn set_schedule(&mut self, schedule: Option<Schedule>) {
+        self.schedule = schedule;
+    }
+
+    pub fn schedule(&self) -> Option<&Schedule> {
+        self.schedule.as_ref()
+    }
+
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    pub fn set_complexity(&mut self, complexity: Complexity) {
+        self.complexity = complexity;
+    }
+
+    pub fn complexity(&self) -> Complexity {
+        self.complexity
+    }
+
+    pub fn set_include_dir(&mut self, include_dir: PathBuf) {
+        self.include_dir = include_dir;
+    }
+
+    pub fn include_dir(&self) -> &Path {
+        &self.include_dir
+    }
+
+    pub fn set_input(&mut self, input: Box<dyn InputSource>) {
+        self.input = input;
+    }
+
+    pub fn set_memory(&mut self, memory: Box<dyn Memory<T>>) {
+        self.memory = memory;
+    }
+
+    pub fn set_exec_hook(&mut self, hook: Option<Box<dyn ExecHook<T>>>) {
+        self.exec_hook = hook;
+    }
+
+    #[cfg(all(feature = "fs", feature = "json"))]
+    pub fn set_checkpoint(&mut self, checkpoint: Option<PathBuf>) {
+        self.checkpoint = checkpoint;
+    }
+
+    pub fn restore(&mut self, snapshot: &StateSnapshot<T>) {
+        self.memory.clear();
+        for (address, value) in snapshot.memory.iter() {
+            self.memory.set(*address, *value);
+        }
+        self.resume_at = snapshot.next_box;
+    }
+
+    pub fn memory(&self) -> &dyn Memory<T> {
+        &*self.memory
+    }
+
+    pub fn memory_mut(&mut self) -> &mut dyn Memory<T> {
+        &mut *self.memory
+    }
+
+    pub fn eval(&mut self, expr: &str) -> Result<(T, String), BsError> {
+        let mut molecule = Molecule::read(expr, [0, 0])?;
+        self.dialect.check(&molecule)?;
+        molecule.set_arith_mode(self.arith_mode);
+        if self.silent_assign {
+            molecule.silence_assignments();
+        }
+        let mut stdout = String::new();
+        let value = molecule.run(&mut self.context(&mut stdout))?;
+
+        Ok((value, stdout))
+    }
+
+    pub fn run_program(&mut self, source: &str) -> Result<String, BsError> {
+        self.run_program_with(source, &mut |_| Ok(()))
+    }
+
+    pub fn run_program_with(
+        &mut self,
+        source: &str,
+        hook: &mut Hook<T>,
+    ) -> Result<String, BsError> {
+        let mut stdout = String::new();
+        self.stream_program(source, &mut stdout, hook)?;
+
+        Ok(stdout)
+    }
+
+    pub fn stream_program(
+        &mut self,
+        source: &str,
+        stdout: &mut dyn Output,
+        hook: &mut Hook<T>,
+    ) -> Result<(), BsError> {
+        let result = self.run_boxes(source, stdout, hook, &mut |_| {});
+        let flushed = stdout.flush();
+        result.and(flushed)
+    }
+
+    fn run_boxes(
+        &mut self,
+        source: &str,
+        stdout: &mut dyn Output,
+        hook: &mut Hook<T>,
+        after: &mut dyn FnMut(&dyn Output),
+    ) -> Result<(), BsError> {
+        let mut blocks = self.prepare(source)?;
+        self.functions.define(&mut blocks, self.dialect)?;
+        let backend = self.backend;
+        #[cfg(all(feature = "fs", feature = "json"))]
+        let checkpoint = self.checkpoint.clone();
+        let start = std::mem::take(&mut self.resume_at);
+        let batches = scheduler::batches(blocks.iter().map(|block| &block.r#box));
+        if start > blocks.len()
+            || batches
+                .iter()
+                .any(|batch| batch.start < start && start < batch.end)
+        {
+            return Err(BsError::RuntimeError(
+                "Saved state does not match this program".to_string(),
+                None,
+            ));
+        }
+
+        let mut context = self.context(stdout);
+        context.stop = Some(hook);
+        context.next_box = start;
+        for batch in batches.into_iter().filter(|batch| batch.start >= start) {
+            for block in std::mem::take(&mut context.reloads) {
+                Block::replace(&mut blocks[batch.start..], block);
+            }
+            Interpreter::execute(backend, &mut blocks[batch.clone()], &mut context)?;
+            context.next_box = batch.end;
+
+            #[cfg(all(feature = "fs", feature = "json"))]
+            if let Some(path) = &checkpoint {
+                fs::write(path, context.snapshot().to_json())?;
+            }
+            after(&*context.stdout);
+        }
+
+        Ok(())
+    }
+
+    pub fn run_frames(&mut self, source: &str) -> Result<Vec<String>, BsError> {
+        self.run_frames_with(source, &mut |_| Ok(()))
+    }
+
+    pub fn run_frames_with(
+        &mut self,
+        source: &str,
+        hook: &mut Hook<T>,
+    ) -> Result<Vec<String>, BsError> {
+        let mut stdout = String::new();
+        let mut frames: Vec<String> = Vec::new();
+        let mut start = 0;
+
+        self.run_boxes(source, &mut stdout, hook, &mut |stdout| {
+            let text = stdout.captured();
+            if text.len() > start {
+                frames.push(text[start..].to_string());
+                start = text.len();
+            }
+        })?;
+
+        Ok(frames)
+    }
+
+    pub fn check(&self, source: &str) -> Vec<BsError> {
+        let errors = analysis::check::<T>(source);
+        if !errors.is_empty() {
+            return errors;
+        }
+
+        match self
+            .prepare(source)
+            .and_then(|mut blocks| Functions::new().define(&mut blocks, self.dialect))
+        {
+            Ok(()) => Vec::new(),
+            Err(error) => vec![error],
+        }
+    }
+
+    pub fn prepare(&self, source: &str) -> Result<Vec<Block<T>>, BsError> {
+        self.complexity.check::<T>(source)?;
+        let mut blocks = Block::<T>::build(source)?;
+        include::resolve(&mut blocks, &self.include_dir)?;
+        for block in Block::walk(&blocks) {
+            for molecule in block.expressions() {
+                self.dialect.check(molecule)?;
+            }
+        }
+
+        for block in blocks.iter_mut() {
+            block.set_arith_mode(self.arith_mode);
+            if self.silent_assign {
+                block.silence_assignments();
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    fn execute(
+        backend: Backend,
+        batch: &mut [Block<T>],
+        context: &mut ExecContext<T>,
+    ) -> Result<T, BsError> {
+        match backend {
+            Backend::Vm if batch.len() == 1 || context.sequential => {
+                let mut value = T::zero();
+                for block in batch.iter_mut() {
+                    value = Program::compile(block).run(context)?;
+                }
+                Ok(value)
+            }
+            _ => scheduler::run(batch, context),
+        }
+    }
+
+    fn context<'a>(&'a mut self, stdout: &'a mut dyn Output) -> ExecContext<'a, T> {
+        let mut context = ExecContext::new(&mut *self.memory, stdout, &mut *self.input);
+        context.max_iterations = self.max_iterations;
+        context.max_steps = self.max_steps;
+        context.max_call_depth = self.max_call_depth;
+        context.timeout = self.timeout;
+        context.sequential = self.sequential;
+        if let Some(hook) = self.exec_hook.as_mut() {
+            context.hook = Some(&mut **hook);
+        }
+        context.schedule = self.schedule.as_mut();
+        context.functions = Some(&mut self.functions);
+        context
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::memory::DenseMemory;
+    use super::super::output::Stream;
+    use super::*;
+
+    #[test]
+    fn it_runs_programs() {
+        assert_eq!(
+            Interpreter::<i64>::new()
+                .run_program("┌─────────────┐\n│▭▀▀▄▄▀▄▄▄    │\n│▭▀▀▀▄▀▄▄▀    │\n└─────────────┘"),
+            Ok("Hi".to_string())
+        );
+
+        let mut interpreter = Interpreter::<i8>::new();
+        assert_eq!(
+            interpreter.run_program("┌────┐\n│▀◈▀▀│\n└────┘\n┌────┐\n│◇▀  │\n└────┘"),
+            Ok(String::new())
+        );
+        assert_eq!(interpreter.memory().cells(), vec![(0, 1)]);
+
+        assert_eq!(
+            Interpreter::<i8>::new().run_program("┌──┐\n│▀▝│\n└──┘"),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                Some([1, 1])
+            ))
+        );
+
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_max_iterations(Some(10));
+        assert_eq!(
+            interpreter.run_program("╔═▀▀═╗\n║▀◈▀▀║\n╚════╝"),
+            Err(BsError::RuntimeError(
+                "Loop exceeded 10 iterations".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_runs_on_the_vm() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_backend(Backend::Vm);
+        assert_eq!(
+            interpreter
+                .run_program("╔═◇▀▨▀▀▄▀═════╗\n║▀◈◇▀▐▀▀      ║\n║▭◇▀▐▀▀▀▄▄▄▄  ║\n╚═════════════╝"),
+            Ok("12345".to_string())
+        );
+        assert_eq!(interpreter.memory().cells(), vec![(0, 5)]);
+
+        interpreter.set_max_iterations(Some(2));
+        assert_eq!(
+            interpreter.run_frames("╔═▀▀═╗\n║▀◈▀▀║\n╚════╝"),
+            Err(BsError::RuntimeError(
+                "Loop exceeded 2 iterations".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_runs_side_by_side_boxes_in_turns() {
+        let code = "┌─────────────────┐\n│▀◈▀▀             │\n│┌─────┐ ┌──────┐ │\n││▭▀▀  │ │▭▀▀▀  │ │\n││▭▀▀▄ │ │▭▀▀▄▄ │ │\n│└─────┘ └──────┘ │\n└─────────────────┘\n┌─────┐┌──────┐    \n│▭▀▀  ││▭▀▀▀  │    \n│▭▀▀▄ ││▭▀▀▄▄ │    \n└─────┘└──────┘    ";
+
+        for backend in &[Backend::Tree, Backend::Vm] {
+            let mut interpreter = Interpreter::<i8>::new();
+            interpreter.set_backend(*backend);
+            assert_eq!(
+                interpreter.run_frames(code),
+                Ok(vec![
+                    "\u{1}\u{3}\u{2}\u{4}".to_string(),
+                    "\u{1}\u{3}\u{2}\u{4}".to_string()
+                ])
+            );
+
+            interpreter.set_sequential(true);
+            assert_eq!(
+                interpreter.run_program(code),
+                Ok("\u{1}\u{2}\u{3}\u{4}\u{1}\u{2}\u{3}\u{4}".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn it_limits_steps() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_max_steps(Some(10));
+        assert_eq!(
+            interpreter.run_program("╔═▀▀═╗\n║▀◈▀▀║\n╚════╝"),
+            Err(BsError::RuntimeError(
+                "Execution budget exceeded after 9 steps".to_string(),
+                Some([1, 1])
+            ))
+        );
+
+        interpreter.set_max_steps(Some(5));
+        assert_eq!(
+            interpreter.run_frames("┌────┐\n│▀◈▀▀│\n└────┘\n┌────┐\n│▀◈▀▀│\n└────┘"),
+            Err(BsError::RuntimeError(
+                "Execution budget exceeded after 3 steps".to_string(),
+                Some([4, 1])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_times_out() {
+        for backend in &[Backend::Tree, Backend::Vm] {
+            let mut interpreter = Interpreter::<i8>::new();
+            interpreter.set_backend(*backend);
+            interpreter.set_timeout(Some(Duration::from_millis(20)));
+            assert_eq!(
+                interpreter
+                    .run_program("╔═▀▀═╗\n║▀◈▀▀║\n╚════╝")
+                    .map_err(|error| error.message().to_string()),
+                Err("Timed out after 0.02 seconds".to_string())
+            );
+
+            interpreter.memory_mut().clear();
+            interpreter.set_timeout(Some(Duration::from_secs(0)));
+            assert_eq!(
+                interpreter.run_program("┌────┐\n│▀◈▀▀│\n└────┘"),
+                Err(BsError::RuntimeError(
+                    "Timed out after 0 seconds".to_string(),
+                    Some([1, 1])
+                ))
+            );
+            assert!(interpreter.memory().is_empty());
+        }
+    }
+
+    #[test]
+    fn it_limits_complexity() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_complexity(Complexity {
+            tokens: Some(2),
+            ..Complexity::default()
+        });
+        assert_eq!(
+            interpreter.run_program("┌──────┐\n│▀◈▀▀  │\n│▭▀▀▀▀▀│\n└──────┘"),
+            Err(BsError::ParseError(
+                "Expression has more than 2 tokens".to_string(),
+                Some([1, 1])
+            ))
+        );
+        assert!(interpreter.memory().is_empty());
+    }
+
+    #[test]
+    fn it_checks_programs_like_it_runs_them() {
+        let mut interpreter = Interpreter::<i8>::new();
+        assert_eq!(
+            interpreter.check("┌───┐\n│▭◉7│\n└───┘"),
+            vec![BsError::ParseError(
+                "Glyph ◉ needs dialect 3 or later".to_string(),
+                Some([1, 2])
+            )]
+        );
+        assert_eq!(interpreter.check("┌──┐\n│▭▀│").len(), 1);
+
+        interpreter.set_dialect(Dialect::V3);
+        assert_eq!(interpreter.check("┌───┐\n│▭◉7│\n└───┘"), vec![]);
+        assert_eq!(
+            interpreter.check("┌───┐\n│◴▀▀│\n└───┘\n┌───┐\n│◴▀▀│\n└───┘"),
+            vec![BsError::ParseError(
+                "Function 1 is defined more than once".to_string(),
+                Some([3, 0])
+            )]
+        );
+
+        interpreter.set_complexity(Complexity {
+            boxes: Some(1),
+            ..Complexity::default()
+        });
+        assert_eq!(
+            interpreter
+                .check("┌───┐\n│▭◉7│\n└───┘\n┌───┐\n│▭◉7│\n└───┘")
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn it_rejects_glyphs_from_newer_dialects() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_dialect(Dialect::V1);
+        assert_eq!(
+            interpreter.run_program("┌──────┐\n│▀◈▀▀  │\n│▀◆▀   │\n└──────┘"),
+            Err(BsError::ParseError(
+                "Glyph ◆ needs dialect 2 or later".to_string(),
+                Some([2, 2])
+            ))
+        );
+        assert!(interpreter.memory().is_empty());
+        assert!(interpreter.eval("▀◆▀").is_err());
+
+        interpreter.set_dialect(Dialect::STABLE);
+        assert_eq!(interpreter.eval("▀◆▀").unwrap().0, 1);
+        assert!(interpreter.eval("◉12").is_err());
+
+        interpreter.set_dialect(Dialect::LATEST);
+        assert_eq!(interpreter.eval("◉12▐◎a").unwrap().0, 22);
+        assert_eq!(
+            interpreter.eval("▭\"Hi!\\n\""),
+            Ok((10, "Hi!\n".to_string()))
+        );
+        assert_eq!(
+            interpreter.eval("▀◈\"Hi\""),
+            Err(BsError::ValidationError(
+                "Text can only be printed".to_string(),
+                Some([0, 1])
+            ))
+        );
+        assert_eq!(
+            interpreter.eval("\"Hi\"").unwrap_err().message(),
+            "Text can only be printed"
+        );
+
+        assert_eq!(interpreter.eval("▬◉42"), Ok((42, "42".to_string())));
+
+        let mut vm = Interpreter::<i8>::new();
+        vm.set_dialect(Dialect::LATEST);
+        vm.set_backend(Backend::Vm);
+        assert_eq!(
+            vm.run_program("┌────────┐\n│▭\"Hi\"   │\n│▭'!'    │\n│▬▀▀▀▀▀▄▄│\n└────────┘"),
+            Ok("Hi!60".to_string())
+        );
+    }
+
+    #[test]
+    fn it_captures_frames() {
+        assert_eq!(
+            Interpreter::<i64>::new().run_frames(
+                "┌─────────┐\n│▭▀▀▄▄▀▄▄▄│\n│▭▀▀▀▄▀▄▄▀│\n└─────────┘\n╭┄┄┄╮\n╰┄┄┄╯\n┌─────────┐\n│▭▀▀▄▄▀▄▄▀│\n└─────────┘"
+            ),
+            Ok(vec!["Hi".to_string(), "I".to_string()])
+        );
+    }
+
+    #[test]
+    fn it_streams_output() {
+        let mut stdout = Stream::new(Vec::new());
+        assert_eq!(
+            Interpreter::<i64>::new().stream_program(
+                "┌─────────┐\n│▭▀▀▄▄▀▄▄▄│\n│▀▝▀      │\n└─────────┘",
+                &mut stdout,
+                &mut |_| Ok(())
+            ),
+            Err(BsError::RuntimeError(
+                "Division caused invalid value".to_string(),
+                Some([2, 2])
+            ))
+        );
+        assert_eq!(stdout.into_inner(), Ok(b"H".to_vec()));
+    }
+
+    #[test]
+    fn it_runs_on_other_memory() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_memory(Box::new(DenseMemory::new(2)));
+        assert_eq!(
+            interpreter.run_program("┌──────────┐\n│▀◈▀▀      │\n│▀▀▀◈◇▀▐▀▀ │\n└──────────┘"),
+            Ok(String::new())
+        );
+        assert_eq!(interpreter.memory().cells(), vec![(0, 1), (3, 2)]);
+        assert!(interpreter.memory().contains(3));
+    }
+
+    #[test]
+    #[cfg(all(feature = "fs", feature = "json"))]
+    fn it_checkpoints_and_resumes() {
+        let code = "┌──────┐\n│▀◈▀▀▄ │\n└──────┘\n┌──────┐\n│▭◇▀   │\n└──────┘\n┌──────┐\n│▀▀◈▀▀ │\n└──────┘";
+        let path =
+            std::env::temp_dir().join(format!("boxscript-state-{}.json", std::process::id()));
+
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_checkpoint(Some(path.clone()));
+        assert_eq!(interpreter.run_program(code), Ok("\u{2}".to_string()));
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(saved, "{\"memory\":[[0,2],[1,1]],\"next_box\":3}");
+
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.restore(&StateSnapshot {
+            memory: vec![(0, 72)],
+            next_box: 1,
+        });
+        assert_eq!(interpreter.run_frames(code), Ok(vec!["H".to_string()]));
+        assert_eq!(interpreter.memory().cells(), vec![(0, 72), (1, 1)]);
+
+        interpreter.restore(&StateSnapshot::from_json(&saved).unwrap());
+        assert_eq!(interpreter.run_program(code), Ok(String::new()));
+
+        interpreter.restore(&StateSnapshot {
+            memory: vec![],
+            next_box: 4,
+        });
+        assert_eq!(
+            interpreter.run_program(code),
+            Err(BsError::RuntimeError(
+                "Saved state does not match this program".to_string(),
+                None
+            ))
+        );
+        assert_eq!(
+            StateSnapshot::<i8>::from_json("{\"memory\":[[0,300]],\"next_box\":0}"),
+            Err(BsError::ParseError("Invalid saved state".to_string(), None))
+        );
+    }
+
+    #[test]
+    fn it_snapshots_contexts() {
+        let mut memory: HashMap<i64, i64> = [(3, 4), (-1, 2)].iter().cloned().collect();
+        let mut stdout = String::new();
+        let mut input = NoInput;
+        let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+        context.next_box = 2;
+
+        let snapshot = context.snapshot();
+        assert_eq!(snapshot.memory, vec![(-1, 2), (3, 4)]);
+        #[cfg(feature = "json")]
+        assert_eq!(
+            StateSnapshot::from_json(&snapshot.to_json()),
+            Ok(snapshot.clone())
+        );
+
+        context.memory.set(9, 9);
+        context.next_box = 0;
+        context.restore(&snapshot);
+        assert_eq!(context.snapshot(), snapshot);
+    }
+
+    #[test]
+    fn it_evaluates_expressions() {
+        let mut interpreter = Interpreter::<i8>::new();
+        assert_eq!(interpreter.eval("▀▀▐▀▀"), Ok((2, String::new())));
+        assert_eq!(interpreter.eval("▭▀▀▄▄▀▄▄▄"), Ok((72, "H".to_string())));
+        assert_eq!(interpreter.eval("▀◈▀▀▀"), Ok((3, String::new())));
+        assert_eq!(interpreter.eval("◇▀"), Ok((3, String::new())));
+        assert_eq!(
+            interpreter.eval("▀ ▝"),
+            Err(BsError::ValidationError(
+                "Malformed expression".to_string(),
+                Some([0, 0])
+            ))
+        );
+
+        let keys: std::collections::VecDeque<char> = "x".chars().collect();
+        interpreter.set_input(Box::new(keys));
+        assert_eq!(interpreter.eval("▮"), Ok((120, String::new())));
+        assert_eq!(interpreter.eval("▮"), Ok((0, String::new())));
+    }
+
+    #[test]
+    fn it_silences_assignments() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_silent_assign(true);
+        assert_eq!(interpreter.eval("▀▀◈▀▀▀"), Ok((0, String::new())));
+        assert_eq!(interpreter.memory().get(1), 3);
+        assert_eq!(
+            interpreter.eval("▀▀◈▀▀◈▀▀▄"),
+            Err(BsError::ValidationError(
+                "Silent assignment has no value to use".to_string(),
+                Some([0, 2])
+            ))
+        );
+        assert_eq!(
+            interpreter.run_program("┌─────────┐\n│▀▀◈▀▀▀   │\n│▭◇▀▀▐▀▀▄▄│\n└─────────┘"),
+            Ok("\u{7}".to_string())
+        );
+    }
 }
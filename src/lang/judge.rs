@@ -0,0 +1,346 @@
+use super::block::{Block, Stop};
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::input::Reader;
+use super::interpreter::Interpreter;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Verdict {
+    Accepted,
+    WrongAnswer,
+    TimeLimitExceeded,
+    MemoryLimitExceeded,
+    RuntimeError,
+}
+
+impl Verdict {
+    pub fn code(&self) -> &'static str {
+        match self {
+            Verdict::Accepted => "AC",
+            Verdict::WrongAnswer => "WA",
+            Verdict::TimeLimitExceeded => "TLE",
+            Verdict::MemoryLimitExceeded => "MLE",
+            Verdict::RuntimeError => "RE",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    pub time: Option<Duration>,
+    pub memory: Option<usize>,
+    pub iterations: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            time: Some(Duration::from_secs(1)),
+            memory: None,
+            iterations: None,
+        }
+    }
+}
+
+impl Limits {
+    pub fn parse(text: &str) -> Result<Limits, String> {
+        let mut limits = Limits::default();
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() || line == "[limits]" {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("invalid line: {}", line)),
+            };
+            let value = value
+                .replace('_', "")
+                .parse::<u64>()
+                .map_err(|_| format!("invalid limit: {} = {}", key, value))?;
+
+            match key {
+                "time_ms" => limits.time = Some(Duration::from_millis(value)),
+                "memory_cells" => limits.memory = Some(value as usize),
+                "iterations" => limits.iterations = Some(value as usize),
+                _ => return Err(format!("unknown limit: {}", key)),
+            }
+        }
+
+        Ok(limits)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Case {
+    pub name: String,
+    pub input: String,
+    pub expected: String,
+}
+
+pub fn cases(dir: &Path) -> Result<Vec<Case>, BsError> {
+    let mut names: Vec<String> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "out"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let input = dir.join(format!("{}.in", name));
+            Ok(Case {
+                input: if input.exists() {
+                    fs::read_to_string(input)?
+                } else {
+                    String::new()
+                },
+                expected: fs::read_to_string(dir.join(format!("{}.out", name)))?,
+                name,
+            })
+        })
+        .collect()
+}
+
+pub fn matches(output: &str, expected: &str) -> bool {
+    let lines = |text: &str| -> Vec<String> {
+        let mut lines: Vec<String> = text.lines().map(|l| l.trim_end().to_string()).collect();
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+        lines
+    };
+
+    lines(output) == lines(expected)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub name: String,
+    pub verdict: Verdict,
+    pub time: Duration,
+    pub message: Option<String>,
+}
+
+fn judge_case<T: BoxInt>(
+    interpreter: &mut Interpreter<T>,
+    source: &str,
+    case: &Case,
+    limits: &Limits,
+) -> Report {
+    interpreter.memory_mut().clear();
+    interpreter.set_input(Box::new(Reader::new(std::io::Cursor::new(
+        case.input.clone().into_bytes(),
+    ))));
+    interpreter.set_max_iterations(limits.iterations);
+
+    let start = Instant::now();
+    let mut exceeded: Option<Verdict> = None;
+    let mut hook = |stop: Stop<T>| {
+        let (verdict, message) = if limits.time.is_some_and(|time| start.elapsed() > time) {
+            (Verdict::TimeLimitExceeded, "Time limit exceeded")
+        } else if limits.memory.is_some_and(|cells| stop.memory.len() > cells) {
+            (Verdict::MemoryLimitExceeded, "Memory limit exceeded")
+        } else {
+            return Ok(());
+        };
+
+        exceeded = Some(verdict);
+        Err(BsError::RuntimeError(message.to_string(), Some(stop.at)))
+    };
+
+    let result = interpreter.run_program_with(source, &mut hook);
+    let time = start.elapsed();
+
+    let (verdict, message) = match result {
+        Err(error) => match exceeded {
+            Some(verdict) => (verdict, Some(error.to_string())),
+            None if error.message().starts_with("Loop exceeded") => {
+                (Verdict::TimeLimitExceeded, Some(error.to_string()))
+            }
+            None => (Verdict::RuntimeError, Some(error.to_string())),
+        },
+        Ok(_) if limits.time.is_some_and(|limit| time > limit) => {
+            (Verdict::TimeLimitExceeded, None)
+        }
+        Ok(_)
+            if limits
+                .memory
+                .is_some_and(|cells| interpreter.memory().len() > cells) =>
+        {
+            (Verdict::MemoryLimitExceeded, None)
+        }
+        Ok(output) if matches(&output, &case.expected) => (Verdict::Accepted, None),
+        Ok(_) => (Verdict::WrongAnswer, None),
+    };
+
+    Report {
+        name: case.name.clone(),
+        verdict,
+        time,
+        message,
+    }
+}
+
+pub fn judge<T: BoxInt>(
+    interpreter: &mut Interpreter<T>,
+    source: &str,
+    cases: &[Case],
+    limits: &Limits,
+) -> Result<Vec<Report>, BsError> {
+    interpreter.complexity().check::<T>(source)?;
+    Block::<T>::build(source)?;
+
+    Ok(cases
+        .iter()
+        .map(|case| judge_case(interpreter, source, case, limits))
+        .collect())
+}
+
+pub fn summary(reports: &[Report]) -> String {
+    let passed = reports
+        .iter()
+        .filter(|report| report.verdict == Verdict::Accepted)
+        .count();
+    let verdict = reports
+        .iter()
+        .map(|report| report.verdict)
+        .find(|verdict| *verdict != Verdict::Accepted)
+        .unwrap_or(Verdict::Accepted);
+
+    json!({
+        "verdict": verdict.code(),
+        "passed": passed,
+        "total": reports.len(),
+        "tests": reports
+            .iter()
+            .map(|report| json!({
+                "name": report.name,
+                "verdict": report.verdict.code(),
+                "time_ms": report.time.as_millis() as u64,
+                "message": report.message,
+            }))
+            .collect::<Vec<_>>(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn case(name: &str, input: &str, expected: &str) -> Case {
+        Case {
+            name: name.to_string(),
+            input: input.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    fn verdicts(source: &str, cases: &[Case], limits: &Limits) -> Vec<Verdict> {
+        judge(&mut Interpreter::<i64>::new(), source, cases, limits)
+            .unwrap()
+            .iter()
+            .map(|report| report.verdict)
+            .collect()
+    }
+
+    #[test]
+    fn it_parses_limits() {
+        assert_eq!(
+            Limits::parse("[limits]\ntime_ms = 2_000 # per test\nmemory_cells = 16\n"),
+            Ok(Limits {
+                time: Some(Duration::from_secs(2)),
+                memory: Some(16),
+                iterations: None,
+            })
+        );
+        assert_eq!(Limits::parse(""), Ok(Limits::default()));
+        assert_eq!(
+            Limits::parse("stack = 1"),
+            Err("unknown limit: stack".to_string())
+        );
+        assert_eq!(
+            Limits::parse("time_ms = soon"),
+            Err("invalid limit: time_ms = soon".to_string())
+        );
+    }
+
+    #[test]
+    fn it_compares_output() {
+        assert!(matches("1 2\n3\n", "1 2  \n3"));
+        assert!(matches("", "\n\n"));
+        assert!(!matches("1\n\n2", "1\n2"));
+    }
+
+    #[test]
+    fn it_judges_cases() {
+        let echo = "┌──────┐\n│▭▯▀   │\n│▭▀▀▄▀▄│\n└──────┘";
+        assert_eq!(
+            verdicts(
+                echo,
+                &[case("1", "a", "a\n"), case("2", "b", "c\n")],
+                &Limits::default()
+            ),
+            vec![Verdict::Accepted, Verdict::WrongAnswer]
+        );
+
+        let number = "┌────┐\n│▯▀▀ │\n└────┘";
+        assert_eq!(
+            verdicts(
+                number,
+                &[case("1", "12", ""), case("2", "x", "")],
+                &Limits::default()
+            ),
+            vec![Verdict::Accepted, Verdict::RuntimeError]
+        );
+
+        let spin = "╔═▀▀═╗\n║▀◈▀▀║\n╚════╝";
+        let limits = Limits {
+            iterations: Some(100),
+            ..Limits::default()
+        };
+        assert_eq!(
+            verdicts(spin, &[case("1", "", "")], &limits),
+            vec![Verdict::TimeLimitExceeded]
+        );
+
+        let fill = "╔═◇▀▨▀▀▄▀════╗\n║◇▀▐▀▀◈▀▀    ║\n║▀◈◇▀▐▀▀     ║\n╚════════════╝";
+        let limits = Limits {
+            memory: Some(3),
+            ..Limits::default()
+        };
+        assert_eq!(
+            verdicts(fill, &[case("1", "", "")], &limits),
+            vec![Verdict::MemoryLimitExceeded]
+        );
+
+        assert!(judge(&mut Interpreter::<i64>::new(), "┌─", &[], &limits).is_err());
+    }
+
+    #[test]
+    fn it_summarizes_reports() {
+        let report = |name: &str, verdict, message: Option<&str>| Report {
+            name: name.to_string(),
+            verdict,
+            time: Duration::from_millis(3),
+            message: message.map(str::to_string),
+        };
+
+        assert_eq!(
+            summary(&[
+                report("1", Verdict::Accepted, None),
+                report("2", Verdict::RuntimeError, Some("Boom at 1:2")),
+            ]),
+            "{\"passed\":1,\"tests\":[{\"message\":null,\"name\":\"1\",\"time_ms\":3,\"verdict\":\"AC\"},{\"message\":\"Boom at 1:2\",\"name\":\"2\",\"time_ms\":3,\"verdict\":\"RE\"}],\"total\":2,\"verdict\":\"RE\"}"
+        );
+    }
+}
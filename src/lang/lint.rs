@@ -0,0 +1,279 @@
+use super::analysis::{self, Reason};
+use super::ast::Expr;
+use super::block::Block;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::Atom;
+use super::r#box::Genus;
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Rule {
+    UnusedWrite,
+    Unreachable,
+    ConstantCondition,
+    ShadowedAssign,
+    EmptyBox,
+}
+
+const RULES: [(Rule, &str); 5] = [
+    (Rule::UnusedWrite, "unused-write"),
+    (Rule::Unreachable, "unreachable"),
+    (Rule::ConstantCondition, "constant-condition"),
+    (Rule::ShadowedAssign, "shadowed-assign"),
+    (Rule::EmptyBox, "empty-box"),
+];
+
+impl Rule {
+    pub fn all() -> impl Iterator<Item = Rule> {
+        RULES.iter().map(|(rule, _)| *rule)
+    }
+
+    pub fn ids() -> Vec<&'static str> {
+        RULES.iter().map(|(_, id)| *id).collect()
+    }
+
+    pub fn from_id(id: &str) -> Option<Rule> {
+        RULES
+            .iter()
+            .find(|(_, name)| *name == id)
+            .map(|(rule, _)| *rule)
+    }
+
+    pub fn id(self) -> &'static str {
+        RULES
+            .iter()
+            .find(|(rule, _)| *rule == self)
+            .map(|(_, id)| *id)
+            .unwrap()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Config {
+    levels: HashMap<Rule, Level>,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config::default()
+    }
+
+    pub fn set(&mut self, rule: Rule, level: Level) {
+        self.levels.insert(rule, level);
+    }
+
+    pub fn level(&self, rule: Rule) -> Level {
+        self.levels.get(&rule).cloned().unwrap_or(Level::Warn)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    pub rule: Rule,
+    pub level: Level,
+    pub location: [usize; 2],
+    pub message: String,
+}
+
+fn assignments<T: BoxInt>(expr: &Expr<T>, found: &mut Vec<(T, [usize; 2])>) {
+    match expr {
+        Expr::Binary(atom, a, b, at) => {
+            if let (Atom::Assign | Atom::SilentAssign, Expr::Number(address), Some(at)) =
+                (atom, &**a, at)
+            {
+                found.push((*address, *at));
+            }
+            assignments(a, found);
+            assignments(b, found);
+        }
+        Expr::Unary(_, a, _) => assignments(a, found),
+        _ => {}
+    }
+}
+
+pub fn lint<T: BoxInt>(source: &str, config: &Config) -> Result<Vec<Lint>, BsError> {
+    let blocks = Block::<T>::build(source)?;
+    let mut found: Vec<(Rule, [usize; 2], String)> = Vec::new();
+
+    for dead in analysis::dead_boxes::<T>(source)? {
+        let rule = match dead.reason {
+            Reason::ConstantCondition => Rule::ConstantCondition,
+            _ => Rule::Unreachable,
+        };
+        let message = format!("Box never runs: {}", dead.reason.describe());
+        found.push((rule, dead.r#box.top_left, message));
+    }
+
+    let mut reads: BTreeSet<T> = BTreeSet::new();
+    let mut reads_any = false;
+    let mut writes: Vec<(T, [usize; 2])> = Vec::new();
+    for block in Block::walk(&blocks) {
+        if block.r#box.genus != Genus::NoOp
+            && block.include.is_none()
+            && block.molecules.is_empty()
+            && block.children.is_empty()
+        {
+            found.push((
+                Rule::EmptyBox,
+                block.r#box.top_left,
+                "Box is empty".to_string(),
+            ));
+        }
+
+        let folded = block.guard.as_ref().map(|guard| guard.folded());
+        if let Some([Atom::Data(value)]) = folded.as_ref().map(|guard| guard.atoms()) {
+            let message = match block.r#box.genus {
+                Genus::Loop if !value.is_zero() => Some(format!(
+                    "Loop never ends: its condition is always {}",
+                    value
+                )),
+                Genus::Condition if !value.is_zero() => Some(format!(
+                    "Box always runs: its condition is always {}",
+                    value
+                )),
+                _ => None,
+            };
+            if let Some(message) = message {
+                found.push((Rule::ConstantCondition, block.r#box.top_left, message));
+            }
+        }
+
+        let cells = analysis::shallow_cells(block);
+        reads.extend(cells.reads);
+        reads_any |= cells.reads_any;
+
+        for molecule in block.expressions() {
+            let mut molecule = molecule.clone();
+            let mut assigned: Vec<(T, [usize; 2])> = Vec::new();
+            if let Ok(expr) = molecule.compile() {
+                assignments(expr, &mut assigned);
+            }
+            assigned.sort_by_key(|(_, at)| *at);
+
+            for (i, (address, at)) in assigned.iter().enumerate() {
+                if assigned[..i].iter().any(|(other, _)| other == address) {
+                    let message = format!(
+                        "Cell {} is assigned more than once in one expression",
+                        address
+                    );
+                    found.push((Rule::ShadowedAssign, *at, message));
+                }
+            }
+            writes.extend(assigned);
+        }
+    }
+
+    if !reads_any {
+        for (address, at) in writes {
+            if !reads.contains(&address) {
+                let message = format!("Cell {} is written but never read", address);
+                found.push((Rule::UnusedWrite, at, message));
+            }
+        }
+    }
+
+    let mut lints: Vec<Lint> = found
+        .into_iter()
+        .map(|(rule, location, message)| Lint {
+            rule,
+            level: config.level(rule),
+            location,
+            message,
+        })
+        .filter(|lint| lint.level != Level::Allow)
+        .collect();
+    lints.sort_by_key(|lint| lint.location);
+
+    Ok(lints)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODE: &str = "┌───────────┐\n│▀◈▀▀▐▕▀◈▀▄▏│\n│▀▀◈▀▀      │\n│▭◇▀        │\n└───────────┘\n┏━▀┓\n┃  ┃\n┗━━┛\n┌─┐\n│◰│\n└─┘\n";
+
+    fn report(config: &Config) -> Vec<(&'static str, [usize; 2])> {
+        lint::<i64>(CODE, config)
+            .unwrap()
+            .iter()
+            .map(|lint| (lint.rule.id(), lint.location))
+            .collect()
+    }
+
+    #[test]
+    fn it_names_rules() {
+        for rule in Rule::all() {
+            assert_eq!(Rule::from_id(rule.id()), Some(rule));
+        }
+        assert_eq!(Rule::from_id("nothing"), None);
+    }
+
+    #[test]
+    fn it_finds_mistakes() {
+        assert_eq!(
+            report(&Config::new()),
+            vec![
+                ("shadowed-assign", [1, 8]),
+                ("unused-write", [2, 3]),
+                ("constant-condition", [5, 0]),
+                ("empty-box", [5, 0]),
+                ("empty-box", [8, 0]),
+            ]
+        );
+        assert_eq!(
+            lint::<i64>(
+                "┌────────┐\n│▀◈▀▀    │\n│▭◇◇▀▀▐▀▀│\n└────────┘",
+                &Config::new()
+            ),
+            Ok(vec![])
+        );
+    }
+
+    #[test]
+    fn it_configures_rules() {
+        let mut config = Config::new();
+        config.set(Rule::EmptyBox, Level::Allow);
+        config.set(Rule::UnusedWrite, Level::Deny);
+
+        let lints = lint::<i64>(CODE, &config).unwrap();
+        assert_eq!(lints.len(), 3);
+        assert_eq!(lints[1].rule, Rule::UnusedWrite);
+        assert_eq!(lints[1].level, Level::Deny);
+        assert_eq!(lints[1].message, "Cell 1 is written but never read");
+    }
+
+    #[test]
+    fn it_reports_constant_conditions() {
+        let messages = |code: &str| -> Vec<String> {
+            lint::<i64>(code, &Config::new())
+                .unwrap()
+                .into_iter()
+                .filter(|lint| lint.rule == Rule::ConstantCondition)
+                .map(|lint| lint.message)
+                .collect()
+        };
+
+        assert_eq!(
+            messages("┏━▀▀━┓\n┃▭▀▀ ┃\n┗━━━━┛"),
+            vec!["Box always runs: its condition is always 1"]
+        );
+        assert_eq!(
+            messages("╔═▄▀═╗\n║▭▀▀ ║\n╚════╝"),
+            vec!["Loop never ends: its condition is always -1"]
+        );
+        assert_eq!(
+            messages("┏━▀━┓\n┃▭▀▀┃\n┗━━━┛"),
+            vec!["Box never runs: its condition is always zero"]
+        );
+    }
+}
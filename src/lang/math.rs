@@ -1,25 +1,96 @@
-use super::interpreter::BoxInt;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::interpreter::ArithMode;
 
-pub fn modulo<T: BoxInt>(a: T, b: T) -> Result<T, String> {
+fn invalid(operation: &str) -> BsError {
+    BsError::RuntimeError(format!("{} caused invalid value", operation), None)
+}
+
+pub fn add<T: BoxInt>(mode: ArithMode, a: T, b: T) -> Option<T> {
+    match mode {
+        ArithMode::Checked => a.checked_add(&b),
+        ArithMode::Wrapping => Some(a.wrapping_add(&b)),
+        ArithMode::Saturating => Some(a.saturating_add(b)),
+    }
+}
+
+pub fn subtract<T: BoxInt>(mode: ArithMode, a: T, b: T) -> Option<T> {
+    match mode {
+        ArithMode::Checked => a.checked_sub(&b),
+        ArithMode::Wrapping => Some(a.wrapping_sub(&b)),
+        ArithMode::Saturating => Some(a.saturating_sub(b)),
+    }
+}
+
+pub fn multiply<T: BoxInt>(mode: ArithMode, a: T, b: T) -> Option<T> {
+    match mode {
+        ArithMode::Checked => a.checked_mul(&b),
+        ArithMode::Wrapping => Some(a.wrapping_mul(&b)),
+        ArithMode::Saturating => Some(a.saturating_mul(&b)),
+    }
+}
+
+pub fn divide<T: BoxInt>(mode: ArithMode, a: T, b: T) -> Option<T> {
     if b.is_zero() {
-        return Err("Modulo caused invalid value".to_string());
+        return None;
     }
 
-    if a.checked_mul(&b).ok_or("Modulo caused invalid value")? < T::zero() {
-        Ok(b.checked_add(&(a % b))
-            .ok_or("Modulo caused invalid value")?)
+    a.checked_div(&b).or(match mode {
+        ArithMode::Checked => None,
+        ArithMode::Wrapping => Some(T::min_value()),
+        ArithMode::Saturating => Some(T::max_value()),
+    })
+}
+
+pub fn shift_left<T: BoxInt>(mode: ArithMode, a: T, b: u32) -> Option<T> {
+    match mode {
+        ArithMode::Checked => a.checked_shl(b),
+        ArithMode::Wrapping => Some(a.wrapping_shl(b)),
+        ArithMode::Saturating => a.checked_shl(b).or_else(|| Some(T::zero())),
+    }
+}
+
+pub fn shift_right<T: BoxInt>(mode: ArithMode, a: T, b: u32) -> Option<T> {
+    match mode {
+        ArithMode::Checked => a.checked_shr(b),
+        ArithMode::Wrapping => Some(a.wrapping_shr(b)),
+        ArithMode::Saturating => a.checked_shr(b).or_else(|| {
+            Some(if a.is_negative() {
+                -T::one()
+            } else {
+                T::zero()
+            })
+        }),
+    }
+}
+
+pub fn modulo<T: BoxInt>(mode: ArithMode, a: T, b: T) -> Result<T, BsError> {
+    if b.is_zero() {
+        return Err(invalid("Modulo"));
+    }
+
+    let remainder = a
+        .checked_rem(&b)
+        .or(match mode {
+            ArithMode::Checked => None,
+            _ => Some(T::zero()),
+        })
+        .ok_or_else(|| invalid("Modulo"))?;
+
+    if !a.is_zero() && a.is_negative() != b.is_negative() {
+        Ok(b.checked_add(&remainder).ok_or_else(|| invalid("Modulo"))?)
     } else {
-        Ok(a % b)
+        Ok(remainder)
     }
 }
 
-pub fn inv_modulo<T: BoxInt>(a: T, b: T) -> Result<T, String> {
-    let x = modulo(a, b)?;
+pub fn inv_modulo<T: BoxInt>(mode: ArithMode, a: T, b: T) -> Result<T, BsError> {
+    let x = modulo(mode, a, b)?;
     let mut n = T::one();
     while n < b {
         let mod_result = modulo(
-            n.checked_mul(&x)
-                .ok_or("Inverse modulo caused invalid value")?,
+            mode,
+            n.checked_mul(&x).ok_or_else(|| invalid("Inverse modulo"))?,
             b,
         );
         if mod_result.is_ok() && mod_result.unwrap().is_one() {
@@ -29,5 +100,8 @@ pub fn inv_modulo<T: BoxInt>(a: T, b: T) -> Result<T, String> {
         n = n + T::one();
     }
 
-    return Err(format!("{} is not invertible", a));
+    Err(BsError::RuntimeError(
+        format!("{} is not invertible", a),
+        None,
+    ))
 }
@@ -0,0 +1,851 @@
+use super::datatype::BoxInt;
+use super::error::BsError;
+#[cfg(feature = "json")]
+use super::mi::number;
+#[cfg(feature = "json")]
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::convert::TryInto;
+#[cfg(feature = "fs")]
+use std::fs;
+use std::hash::Hasher;
+#[cfg(feature = "fs")]
+use std::io;
+use std::mem;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+const SHARDS: usize = 16;
+
+const MAGIC: &[u8] = b"BSMEM\x01";
+
+pub trait Memory<T: BoxInt> {
+    fn get(&self, address: T) -> T;
+    fn set(&mut self, address: T, value: T);
+    fn contains(&self, address: T) -> bool;
+    fn iter(&self) -> std::boxed::Box<dyn Iterator<Item = (T, T)> + '_>;
+    fn clear(&mut self);
+
+    fn compare_and_assign(&mut self, address: T, expected: T, value: T) -> bool {
+        if self.get(address) != expected {
+            return false;
+        }
+
+        self.set(address, value);
+        true
+    }
+
+    fn copy(&mut self, from: T, to: T, len: usize) {
+        copy_cells(self, from, to, len);
+    }
+
+    fn fill(&mut self, start: T, len: usize, value: T) {
+        fill_cells(self, start, len, value);
+    }
+
+    fn cells(&self) -> Vec<(T, T)> {
+        let mut cells: Vec<(T, T)> = self.iter().collect();
+        cells.sort();
+        cells
+    }
+
+    fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn flush(&mut self) -> Result<(), BsError> {
+        Ok(())
+    }
+
+    fn bytes(&self) -> usize {
+        self.len() * mem::size_of::<(T, T)>()
+    }
+
+    fn stats(&self) -> MemoryStats<T> {
+        let mut stats: MemoryStats<T> = MemoryStats {
+            cells: 0,
+            negative: 0,
+            lowest: None,
+            highest: None,
+            bytes: self.bytes(),
+        };
+        for (address, _) in self.iter() {
+            stats.cells += 1;
+            if address < T::zero() {
+                stats.negative += 1;
+            }
+            stats.lowest = Some(stats.lowest.map_or(address, |lowest| lowest.min(address)));
+            stats.highest = Some(
+                stats
+                    .highest
+                    .map_or(address, |highest| highest.max(address)),
+            );
+        }
+
+        stats
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryStats<T: BoxInt> {
+    pub cells: usize,
+    pub negative: usize,
+    pub lowest: Option<T>,
+    pub highest: Option<T>,
+    pub bytes: usize,
+}
+
+impl<T: BoxInt> MemoryStats<T> {
+    pub fn span(&self) -> u128 {
+        match (self.lowest, self.highest) {
+            (Some(lowest), Some(highest)) => {
+                let (lowest, highest) = (
+                    lowest.to_i128().unwrap_or_default(),
+                    highest.to_i128().unwrap_or_default(),
+                );
+                (highest.wrapping_sub(lowest) as u128).saturating_add(1)
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn density(&self) -> f64 {
+        match self.span() {
+            0 => 0.0,
+            span => self.cells as f64 / span as f64,
+        }
+    }
+
+    pub fn table(&self) -> String {
+        let address = |address: Option<T>| address.map_or("-".to_string(), |a| a.to_string());
+        let rows = [
+            ("cells", self.cells.to_string()),
+            ("negative", self.negative.to_string()),
+            ("lowest", address(self.lowest)),
+            ("highest", address(self.highest)),
+            ("span", self.span().to_string()),
+            ("density", format!("{:.0}%", self.density() * 100.0)),
+            ("bytes", self.bytes.to_string()),
+        ];
+
+        rows.iter()
+            .map(|(label, value)| format!("{:8}  {}\n", label, value))
+            .collect()
+    }
+}
+
+fn offset<T: BoxInt>(address: T, i: usize) -> T {
+    address + T::from(i).unwrap()
+}
+
+fn copy_cells<T: BoxInt, M: Memory<T> + ?Sized>(memory: &mut M, from: T, to: T, len: usize) {
+    let values: Vec<T> = (0..len).map(|i| memory.get(offset(from, i))).collect();
+    for (i, value) in values.into_iter().enumerate() {
+        memory.set(offset(to, i), value);
+    }
+}
+
+fn fill_cells<T: BoxInt, M: Memory<T> + ?Sized>(memory: &mut M, start: T, len: usize, value: T) {
+    for i in 0..len {
+        memory.set(offset(start, i), value);
+    }
+}
+
+impl<T: BoxInt> Memory<T> for HashMap<T, T> {
+    fn get(&self, address: T) -> T {
+        HashMap::get(self, &address)
+            .cloned()
+            .unwrap_or_else(T::zero)
+    }
+
+    fn set(&mut self, address: T, value: T) {
+        self.insert(address, value);
+    }
+
+    fn contains(&self, address: T) -> bool {
+        self.contains_key(&address)
+    }
+
+    fn iter(&self) -> std::boxed::Box<dyn Iterator<Item = (T, T)> + '_> {
+        std::boxed::Box::new(HashMap::iter(self).map(|(a, v)| (*a, *v)))
+    }
+
+    fn clear(&mut self) {
+        HashMap::clear(self);
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+
+    fn bytes(&self) -> usize {
+        self.capacity() * mem::size_of::<(T, T)>()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DenseMemory<T: BoxInt> {
+    dense: Vec<Option<T>>,
+    size: usize,
+    sparse: HashMap<T, T>,
+}
+
+impl<T: BoxInt> DenseMemory<T> {
+    pub fn new(size: usize) -> DenseMemory<T> {
+        DenseMemory {
+            dense: Vec::new(),
+            size,
+            sparse: HashMap::new(),
+        }
+    }
+
+    fn index(&self, address: T) -> Option<usize> {
+        address.to_usize().filter(|index| *index < self.size)
+    }
+}
+
+impl<T: BoxInt> Memory<T> for DenseMemory<T> {
+    fn get(&self, address: T) -> T {
+        match self.index(address) {
+            Some(index) => self.dense.get(index).cloned().flatten(),
+            None => self.sparse.get(&address).cloned(),
+        }
+        .unwrap_or_else(T::zero)
+    }
+
+    fn set(&mut self, address: T, value: T) {
+        match self.index(address) {
+            Some(index) => {
+                if index >= self.dense.len() {
+                    self.dense.resize(index + 1, None);
+                }
+                self.dense[index] = Some(value);
+            }
+            None => {
+                self.sparse.insert(address, value);
+            }
+        }
+    }
+
+    fn copy(&mut self, from: T, to: T, len: usize) {
+        match (self.index(from), self.index(to)) {
+            (Some(from), Some(to)) if from.max(to) + len <= self.size => {
+                if self.dense.len() < to + len {
+                    self.dense.resize(to + len, None);
+                }
+                let values: Vec<Option<T>> = (from..from + len)
+                    .map(|index| {
+                        Some(
+                            self.dense
+                                .get(index)
+                                .cloned()
+                                .flatten()
+                                .unwrap_or_else(T::zero),
+                        )
+                    })
+                    .collect();
+                self.dense[to..to + len].copy_from_slice(&values);
+            }
+            _ => copy_cells(self, from, to, len),
+        }
+    }
+
+    fn fill(&mut self, start: T, len: usize, value: T) {
+        match self.index(start) {
+            Some(start) if start + len <= self.size => {
+                if self.dense.len() < start + len {
+                    self.dense.resize(start + len, None);
+                }
+                self.dense[start..start + len].fill(Some(value));
+            }
+            _ => fill_cells(self, start, len, value),
+        }
+    }
+
+    fn contains(&self, address: T) -> bool {
+        match self.index(address) {
+            Some(index) => self.dense.get(index).is_some_and(Option::is_some),
+            None => self.sparse.contains_key(&address),
+        }
+    }
+
+    fn iter(&self) -> std::boxed::Box<dyn Iterator<Item = (T, T)> + '_> {
+        std::boxed::Box::new(
+            self.dense
+                .iter()
+                .enumerate()
+                .filter_map(|(index, value)| Some((T::from(index)?, (*value)?)))
+                .chain(self.sparse.iter().map(|(a, v)| (*a, *v))),
+        )
+    }
+
+    fn clear(&mut self) {
+        self.dense.clear();
+        self.sparse.clear();
+    }
+
+    fn bytes(&self) -> usize {
+        self.dense.capacity() * mem::size_of::<Option<T>>() + Memory::bytes(&self.sparse)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Format {
+    #[cfg(feature = "json")]
+    Json,
+    Binary,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            #[cfg(feature = "json")]
+            "json" => Some(Format::Json),
+            "binary" => Some(Format::Binary),
+            _ => None,
+        }
+    }
+
+    fn encode<T: BoxInt>(&self, memory: &dyn Memory<T>) -> Vec<u8> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => (json(memory) + "\n").into_bytes(),
+            Format::Binary => binary(memory),
+        }
+    }
+
+    fn decode<T: BoxInt>(&self, bytes: &[u8]) -> Result<HashMap<T, T>, BsError> {
+        match self {
+            #[cfg(feature = "json")]
+            Format::Json => from_json(&String::from_utf8_lossy(bytes)),
+            Format::Binary => from_binary(bytes),
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+#[derive(Debug)]
+pub struct FileMemory<T: BoxInt> {
+    path: PathBuf,
+    format: Format,
+    cells: HashMap<T, T>,
+    dirty: bool,
+}
+
+#[cfg(feature = "fs")]
+impl<T: BoxInt> FileMemory<T> {
+    #[cfg(feature = "json")]
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<FileMemory<T>, BsError> {
+        FileMemory::open_as(path, Format::Json)
+    }
+
+    pub fn open_as<P: Into<PathBuf>>(path: P, format: Format) -> Result<FileMemory<T>, BsError> {
+        let path = path.into();
+        let cells = match fs::read(&path) {
+            Ok(bytes) => format.decode(&bytes)?,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => {
+                return Err(BsError::IoError(format!("{}: {}", path.display(), error)));
+            }
+        };
+
+        Ok(FileMemory {
+            path,
+            format,
+            cells,
+            dirty: false,
+        })
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<T: BoxInt> Drop for FileMemory<T> {
+    fn drop(&mut self) {
+        let _ = Memory::flush(self);
+    }
+}
+
+#[cfg(feature = "fs")]
+impl<T: BoxInt> Memory<T> for FileMemory<T> {
+    fn get(&self, address: T) -> T {
+        Memory::get(&self.cells, address)
+    }
+
+    fn set(&mut self, address: T, value: T) {
+        self.cells.insert(address, value);
+        self.dirty = true;
+    }
+
+    fn contains(&self, address: T) -> bool {
+        self.cells.contains_key(&address)
+    }
+
+    fn iter(&self) -> std::boxed::Box<dyn Iterator<Item = (T, T)> + '_> {
+        Memory::iter(&self.cells)
+    }
+
+    fn clear(&mut self) {
+        self.dirty |= !self.cells.is_empty();
+        self.cells.clear();
+    }
+
+    fn bytes(&self) -> usize {
+        Memory::bytes(&self.cells)
+    }
+
+    fn flush(&mut self) -> Result<(), BsError> {
+        if self.dirty {
+            fs::write(&self.path, self.format.encode(&self.cells))
+                .map_err(|error| BsError::IoError(format!("{}: {}", self.path.display(), error)))?;
+            self.dirty = false;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SharedMemory<T: BoxInt> {
+    shards: Arc<Vec<RwLock<HashMap<T, T>>>>,
+}
+
+impl<T: BoxInt> SharedMemory<T> {
+    pub fn new() -> SharedMemory<T> {
+        SharedMemory {
+            shards: Arc::new((0..SHARDS).map(|_| RwLock::new(HashMap::new())).collect()),
+        }
+    }
+
+    fn shard(&self, address: T) -> &RwLock<HashMap<T, T>> {
+        let mut hasher = DefaultHasher::new();
+        address.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    fn read(&self, address: T) -> RwLockReadGuard<'_, HashMap<T, T>> {
+        self.shard(address)
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self, address: T) -> RwLockWriteGuard<'_, HashMap<T, T>> {
+        self.shard(address)
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn shards(&self) -> impl Iterator<Item = RwLockReadGuard<'_, HashMap<T, T>>> {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub fn load(&self, address: T) -> T {
+        Memory::get(&*self.read(address), address)
+    }
+
+    pub fn store(&self, address: T, value: T) {
+        self.write(address).insert(address, value);
+    }
+
+    pub fn compare_and_store(&self, address: T, expected: T, value: T) -> bool {
+        self.write(address)
+            .compare_and_assign(address, expected, value)
+    }
+
+    pub fn holds(&self, address: T) -> bool {
+        self.read(address).contains_key(&address)
+    }
+
+    pub fn to_map(&self) -> HashMap<T, T> {
+        self.shards()
+            .flat_map(|shard| shard.clone().into_iter())
+            .collect()
+    }
+}
+
+impl<T: BoxInt> Default for SharedMemory<T> {
+    fn default() -> SharedMemory<T> {
+        SharedMemory::new()
+    }
+}
+
+impl<T: BoxInt> From<HashMap<T, T>> for SharedMemory<T> {
+    fn from(memory: HashMap<T, T>) -> SharedMemory<T> {
+        let shared = SharedMemory::new();
+        for (address, value) in memory {
+            shared.store(address, value);
+        }
+        shared
+    }
+}
+
+impl<T: BoxInt> Memory<T> for SharedMemory<T> {
+    fn get(&self, address: T) -> T {
+        self.load(address)
+    }
+
+    fn set(&mut self, address: T, value: T) {
+        self.store(address, value);
+    }
+
+    fn contains(&self, address: T) -> bool {
+        self.holds(address)
+    }
+
+    fn iter(&self) -> std::boxed::Box<dyn Iterator<Item = (T, T)> + '_> {
+        std::boxed::Box::new(self.to_map().into_iter())
+    }
+
+    fn compare_and_assign(&mut self, address: T, expected: T, value: T) -> bool {
+        self.compare_and_store(address, expected, value)
+    }
+
+    fn len(&self) -> usize {
+        self.shards().map(|shard| shard.len()).sum()
+    }
+
+    fn clear(&mut self) {
+        for shard in self.shards.iter() {
+            shard
+                .write()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clear();
+        }
+    }
+}
+
+fn character<T: BoxInt>(value: T) -> Option<String> {
+    value.to_char().map(|c| {
+        if c.is_control() {
+            c.escape_default().to_string()
+        } else {
+            c.to_string()
+        }
+    })
+}
+
+pub fn table<T: BoxInt>(memory: &dyn Memory<T>) -> String {
+    let mut rows: Vec<[String; 3]> = vec![[
+        "address".to_string(),
+        "value".to_string(),
+        "char".to_string(),
+    ]];
+    for (address, value) in memory.cells() {
+        rows.push([
+            address.to_string(),
+            value.to_string(),
+            character(value).unwrap_or_default(),
+        ]);
+    }
+
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let (first, second) = (width(0), width(1));
+
+    rows.iter()
+        .map(|row| {
+            let line = format!(
+                "{:first$}  {:second$}  {}",
+                row[0],
+                row[1],
+                row[2],
+                first = first,
+                second = second
+            );
+            format!("{}\n", line.trim_end())
+        })
+        .collect()
+}
+
+#[cfg(feature = "json")]
+pub fn json<T: BoxInt>(memory: &dyn Memory<T>) -> String {
+    json!({
+        "memory": memory
+            .cells()
+            .into_iter()
+            .map(|(address, value)| json!({
+                "address": number(address),
+                "value": number(value),
+                "char": value.to_char().map(String::from),
+            }))
+            .collect::<Vec<Value>>(),
+    })
+    .to_string()
+}
+
+#[cfg(feature = "json")]
+pub fn from_json<T: BoxInt>(text: &str) -> Result<HashMap<T, T>, BsError> {
+    let invalid = || BsError::ParseError("Invalid memory file".to_string(), None);
+    let number = |value: &Value| match value {
+        Value::Number(number) => number.as_i64().and_then(T::from),
+        Value::String(text) => T::from_str_radix(text, 10).ok(),
+        _ => None,
+    };
+
+    let memory: Value = serde_json::from_str(text).map_err(|_| invalid())?;
+    memory["memory"]
+        .as_array()
+        .ok_or_else(invalid)?
+        .iter()
+        .map(|cell| Some((number(&cell["address"])?, number(&cell["value"])?)))
+        .collect::<Option<HashMap<T, T>>>()
+        .ok_or_else(invalid)
+}
+
+pub fn binary<T: BoxInt>(memory: &dyn Memory<T>) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    for (address, value) in memory.cells() {
+        bytes.extend_from_slice(&address.to_i128().unwrap_or_default().to_le_bytes());
+        bytes.extend_from_slice(&value.to_i128().unwrap_or_default().to_le_bytes());
+    }
+    bytes
+}
+
+pub fn from_binary<T: BoxInt>(bytes: &[u8]) -> Result<HashMap<T, T>, BsError> {
+    let invalid = || BsError::ParseError("Invalid memory file".to_string(), None);
+    let cells = bytes.strip_prefix(MAGIC).ok_or_else(invalid)?;
+    if cells.len() % 32 != 0 {
+        return Err(invalid());
+    }
+
+    cells
+        .chunks(32)
+        .map(|cell| {
+            let (address, value) = cell.split_at(16);
+            Some((
+                T::from(i128::from_le_bytes(address.try_into().ok()?))?,
+                T::from(i128::from_le_bytes(value.try_into().ok()?))?,
+            ))
+        })
+        .collect::<Option<HashMap<T, T>>>()
+        .ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::block::Block;
+    use super::super::input::NoInput;
+    use super::super::interpreter::{ExecContext, Runnable};
+    use super::*;
+    use std::thread;
+
+    fn counter(cell: &str) -> String {
+        format!(
+            "╔═◇{0}▨▀▀▀▄▄▀▄▄══╗\n║╔═▕▀▀◆▀▀▏▤▀══╗  ║\n║║            ║  ║\n║╚════════════╝  ║\n║▀◈◇▀▐▀▀         ║\n║▀▀◈▀            ║\n║{0}◈◇{0}▐▀▀     ║\n╚════════════════╝",
+            cell
+        )
+    }
+
+    fn memory() -> HashMap<i64, i64> {
+        [(10, 10), (-1, 72), (2, -5)].iter().cloned().collect()
+    }
+
+    #[test]
+    fn it_prints_tables() {
+        assert_eq!(
+            table(&memory()),
+            "address  value  char\n-1       72     H\n2        -5\n10       10     \\n\n"
+        );
+        assert_eq!(table(&HashMap::<i8, i8>::new()), "address  value  char\n");
+    }
+
+    #[test]
+    fn it_compares_and_assigns() {
+        let mut memory: HashMap<i8, i8> = HashMap::new();
+        assert!(memory.compare_and_assign(4, 0, 7));
+        assert!(!memory.compare_and_assign(4, 0, 9));
+        assert_eq!(Memory::get(&memory, 4), 7);
+
+        let mut shared = SharedMemory::<i8>::new();
+        let other = shared.clone();
+        assert!(other.compare_and_store(-3, 0, 1));
+        assert!(!shared.compare_and_assign(-3, 0, 2));
+        shared.set(5, 6);
+        assert_eq!(other.cells(), vec![(-3, 1), (5, 6)]);
+        assert_eq!(other.len(), 2);
+
+        shared.clear();
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn it_counts_cells() {
+        let stats = memory().stats();
+        assert_eq!(
+            (stats.cells, stats.negative, stats.lowest, stats.highest),
+            (3, 1, Some(-1), Some(10))
+        );
+        assert_eq!(stats.span(), 12);
+        assert_eq!(
+            MemoryStats {
+                bytes: 96,
+                ..stats
+            }
+            .table(),
+            "cells     3\nnegative  1\nlowest    -1\nhighest   10\nspan      12\ndensity   25%\nbytes     96\n"
+        );
+
+        let empty = HashMap::<i8, i8>::new().stats();
+        assert_eq!((empty.span(), empty.density()), (0, 0.0));
+        assert!(empty.table().contains("lowest    -\n"));
+
+        let mut dense = DenseMemory::<i128>::new(64);
+        dense.set(i128::MIN, 1);
+        dense.set(i128::MAX, 1);
+        dense.set(3, 1);
+        let stats = dense.stats();
+        assert_eq!(stats.span(), u128::MAX);
+        assert!(stats.bytes >= 4 * 32);
+    }
+
+    #[test]
+    fn it_keeps_low_addresses_dense() {
+        let mut memory = DenseMemory::<i8>::new(4);
+        memory.set(2, 5);
+        memory.set(-1, 3);
+        memory.set(90, 0);
+
+        assert_eq!(memory.dense.len(), 3);
+        assert!(memory.contains(90) && !memory.contains(1) && !memory.contains(7));
+        assert_eq!((memory.get(2), memory.get(1), memory.get(-1)), (5, 0, 3));
+        assert_eq!(memory.cells(), vec![(-1, 3), (2, 5), (90, 0)]);
+        memory.clear();
+        assert!(memory.is_empty());
+    }
+
+    #[test]
+    fn it_copies_and_fills_ranges() {
+        let mut sparse: HashMap<i8, i8> = HashMap::new();
+        let mut dense = DenseMemory::<i8>::new(8);
+        let memories: [&mut dyn Memory<i8>; 2] = [&mut sparse, &mut dense];
+
+        for memory in memories {
+            memory.fill(2, 3, 7);
+            memory.set(3, 1);
+            memory.copy(2, 3, 3);
+            assert_eq!(memory.cells(), vec![(2, 7), (3, 7), (4, 1), (5, 7)]);
+
+            memory.copy(10, 6, 2);
+            memory.fill(126, 2, -1);
+            memory.copy(126, -1, 2);
+            assert_eq!(
+                memory.cells(),
+                vec![
+                    (-1, -1),
+                    (0, -1),
+                    (2, 7),
+                    (3, 7),
+                    (4, 1),
+                    (5, 7),
+                    (6, 0),
+                    (7, 0),
+                    (126, -1),
+                    (127, -1)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    #[cfg(all(feature = "fs", feature = "json"))]
+    fn it_persists_memory_to_files() {
+        let path =
+            std::env::temp_dir().join(format!("boxscript-memory-{}.json", std::process::id()));
+
+        let mut memory = FileMemory::<i8>::open(&path).unwrap();
+        assert!(memory.is_empty());
+        memory.set(10, 10);
+        memory.set(-1, 72);
+        drop(memory);
+
+        let mut memory = FileMemory::<i8>::open(&path).unwrap();
+        assert_eq!(memory.cells(), vec![(-1, 72), (10, 10)]);
+        memory.clear();
+        memory.flush().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"memory\":[]}\n");
+
+        fs::write(&path, "{\"memory\":[{\"address\":1}]}").unwrap();
+        let error = FileMemory::<i8>::open(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(error.message(), "Invalid memory file");
+    }
+
+    #[test]
+    fn it_persists_memory_as_binary() {
+        let bytes = binary(&memory());
+        assert_eq!(bytes.len(), MAGIC.len() + 3 * 32);
+        assert_eq!(&bytes[MAGIC.len()..MAGIC.len() + 16], &[255; 16]);
+        assert_eq!(from_binary(&bytes), Ok(memory()));
+        assert!(from_binary::<i8>(&bytes[..bytes.len() - 1]).is_err());
+        assert!(from_binary::<i8>(&binary(
+            &[(300i16, 1i16)].iter().cloned().collect::<HashMap<_, _>>()
+        ))
+        .is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn it_persists_binary_memory_to_files() {
+        let path =
+            std::env::temp_dir().join(format!("boxscript-memory-{}.bin", std::process::id()));
+        let mut memory = FileMemory::<i64>::open_as(&path, Format::Binary).unwrap();
+        memory.set(1 << 40, -3);
+        drop(memory);
+
+        let memory = FileMemory::<i64>::open_as(&path, Format::Binary).unwrap();
+        let cells = memory.cells();
+        drop(memory);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(cells, vec![(1 << 40, -3)]);
+    }
+
+    #[test]
+    fn it_shares_memory_between_threads() {
+        let memory = SharedMemory::<i64>::new();
+        let workers: Vec<_> = ["▀▀▄", "▀▀▀"]
+            .iter()
+            .map(|cell| {
+                let (mut memory, code) = (memory.clone(), counter(cell));
+                thread::spawn(move || {
+                    let mut stdout = String::new();
+                    for mut block in Block::<i64>::build(&code).unwrap() {
+                        block
+                            .run(&mut ExecContext::new(
+                                &mut memory,
+                                &mut stdout,
+                                &mut NoInput,
+                            ))
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(memory.cells(), vec![(0, 200), (1, 0), (2, 100), (3, 100)]);
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn it_prints_json() {
+        assert_eq!(
+            json(&memory()),
+            "{\"memory\":[{\"address\":-1,\"char\":\"H\",\"value\":72},{\"address\":2,\"char\":null,\"value\":-5},{\"address\":10,\"char\":\"\\n\",\"value\":10}]}"
+        );
+        assert_eq!(from_json(&json(&memory())), Ok(memory()));
+    }
+}
@@ -0,0 +1,248 @@
+use super::error::BsError;
+use super::matrix;
+use super::r#box::{Box, Relation};
+
+#[derive(Clone, Debug, PartialEq)]
+struct Piece {
+    r#box: Box,
+    lines: Vec<String>,
+}
+
+impl Piece {
+    fn all(code: &str) -> Result<Vec<Piece>, BsError> {
+        let boxes = Box::find_all(code)?;
+        let grid = matrix::chars(code);
+
+        Ok(boxes
+            .iter()
+            .filter(|b| {
+                boxes
+                    .iter()
+                    .all(|other| b.relationship(other) != Relation::Child)
+            })
+            .map(|b| Piece {
+                r#box: b.clone(),
+                lines: (b.top_left[0]..=b.bottom_right[0])
+                    .map(|row| {
+                        grid[row][b.top_left[1]..=b.bottom_right[1]]
+                            .iter()
+                            .map(|c| if *c == '\0' { ' ' } else { *c })
+                            .collect()
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    fn width(&self) -> usize {
+        self.lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+fn pair(base: &[Piece], other: &[Piece]) -> Vec<Option<usize>> {
+    let mut used = vec![false; other.len()];
+    let mut matches: Vec<Option<usize>> = vec![None; base.len()];
+
+    let passes: [fn(&Piece, &Piece) -> bool; 2] = [
+        |a, b| a.lines == b.lines,
+        |a, b| a.r#box.top_left == b.r#box.top_left && a.r#box.genus == b.r#box.genus,
+    ];
+
+    for pass in passes.iter() {
+        for (i, a) in base.iter().enumerate() {
+            if matches[i].is_some() {
+                continue;
+            }
+
+            if let Some(j) = (0..other.len()).find(|j| !used[*j] && pass(a, &other[*j])) {
+                used[j] = true;
+                matches[i] = Some(j);
+            }
+        }
+    }
+
+    matches
+}
+
+fn conflict(at: &Box, ours: Option<&Piece>, theirs: Option<&Piece>) -> Piece {
+    let mut body: Vec<String> = vec!["<<<<<<< ours".to_string()];
+    body.extend(ours.map_or(vec![], |piece| piece.lines.clone()));
+    body.push("=======".to_string());
+    body.extend(theirs.map_or(vec![], |piece| piece.lines.clone()));
+    body.push(">>>>>>> theirs".to_string());
+
+    let width = body
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut lines: Vec<String> = vec![format!("╭{}╮", "┄".repeat(width))];
+    for line in body {
+        let padding = width - line.chars().count();
+        lines.push(format!("┆{}{}┆", line, " ".repeat(padding)));
+    }
+    lines.push(format!("╰{}╯", "┄".repeat(width)));
+
+    Piece {
+        r#box: at.clone(),
+        lines,
+    }
+}
+
+fn paint(mut pieces: Vec<Piece>) -> String {
+    pieces.sort_by_key(|piece| piece.r#box.top_left);
+
+    let mut placed: Vec<([usize; 2], &Piece)> = Vec::new();
+    let mut shift = 0;
+
+    for piece in &pieces {
+        let [row, col] = piece.r#box.top_left;
+        let mut top = row + shift;
+
+        for ([other_top, other_left], other) in &placed {
+            let rows = top < other_top + other.height() && *other_top < top + piece.height();
+            let cols = col < other_left + other.width() && *other_left < col + piece.width();
+            if rows && cols {
+                top = top.max(other_top + other.height() + 1);
+            }
+        }
+
+        shift = top - row;
+        placed.push(([top, col], piece));
+    }
+
+    let height = placed
+        .iter()
+        .map(|([top, _], piece)| top + piece.height())
+        .max()
+        .unwrap_or(0);
+    let mut canvas: Vec<Vec<char>> = vec![Vec::new(); height];
+
+    for ([top, left], piece) in placed {
+        for (i, line) in piece.lines.iter().enumerate() {
+            let row = &mut canvas[top + i];
+            for (j, c) in line.chars().enumerate() {
+                if row.len() <= left + j {
+                    row.resize(left + j + 1, ' ');
+                }
+                row[left + j] = c;
+            }
+        }
+    }
+
+    canvas
+        .iter()
+        .map(|row| row.iter().collect::<String>().trim_end().to_string() + "\n")
+        .collect()
+}
+
+pub fn merge(base: &str, ours: &str, theirs: &str) -> Result<(String, usize), BsError> {
+    let base = Piece::all(base)?;
+    let ours = Piece::all(ours)?;
+    let theirs = Piece::all(theirs)?;
+    let ours_matches = pair(&base, &ours);
+    let theirs_matches = pair(&base, &theirs);
+    let mut pieces: Vec<Piece> = Vec::new();
+    let mut conflicts = 0;
+
+    for (i, original) in base.iter().enumerate() {
+        let mine = ours_matches[i].map(|j| &ours[j]);
+        let other = theirs_matches[i].map(|j| &theirs[j]);
+        let unchanged = |piece: Option<&Piece>| piece.is_some_and(|p| p == original);
+
+        if unchanged(mine) {
+            pieces.extend(other.cloned());
+        } else if unchanged(other) || mine.map(|p| &p.lines) == other.map(|p| &p.lines) {
+            pieces.extend(mine.cloned());
+        } else {
+            conflicts += 1;
+            pieces.push(conflict(
+                mine.map_or(&original.r#box, |p| &p.r#box),
+                mine,
+                other,
+            ));
+        }
+    }
+
+    for (j, piece) in ours.iter().enumerate() {
+        if !ours_matches.contains(&Some(j)) {
+            pieces.push(piece.clone());
+        }
+    }
+    for (j, piece) in theirs.iter().enumerate() {
+        if !theirs_matches.contains(&Some(j)) && !pieces.iter().any(|p| p.lines == piece.lines) {
+            pieces.push(piece.clone());
+        }
+    }
+
+    Ok((paint(pieces), conflicts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_merges_independent_changes() {
+        let base = "┌────┐ ┌────┐\n│▀◈▀▀│ │▭▀▀ │\n└────┘ └────┘\n";
+        let ours = "┌────┐ ┌────┐\n│▀◈▀▄│ │▭▀▀ │\n└────┘ └────┘\n";
+        let theirs = "┌────┐ ┌─────┐\n│▀◈▀▀│ │▭▀▀▀ │\n└────┘ └─────┘\n";
+
+        assert_eq!(
+            merge(base, ours, theirs),
+            Ok((
+                "┌────┐ ┌─────┐\n│▀◈▀▄│ │▭▀▀▀ │\n└────┘ └─────┘\n".to_string(),
+                0
+            ))
+        );
+    }
+
+    #[test]
+    fn it_keeps_additions_and_deletions() {
+        let base = "┌────┐\n│▀◈▀▀│\n└────┘\n";
+        let ours = "┌────┐\n│▀◈▀▀│\n└────┘\n┌───┐\n│▭▀ │\n└───┘\n";
+        let theirs = "";
+
+        assert_eq!(
+            merge(base, ours, theirs),
+            Ok(("\n\n\n┌───┐\n│▭▀ │\n└───┘\n".to_string(), 0))
+        );
+    }
+
+    #[test]
+    fn it_renders_conflicts_as_comments() {
+        let base = "┌────┐\n│▀◈▀▀│\n└────┘\n";
+        let ours = "┌────┐\n│▀◈▀▄│\n└────┘\n";
+        let theirs = "┌─────┐\n│▀◈▀▀▀│\n└─────┘\n";
+
+        let (merged, conflicts) = merge(base, ours, theirs).unwrap();
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            merged,
+            [
+                "╭┄┄┄┄┄┄┄┄┄┄┄┄┄┄╮",
+                "┆<<<<<<< ours  ┆",
+                "┆┌────┐        ┆",
+                "┆│▀◈▀▄│        ┆",
+                "┆└────┘        ┆",
+                "┆=======       ┆",
+                "┆┌─────┐       ┆",
+                "┆│▀◈▀▀▀│       ┆",
+                "┆└─────┘       ┆",
+                "┆>>>>>>> theirs┆",
+                "╰┄┄┄┄┄┄┄┄┄┄┄┄┄┄╯",
+                "",
+            ]
+            .join("\n")
+        );
+        assert_eq!(Box::find_all(&merged).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+use super::datatype::BoxInt;
+use super::debugger::{Command, Event};
+use num_traits::NumCast;
+use serde_json::Value;
+
+pub fn number<T: BoxInt>(value: T) -> Value {
+    match value.to_i64() {
+        Some(value) => Value::from(value),
+        None => Value::String(value.to_string()),
+    }
+}
+
+pub fn location(at: [usize; 2]) -> Value {
+    json!({ "line": at[0] + 1, "column": at[1] + 1 })
+}
+
+fn text(request: &Value, name: &str) -> Result<String, String> {
+    request
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| format!("missing field: {}", name))
+}
+
+fn corner(request: &Value) -> Result<[usize; 2], String> {
+    let field = |name: &str| match request["box"].get(name).and_then(Value::as_i64) {
+        Some(value) if value > 0 => Ok(value as usize - 1),
+        Some(value) => Err(format!("invalid {}: {}", name, value)),
+        None => Err(format!("missing field: box.{}", name)),
+    };
+
+    Ok([field("line")?, field("column")?])
+}
+
+pub fn command<T: BoxInt>(line: &str) -> Result<Option<Command<T>>, String> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let request: Value =
+        serde_json::from_str(line).map_err(|error| format!("invalid json: {}", error))?;
+    let field = |name: &str| {
+        request
+            .get(name)
+            .and_then(Value::as_i64)
+            .ok_or_else(|| format!("missing field: {}", name))
+    };
+    let number = |name: &str| {
+        <T as NumCast>::from(field(name)?).ok_or_else(|| format!("invalid number: {}", name))
+    };
+    let line_number = |name: &str| match field(name)? {
+        line if line > 0 => Ok(line as usize - 1),
+        line => Err(format!("invalid line: {}", line)),
+    };
+
+    Ok(Some(match request.get("command").and_then(Value::as_str) {
+        Some("break") if request.get("box").is_some() => Command::BreakBox(corner(&request)?),
+        Some("delete") if request.get("box").is_some() => Command::DeleteBox(corner(&request)?),
+        Some("break") => Command::Break(line_number("line")?),
+        Some("delete") => Command::Delete(line_number("line")?),
+        Some("run") | Some("continue") => Command::Continue,
+        Some("step") => Command::Step,
+        Some("print") => Command::Print(number("address")?),
+        Some("set") => Command::Set(number("address")?, number("value")?),
+        Some("memory") => Command::Memory,
+        Some("where") => Command::Where,
+        Some("reload") => Command::Reload(corner(&request)?),
+        Some("watch") => Command::Watch(text(&request, "expression")?),
+        Some("unwatch") => Command::Unwatch(text(&request, "expression")?),
+        Some("quit") | Some("abort") => Command::Quit,
+        Some(name) => return Err(format!("unknown command: {}", name)),
+        None => return Err("missing field: command".to_string()),
+    }))
+}
+
+pub fn event<T: BoxInt>(event: &Event<T>) -> String {
+    let value = match event {
+        Event::Breakpoint(line) => json!({ "event": "breakpoint", "line": line + 1 }),
+        Event::Deleted(line) => json!({ "event": "deleted", "line": line + 1 }),
+        Event::BoxBreakpoint(at) => json!({ "event": "breakpoint", "box": location(*at) }),
+        Event::BoxDeleted(at) => json!({ "event": "deleted", "box": location(*at) }),
+        Event::Stopped(at, source) => json!({
+            "event": "stopped",
+            "location": location(*at),
+            "source": source,
+        }),
+        Event::Output(text) => json!({ "event": "output", "text": text }),
+        Event::Value(address, value) => json!({
+            "event": "value",
+            "address": number(*address),
+            "value": number(*value),
+        }),
+        Event::Memory(cells) => json!({
+            "event": "memory",
+            "cells": cells
+                .iter()
+                .map(|(address, value)| json!({
+                    "address": number(*address),
+                    "value": number(*value),
+                }))
+                .collect::<Vec<Value>>(),
+        }),
+        Event::Watch(expr, Ok(value)) => json!({
+            "event": "watch",
+            "expression": expr,
+            "value": number(*value),
+        }),
+        Event::Watch(expr, Err(error)) => json!({
+            "event": "watch",
+            "expression": expr,
+            "message": error.message(),
+            "location": error.location().map(location),
+        }),
+        Event::Unwatched(expr) => json!({ "event": "unwatched", "expression": expr }),
+        Event::Reloaded(at) => json!({ "event": "reloaded", "box": location(*at) }),
+        Event::Finished => json!({ "event": "finished" }),
+        Event::Failed(error) => json!({
+            "event": "failed",
+            "message": error.message(),
+            "location": error.location().map(location),
+        }),
+        Event::Aborted => json!({ "event": "aborted" }),
+        Event::NotRunning => json!({ "event": "not-running" }),
+        Event::Invalid(message) => json!({ "event": "invalid", "message": message }),
+    };
+
+    value.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::debugger::Debugger;
+    use crate::lang::interpreter::Interpreter;
+
+    #[test]
+    fn it_parses_commands() {
+        assert_eq!(
+            command::<i8>(r#"{"command": "break", "line": 3}"#),
+            Ok(Some(Command::Break(2)))
+        );
+        assert_eq!(
+            command::<i8>(r#"{"command": "set", "address": -1, "value": 5}"#),
+            Ok(Some(Command::Set(-1, 5)))
+        );
+        assert_eq!(command::<i8>("  "), Ok(None));
+        assert_eq!(
+            command::<i8>(r#"{"command": "break", "box": {"line": 3, "column": 2}}"#),
+            Ok(Some(Command::BreakBox([2, 1])))
+        );
+        assert_eq!(
+            command::<i8>(r#"{"command": "delete", "box": {"line": 3}}"#),
+            Err("missing field: box.column".to_string())
+        );
+        assert_eq!(
+            event::<i8>(&Event::BoxBreakpoint([2, 1])),
+            r#"{"box":{"column":2,"line":3},"event":"breakpoint"}"#
+        );
+        assert_eq!(
+            command::<i8>(r#"{"command": "print", "address": 300}"#),
+            Err("invalid number: address".to_string())
+        );
+        assert_eq!(
+            command::<i8>(r#"{"command": "break"}"#),
+            Err("missing field: line".to_string())
+        );
+        assert!(command::<i8>("break 3")
+            .unwrap_err()
+            .starts_with("invalid json"));
+    }
+
+    #[test]
+    fn it_drives_the_debugger() {
+        let code = "┌────┐\n│▀◈▀▀│\n│▭◇▀ │\n└────┘";
+        let script = [
+            r#"{"command": "break", "line": 3}"#,
+            r#"{"command": "run"}"#,
+            r#"{"command": "set", "address": 0, "value": 72}"#,
+            r#"{"command": "continue"}"#,
+            r#"{"command": "memory"}"#,
+        ];
+
+        let mut events: Vec<String> = Vec::new();
+        let mut commands = script
+            .iter()
+            .filter_map(|line| command::<i8>(line).transpose());
+        let mut emit = |e: Event<i8>| events.push(event(&e));
+        Debugger::new(&mut commands, &mut emit).run(&mut Interpreter::new(), code);
+
+        assert_eq!(
+            events,
+            vec![
+                r#"{"event":"breakpoint","line":3}"#,
+                r#"{"event":"stopped","location":{"column":2,"line":3},"source":"▭◇▀"}"#,
+                r#"{"address":0,"event":"value","value":72}"#,
+                r#"{"event":"output","text":"H"}"#,
+                r#"{"event":"finished"}"#,
+                r#"{"cells":[{"address":0,"value":72}],"event":"memory"}"#,
+            ]
+        );
+    }
+}
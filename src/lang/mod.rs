@@ -1,4 +1,52 @@
-mod expression;
+pub mod analysis;
+pub mod ascii;
+pub mod ast;
+pub mod block;
+pub mod r#box;
+pub mod canon;
+pub mod complexity;
+#[cfg(feature = "control")]
+pub mod control;
+#[cfg(feature = "crash")]
+pub mod crash;
+pub mod datatype;
+pub mod debugger;
+#[cfg(feature = "cli")]
+pub mod diagnostics;
+pub mod diff;
+pub mod doc;
+pub mod dump;
+pub mod encode;
+pub mod error;
+pub mod expression;
+pub mod fix;
+pub mod function;
+pub mod graph;
+pub mod include;
+pub mod input;
 pub mod interpreter;
+#[cfg(feature = "judge")]
+pub mod judge;
+pub mod lint;
 mod math;
 mod matrix;
+pub mod memory;
+pub mod merge;
+#[cfg(feature = "json")]
+pub mod mi;
+pub mod output;
+#[cfg(feature = "registry")]
+pub mod registry;
+pub mod render;
+pub mod replay;
+pub mod rust;
+pub mod scheduler;
+#[cfg(feature = "selftest")]
+pub mod selftest;
+pub mod session;
+pub mod stdlib;
+pub mod terminal;
+pub mod token;
+pub mod trace;
+pub mod vm;
+pub mod xref;
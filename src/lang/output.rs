@@ -0,0 +1,94 @@
+use super::error::BsError;
+use std::io::{BufWriter, Write};
+
+pub trait Output {
+    fn write(&mut self, text: &str) -> Result<(), BsError>;
+
+    fn flush(&mut self) -> Result<(), BsError> {
+        Ok(())
+    }
+
+    fn captured(&self) -> &str {
+        ""
+    }
+}
+
+impl Output for String {
+    fn write(&mut self, text: &str) -> Result<(), BsError> {
+        self.push_str(text);
+        Ok(())
+    }
+
+    fn captured(&self) -> &str {
+        self
+    }
+}
+
+pub struct Stream<W: Write> {
+    writer: BufWriter<W>,
+}
+
+impl<W: Write> Stream<W> {
+    pub fn new(writer: W) -> Stream<W> {
+        Stream {
+            writer: BufWriter::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> Result<W, BsError> {
+        self.writer
+            .into_inner()
+            .map_err(|error| BsError::from(error.into_error()))
+    }
+}
+
+impl<W: Write> Output for Stream<W> {
+    fn write(&mut self, text: &str) -> Result<(), BsError> {
+        Ok(self.writer.write_all(text.as_bytes())?)
+    }
+
+    fn flush(&mut self) -> Result<(), BsError> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "Broken pipe"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn it_collects_and_streams_output() {
+        let mut collected = String::new();
+        collected.write("a").unwrap();
+        collected.write("b").unwrap();
+        assert_eq!(collected.captured(), "ab");
+
+        let mut stream = Stream::new(Vec::new());
+        stream.write("é").unwrap();
+        assert_eq!(stream.captured(), "");
+        assert_eq!(stream.into_inner(), Ok("é".as_bytes().to_vec()));
+    }
+
+    #[test]
+    fn it_reports_write_errors() {
+        let mut stream = Stream::new(Closed);
+        assert_eq!(stream.write("a"), Ok(()));
+        assert_eq!(
+            stream.flush(),
+            Err(BsError::IoError("Broken pipe".to_string()))
+        );
+    }
+}
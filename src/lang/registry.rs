@@ -0,0 +1,348 @@
+use super::canon;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::include::Include;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::process::{self, Command};
+
+pub const MANIFEST: &str = "boxscript.json";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub files: Vec<String>,
+    pub hashes: BTreeMap<String, u64>,
+}
+
+impl Manifest {
+    pub fn parse(text: &str) -> Result<Manifest, String> {
+        let value: Value =
+            serde_json::from_str(text).map_err(|error| format!("invalid json: {}", error))?;
+        let text = |name: &str| {
+            value
+                .get(name)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .ok_or_else(|| format!("missing field: {}", name))
+        };
+        let files = value
+            .get("files")
+            .and_then(Value::as_array)
+            .ok_or("missing field: files")?
+            .iter()
+            .map(|file| {
+                file.as_str()
+                    .filter(|file| plain(file))
+                    .map(str::to_string)
+                    .ok_or_else(|| format!("invalid file: {}", file))
+            })
+            .collect::<Result<Vec<String>, String>>()?;
+        let hashes = match value.get("hashes").and_then(Value::as_object) {
+            Some(hashes) => hashes
+                .iter()
+                .map(|(file, hash)| {
+                    hash.as_str()
+                        .filter(|hash| hash.len() == 16)
+                        .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+                        .map(|hash| (file.clone(), hash))
+                        .ok_or_else(|| format!("invalid hash: {}", file))
+                })
+                .collect::<Result<BTreeMap<String, u64>, String>>()?,
+            None => BTreeMap::new(),
+        };
+
+        let name = text("name")?;
+        if !plain(&name) || name.contains('/') {
+            return Err(format!("invalid name: {}", name));
+        }
+
+        Ok(Manifest {
+            name,
+            version: text("version")?,
+            files,
+            hashes,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let hashes: Map<String, Value> = self
+            .hashes
+            .iter()
+            .map(|(file, hash)| (file.clone(), Value::from(format!("{:016x}", hash))))
+            .collect();
+
+        json!({
+            "name": self.name,
+            "version": self.version,
+            "files": self.files,
+            "hashes": hashes,
+        })
+        .to_string()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Registry {
+    Dir(PathBuf),
+    Git(String),
+}
+
+impl Registry {
+    pub fn parse(location: &str) -> Registry {
+        if location.ends_with(".git")
+            || location.starts_with("git@")
+            || location.starts_with("git://")
+            || location.starts_with("ssh://")
+            || location.starts_with("https://")
+        {
+            Registry::Git(location.to_string())
+        } else {
+            Registry::Dir(PathBuf::from(
+                location.strip_prefix("file://").unwrap_or(location),
+            ))
+        }
+    }
+
+    fn open<R>(
+        &self,
+        body: impl FnOnce(&Path) -> Result<R, BsError>,
+        push: Option<&str>,
+    ) -> Result<R, BsError> {
+        let url = match self {
+            Registry::Dir(dir) => return body(dir),
+            Registry::Git(url) => url,
+        };
+
+        let dir = std::env::temp_dir().join(format!("boxscript-registry-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let dir_name = dir.to_string_lossy().to_string();
+        git(&["clone", "--depth", "1", url, &dir_name], None)?;
+
+        let result = body(&dir).and_then(|value| {
+            if let Some(message) = push {
+                git(&["add", "-A"], Some(&dir))?;
+                git(&["commit", "-m", message], Some(&dir))?;
+                git(&["push"], Some(&dir))?;
+            }
+            Ok(value)
+        });
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+}
+
+fn plain(file: &str) -> bool {
+    let path = Path::new(file);
+    !file.is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn git(arguments: &[&str], dir: Option<&Path>) -> Result<(), BsError> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    let output = command
+        .args(arguments)
+        .output()
+        .map_err(|error| BsError::IoError(format!("git: {}", error)))?;
+    if !output.status.success() {
+        return Err(BsError::IoError(format!(
+            "git {}: {}",
+            arguments[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(())
+}
+
+fn read_manifest(path: &Path) -> Result<Manifest, BsError> {
+    let text = fs::read_to_string(path)
+        .map_err(|error| BsError::IoError(format!("{}: {}", path.display(), error)))?;
+    Manifest::parse(&text).map_err(|message| {
+        BsError::ValidationError(format!("{}: {}", path.display(), message), None)
+    })
+}
+
+fn hash<T: BoxInt>(path: &Path, file: &str) -> Result<u64, BsError> {
+    let source = fs::read_to_string(path)
+        .map_err(|error| BsError::IoError(format!("{}: {}", file, error)))?;
+    let canonical = canon::canonicalize::<T>(&source)
+        .map_err(|error| BsError::ParseError(format!("{} in {}", error, file), None))?;
+
+    Ok(canon::hash(&canonical))
+}
+
+pub fn publish<T: BoxInt>(registry: &Registry, project: &Path) -> Result<Manifest, BsError> {
+    let mut manifest = read_manifest(&project.join(MANIFEST))?;
+    manifest.hashes = manifest
+        .files
+        .iter()
+        .map(|file| Ok((file.clone(), hash::<T>(&project.join(file), file)?)))
+        .collect::<Result<BTreeMap<String, u64>, BsError>>()?;
+
+    let message = format!("Publish {} {}", manifest.name, manifest.version);
+    registry.open(
+        |root| {
+            let package = root.join(&manifest.name);
+            let _ = fs::remove_dir_all(&package);
+            for file in &manifest.files {
+                let target = package.join(file);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(project.join(file), target)?;
+            }
+            fs::write(package.join(MANIFEST), manifest.to_json())?;
+            Ok(manifest.clone())
+        },
+        Some(&message),
+    )
+}
+
+pub fn add<T: BoxInt>(
+    registry: &Registry,
+    name: &str,
+    include_dir: &Path,
+) -> Result<(Manifest, Vec<Include>), BsError> {
+    if !plain(name) || name.contains('/') {
+        return Err(BsError::ValidationError(
+            format!("Invalid library name {}", name),
+            None,
+        ));
+    }
+
+    registry.open(
+        |root| {
+            let package = root.join(name);
+            let manifest = read_manifest(&package.join(MANIFEST))?;
+            let target = include_dir.join(name);
+            let mut includes = Vec::new();
+
+            for file in &manifest.files {
+                let expected = manifest.hashes.get(file).cloned().ok_or_else(|| {
+                    BsError::ValidationError(format!("No hash for {} in {}", file, name), None)
+                })?;
+                let found = hash::<T>(&package.join(file), file)?;
+                if found != expected {
+                    return Err(BsError::ValidationError(
+                        format!(
+                            "Library hash mismatch for {}/{} (expected {:016x}, found {:016x})",
+                            name, file, expected, found
+                        ),
+                        None,
+                    ));
+                }
+
+                includes.push(Include {
+                    path: format!("{}/{}", name, file),
+                    hash: found,
+                });
+            }
+
+            let _ = fs::remove_dir_all(&target);
+            for file in &manifest.files {
+                let path = target.join(file);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(package.join(file), path)?;
+            }
+            fs::write(target.join(MANIFEST), manifest.to_json())?;
+
+            Ok((manifest, includes))
+        },
+        None,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lang::block::Block;
+    use crate::lang::include;
+
+    const LIBRARY: &str = "┌──────┐\n│▭▀▀▄▄▀│\n└──────┘";
+
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("boxscript-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn project(name: &str) -> PathBuf {
+        let dir = scratch(name);
+        fs::write(
+            dir.join(MANIFEST),
+            r#"{"name": "tabs", "version": "1.0.0", "files": ["lib.bs"]}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("lib.bs"), LIBRARY).unwrap();
+        dir
+    }
+
+    #[test]
+    fn it_parses_manifests() {
+        let manifest =
+            Manifest::parse(r#"{"name": "tabs", "version": "1.0.0", "files": ["lib.bs"]}"#)
+                .unwrap();
+
+        assert_eq!(manifest.files, vec!["lib.bs".to_string()]);
+        assert_eq!(Manifest::parse(&manifest.to_json()), Ok(manifest));
+        assert_eq!(
+            Manifest::parse(r#"{"name": "tabs", "version": "1", "files": ["../lib.bs"]}"#),
+            Err("invalid file: \"../lib.bs\"".to_string())
+        );
+        assert_eq!(
+            Registry::parse("https://example.com/libs.git"),
+            Registry::Git("https://example.com/libs.git".to_string())
+        );
+        assert_eq!(
+            Registry::parse("file:///srv/libs"),
+            Registry::Dir(PathBuf::from("/srv/libs"))
+        );
+    }
+
+    #[test]
+    fn it_publishes_and_adds_libraries() {
+        let registry = Registry::Dir(scratch("registry-published"));
+        let published = publish::<i8>(&registry, &project("registry-project")).unwrap();
+        let include_dir = scratch("registry-includes");
+        let (added, includes) = add::<i8>(&registry, "tabs", &include_dir).unwrap();
+
+        assert_eq!(added, published);
+        assert_eq!(includes[0].path, "tabs/lib.bs");
+
+        let header = includes[0].header();
+        let code = format!("┌{}┐\n└{}┘", header, "─".repeat(header.chars().count()));
+        let mut blocks = Block::<i8>::build(&code).unwrap();
+        assert_eq!(include::resolve(&mut blocks, &include_dir), Ok(()));
+    }
+
+    #[test]
+    fn it_rejects_changed_libraries() {
+        let root = scratch("registry-changed");
+        let registry = Registry::Dir(root.clone());
+        publish::<i8>(&registry, &project("registry-changed-project")).unwrap();
+        fs::write(root.join("tabs").join("lib.bs"), LIBRARY.replace('▀', "▄")).unwrap();
+
+        let error =
+            add::<i8>(&registry, "tabs", &scratch("registry-changed-includes")).unwrap_err();
+        assert!(error
+            .message()
+            .starts_with("Library hash mismatch for tabs/lib.bs"));
+        assert_eq!(
+            add::<i8>(&registry, "../tabs", &root).unwrap_err(),
+            BsError::ValidationError("Invalid library name ../tabs".to_string(), None)
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::env;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Render {
+    Unicode,
+    Ascii,
+}
+
+impl Render {
+    pub fn from_name(name: &str) -> Option<Render> {
+        match name {
+            "auto" => Some(Render::detect()),
+            "unicode" => Some(Render::Unicode),
+            "ascii" => Some(Render::Ascii),
+            _ => None,
+        }
+    }
+
+    pub fn detect() -> Render {
+        Render::detect_from(|key| env::var(key).ok(), cfg!(windows))
+    }
+
+    pub fn detect_from<F: Fn(&str) -> Option<String>>(var: F, windows: bool) -> Render {
+        if windows {
+            // Legacy conhost uses an OEM codepage without the block glyphs, while
+            // Windows Terminal and editor terminals announce themselves.
+            if var("WT_SESSION").is_some() || var("TERM_PROGRAM").as_deref() == Some("vscode") {
+                return Render::Unicode;
+            }
+
+            return Render::Ascii;
+        }
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|key| var(key))
+            .find(|value| !value.is_empty());
+
+        match locale {
+            Some(value) => {
+                let value = value.to_lowercase();
+                if value.contains("utf-8") || value.contains("utf8") {
+                    Render::Unicode
+                } else {
+                    Render::Ascii
+                }
+            }
+            None => Render::Unicode,
+        }
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            Render::Unicode => text.to_string(),
+            Render::Ascii => text.chars().map(transliterate).collect(),
+        }
+    }
+}
+
+pub fn transliterate(c: char) -> char {
+    if c.is_ascii() {
+        return c;
+    }
+
+    match c {
+        '▀' => '1',
+        '▄' => '0',
+        '▕' => '(',
+        '▏' => ')',
+        '▔' => '~',
+        '▁' => '_',
+        '▣' => 'a',
+        '▖' => '%',
+        '▗' => '\\',
+        '▘' => '*',
+        '▝' => '/',
+        '▚' => '{',
+        '▞' => '}',
+        '▐' => '+',
+        '▌' => '-',
+        '▨' => '<',
+        '▧' => '>',
+        '▦' => 'm',
+        '▩' => 'M',
+        '▤' => '=',
+        '▥' => '!',
+        '░' => '&',
+        '▒' => '^',
+        '▓' => '|',
+        '◇' => '@',
+        '◈' => ':',
+        '◘' => 's',
+        '◙' => 'p',
+        '◫' => 'c',
+        '◼' => 'f',
+        '◵' => 'k',
+        '◶' => 'v',
+        '◷' => 'l',
+        '◸' => 'w',
+        '◆' => '$',
+        '▭' => '.',
+        '▬' => ';',
+        '▯' => ',',
+        '▮' => '#',
+        '◰' => 'E',
+        '◱' => 'L',
+        '◲' => 'C',
+        '◳' => 'N',
+        '◴' => 'F',
+        '═' => '=',
+        '─' | '━' | '┄' | '┅' | '┈' | '┉' | '╌' | '╍' => '-',
+        '│' | '┃' | '║' | '┆' | '┇' | '┊' | '┋' | '╎' | '╏' => '|',
+        '┌' | '┐' | '└' | '┘' | '┏' | '┓' | '┗' | '┛' | '╔' | '╗' | '╚' | '╝' | '╭' | '╮' | '╯'
+        | '╰' => '+',
+        _ => '?',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_transliterates_glyphs() {
+        assert_eq!(Render::Ascii.apply("▭▀▀▄▄▄▄▄▄"), ".11000000");
+        assert_eq!(Render::Ascii.apply("┌──┐\n│▀◈▀│"), "+--+\n|1:1|");
+        assert_eq!(Render::Ascii.apply("é"), "?");
+        assert_eq!(Render::Unicode.apply("▭▀"), "▭▀");
+    }
+
+    #[test]
+    fn it_keeps_columns_aligned() {
+        let line = "╔═══╗ ▀▀▐▀▀▘▕▀▀▌◇▀▏";
+        assert_eq!(
+            Render::Ascii.apply(line).chars().count(),
+            line.chars().count()
+        );
+    }
+
+    #[test]
+    fn it_detects_terminals() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |key: &str| {
+                pairs
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        assert_eq!(Render::detect_from(env(&[]), true), Render::Ascii);
+        assert_eq!(
+            Render::detect_from(env(&[("WT_SESSION", "1")]), true),
+            Render::Unicode
+        );
+        assert_eq!(Render::detect_from(env(&[]), false), Render::Unicode);
+        assert_eq!(
+            Render::detect_from(env(&[("LANG", "en_US.UTF-8")]), false),
+            Render::Unicode
+        );
+        assert_eq!(
+            Render::detect_from(env(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")]), false),
+            Render::Ascii
+        );
+    }
+}
@@ -0,0 +1,151 @@
+#[cfg(feature = "json")]
+use super::error::BsError;
+#[cfg(feature = "json")]
+use super::mi::location;
+#[cfg(feature = "json")]
+use serde_json::Value;
+use std::collections::VecDeque;
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Decision {
+    Wave(Vec<[usize; 2]>),
+    Start([usize; 2], usize),
+    Turn([usize; 2], usize),
+    Finish([usize; 2], usize),
+}
+
+#[cfg(feature = "json")]
+impl Decision {
+    pub fn to_json(&self) -> Value {
+        match self {
+            Decision::Wave(boxes) => json!({
+                "event": "wave",
+                "boxes": boxes.iter().map(|at| location(*at)).collect::<Vec<Value>>(),
+            }),
+            Decision::Start(at, thread) => {
+                json!({ "event": "start", "box": location(*at), "thread": thread })
+            }
+            Decision::Turn(at, thread) => {
+                json!({ "event": "turn", "box": location(*at), "thread": thread })
+            }
+            Decision::Finish(at, thread) => {
+                json!({ "event": "finish", "box": location(*at), "thread": thread })
+            }
+        }
+    }
+
+    fn from_json(value: &Value) -> Option<Decision> {
+        let corner = |value: &Value| -> Option<[usize; 2]> {
+            let field = |name: &str| match value.get(name).and_then(Value::as_u64) {
+                Some(n) if n > 0 => Some(n as usize - 1),
+                _ => None,
+            };
+            Some([field("line")?, field("column")?])
+        };
+        let thread = || value["thread"].as_u64().map(|thread| thread as usize);
+
+        match value["event"].as_str()? {
+            "wave" => value["boxes"]
+                .as_array()?
+                .iter()
+                .map(corner)
+                .collect::<Option<Vec<[usize; 2]>>>()
+                .map(Decision::Wave),
+            "start" => Some(Decision::Start(corner(&value["box"])?, thread()?)),
+            "turn" => Some(Decision::Turn(corner(&value["box"])?, thread()?)),
+            "finish" => Some(Decision::Finish(corner(&value["box"])?, thread()?)),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schedule {
+    pub decisions: Vec<Decision>,
+    replay: VecDeque<[usize; 2]>,
+}
+
+impl Schedule {
+    pub fn new() -> Schedule {
+        Schedule::default()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn replaying(log: &str) -> Result<Schedule, BsError> {
+        let mut schedule = Schedule::new();
+
+        for (i, line) in log.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let decision = serde_json::from_str::<Value>(line)
+                .ok()
+                .as_ref()
+                .and_then(Decision::from_json)
+                .ok_or_else(|| {
+                    BsError::ParseError(format!("Invalid schedule entry on line {}", i + 1), None)
+                })?;
+            if let Decision::Turn(at, _) = decision {
+                schedule.replay.push_back(at);
+            }
+        }
+
+        Ok(schedule)
+    }
+
+    pub fn record(&mut self, decision: Decision) {
+        self.decisions.push(decision);
+    }
+
+    pub fn next_turn(&mut self) -> Option<[usize; 2]> {
+        self.replay.pop_front()
+    }
+
+    #[cfg(feature = "json")]
+    pub fn log(&self) -> String {
+        self.decisions
+            .iter()
+            .map(|decision| format!("{}\n", decision.to_json()))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_logs() {
+        let mut schedule = Schedule::new();
+        schedule.record(Decision::Wave(vec![[0, 0], [0, 8]]));
+        schedule.record(Decision::Start([0, 0], 0));
+        schedule.record(Decision::Turn([0, 0], 0));
+        schedule.record(Decision::Turn([0, 8], 1));
+        schedule.record(Decision::Finish([0, 8], 1));
+
+        let log = schedule.log();
+        assert_eq!(
+            log.lines().next(),
+            Some(r#"{"boxes":[{"column":1,"line":1},{"column":9,"line":1}],"event":"wave"}"#)
+        );
+
+        let mut replay = Schedule::replaying(&log).unwrap();
+        assert_eq!(replay.next_turn(), Some([0, 0]));
+        assert_eq!(replay.next_turn(), Some([0, 8]));
+        assert_eq!(replay.next_turn(), None);
+        assert!(replay.decisions.is_empty());
+    }
+
+    #[test]
+    fn it_rejects_bad_entries() {
+        assert_eq!(
+            Schedule::replaying("\n{\"event\":\"turn\",\"box\":{\"line\":0,\"column\":1}}"),
+            Err(BsError::ParseError(
+                "Invalid schedule entry on line 2".to_string(),
+                None
+            ))
+        );
+    }
+}
@@ -0,0 +1,729 @@
+use super::ast::Expr;
+use super::block::Block;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Atom, Molecule};
+use super::function::Functions;
+use super::interpreter::{ArithMode, Interpreter};
+use super::r#box::Genus;
+use super::scheduler::{self, Step};
+use super::stdlib::Builtin;
+use std::collections::HashMap;
+
+const HEADER: &str = r#"// Generated by `bs compile --target rust`.
+#![allow(dead_code, unreachable_code, unused_assignments, unused_imports, unused_mut, unused_parens, unused_variables)]
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, BufReader, BufWriter, Bytes, Read, Stdin, Stdout, Write};
+use std::process;
+
+type Int = $INT;
+
+const MAX_CALL_DEPTH: usize = $DEPTH;
+"#;
+
+const RUNTIME: &str = r#"
+struct Frame {
+    argument: Int,
+    scratch: HashMap<Int, Int>,
+}
+
+struct State {
+    memory: HashMap<Int, Int>,
+    frames: Vec<Frame>,
+    stdout: BufWriter<Stdout>,
+    stdin: Bytes<BufReader<Stdin>>,
+}
+
+fn control(code: i64) -> Option<String> {
+    Some(match code {
+        -1 => "\u{1b}[2J\u{1b}[H".to_string(),
+        -2 => "\u{1b}[0m".to_string(),
+        -3 => "\u{1b}[?25l".to_string(),
+        -4 => "\u{1b}[?25h".to_string(),
+        _ if code > -0x200 && code <= -0x100 => format!("\u{1b}[38;5;{}m", -0x100 - code),
+        _ if code > -0x300 && code <= -0x200 => format!("\u{1b}[48;5;{}m", -0x200 - code),
+        _ if code > -0x2_0000 && code <= -0x1_0000 => {
+            let position = -0x1_0000 - code;
+            format!("\u{1b}[{};{}H", (position >> 8) + 1, (position & 0xff) + 1)
+        }
+        _ => return None,
+    })
+}
+
+impl State {
+    fn new() -> State {
+        State {
+            memory: HashMap::new(),
+            frames: Vec::new(),
+            stdout: BufWriter::new(io::stdout()),
+            stdin: BufReader::new(io::stdin()).bytes(),
+        }
+    }
+
+    fn fail(&mut self, message: String, at: (usize, usize)) -> ! {
+        let _ = self.stdout.flush();
+        eprintln!("error: {} at {}:{}", message, at.0, at.1);
+        process::exit(1);
+    }
+
+    fn invalid(&mut self, operation: &str, at: (usize, usize)) -> ! {
+        self.fail(format!("{} caused invalid value", operation), at)
+    }
+
+    fn get(&self, address: Int) -> Int {
+        self.memory.get(&address).cloned().unwrap_or(0)
+    }
+
+    fn assign(&mut self, address: Int, value: Int) -> Int {
+        self.memory.insert(address, value);
+        value
+    }
+
+    fn assign_silently(&mut self, address: Int, value: Int) -> Int {
+        self.memory.insert(address, value);
+        0
+    }
+
+    fn assign_indirect(&mut self, address: Int, value: Int) -> Int {
+        let address = self.get(address);
+        self.assign(address, value)
+    }
+
+    fn set_if_zero(&mut self, address: Int, value: Int) -> Int {
+        if self.get(address) != 0 {
+            return 0;
+        }
+        self.memory.insert(address, value);
+        1
+    }
+
+    fn frame(&mut self, what: &str, at: (usize, usize)) -> &mut Frame {
+        if self.frames.is_empty() {
+            self.fail(format!("{} used outside of a function", what), at);
+        }
+        self.frames.last_mut().unwrap()
+    }
+
+    fn argument(&mut self, at: (usize, usize)) -> Int {
+        self.frame("Argument", at).argument
+    }
+
+    fn local(&mut self, address: Int, at: (usize, usize)) -> Int {
+        let frame = self.frame("Local memory", at);
+        frame.scratch.get(&address).cloned().unwrap_or(0)
+    }
+
+    fn assign_local(&mut self, address: Int, value: Int, at: (usize, usize)) -> Int {
+        self.frame("Local memory", at).scratch.insert(address, value);
+        value
+    }
+
+    fn output(&mut self, value: Int) -> Int {
+        let text = match u32::try_from(value).ok().and_then(std::char::from_u32) {
+            Some(c) => c.to_string(),
+            None => i64::try_from(value)
+                .ok()
+                .and_then(control)
+                .unwrap_or_else(|| "\u{ffff}".to_string()),
+        };
+        let _ = self.stdout.write_all(text.as_bytes());
+        value
+    }
+
+    fn print(&mut self, value: Int) -> Int {
+        let _ = write!(self.stdout, "{}", value);
+        value
+    }
+
+    fn read_char(&mut self) -> Option<char> {
+        let first = self.stdin.next()?.ok()?;
+        let length = match first {
+            0x00..=0x7f => return Some(first as char),
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Some('\u{fffd}'),
+        };
+
+        let mut buffer = vec![first];
+        for _ in 1..length {
+            buffer.push(self.stdin.next()?.ok()?);
+        }
+
+        Some(
+            std::str::from_utf8(&buffer)
+                .ok()
+                .and_then(|s| s.chars().next())
+                .unwrap_or('\u{fffd}'),
+        )
+    }
+
+    fn read_int(&mut self) -> Option<i128> {
+        let mut c = self.read_char()?;
+        while c.is_whitespace() {
+            c = self.read_char()?;
+        }
+
+        let mut digits = String::new();
+        if c == '-' || c == '+' {
+            digits.push(c);
+            c = self.read_char()?;
+        }
+        while c.is_ascii_digit() {
+            digits.push(c);
+            match self.read_char() {
+                Some(next) => c = next,
+                None => break,
+            }
+        }
+
+        digits.parse().ok()
+    }
+
+    fn input(&mut self, number: Int, at: (usize, usize)) -> Int {
+        let _ = self.stdout.flush();
+        if number != 0 {
+            match self.read_int().and_then(|n| Int::try_from(n).ok()) {
+                Some(n) => n,
+                None => self.fail("Expected an integer on input".to_string(), at),
+            }
+        } else {
+            match self.read_char().map(|c| Int::try_from(c as u32)) {
+                Some(Ok(n)) => n,
+                Some(Err(_)) => self.fail("Input character is out of range".to_string(), at),
+                None => -1,
+            }
+        }
+    }
+
+    fn add(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        match $ADD {
+            Some(value) => value,
+            None => self.fail(format!("Arithmetic overflow: {} ▐ {}", a, b), at),
+        }
+    }
+
+    fn subtract(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        match $SUBTRACT {
+            Some(value) => value,
+            None => self.fail(format!("Arithmetic overflow: {} ▌ {}", a, b), at),
+        }
+    }
+
+    fn multiply(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        match $MULTIPLY {
+            Some(value) => value,
+            None => self.fail(format!("Arithmetic overflow: {} ▘ {}", a, b), at),
+        }
+    }
+
+    fn negate(&mut self, a: Int, at: (usize, usize)) -> Int {
+        match $NEGATE {
+            Some(value) => value,
+            None => self.fail(format!("Arithmetic overflow: ▁{}", a), at),
+        }
+    }
+
+    fn abs(&mut self, a: Int, at: (usize, usize)) -> Int {
+        if a >= 0 {
+            return a;
+        }
+        match $NEGATE {
+            Some(value) => value,
+            None => self.fail(format!("Arithmetic overflow: ▣{}", a), at),
+        }
+    }
+
+    fn divide(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        if b == 0 {
+            self.invalid("Division", at);
+        }
+        match a.checked_div(b).or($QUOTIENT) {
+            Some(value) => value,
+            None => self.invalid("Division", at),
+        }
+    }
+
+    fn modulo(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        if b == 0 {
+            self.invalid("Modulo", at);
+        }
+        let remainder = match a.checked_rem(b).or($REMAINDER) {
+            Some(remainder) => remainder,
+            None => self.invalid("Modulo", at),
+        };
+
+        if a != 0 && (a < 0) != (b < 0) {
+            match b.checked_add(remainder) {
+                Some(value) => value,
+                None => self.invalid("Modulo", at),
+            }
+        } else {
+            remainder
+        }
+    }
+
+    fn inverse_modulo(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        let x = self.modulo(a, b, at);
+        let mut n: Int = 1;
+        while n < b {
+            let product = match n.checked_mul(x) {
+                Some(product) => product,
+                None => self.invalid("Inverse modulo", at),
+            };
+            if self.modulo(product, b, at) == 1 {
+                return n;
+            }
+            n += 1;
+        }
+
+        self.fail(format!("{} is not invertible", a), at)
+    }
+
+    fn shift_left(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        match u32::try_from(b).ok().and_then(|b| $SHIFT_LEFT) {
+            Some(value) => value,
+            None => self.fail("Bitwise shift got invalid value".to_string(), at),
+        }
+    }
+
+    fn shift_right(&mut self, a: Int, b: Int, at: (usize, usize)) -> Int {
+        match u32::try_from(b).ok().and_then(|b| $SHIFT_RIGHT) {
+            Some(value) => value,
+            None => self.fail("Bitwise shift got invalid value".to_string(), at),
+        }
+    }
+}
+"#;
+
+fn arithmetic(mode: ArithMode) -> [(&'static str, &'static str); 8] {
+    match mode {
+        ArithMode::Wrapping => [
+            ("$ADD", "Some(a.wrapping_add(b))"),
+            ("$SUBTRACT", "Some(a.wrapping_sub(b))"),
+            ("$MULTIPLY", "Some(a.wrapping_mul(b))"),
+            ("$NEGATE", "Some(a.wrapping_neg())"),
+            ("$QUOTIENT", "Some(Int::MIN)"),
+            ("$REMAINDER", "Some(0)"),
+            ("$SHIFT_LEFT", "Some(a.wrapping_shl(b))"),
+            ("$SHIFT_RIGHT", "Some(a.wrapping_shr(b))"),
+        ],
+        ArithMode::Saturating => [
+            ("$ADD", "Some(a.saturating_add(b))"),
+            ("$SUBTRACT", "Some(a.saturating_sub(b))"),
+            ("$MULTIPLY", "Some(a.saturating_mul(b))"),
+            ("$NEGATE", "Some(a.saturating_neg())"),
+            ("$QUOTIENT", "Some(Int::MAX)"),
+            ("$REMAINDER", "Some(0)"),
+            ("$SHIFT_LEFT", "a.checked_shl(b).or(Some(0))"),
+            (
+                "$SHIFT_RIGHT",
+                "a.checked_shr(b).or(Some(if a < 0 { -1 } else { 0 }))",
+            ),
+        ],
+        _ => [
+            ("$ADD", "a.checked_add(b)"),
+            ("$SUBTRACT", "a.checked_sub(b)"),
+            ("$MULTIPLY", "a.checked_mul(b)"),
+            ("$NEGATE", "a.checked_neg()"),
+            ("$QUOTIENT", "None"),
+            ("$REMAINDER", "None"),
+            ("$SHIFT_LEFT", "a.checked_shl(b)"),
+            ("$SHIFT_RIGHT", "a.checked_shr(b)"),
+        ],
+    }
+}
+
+fn unsupported(what: &str, at: Option<[usize; 2]>) -> BsError {
+    BsError::ValidationError(format!("{} is not supported by the Rust backend", what), at)
+}
+
+struct Emitter<T: BoxInt> {
+    code: String,
+    indent: usize,
+    sequential: bool,
+    functions: HashMap<T, usize>,
+}
+
+impl<T: BoxInt> Emitter<T> {
+    fn line(&mut self, text: &str) {
+        self.code.push_str(&"    ".repeat(self.indent));
+        self.code.push_str(text);
+        self.code.push('\n');
+    }
+
+    fn number(num: T) -> String {
+        format!("{}{}", num, std::any::type_name::<T>())
+    }
+
+    fn operand(
+        &self,
+        expr: &Expr<T>,
+        name: &str,
+        at: [usize; 2],
+    ) -> Result<(String, String), BsError> {
+        match expr {
+            Expr::Number(num) => Ok((String::new(), Emitter::number(*num))),
+            _ => Ok((
+                format!("let {} = {}; ", name, self.expr(expr, at)?),
+                name.to_string(),
+            )),
+        }
+    }
+
+    fn expr(&self, expr: &Expr<T>, fallback: [usize; 2]) -> Result<String, BsError> {
+        let here = |at: &Option<[usize; 2]>| {
+            let [row, col] = at.unwrap_or(fallback);
+            format!("({}, {})", row + 1, col + 1)
+        };
+
+        Ok(match expr {
+            Expr::Number(num) => Emitter::number(*num),
+            Expr::PollKey(at) => return Err(unsupported("Polling keys", *at)),
+            Expr::Argument(at) => format!("st.argument({})", here(at)),
+            Expr::Unary(atom, operand, at) => {
+                let (bind, a) = self.operand(operand, "a", fallback)?;
+                let operation = match atom {
+                    Atom::Memory => format!("st.get({})", a),
+                    Atom::Not => format!("!{}", a),
+                    Atom::Negate => format!("st.negate({}, {})", a, here(at)),
+                    Atom::Abs => format!("st.abs({}, {})", a, here(at)),
+                    Atom::Output => format!("st.output({})", a),
+                    Atom::OutputNumber => format!("st.print({})", a),
+                    Atom::Input => format!("st.input({}, {})", a, here(at)),
+                    Atom::Local => format!("st.local({}, {})", a, here(at)),
+                    Atom::Copy => return Err(unsupported("Copying memory", *at)),
+                    _ => return Err(unsupported("Filling memory", *at)),
+                };
+                if bind.is_empty() {
+                    operation
+                } else {
+                    format!("{{ {}{} }}", bind, operation)
+                }
+            }
+            Expr::Binary(Atom::Concat, left, right, _) => format!(
+                "{{ let _ = {}; {} }}",
+                self.expr(left, fallback)?,
+                self.expr(right, fallback)?
+            ),
+            Expr::Binary(atom, left, right, at) => {
+                if let (Atom::Call, Expr::Number(number)) = (atom, &**left) {
+                    if !self.functions.contains_key(number)
+                        && Builtin::from_number(*number).is_some()
+                    {
+                        return Err(unsupported("Calling the standard library", *at));
+                    }
+                }
+
+                let (bind_a, a) = self.operand(left, "a", fallback)?;
+                let (bind_b, b) = self.operand(right, "b", fallback)?;
+                let operation = match atom {
+                    Atom::Add => format!("st.add({}, {}, {})", a, b, here(at)),
+                    Atom::Subtract => format!("st.subtract({}, {}, {})", a, b, here(at)),
+                    Atom::Multiply => format!("st.multiply({}, {}, {})", a, b, here(at)),
+                    Atom::Divide => format!("st.divide({}, {}, {})", a, b, here(at)),
+                    Atom::Modulo => format!("st.modulo({}, {}, {})", a, b, here(at)),
+                    Atom::InverseModulo => {
+                        format!("st.inverse_modulo({}, {}, {})", a, b, here(at))
+                    }
+                    Atom::LeftShift => format!("st.shift_left({}, {}, {})", a, b, here(at)),
+                    Atom::RightShift => format!("st.shift_right({}, {}, {})", a, b, here(at)),
+                    Atom::And => format!("({} & {})", a, b),
+                    Atom::Or => format!("({} | {})", a, b),
+                    Atom::Xor => format!("({} ^ {})", a, b),
+                    Atom::Less => format!("({} < {}) as Int", a, b),
+                    Atom::Greater => format!("({} > {}) as Int", a, b),
+                    Atom::Equal => format!("({} == {}) as Int", a, b),
+                    Atom::NotEqual => format!("({} != {}) as Int", a, b),
+                    Atom::Min => format!("Int::min({}, {})", a, b),
+                    Atom::Max => format!("Int::max({}, {})", a, b),
+                    Atom::Assign => format!("st.assign({}, {})", a, b),
+                    Atom::SilentAssign => format!("st.assign_silently({}, {})", a, b),
+                    Atom::IndirectAssign => format!("st.assign_indirect({}, {})", a, b),
+                    Atom::SetIfZero => format!("st.set_if_zero({}, {})", a, b),
+                    Atom::LocalAssign => format!("st.assign_local({}, {}, {})", a, b, here(at)),
+                    _ => format!("call(st, {}, {}, {})", a, b, here(at)),
+                };
+                if bind_a.is_empty() && bind_b.is_empty() {
+                    operation
+                } else {
+                    format!("{{ {}{}{} }}", bind_a, bind_b, operation)
+                }
+            }
+        })
+    }
+
+    fn molecule(&self, molecule: &mut Molecule<T>, at: [usize; 2]) -> Result<String, BsError> {
+        let at = molecule.positions().first().cloned().unwrap_or(at);
+        let expr = molecule.compile()?.clone();
+        self.expr(&expr, at)
+    }
+
+    fn body(&mut self, block: &mut Block<T>) -> Result<(), BsError> {
+        self.line("let mut value: Int = 0;");
+        for step in scheduler::plan(block) {
+            match step {
+                Step::Molecule(i) => {
+                    let (row, molecule) = &mut block.molecules[i];
+                    let at = [*row, block.r#box.top_left[1] + 1];
+                    let expr = self.molecule(molecule, at)?;
+                    self.line(&format!("// {}", molecule.source()));
+                    self.line(&format!("value = {};", expr));
+                }
+                Step::Block(i) => self.block(&mut block.children[i])?,
+                Step::Simultaneous(range) => self.batch(&mut block.children[range])?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn batch(&mut self, blocks: &mut [Block<T>]) -> Result<(), BsError> {
+        if blocks.len() > 1 && !self.sequential {
+            return Err(unsupported(
+                "Running side-by-side boxes in turns",
+                Some(blocks[1].r#box.top_left),
+            ));
+        }
+
+        for block in blocks.iter_mut() {
+            self.block(block)?;
+        }
+
+        Ok(())
+    }
+
+    fn nested(&mut self, open: &str, block: &mut Block<T>, close: &str) -> Result<(), BsError> {
+        self.line(open);
+        self.indent += 1;
+        self.body(block)?;
+        self.line("value");
+        self.indent -= 1;
+        self.line(close);
+        Ok(())
+    }
+
+    fn guard(&self, block: &mut Block<T>) -> Result<String, BsError> {
+        let at = block.r#box.top_left;
+        self.molecule(block.guard.as_mut().unwrap(), at)
+    }
+
+    fn block(&mut self, block: &mut Block<T>) -> Result<(), BsError> {
+        match block.r#box.genus {
+            Genus::Execution => self.nested("value = {", block, "};"),
+            Genus::Condition => {
+                let guard = self.guard(block)?;
+                self.nested(
+                    &format!("value = if {} != 0 {{", guard),
+                    block,
+                    "} else { 0 };",
+                )
+            }
+            Genus::Loop => {
+                let guard = self.guard(block)?;
+                self.line("value = {");
+                self.indent += 1;
+                self.line("let mut last: Int = 0;");
+                self.line(&format!("while {} != 0 {{", guard));
+                self.indent += 1;
+                self.nested("last = {", block, "};")?;
+                self.indent -= 1;
+                self.line("}");
+                self.line("last");
+                self.indent -= 1;
+                self.line("};");
+                Ok(())
+            }
+            Genus::NoOp | Genus::Function => {
+                self.line("value = 0;");
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn compile<T: BoxInt>(interpreter: &Interpreter<T>, source: &str) -> Result<String, BsError> {
+    let mut blocks = interpreter.prepare(source)?;
+    let mut functions = Functions::new();
+    functions.define(&mut blocks, interpreter.dialect())?;
+
+    let mut numbers: Vec<T> = functions.bodies().keys().cloned().collect();
+    numbers.sort();
+    let mut emitter = Emitter {
+        code: String::new(),
+        indent: 0,
+        sequential: interpreter.sequential(),
+        functions: numbers.iter().enumerate().map(|(i, n)| (*n, i)).collect(),
+    };
+
+    for (i, number) in numbers.iter().enumerate() {
+        let mut body = functions.bodies()[number].clone();
+        emitter.line("");
+        emitter.line(&format!("// Function {}", number));
+        emitter.line(&format!("fn function_{}(st: &mut State) -> Int {{", i));
+        emitter.indent += 1;
+        emitter.body(&mut body)?;
+        emitter.line("value");
+        emitter.indent -= 1;
+        emitter.line("}");
+    }
+
+    emitter.line("");
+    emitter
+        .line("fn call(st: &mut State, number: Int, argument: Int, at: (usize, usize)) -> Int {");
+    emitter.indent += 1;
+    emitter.line("if st.frames.len() >= MAX_CALL_DEPTH {");
+    emitter.line("    let depth = st.frames.len();");
+    emitter.line("    st.fail(format!(\"Call stack exceeded {} frames\", depth), at);");
+    emitter.line("}");
+    emitter.line("let function: fn(&mut State) -> Int = match number {");
+    for (i, number) in numbers.iter().enumerate() {
+        emitter.line(&format!(
+            "    {} => function_{},",
+            Emitter::number(*number),
+            i
+        ));
+    }
+    emitter.line("    _ => st.fail(format!(\"No function numbered {}\", number), at),");
+    emitter.line("};");
+    emitter.line("st.frames.push(Frame {");
+    emitter.line("    argument,");
+    emitter.line("    scratch: HashMap::new(),");
+    emitter.line("});");
+    emitter.line("let value = function(st);");
+    emitter.line("st.frames.pop();");
+    emitter.line("value");
+    emitter.indent -= 1;
+    emitter.line("}");
+
+    emitter.line("");
+    emitter.line("fn main() {");
+    emitter.indent += 1;
+    emitter.line("let st = &mut State::new();");
+    emitter.line("let mut value: Int = 0;");
+    for batch in scheduler::batches(blocks.iter().map(|block| &block.r#box)) {
+        emitter.batch(&mut blocks[batch])?;
+    }
+    emitter.line("let _ = st.stdout.flush();");
+    emitter.indent -= 1;
+    emitter.line("}");
+
+    let mut runtime = RUNTIME.to_string();
+    for (placeholder, code) in arithmetic(interpreter.arith_mode()).iter() {
+        runtime = runtime.replace(placeholder, code);
+    }
+    let header = HEADER
+        .replace("$INT", std::any::type_name::<T>())
+        .replace("$DEPTH", &interpreter.max_call_depth().to_string());
+
+    Ok(format!("{}{}{}", header, runtime, emitter.code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::expression::Dialect;
+    use super::*;
+    use std::fs;
+    use std::process::Command;
+
+    fn compile_in(dialect: Dialect, code: &str) -> Result<String, BsError> {
+        let mut interpreter = Interpreter::<i64>::new();
+        interpreter.set_dialect(dialect);
+        compile(&interpreter, code)
+    }
+
+    #[test]
+    fn it_compiles_loops_and_conditions() {
+        let code = "╔═◇▀▨▀▀▄▄════╗\n║▭◇▀▐▀▀▀▄▄▄▄ ║\n║▀◈◇▀▐▀▀     ║\n╚════════════╝\n┏━▀▀▀▄░▀▀▀━┓\n┃▭▀▀▀▀▀▄▄▀ ┃\n┗━━━━━━━━━━┛";
+        let program = compile_in(Dialect::STABLE, code).unwrap();
+
+        assert!(program.contains("type Int = i64;"));
+        assert!(program.contains("while { let a = st.get(0i64); (a < 4i64) as Int } != 0 {"));
+        assert!(program.contains("// ▭◇▀▐▀▀▀▄▄▄▄\n"));
+        assert!(program.contains(
+            "value = { let a = { let a = st.get(0i64); st.add(a, 48i64, (2, 5)) }; st.output(a) };"
+        ));
+        assert!(program.contains("value = if (6i64 & 3i64) != 0 {"));
+        assert!(program.contains("value = st.output(121i64);"));
+    }
+
+    #[test]
+    fn it_compiles_functions() {
+        let code = "┌────────────┐\n│◴▀▀         │\n│▀◈◶▐◶       │\n│◇▀          │\n└────────────┘\n┌────────────┐\n│▬▀▀◵▀▀▀     │\n└────────────┘";
+        let program = compile_in(Dialect::V3, code).unwrap();
+
+        assert!(program.contains("// Function 1\nfn function_0(st: &mut State) -> Int {"));
+        assert!(program.contains("        1i64 => function_0,\n"));
+        assert!(program.contains("value = { let a = call(st, 1i64, 3i64, (7, 5)); st.print(a) };"));
+        assert!(program.contains("const MAX_CALL_DEPTH: usize = 64;"));
+    }
+
+    #[test]
+    fn it_follows_the_interpreter_settings() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_arith_mode(ArithMode::Wrapping);
+        let program = compile(&interpreter, "┌────┐\n│▀◈▀▀│\n└────┘").unwrap();
+
+        assert!(program.contains("type Int = i8;"));
+        assert!(program.contains("match Some(a.wrapping_add(b)) {"));
+        assert!(!program.contains("a.checked_add(b)"));
+        assert!(program.contains("value = st.assign(0i8, 1i8);"));
+    }
+
+    #[test]
+    fn it_keeps_negative_operands_intact() {
+        let code = "┌─────────┐\n│▬▕▄▀▀▦▀▀▏│\n│▬▄▀▀▩▄▀▀▀│\n└─────────┘";
+        let program = compile_in(Dialect::V3, code).unwrap();
+        assert!(program.contains("value = { let a = Int::min(-3i64, 1i64); st.print(a) };"));
+        assert!(program.contains("value = { let a = Int::max(-3i64, -7i64); st.print(a) };"));
+
+        let dir = std::env::temp_dir().join(format!("boxscript-rust-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("main.rs"), program).unwrap();
+        let built = Command::new("rustc")
+            .arg("main.rs")
+            .current_dir(&dir)
+            .output();
+        if !built.map(|built| built.status.success()).unwrap_or(false) {
+            fs::remove_dir_all(&dir).unwrap();
+            return;
+        }
+
+        let output = Command::new(dir.join("main")).output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "-3-3");
+    }
+
+    #[test]
+    fn it_rejects_what_it_cannot_compile() {
+        assert_eq!(
+            compile_in(Dialect::V3, "┌───┐\n│▀◈▮│\n└───┘"),
+            Err(BsError::ValidationError(
+                "Polling keys is not supported by the Rust backend".to_string(),
+                Some([1, 3])
+            ))
+        );
+        assert_eq!(
+            compile_in(Dialect::V3, "┌──────┐\n│▬▄▀▀◵▀│\n└──────┘"),
+            Err(BsError::ValidationError(
+                "Calling the standard library is not supported by the Rust backend".to_string(),
+                Some([1, 5])
+            ))
+        );
+
+        let code = "┌────┐┌────┐\n│▭▀▀▀││▭▀▀▄│\n└────┘└────┘";
+        let mut interpreter = Interpreter::<i64>::new();
+        assert_eq!(
+            compile(&interpreter, code),
+            Err(BsError::ValidationError(
+                "Running side-by-side boxes in turns is not supported by the Rust backend"
+                    .to_string(),
+                Some([0, 6])
+            ))
+        );
+        interpreter.set_sequential(true);
+        assert!(compile(&interpreter, code).is_ok());
+    }
+}
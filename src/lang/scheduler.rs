@@ -0,0 +1,490 @@
+use super::block::{Block, Node};
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::interpreter::{ExecContext, Runnable};
+use super::r#box::{Box, Genus};
+use super::replay::Decision;
+use std::ops::Range;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Step {
+    Molecule(usize),
+    Block(usize),
+    Simultaneous(Range<usize>),
+}
+
+pub fn batches<'a>(boxes: impl IntoIterator<Item = &'a Box>) -> Vec<Range<usize>> {
+    let mut batches: Vec<Range<usize>> = Vec::new();
+    let mut bottom = 0;
+
+    for (i, r#box) in boxes.into_iter().enumerate() {
+        match batches.last_mut() {
+            Some(batch) if r#box.top_left[0] <= bottom => {
+                batch.end = i + 1;
+                bottom = bottom.max(r#box.bottom_right[0]);
+            }
+            _ => {
+                batches.push(i..i + 1);
+                bottom = r#box.bottom_right[0];
+            }
+        }
+    }
+
+    batches
+}
+
+pub fn plan<T: BoxInt>(block: &Block<T>) -> Vec<Step> {
+    let mut steps: Vec<Step> = Vec::new();
+    let mut run: Vec<usize> = Vec::new();
+    let flush = |run: &mut Vec<usize>, steps: &mut Vec<Step>| {
+        if let (Some(first), Some(last)) = (run.first(), run.last()) {
+            let (first, last) = (*first, *last);
+            for batch in batches(block.children[first..=last].iter().map(|b| &b.r#box)) {
+                let batch = batch.start + first..batch.end + first;
+                steps.push(if batch.len() > 1 {
+                    Step::Simultaneous(batch)
+                } else {
+                    Step::Block(batch.start)
+                });
+            }
+        }
+        run.clear();
+    };
+
+    for node in block.schedule() {
+        match node {
+            Node::Block(i) => run.push(i),
+            Node::Molecule(i) => {
+                flush(&mut run, &mut steps);
+                steps.push(Step::Molecule(i));
+            }
+        }
+    }
+    flush(&mut run, &mut steps);
+
+    steps
+}
+
+enum Stage {
+    Guard,
+    Body(usize),
+}
+
+struct Frame<T: BoxInt> {
+    path: Vec<usize>,
+    steps: Vec<Step>,
+    stage: Stage,
+    iterations: usize,
+    value: T,
+}
+
+enum Task<T: BoxInt> {
+    Frame(Frame<T>),
+    Group(Group<T>),
+}
+
+struct Thread<T: BoxInt> {
+    tasks: Vec<Task<T>>,
+    value: T,
+}
+
+struct Group<T: BoxInt> {
+    threads: Vec<Option<Thread<T>>>,
+    boxes: Vec<[usize; 2]>,
+    started: Vec<bool>,
+    values: Vec<T>,
+    turns: Vec<usize>,
+    next: usize,
+}
+
+fn locate<'a, T: BoxInt>(roots: &'a mut [Block<T>], path: &[usize]) -> &'a mut Block<T> {
+    let mut block = &mut roots[path[0]];
+    for i in &path[1..] {
+        block = &mut block.children[*i];
+    }
+    block
+}
+
+impl<T: BoxInt> Frame<T> {
+    fn new(roots: &mut [Block<T>], path: Vec<usize>) -> Frame<T> {
+        let steps = plan(locate(roots, &path));
+        Frame {
+            path,
+            steps,
+            stage: Stage::Guard,
+            iterations: 0,
+            value: T::zero(),
+        }
+    }
+}
+
+impl<T: BoxInt> Thread<T> {
+    fn new(roots: &mut [Block<T>], path: Vec<usize>) -> Thread<T> {
+        Thread {
+            tasks: vec![Task::Frame(Frame::new(roots, path))],
+            value: T::zero(),
+        }
+    }
+
+    fn finish(&mut self, value: T) {
+        match self.tasks.last_mut() {
+            Some(Task::Frame(parent)) => parent.value = value,
+            _ => self.value = value,
+        }
+    }
+
+    fn advance(
+        &mut self,
+        roots: &mut [Block<T>],
+        context: &mut ExecContext<T>,
+    ) -> Result<(bool, bool), BsError> {
+        let mut evaluated = false;
+
+        loop {
+            let frame = match self.tasks.last_mut() {
+                None => return Ok((true, evaluated)),
+                Some(Task::Group(group)) => {
+                    if evaluated {
+                        return Ok((false, true));
+                    }
+
+                    let (finished, spent) = group.turn(roots, context)?;
+                    evaluated = spent;
+                    if !finished {
+                        return Ok((false, evaluated));
+                    }
+
+                    let value = group.value();
+                    self.tasks.pop();
+                    self.finish(value);
+                    continue;
+                }
+                Some(Task::Frame(frame)) => frame,
+            };
+            let block = locate(roots, &frame.path);
+
+            match frame.stage {
+                Stage::Guard => {
+                    let passed = match (block.r#box.genus, block.guard.as_mut()) {
+                        (Genus::NoOp, _) | (Genus::Function, _) => false,
+                        (Genus::Execution, _) | (_, None) => true,
+                        (_, Some(guard)) => {
+                            if evaluated {
+                                return Ok((false, true));
+                            }
+                            evaluated = true;
+                            !Block::evaluate(guard, block.r#box.top_left, context)?.is_zero()
+                        }
+                    };
+
+                    if !passed {
+                        let value = frame.value;
+                        self.tasks.pop();
+                        self.finish(value);
+                        continue;
+                    }
+                    if block.r#box.genus == Genus::Loop {
+                        if Some(frame.iterations) == context.max_iterations {
+                            return Err(BsError::RuntimeError(
+                                format!("Loop exceeded {} iterations", frame.iterations),
+                                Some(block.r#box.top_left),
+                            ));
+                        }
+                        frame.iterations += 1;
+                    }
+                    frame.stage = Stage::Body(0);
+                }
+                Stage::Body(i) => match frame.steps.get(i).cloned() {
+                    None if block.r#box.genus == Genus::Loop => frame.stage = Stage::Guard,
+                    None => {
+                        let value = frame.value;
+                        self.tasks.pop();
+                        self.finish(value);
+                    }
+                    Some(Step::Molecule(j)) => {
+                        if evaluated {
+                            return Ok((false, true));
+                        }
+                        evaluated = true;
+
+                        let (row, molecule) = &mut block.molecules[j];
+                        let at = [*row, block.r#box.top_left[1] + 1];
+                        frame.value = Block::evaluate(molecule, at, context)?;
+                        frame.stage = Stage::Body(i + 1);
+                    }
+                    Some(Step::Block(j)) => {
+                        frame.stage = Stage::Body(i + 1);
+                        let mut path = frame.path.clone();
+                        path.push(j);
+                        self.tasks.push(Task::Frame(Frame::new(roots, path)));
+                    }
+                    Some(Step::Simultaneous(range)) => {
+                        frame.stage = Stage::Body(i + 1);
+                        let paths: Vec<Vec<usize>> = range
+                            .map(|j| {
+                                let mut path = frame.path.clone();
+                                path.push(j);
+                                path
+                            })
+                            .collect();
+                        self.tasks
+                            .push(Task::Group(Group::new(roots, paths, context)));
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<T: BoxInt> Group<T> {
+    fn new(
+        roots: &mut [Block<T>],
+        paths: Vec<Vec<usize>>,
+        context: &mut ExecContext<T>,
+    ) -> Group<T> {
+        let mut turns: Vec<usize> = (0..paths.len()).collect();
+        turns.sort_by_key(|i| locate(roots, &paths[*i]).r#box.top_left[1]);
+        let boxes: Vec<[usize; 2]> = paths
+            .iter()
+            .map(|path| locate(roots, path).r#box.top_left)
+            .collect();
+
+        if let Some(schedule) = context.schedule.as_mut() {
+            schedule.record(Decision::Wave(boxes.clone()));
+        }
+
+        Group {
+            values: vec![T::zero(); paths.len()],
+            started: vec![false; paths.len()],
+            boxes,
+            threads: paths
+                .into_iter()
+                .map(|path| Some(Thread::new(roots, path)))
+                .collect(),
+            turns,
+            next: 0,
+        }
+    }
+
+    fn value(&self) -> T {
+        self.values.last().cloned().unwrap_or_else(T::zero)
+    }
+
+    fn turn(
+        &mut self,
+        roots: &mut [Block<T>],
+        context: &mut ExecContext<T>,
+    ) -> Result<(bool, bool), BsError> {
+        let mut evaluated = false;
+
+        if let Some(i) = self.pick(context)? {
+            if let Some(schedule) = context.schedule.as_mut() {
+                if !self.started[i] {
+                    schedule.record(Decision::Start(self.boxes[i], i));
+                }
+                schedule.record(Decision::Turn(self.boxes[i], i));
+            }
+            self.started[i] = true;
+
+            let thread = self.threads[i].as_mut().unwrap();
+            let (done, spent) = thread.advance(roots, context)?;
+            evaluated = spent;
+            if done {
+                self.values[i] = thread.value;
+                self.threads[i] = None;
+                if let Some(schedule) = context.schedule.as_mut() {
+                    schedule.record(Decision::Finish(self.boxes[i], i));
+                }
+            }
+        }
+
+        Ok((self.threads.iter().all(Option::is_none), evaluated))
+    }
+
+    fn pick(&mut self, context: &mut ExecContext<T>) -> Result<Option<usize>, BsError> {
+        if let Some(at) = context
+            .schedule
+            .as_mut()
+            .and_then(|schedule| schedule.next_turn())
+        {
+            return match (0..self.boxes.len())
+                .find(|i| self.boxes[*i] == at && self.threads[*i].is_some())
+            {
+                Some(i) => Ok(Some(i)),
+                None => Err(BsError::RuntimeError(
+                    "Schedule log does not match this program".to_string(),
+                    Some(at),
+                )),
+            };
+        }
+
+        for _ in 0..self.turns.len() {
+            let i = self.turns[self.next];
+            self.next = (self.next + 1) % self.turns.len();
+
+            if self.threads[i].is_some() {
+                return Ok(Some(i));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+pub fn run<T: BoxInt>(blocks: &mut [Block<T>], context: &mut ExecContext<T>) -> Result<T, BsError> {
+    if context.sequential || blocks.len() == 1 {
+        let mut value = T::zero();
+        for block in blocks.iter_mut() {
+            value = block.run(context)?;
+        }
+        return Ok(value);
+    }
+
+    let paths: Vec<Vec<usize>> = (0..blocks.len()).map(|i| vec![i]).collect();
+    let mut group = Group::new(blocks, paths, context);
+
+    while !group.turn(blocks, context)?.0 {}
+
+    Ok(group.value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::input::NoInput;
+    use super::super::replay::Schedule;
+    use super::*;
+    use std::collections::HashMap;
+
+    fn run_all(code: &str, sequential: bool) -> Result<(String, HashMap<i8, i8>), BsError> {
+        let mut blocks = Block::<i8>::build(code).unwrap();
+        let mut memory = HashMap::new();
+        let mut stdout = String::new();
+        let mut input = NoInput;
+        let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+        context.sequential = sequential;
+
+        for batch in batches(blocks.iter().map(|b| &b.r#box)) {
+            run(&mut blocks[batch], &mut context)?;
+        }
+
+        Ok((stdout, memory))
+    }
+
+    #[test]
+    fn it_batches_simultaneous_boxes() {
+        let boxes = vec![
+            Box::new([0, 0], [2, 3], Genus::Execution),
+            Box::new([0, 5], [4, 8], Genus::Execution),
+            Box::new([3, 0], [5, 3], Genus::Execution),
+            Box::new([6, 0], [8, 3], Genus::Execution),
+        ];
+
+        assert_eq!(batches(&boxes), vec![0..3, 3..4]);
+        assert_eq!(batches(&boxes[..1]), vec![0..1]);
+        assert!(batches(&[]).is_empty());
+    }
+
+    #[test]
+    fn it_plans_bodies() {
+        let code = "┌──────────────┐\n│▀◈▀▀          │\n│┌────┐ ┌────┐ │\n││▭◇▀ │ │▭◇▀ │ │\n│└────┘ └────┘ │\n│┌────┐        │\n││▭◇▀ │        │\n│└────┘        │\n└──────────────┘";
+        let block = Block::<i8>::build(code).unwrap().remove(0);
+
+        assert_eq!(
+            plan(&block),
+            vec![Step::Molecule(0), Step::Simultaneous(0..2), Step::Block(2)]
+        );
+    }
+
+    #[test]
+    fn it_interleaves_lines() {
+        let code = "┌──────┐┌──────┐\n│▭▀▀   ││▭▀▀▄▄ │\n│▭▀▀▄  ││▭▀▀▄▀ │\n│▭▀▀▀  ││▭▀▀▀▄ │\n└──────┘└──────┘";
+
+        assert_eq!(
+            run_all(code, false).unwrap().0,
+            "\u{1}\u{4}\u{2}\u{5}\u{3}\u{6}"
+        );
+        assert_eq!(
+            run_all(code, true).unwrap().0,
+            "\u{1}\u{2}\u{3}\u{4}\u{5}\u{6}"
+        );
+    }
+
+    #[test]
+    fn it_interleaves_loops_and_nested_boxes() {
+        let code = "╔═◇▀▨▀▀▄▄══╗╔═◇▀▀▨▀▀▄▄══╗\n║▀◈◇▀▐▀▀   ║║▀▀◈◇▀▀▐▀▀  ║\n║┌────┐    ║║▭◇▀▀▐▀▀▄▄▄ ║\n║│▭◇▀ │    ║╚═══════════╝\n║└────┘    ║             \n╚══════════╝             ";
+
+        let (stdout, memory) = run_all(code, false).unwrap();
+        assert_eq!(stdout, "\u{1}\t\u{2}\n\u{3}\u{b}\u{4}\u{c}");
+        assert_eq!(memory, [(0, 4), (1, 4)].iter().cloned().collect());
+
+        let (stdout, _) = run_all(code, true).unwrap();
+        assert_eq!(stdout, "\u{1}\u{2}\u{3}\u{4}\t\n\u{b}\u{c}");
+    }
+
+    fn run_scheduled(code: &str, schedule: &mut Schedule) -> Result<String, BsError> {
+        let mut blocks = Block::<i8>::build(code).unwrap();
+        let (mut memory, mut stdout, mut input) = (HashMap::new(), String::new(), NoInput);
+        let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+        context.schedule = Some(schedule);
+
+        run(&mut blocks, &mut context)?;
+        Ok(stdout)
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn it_logs_and_replays_schedules() {
+        let code = "┌──────┐┌──────┐\n│▭▀▀   ││▭▀▀▄▄ │\n│▭▀▀▄  ││▭▀▀▄▀ │\n│▭▀▀▀  ││▭▀▀▀▄ │\n└──────┘└──────┘";
+        let mut schedule = Schedule::new();
+
+        assert_eq!(
+            run_scheduled(code, &mut schedule).unwrap(),
+            "\u{1}\u{4}\u{2}\u{5}\u{3}\u{6}"
+        );
+        assert_eq!(schedule.decisions.len(), 1 + 2 + 6 + 2);
+        assert_eq!(schedule.decisions[0], Decision::Wave(vec![[0, 0], [0, 8]]));
+        assert_eq!(schedule.decisions[1], Decision::Start([0, 0], 0));
+        assert_eq!(schedule.decisions[2], Decision::Turn([0, 0], 0));
+        assert_eq!(schedule.decisions[3], Decision::Start([0, 8], 1));
+        assert_eq!(
+            schedule.decisions.last(),
+            Some(&Decision::Finish([0, 8], 1))
+        );
+
+        let mut replay = Schedule::replaying(&schedule.log()).unwrap();
+        assert_eq!(
+            run_scheduled(code, &mut replay).unwrap(),
+            "\u{1}\u{4}\u{2}\u{5}\u{3}\u{6}"
+        );
+        assert_eq!(replay.log(), schedule.log());
+
+        let turn = |at: [usize; 2]| Decision::Turn(at, 0).to_json().to_string() + "\n";
+        let forced = [turn([0, 8]).repeat(3), turn([0, 0]).repeat(3)].concat();
+        assert_eq!(
+            run_scheduled(code, &mut Schedule::replaying(&forced).unwrap()).unwrap(),
+            "\u{4}\u{5}\u{6}\u{1}\u{2}\u{3}"
+        );
+
+        let stale = turn([0, 8]).repeat(4);
+        assert_eq!(
+            run_scheduled(code, &mut Schedule::replaying(&stale).unwrap()),
+            Err(BsError::RuntimeError(
+                "Schedule log does not match this program".to_string(),
+                Some([0, 8])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_reports_errors_from_any_box() {
+        let code = "┌─────┐┌────┐\n│▀◈▀▀ ││▭▀▀ │\n│▭▀▀▄ ││▀▝▀ │\n└─────┘└────┘";
+
+        assert_eq!(
+            run_all(code, false),
+            Err(BsError::RuntimeError(
+                "Division caused invalid value".to_string(),
+                Some([2, 9])
+            ))
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use super::datatype::BoxInt;
+use super::input::InputSource;
+use super::interpreter::{Backend, Interpreter};
+use std::collections::VecDeque;
+
+const INPUT: &str = "B";
+
+const EXPRESSIONS: [(&str, &str, i64, &str); 23] = [
+    ("add", "▀▀▀▐▀▀▄", 5, ""),
+    ("subtract", "▀▀▀▌▀▀▄▀", -2, ""),
+    ("multiply", "▀▀▀▘▄▀▄", -6, ""),
+    ("divide", "▄▀▀▀▝▀▀▄", -3, ""),
+    ("modulo", "▀▀▀▀▖▀▀▀", 1, ""),
+    ("inverse modulo", "▀▀▀▗▀▀▀▀", 5, ""),
+    ("shift left", "▀▀▚▀▀▀", 8, ""),
+    ("shift right", "▀▀▄▄▄▄▞▀▀▄", 4, ""),
+    ("and", "▀▀▀▄░▀▀▀", 2, ""),
+    ("xor", "▀▀▀▄▒▀▀▀", 5, ""),
+    ("or", "▀▀▀▄▓▀▀▀", 7, ""),
+    ("not", "▔▀▀▄▀", -6, ""),
+    ("less", "▀▀▄▨▀▀▀", 1, ""),
+    ("greater", "▀▀▄▧▀▀▀", 0, ""),
+    ("equal", "▀▀▀▤▀▀▀", 1, ""),
+    ("not equal", "▀▀▀▥▀▀▀", 0, ""),
+    ("parentheses", "▕▀▀▐▀▀▄▏▘▀▀▀", 9, ""),
+    ("precedence", "▀▀▐▀▀▄▘▀▀▀", 7, ""),
+    ("memory", "▕▀◈▀▀▀▏▐◇▀", 6, ""),
+    ("output", "▭▀▀▄▄▄▄▄▀", 65, "A"),
+    ("input", "▯▀", 66, ""),
+    ("poll key", "▮", 66, ""),
+    ("set if zero", "▀◆▀▀", 1, ""),
+];
+
+const PROGRAMS: [(&str, &str, &str, &str); 4] = [
+    (
+        "loop",
+        "╔═◇▀▨▀▀▄▄════╗\n║▭◇▀▐▀▀▀▄▄▄▄ ║\n║▀◈◇▀▐▀▀     ║\n╚════════════╝",
+        "0123",
+        "0123",
+    ),
+    (
+        "condition",
+        "┏━▀▀▤▀▀━━━━┓\n┃▭▀▀▀▀▀▄▄▀ ┃\n┗━━━━━━━━━━┛\n┏━▀▀▤▀━━━━━┓\n┃▭▀▀▀▄▀▀▀▄ ┃\n┗━━━━━━━━━━┛",
+        "y",
+        "y",
+    ),
+    (
+        "nesting",
+        "╔═◇▀▨▀▀▀▄═══════╗\n║┏━◇▀▖▀▀▄▤▀━━━┓ ║\n║┃▭◇▀▐▀▀▀▄▄▄▄ ┃ ║\n║┗━━━━━━━━━━━━┛ ║\n║▀◈◇▀▐▀▀        ║\n╚═══════════════╝",
+        "024",
+        "024",
+    ),
+    (
+        "side by side",
+        "┌──────────┐┌──────────┐\n│▭▀▀▀▄▄▄▄▀ ││▭▀▀▀▄▄▄▀▀ │\n│▭▀▀▀▄▄▄▀▄ ││▭▀▀▀▄▄▀▄▄ │\n└──────────┘└──────────┘",
+        "acbd",
+        "abcd",
+    ),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Outcome {
+    pub name: String,
+    pub failure: Option<String>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.failure {
+            None => format!("ok    {}", self.name),
+            Some(failure) => format!("FAIL  {}: {}", self.name, failure),
+        }
+    }
+}
+
+fn input() -> Box<dyn InputSource> {
+    Box::new(INPUT.chars().collect::<VecDeque<char>>())
+}
+
+fn expression<T: BoxInt>(source: &str, value: i64, stdout: &str) -> Option<String> {
+    let mut interpreter = Interpreter::<T>::new();
+    interpreter.set_input(input());
+
+    match interpreter.eval(source) {
+        Ok((found, output)) if found.to_i64() == Some(value) && output == stdout => None,
+        Ok((found, output)) => Some(format!(
+            "expected {} {:?}, got {} {:?}",
+            value, stdout, found, output
+        )),
+        Err(error) => Some(error.to_string()),
+    }
+}
+
+fn program<T: BoxInt>(
+    source: &str,
+    backend: Backend,
+    sequential: bool,
+    expected: &str,
+) -> Option<String> {
+    let mut interpreter = Interpreter::<T>::new();
+    interpreter.set_backend(backend);
+    interpreter.set_sequential(sequential);
+    interpreter.set_input(input());
+
+    match interpreter.run_program(source) {
+        Ok(output) if output == expected => None,
+        Ok(output) => Some(format!("expected {:?}, got {:?}", expected, output)),
+        Err(error) => Some(error.to_string()),
+    }
+}
+
+fn width<T: BoxInt>(bits: u32) -> Vec<Outcome> {
+    let mut outcomes: Vec<Outcome> = Vec::new();
+
+    for (name, source, value, stdout) in EXPRESSIONS.iter() {
+        outcomes.push(Outcome {
+            name: format!("{} (i{})", name, bits),
+            failure: expression::<T>(source, *value, stdout),
+        });
+    }
+
+    for (name, source, turns, sequential) in PROGRAMS.iter() {
+        for (backend, label) in [(Backend::Tree, "tree"), (Backend::Vm, "vm")] {
+            outcomes.push(Outcome {
+                name: format!("{} (i{}, {}, turns)", name, bits, label),
+                failure: program::<T>(source, backend, false, turns),
+            });
+            outcomes.push(Outcome {
+                name: format!("{} (i{}, {}, sequential)", name, bits, label),
+                failure: program::<T>(source, backend, true, sequential),
+            });
+        }
+    }
+
+    outcomes
+}
+
+pub fn run() -> Vec<Outcome> {
+    let mut outcomes = width::<i8>(8);
+    outcomes.extend(width::<i16>(16));
+    outcomes.extend(width::<i32>(32));
+    outcomes.extend(width::<i64>(64));
+    outcomes.extend(width::<i128>(128));
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_passes_on_this_platform() {
+        let outcomes = run();
+
+        assert_eq!(outcomes.len(), 5 * (EXPRESSIONS.len() + PROGRAMS.len() * 4));
+        let failures: Vec<String> = outcomes
+            .iter()
+            .filter(|outcome| !outcome.passed())
+            .map(Outcome::describe)
+            .collect();
+        assert_eq!(failures, Vec::<String>::new());
+    }
+
+    #[test]
+    fn it_describes_outcomes() {
+        assert_eq!(
+            program::<i8>(PROGRAMS[3].1, Backend::Tree, false, "abcd"),
+            Some("expected \"abcd\", got \"acbd\"".to_string())
+        );
+        assert_eq!(
+            Outcome {
+                name: "add (i8)".to_string(),
+                failure: None,
+            }
+            .describe(),
+            "ok    add (i8)"
+        );
+        assert_eq!(
+            Outcome {
+                name: "add (i8)".to_string(),
+                failure: Some("expected 5 \"\", got 4 \"\"".to_string()),
+            }
+            .describe(),
+            "FAIL  add (i8): expected 5 \"\", got 4 \"\""
+        );
+    }
+}
@@ -0,0 +1,181 @@
+use super::block::Block;
+use super::complexity::Complexity;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::include;
+use super::input::{InputSource, Reader};
+use super::interpreter::{ArithMode, ExecContext};
+use super::scheduler;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+struct Program<T: BoxInt> {
+    blocks: Vec<Block<T>>,
+    memory: HashMap<T, T>,
+}
+
+pub struct Session<T: BoxInt> {
+    programs: Vec<Program<T>>,
+    max_iterations: Option<usize>,
+    max_steps: Option<usize>,
+    timeout: Option<Duration>,
+    sequential: bool,
+    arith_mode: ArithMode,
+    complexity: Complexity,
+    include_dir: PathBuf,
+    stdout: String,
+}
+
+impl<T: BoxInt> Default for Session<T> {
+    fn default() -> Session<T> {
+        Session::new()
+    }
+}
+
+impl<T: BoxInt> Session<T> {
+    pub fn new() -> Session<T> {
+        Session {
+            programs: Vec::new(),
+            max_iterations: None,
+            max_steps: None,
+            timeout: None,
+            sequential: false,
+            arith_mode: ArithMode::Checked,
+            complexity: Complexity::default(),
+            include_dir: PathBuf::from("."),
+            stdout: String::new(),
+        }
+    }
+
+    pub fn set_max_iterations(&mut self, max_iterations: Option<usize>) {
+        self.max_iterations = max_iterations;
+    }
+
+    pub fn set_max_steps(&mut self, max_steps: Option<usize>) {
+        self.max_steps = max_steps;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn set_sequential(&mut self, sequential: bool) {
+        self.sequential = sequential;
+    }
+
+    pub fn set_arith_mode(&mut self, arith_mode: ArithMode) {
+        self.arith_mode = arith_mode;
+    }
+
+    pub fn set_complexity(&mut self, complexity: Complexity) {
+        self.complexity = complexity;
+    }
+
+    pub fn set_include_dir(&mut self, include_dir: PathBuf) {
+        self.include_dir = include_dir;
+    }
+
+    pub fn load(&mut self, source: &str) -> Result<usize, BsError> {
+        self.complexity.check::<T>(source)?;
+        let mut blocks = Block::<T>::build(source)?;
+        include::resolve(&mut blocks, &self.include_dir)?;
+
+        self.programs.push(Program {
+            blocks,
+            memory: HashMap::new(),
+        });
+
+        Ok(self.programs.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.programs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty()
+    }
+
+    pub fn memory(&self, program: usize) -> &HashMap<T, T> {
+        &self.programs[program].memory
+    }
+
+    pub fn reset(&mut self, program: usize) {
+        self.programs[program].memory.clear();
+    }
+
+    pub fn run(&mut self, program: usize, input: &mut dyn InputSource) -> Result<String, BsError> {
+        let Program { blocks, memory } = &mut self.programs[program];
+        self.stdout.clear();
+
+        let mut context = ExecContext::new(memory, &mut self.stdout, input);
+        context.max_iterations = self.max_iterations;
+        context.max_steps = self.max_steps;
+        context.timeout = self.timeout;
+        context.sequential = self.sequential;
+        for block in blocks.iter_mut() {
+            block.set_arith_mode(self.arith_mode);
+        }
+        for batch in scheduler::batches(blocks.iter().map(|block| &block.r#box)) {
+            scheduler::run(&mut blocks[batch], &mut context)?;
+        }
+
+        Ok(self.stdout.clone())
+    }
+
+    pub fn run_all(&mut self, input: &str) -> Vec<Result<String, BsError>> {
+        (0..self.programs.len())
+            .map(|program| self.run(program, &mut Reader::new(input.as_bytes())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::input::NoInput;
+    use super::*;
+
+    #[test]
+    fn it_keeps_memories_apart() {
+        let mut session = Session::<i64>::new();
+        let counter = session
+            .load("┌───────────┐\n│▀◈◇▀▐▀▀    │\n│▭◇▀▐▀▀▀▄▄▄▄│\n└───────────┘")
+            .unwrap();
+        let echo = session.load("┌─────┐\n│▭▯▀  │\n└─────┘").unwrap();
+        assert_eq!(session.len(), 2);
+
+        assert_eq!(session.run(counter, &mut NoInput), Ok("1".to_string()));
+        assert_eq!(session.run(counter, &mut NoInput), Ok("2".to_string()));
+        assert_eq!(session.memory(counter), &[(0, 2)].iter().cloned().collect());
+        assert!(session.memory(echo).is_empty());
+
+        session.reset(counter);
+        assert_eq!(
+            session.run_all("x"),
+            vec![Ok("1".to_string()), Ok("x".to_string())]
+        );
+    }
+
+    #[test]
+    fn it_shares_configuration() {
+        let mut session = Session::<i8>::new();
+        let looping = session.load("╔═▀▀═╗\n║▀◈▀▀║\n╚════╝").unwrap();
+        let overflow = session.load("┌───────────┐\n│▀▀▀▀▀▀▀▀▐▀▀│\n└───────────┘");
+        assert!(overflow.is_ok());
+
+        session.set_max_iterations(Some(5));
+        session.set_arith_mode(ArithMode::Wrapping);
+        assert_eq!(
+            session.run_all(""),
+            vec![
+                Err(BsError::RuntimeError(
+                    "Loop exceeded 5 iterations".to_string(),
+                    Some([0, 0])
+                )),
+                Ok(String::new())
+            ]
+        );
+        assert_eq!(session.memory(looping).get(&0), Some(&1));
+    }
+}
@@ -0,0 +1,289 @@
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::Atom;
+use super::interpreter::{ArithMode, ExecContext};
+use super::math;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Builtin {
+    Format,
+    ReadLine,
+    Abs,
+    Gcd,
+    Pow,
+    Length,
+    Reverse,
+}
+
+const BUILTINS: [(i8, Builtin); 7] = [
+    (-1, Builtin::Format),
+    (-2, Builtin::ReadLine),
+    (-3, Builtin::Abs),
+    (-4, Builtin::Gcd),
+    (-5, Builtin::Pow),
+    (-6, Builtin::Length),
+    (-7, Builtin::Reverse),
+];
+
+fn out_of_bounds() -> BsError {
+    BsError::RuntimeError("Memory range is out of bounds".to_string(), None)
+}
+
+fn offset<T: BoxInt>(start: T, i: usize) -> Result<T, BsError> {
+    T::from(i)
+        .and_then(|i| start.checked_add(&i))
+        .ok_or_else(out_of_bounds)
+}
+
+fn parameters<T: BoxInt, const N: usize>(
+    context: &mut ExecContext<T>,
+    start: T,
+) -> Result<[T; N], BsError> {
+    let mut parameters = [T::zero(); N];
+    for (i, parameter) in parameters.iter_mut().enumerate() {
+        *parameter = context.memory.get(offset(start, i)?);
+    }
+
+    Ok(parameters)
+}
+
+fn store<T: BoxInt>(context: &mut ExecContext<T>, address: T, value: T) {
+    context.memory.set(address, value);
+    if let Some(hook) = context.hook.as_mut() {
+        hook.wrote(address, value);
+    }
+}
+
+fn store_text<T: BoxInt>(context: &mut ExecContext<T>, start: T, text: &[T]) -> Result<T, BsError> {
+    let end = offset(start, text.len())?;
+    for (i, c) in text.iter().enumerate() {
+        store(context, offset(start, i)?, *c);
+    }
+    store(context, end, T::zero());
+
+    T::from(text.len()).ok_or_else(out_of_bounds)
+}
+
+impl Builtin {
+    pub fn all() -> impl Iterator<Item = Builtin> {
+        BUILTINS.iter().map(|(_, builtin)| *builtin)
+    }
+
+    pub fn from_number<T: BoxInt>(number: T) -> Option<Builtin> {
+        BUILTINS
+            .iter()
+            .find(|(n, _)| T::from(*n) == Some(number))
+            .map(|(_, builtin)| *builtin)
+    }
+
+    pub fn number(self) -> i8 {
+        BUILTINS
+            .iter()
+            .find(|(_, builtin)| *builtin == self)
+            .map(|(n, _)| *n)
+            .unwrap()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Format => "format",
+            Builtin::ReadLine => "read-line",
+            Builtin::Abs => "abs",
+            Builtin::Gcd => "gcd",
+            Builtin::Pow => "pow",
+            Builtin::Length => "length",
+            Builtin::Reverse => "reverse",
+        }
+    }
+
+    pub fn run<T: BoxInt>(
+        self,
+        context: &mut ExecContext<T>,
+        argument: T,
+        mode: ArithMode,
+    ) -> Result<T, BsError> {
+        match self {
+            Builtin::Format => {
+                let [value, start] = parameters(context, argument)?;
+                let text = value
+                    .to_string()
+                    .chars()
+                    .map(|c| T::from_char(c).ok_or_else(out_of_bounds))
+                    .collect::<Result<Vec<T>, BsError>>()?;
+                store_text(context, start, &text)
+            }
+            Builtin::ReadLine => {
+                let mut text: Vec<T> = Vec::new();
+                context.stdout.flush()?;
+                while let Some(c) = context.input.read_char() {
+                    match c {
+                        '\n' => break,
+                        '\r' => {}
+                        _ => text.push(T::from_char(c).ok_or_else(|| {
+                            BsError::RuntimeError(
+                                "Input character is out of range".to_string(),
+                                None,
+                            )
+                        })?),
+                    }
+                }
+                store_text(context, argument, &text)
+            }
+            Builtin::Abs => Atom::Abs.unary_in(mode, argument),
+            Builtin::Gcd => {
+                let [mut a, mut b] = parameters(context, argument)?;
+                while !b.is_zero() {
+                    let rest = a.checked_rem(&b).unwrap_or_else(T::zero);
+                    a = b;
+                    b = rest;
+                }
+                Atom::Abs.unary_in(mode, a)
+            }
+            Builtin::Pow => {
+                let [base, exponent] = parameters(context, argument)?;
+                let overflow = || {
+                    BsError::RuntimeError(
+                        format!("Arithmetic overflow: pow({}, {})", base, exponent),
+                        None,
+                    )
+                };
+                if exponent.is_negative() {
+                    return Err(BsError::RuntimeError(
+                        "Exponent must not be negative".to_string(),
+                        None,
+                    ));
+                }
+
+                let (mut value, mut square, mut rest) = (T::one(), base, exponent);
+                while !rest.is_zero() {
+                    if rest & T::one() == T::one() {
+                        value = math::multiply(mode, value, square).ok_or_else(overflow)?;
+                    }
+                    rest = rest.signed_shr(1);
+                    if !rest.is_zero() {
+                        square = math::multiply(mode, square, square).ok_or_else(overflow)?;
+                    }
+                }
+                Ok(value)
+            }
+            Builtin::Length => {
+                let mut len = 0;
+                while !context.memory.get(offset(argument, len)?).is_zero() {
+                    context.spend(1, None)?;
+                    len += 1;
+                }
+                T::from(len).ok_or_else(out_of_bounds)
+            }
+            Builtin::Reverse => {
+                let [start, len] = parameters(context, argument)?;
+                let len = len.to_usize().ok_or_else(out_of_bounds)?;
+                if len > 0 {
+                    offset(start, len - 1)?;
+                }
+                context.spend(len, None)?;
+
+                for i in 0..len / 2 {
+                    let (left, right) = (offset(start, i)?, offset(start, len - 1 - i)?);
+                    let (a, b) = (context.memory.get(left), context.memory.get(right));
+                    store(context, left, b);
+                    store(context, right, a);
+                }
+                T::from(len).ok_or_else(out_of_bounds)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::expression::Dialect;
+    use super::super::interpreter::Interpreter;
+    use super::*;
+    use std::collections::VecDeque;
+
+    fn interpreter(input: &str) -> Interpreter<i64> {
+        let mut interpreter = Interpreter::<i64>::new();
+        interpreter.set_dialect(Dialect::V3);
+        interpreter.set_input(Box::new(input.chars().collect::<VecDeque<char>>()));
+        interpreter
+    }
+
+    fn text(interpreter: &Interpreter<i64>, start: i64) -> String {
+        (start..)
+            .map(|address| interpreter.memory().get(address))
+            .take_while(|c| *c != 0)
+            .filter_map(|c| c.to_char())
+            .collect()
+    }
+
+    #[test]
+    fn it_numbers_builtins() {
+        for builtin in Builtin::all() {
+            assert_eq!(Builtin::from_number(builtin.number() as i64), Some(builtin));
+        }
+        assert_eq!(Builtin::from_number(1i64), None);
+    }
+
+    #[test]
+    fn it_runs_arithmetic() {
+        assert_eq!(
+            interpreter("").run_program("┌────────┐\n│▬▄▀▀◵◉-5│\n│▀◈◉12   │\n│▀▀◈◉18  │\n│▬▄▀▄▄◵▀ │\n│▀◈◉3    │\n│▀▀◈◉4   │\n│▬▄▀▄▀◵▀ │\n└────────┘"),
+            Ok("5681".to_string())
+        );
+        assert_eq!(
+            interpreter("").run_program("┌──────┐\n│▀◈◉2  │\n│▀▀◈◉-1│\n│▄▀▄▀◵▀│\n└──────┘"),
+            Err(BsError::RuntimeError(
+                "Exponent must not be negative".to_string(),
+                Some([3, 5])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_takes_the_gcd_of_the_smallest_integer() {
+        let mut interpreter = Interpreter::<i8>::new();
+        interpreter.set_dialect(Dialect::V3);
+        assert_eq!(
+            interpreter.run_program("┌───────┐\n│▀◈◉-128│\n│▀▀◈◉-1 │\n│▬◉-4◵▀ │\n└───────┘"),
+            Ok("1".to_string())
+        );
+        assert_eq!(
+            interpreter.run_program("┌───────┐\n│▀◈◉-128│\n│▀▀◈▀   │\n│▬◉-4◵▀ │\n└───────┘"),
+            Err(BsError::RuntimeError(
+                "Arithmetic overflow: ▣-128".to_string(),
+                Some([3, 5])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_handles_text() {
+        let mut interpreter = interpreter("hi there\nrest");
+        assert_eq!(
+            interpreter.run_program("┌─────────┐\n│▬▄▀▄◵◉10 │\n│▀◈◉10    │\n│▀▀◈◉8    │\n│▄▀▀▀◵▀   │\n│▬▄▀▀▄◵◉10│\n│▀▀▄◈◉-42 │\n│▀▀▀◈◉20  │\n│▬▄▀◵▀▀▄  │\n└─────────┘"),
+            Ok("883".to_string())
+        );
+        assert_eq!(text(&interpreter, 10), "ereht ih");
+        assert_eq!(text(&interpreter, 20), "-42");
+    }
+
+    #[test]
+    fn it_counts_steps_for_long_text() {
+        let mut interpreter = interpreter("");
+        interpreter.set_max_steps(Some(1500));
+        let error = interpreter
+            .run_program("┌────────┐\n│▀◈◉10   │\n│▀▀◈◉1000│\n│▀▀▄◈▀▀  │\n│◼▀      │\n│▬◉-6◵◉10│\n└────────┘")
+            .unwrap_err();
+        assert!(error.message().starts_with("Execution budget exceeded"));
+        assert_eq!(error.location().map(|[row, _]| row), Some(5));
+
+        let error = interpreter
+            .run_program(
+                "┌───────────┐\n│▀◈◉10      │\n│▀▀◈◉1000000│\n│◉-7◵▀      │\n└───────────┘",
+            )
+            .unwrap_err();
+        assert!(error.message().starts_with("Execution budget exceeded"));
+        assert_eq!(error.location().map(|[row, _]| row), Some(3));
+    }
+}
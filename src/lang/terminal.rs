@@ -0,0 +1,260 @@
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::input::InputSource;
+use super::interpreter::Interpreter;
+use super::output::Output;
+use std::collections::VecDeque;
+
+const CLEAR: i64 = -1;
+const RESET: i64 = -2;
+const HIDE_CURSOR: i64 = -3;
+const SHOW_CURSOR: i64 = -4;
+const FOREGROUND: i64 = -0x100;
+const BACKGROUND: i64 = -0x200;
+const MOVE: i64 = -0x1_0000;
+
+fn control(code: i64) -> Option<String> {
+    Some(match code {
+        CLEAR => "\u{1b}[2J\u{1b}[H".to_string(),
+        RESET => "\u{1b}[0m".to_string(),
+        HIDE_CURSOR => "\u{1b}[?25l".to_string(),
+        SHOW_CURSOR => "\u{1b}[?25h".to_string(),
+        _ if code > FOREGROUND - 0x100 && code <= FOREGROUND => {
+            format!("\u{1b}[38;5;{}m", FOREGROUND - code)
+        }
+        _ if code > BACKGROUND - 0x100 && code <= BACKGROUND => {
+            format!("\u{1b}[48;5;{}m", BACKGROUND - code)
+        }
+        _ if code > MOVE - 0x1_0000 && code <= MOVE => {
+            let position = MOVE - code;
+            format!("\u{1b}[{};{}H", (position >> 8) + 1, (position & 0xff) + 1)
+        }
+        _ => return None,
+    })
+}
+
+pub fn encode<T: BoxInt>(value: T, stdout: &mut dyn Output) -> Result<(), BsError> {
+    if let Some(chr) = value.to_char() {
+        stdout.write(chr.encode_utf8(&mut [0; 4]))
+    } else if let Some(sequence) = value.to_i64().and_then(control) {
+        stdout.write(&sequence)
+    } else {
+        stdout.write("\u{ffff}")
+    }
+}
+
+pub struct VirtualTerminal {
+    width: usize,
+    height: usize,
+    cells: Vec<Vec<char>>,
+    cursor: [usize; 2],
+    keys: VecDeque<char>,
+}
+
+impl VirtualTerminal {
+    pub fn new(width: usize, height: usize) -> VirtualTerminal {
+        VirtualTerminal {
+            width,
+            height,
+            cells: vec![vec![' '; width]; height],
+            cursor: [0, 0],
+            keys: VecDeque::new(),
+        }
+    }
+
+    pub fn press(&mut self, keys: &str) {
+        self.keys.extend(keys.chars());
+    }
+
+    pub fn cursor(&self) -> [usize; 2] {
+        self.cursor
+    }
+
+    pub fn screen(&self) -> String {
+        self.cells
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_string())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn write(&mut self, output: &str) {
+        let mut chars = output.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\u{1b}' if chars.peek() == Some(&'[') => {
+                    chars.next();
+                    let mut parameters = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if c.is_ascii_digit() || c == ';' || c == '?' {
+                            parameters.push(c);
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+
+                    if let Some(command) = chars.next() {
+                        self.control(&parameters, command);
+                    }
+                }
+                '\n' => self.line_feed(),
+                '\r' => self.cursor[1] = 0,
+                _ => {
+                    if self.cursor[1] == self.width {
+                        self.line_feed();
+                    }
+                    if self.width > 0 && self.height > 0 {
+                        self.cells[self.cursor[0]][self.cursor[1]] = c;
+                    }
+                    self.cursor[1] += 1;
+                }
+            }
+        }
+    }
+
+    pub fn run<T: BoxInt>(
+        &mut self,
+        interpreter: &mut Interpreter<T>,
+        source: &str,
+    ) -> Result<Vec<String>, BsError> {
+        interpreter.set_input(Box::new(std::mem::take(&mut self.keys)));
+
+        let mut screens: Vec<String> = Vec::new();
+        for frame in interpreter.run_frames(source)? {
+            self.write(&frame);
+            screens.push(self.screen());
+        }
+
+        Ok(screens)
+    }
+
+    fn control(&mut self, parameters: &str, command: char) {
+        match command {
+            'J' if parameters == "2" => {
+                self.cells = vec![vec![' '; self.width]; self.height];
+            }
+            'H' => {
+                let mut position = parameters
+                    .split(';')
+                    .map(|n| n.parse::<usize>().unwrap_or(1).max(1) - 1);
+                self.cursor = [
+                    position
+                        .next()
+                        .unwrap_or(0)
+                        .min(self.height.saturating_sub(1)),
+                    position.next().unwrap_or(0).min(self.width),
+                ];
+            }
+            _ => {}
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.cursor[1] = 0;
+        if self.cursor[0] + 1 < self.height {
+            self.cursor[0] += 1;
+        } else if self.height > 0 {
+            self.cells.remove(0);
+            self.cells.push(vec![' '; self.width]);
+        }
+    }
+}
+
+impl Output for VirtualTerminal {
+    fn write(&mut self, text: &str) -> Result<(), BsError> {
+        VirtualTerminal::write(self, text);
+        Ok(())
+    }
+}
+
+impl InputSource for VirtualTerminal {
+    fn read_char(&mut self) -> Option<char> {
+        self.keys.pop_front()
+    }
+
+    fn poll_key(&mut self) -> Option<char> {
+        self.keys.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded<T: BoxInt>(value: T) -> String {
+        let mut stdout = String::new();
+        encode(value, &mut stdout).unwrap();
+        stdout
+    }
+
+    #[test]
+    fn it_encodes_characters() {
+        assert_eq!(encoded(72i64), "H");
+        assert_eq!(encoded(0x11_0000), "\u{ffff}");
+        assert_eq!(encoded(-5), "\u{ffff}");
+    }
+
+    #[test]
+    fn it_encodes_terminal_controls() {
+        assert_eq!(encoded(-1), "\u{1b}[2J\u{1b}[H");
+        assert_eq!(encoded(-2), "\u{1b}[0m");
+        assert_eq!(encoded(-0x100 - 196), "\u{1b}[38;5;196m");
+        assert_eq!(encoded(-0x200), "\u{1b}[48;5;0m");
+        assert_eq!(encoded(-0x1_0000 - 0x0305), "\u{1b}[4;6H");
+        assert_eq!(encoded(-0x2_0000), "\u{ffff}");
+        assert_eq!(encoded(-1i8), "\u{1b}[2J\u{1b}[H");
+    }
+
+    #[test]
+    fn it_draws_a_virtual_screen() {
+        let mut terminal = VirtualTerminal::new(4, 2);
+        terminal.write("ab\ncd");
+        assert_eq!(terminal.screen(), "ab\ncd");
+        assert_eq!(terminal.cursor(), [1, 2]);
+
+        terminal.write(&format!(
+            "{}x{}yzwv",
+            encoded(-0x1_0000 - 1),
+            encoded(-0x100 - 3)
+        ));
+        assert_eq!(terminal.screen(), "axyz\nwv");
+
+        terminal.write(&encoded(-1));
+        assert_eq!(terminal.screen(), "\n");
+        assert_eq!(terminal.cursor(), [0, 0]);
+
+        terminal.press("q");
+        assert_eq!(terminal.poll_key(), Some('q'));
+        assert_eq!(terminal.poll_key(), None);
+    }
+
+    #[test]
+    fn it_runs_interactive_programs() {
+        use super::super::expression::Atom;
+
+        let clear = Atom::Data(-1i64).source();
+        let home = Atom::Data(-0x1_0000 - 0x0001i64).source();
+        let line = |code: &str| format!("│▭{:width$}│", code, width = home.chars().count());
+        let program = [
+            format!("┌{}┐", "─".repeat(home.chars().count() + 1)),
+            line("▮"),
+            line("▮"),
+            format!("└{}┘", "─".repeat(home.chars().count() + 1)),
+            format!("┌{}┐", "─".repeat(home.chars().count() + 1)),
+            line(&clear),
+            line(&home),
+            line("▮"),
+            format!("└{}┘", "─".repeat(home.chars().count() + 1)),
+        ]
+        .join("\n");
+
+        let mut terminal = VirtualTerminal::new(8, 2);
+        terminal.press("hi!");
+        assert_eq!(
+            terminal.run(&mut Interpreter::<i64>::new(), &program),
+            Ok(vec!["hi\n".to_string(), " !\n".to_string()])
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use super::error::BsError;
+use super::expression::{Atom, Molecule};
+use super::r#box::{Box, Genus};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Span {
+    pub start: [usize; 2],
+    pub width: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum TokenKind {
+    Number,
+    Text,
+    Operator,
+    Paren,
+    Border,
+    Marker,
+    Comment,
+    Invalid,
+}
+
+impl TokenKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenKind::Number => "number",
+            TokenKind::Text => "text",
+            TokenKind::Operator => "operator",
+            TokenKind::Paren => "paren",
+            TokenKind::Border => "border",
+            TokenKind::Marker => "marker",
+            TokenKind::Comment => "comment",
+            TokenKind::Invalid => "invalid",
+        }
+    }
+}
+
+fn span(start: [usize; 2], width: usize) -> Span {
+    Span { start, width }
+}
+
+fn expression(line: &str, origin: [usize; 2], tokens: &mut Vec<(Span, TokenKind)>) {
+    let molecule = match Molecule::<i64>::read(line, origin) {
+        Ok(molecule) => molecule,
+        Err(BsError::ParseError(_, Some(at))) if at[0] == origin[0] && at[1] > origin[1] => {
+            let valid: String = line.chars().take(at[1] - origin[1]).collect();
+            expression(&valid, origin, tokens);
+            let rest = line[valid.len()..].trim_end().chars().count();
+            tokens.push((span(at, rest), TokenKind::Invalid));
+            return;
+        }
+        Err(_) => {
+            let start = line.chars().take_while(|c| c.is_whitespace()).count();
+            let width = line.trim().chars().count();
+            tokens.push((
+                span([origin[0], origin[1] + start], width),
+                TokenKind::Invalid,
+            ));
+            return;
+        }
+    };
+
+    let quoted = |i: usize| {
+        molecule
+            .prefixes()
+            .iter()
+            .any(|(j, prefix)| *j == i && (*prefix == '"' || *prefix == '\''))
+    };
+    let atoms = molecule.atoms();
+    for (i, (atom, start, end)) in molecule.spans().enumerate() {
+        let kind = match atom {
+            Atom::Concat => continue,
+            Atom::Data(_) if quoted(i) || i > 0 && atoms[i - 1] == Atom::Concat => TokenKind::Text,
+            Atom::Data(_) => TokenKind::Number,
+            Atom::LeftParen | Atom::RightParen => TokenKind::Paren,
+            _ => TokenKind::Operator,
+        };
+        tokens.push((span(start, end[1] - start[1]), kind));
+    }
+}
+
+fn borders(r#box: &Box, source: &str, tokens: &mut Vec<(Span, TokenKind)>) {
+    let [top, left] = r#box.top_left;
+    let [bottom, right] = r#box.bottom_right;
+    let width = right - left + 1;
+
+    match r#box.header_at(source) {
+        Some((at, header)) => {
+            let length = header.chars().count();
+            tokens.push((span(r#box.top_left, at[1] - left), TokenKind::Border));
+            expression(&header, at, tokens);
+            tokens.push((
+                span([top, at[1] + length], right + 1 - at[1] - length),
+                TokenKind::Border,
+            ));
+        }
+        None => tokens.push((span(r#box.top_left, width), TokenKind::Border)),
+    }
+    for row in top + 1..bottom {
+        tokens.push((span([row, left], 1), TokenKind::Border));
+        tokens.push((span([row, right], 1), TokenKind::Border));
+    }
+    tokens.push((span([bottom, left], width), TokenKind::Border));
+}
+
+pub fn tokenize_with_spans(source: &str) -> Vec<(Span, TokenKind)> {
+    let boxes = match Box::find_all(source) {
+        Ok(boxes) => boxes,
+        Err(_) => return Vec::new(),
+    };
+    let mut tokens: Vec<(Span, TokenKind)> = Vec::new();
+
+    for r#box in boxes.iter() {
+        borders(r#box, source, &mut tokens);
+
+        let [top, left] = r#box.top_left;
+        if r#box.has_marker(source) {
+            tokens.push((span([top + 1, left + 1], 1), TokenKind::Marker));
+        }
+        for (i, line) in r#box.content(source, &boxes).iter().enumerate() {
+            let origin = [top + 1 + i, left + 1];
+            if line.trim().is_empty() {
+                continue;
+            }
+            if r#box.genus == Genus::NoOp {
+                let start = line.chars().take_while(|c| c.is_whitespace()).count();
+                let width = line.trim().chars().count();
+                tokens.push((
+                    span([origin[0], origin[1] + start], width),
+                    TokenKind::Comment,
+                ));
+            } else {
+                expression(line, origin, &mut tokens);
+            }
+        }
+    }
+
+    tokens.sort_by_key(|(span, _)| span.start);
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(usize, usize, usize, &'static str)> {
+        tokenize_with_spans(source)
+            .iter()
+            .map(|(span, kind)| (span.start[0], span.start[1], span.width, kind.name()))
+            .collect()
+    }
+
+    #[test]
+    fn it_tokenizes_boxes() {
+        let code =
+            "┌▀▀─────┐\n│▀◈\"hi\" │\n│▬▕▀▀▐▄▏│\n│▬◉12▲  │\n└───────┘\n╭┄┄┄┄╮\n┆note┆\n╰┄┄┄┄╯";
+        assert_eq!(
+            kinds(code),
+            vec![
+                (0, 0, 1, "border"),
+                (0, 1, 2, "number"),
+                (0, 3, 6, "border"),
+                (1, 0, 1, "border"),
+                (1, 1, 1, "number"),
+                (1, 2, 1, "operator"),
+                (1, 3, 2, "text"),
+                (1, 5, 2, "text"),
+                (1, 8, 1, "border"),
+                (2, 0, 1, "border"),
+                (2, 1, 1, "operator"),
+                (2, 2, 1, "paren"),
+                (2, 3, 2, "number"),
+                (2, 5, 1, "operator"),
+                (2, 6, 1, "number"),
+                (2, 7, 1, "paren"),
+                (2, 8, 1, "border"),
+                (3, 0, 1, "border"),
+                (3, 1, 1, "operator"),
+                (3, 2, 3, "number"),
+                (3, 5, 1, "invalid"),
+                (3, 8, 1, "border"),
+                (4, 0, 9, "border"),
+                (5, 0, 6, "border"),
+                (6, 0, 1, "border"),
+                (6, 1, 4, "comment"),
+                (6, 5, 1, "border"),
+                (7, 0, 6, "border"),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_marks_boxes_and_skips_broken_layouts() {
+        assert_eq!(
+            kinds("┌───┐\n│◴▀▀│\n└───┘")[1..3],
+            [(1, 0, 1, "border"), (1, 1, 1, "marker")]
+        );
+        assert_eq!(kinds("┌──┐\n│▀"), vec![]);
+    }
+}
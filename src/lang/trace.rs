@@ -0,0 +1,115 @@
+use super::datatype::BoxInt;
+use super::expression::Molecule;
+use std::io::Write;
+
+pub trait ExecHook<T: BoxInt> {
+    fn evaluated(&mut self, _molecule: &Molecule<T>, _value: T) {}
+
+    fn wrote(&mut self, _address: T, _value: T) {}
+}
+
+pub fn range<T: BoxInt>(text: &str) -> Option<(T, T)> {
+    let (low, high) = text.split_once("..")?;
+    let low = T::from_str_radix(low.trim(), 10).ok()?;
+    let high = T::from_str_radix(high.trim(), 10).ok()?;
+
+    if low <= high {
+        Some((low, high))
+    } else {
+        None
+    }
+}
+
+pub struct Tracer<T: BoxInt, W: Write> {
+    out: W,
+    range: Option<(T, T)>,
+    writes: Vec<(T, T)>,
+}
+
+impl<T: BoxInt, W: Write> Tracer<T, W> {
+    pub fn new(out: W, range: Option<(T, T)>) -> Tracer<T, W> {
+        Tracer {
+            out,
+            range,
+            writes: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<T: BoxInt, W: Write> ExecHook<T> for Tracer<T, W> {
+    fn evaluated(&mut self, molecule: &Molecule<T>, value: T) {
+        let at = molecule.positions().first().cloned().unwrap_or([0, 0]);
+        let _ = writeln!(
+            self.out,
+            "{}:{} {} = {}",
+            at[0] + 1,
+            at[1] + 1,
+            molecule.source(),
+            value
+        );
+
+        for (address, value) in self.writes.drain(..) {
+            let _ = writeln!(self.out, "  {} → {}", address, value);
+        }
+    }
+
+    fn wrote(&mut self, address: T, value: T) {
+        if self
+            .range
+            .is_none_or(|(low, high)| low <= address && address <= high)
+        {
+            self.writes.push((address, value));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::block::Block;
+    use super::super::input::NoInput;
+    use super::super::interpreter::{ExecContext, Runnable};
+    use super::*;
+    use std::collections::HashMap;
+
+    fn trace(code: &str, range: Option<(i8, i8)>) -> String {
+        let mut tracer = Tracer::new(Vec::new(), range);
+        let mut memory = HashMap::new();
+        let mut stdout = String::new();
+        let mut input = NoInput;
+        let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+        context.hook = Some(&mut tracer);
+
+        for mut block in Block::<i8>::build(code).unwrap() {
+            block.run(&mut context).unwrap();
+        }
+
+        String::from_utf8(tracer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn it_parses_ranges() {
+        assert_eq!(range::<i8>("0..15"), Some((0, 15)));
+        assert_eq!(range::<i8>("-3..-1"), Some((-3, -1)));
+        assert_eq!(range::<i8>("4..2"), None);
+        assert_eq!(range::<i8>("0..300"), None);
+        assert_eq!(range::<i8>("7"), None);
+    }
+
+    #[test]
+    fn it_traces_molecules_and_writes() {
+        let code = "╔═◇▀▨▀▀▄═╗\n║▀◈◇▀▐▀▀ ║\n║▀▀◈▀▀   ║\n╚════════╝";
+
+        assert_eq!(
+            trace(code, None),
+            "1:3 ◇▀▨▀▀▄ = 1\n2:2 ▀◈◇▀▐▀▀ = 1\n  0 → 1\n3:2 ▀▀◈▀▀ = 1\n  1 → 1\n1:3 ◇▀▨▀▀▄ = 1\n2:2 ▀◈◇▀▐▀▀ = 2\n  0 → 2\n3:2 ▀▀◈▀▀ = 1\n  1 → 1\n1:3 ◇▀▨▀▀▄ = 0\n"
+        );
+        assert_eq!(
+            trace(code, Some((1, 1))),
+            "1:3 ◇▀▨▀▀▄ = 1\n2:2 ▀◈◇▀▐▀▀ = 1\n3:2 ▀▀◈▀▀ = 1\n  1 → 1\n1:3 ◇▀▨▀▀▄ = 1\n2:2 ▀◈◇▀▐▀▀ = 2\n3:2 ▀▀◈▀▀ = 1\n  1 → 1\n1:3 ◇▀▨▀▀▄ = 0\n"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+use super::ast::{self, Access, Expr};
+use super::block::Block;
+use super::datatype::BoxInt;
+use super::error::BsError;
+use super::expression::{Atom, Molecule};
+use super::interpreter::{ArithMode, ExecContext, Runnable};
+use super::r#box::Genus;
+use super::scheduler::{self, Step};
+
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum Instruction<T: BoxInt> {
+    Push(T),
+    PollKey(Option<[usize; 2]>),
+    Argument(Option<[usize; 2]>),
+    Unary(Atom<T>, ArithMode, Option<[usize; 2]>),
+    Binary(Atom<T>, ArithMode, Option<[usize; 2]>),
+    Fail(BsError),
+    Evaluated(usize),
+    Simultaneous(usize),
+    Spend(usize, Option<[usize; 2]>),
+    Store,
+    Reset,
+    Jump(usize),
+    JumpIfZero(usize),
+    Enter,
+    Tick([usize; 2]),
+    Leave,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Program<T: BoxInt> {
+    pub code: Vec<Instruction<T>>,
+    molecules: Vec<Molecule<T>>,
+    groups: Vec<Vec<Block<T>>>,
+}
+
+impl<T: BoxInt> Program<T> {
+    pub fn compile(block: &mut Block<T>) -> Program<T> {
+        let mut program = Program {
+            code: Vec::new(),
+            molecules: Vec::new(),
+            groups: Vec::new(),
+        };
+        program.block(block);
+        program
+    }
+
+    fn emit(&mut self, instruction: Instruction<T>) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    fn patch(&mut self, jump: usize) {
+        let target = self.code.len();
+        match &mut self.code[jump] {
+            Instruction::Jump(to) | Instruction::JumpIfZero(to) => *to = target,
+            _ => unreachable!(),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr<T>, mode: ArithMode) {
+        match expr {
+            Expr::Number(num) => self.emit(Instruction::Push(*num)),
+            Expr::PollKey(at) => self.emit(Instruction::PollKey(*at)),
+            Expr::Argument(at) => self.emit(Instruction::Argument(*at)),
+            Expr::Unary(atom, operand, at) => {
+                self.expr(operand, mode);
+                self.emit(Instruction::Unary(*atom, mode, *at))
+            }
+            Expr::Binary(atom, left, right, at) => {
+                self.expr(left, mode);
+                self.expr(right, mode);
+                self.emit(Instruction::Binary(*atom, mode, *at))
+            }
+        };
+    }
+
+    fn molecule(&mut self, block: &mut Block<T>, node: Option<usize>) {
+        let mode = block.arith_mode;
+        let molecule = match node {
+            Some(i) => &mut block.molecules[i].1,
+            None => block.guard.as_mut().unwrap(),
+        };
+
+        self.emit(Instruction::Spend(
+            molecule.atoms().len(),
+            molecule.positions().first().cloned(),
+        ));
+        match molecule.compile() {
+            Ok(expr) => {
+                let expr = expr.clone();
+                self.expr(&expr, mode);
+                self.molecules.push(molecule.clone());
+                self.emit(Instruction::Evaluated(self.molecules.len() - 1));
+            }
+            Err(error) => {
+                self.emit(Instruction::Fail(error));
+            }
+        }
+    }
+
+    fn body(&mut self, block: &mut Block<T>) {
+        for step in scheduler::plan(block) {
+            match step {
+                Step::Molecule(i) => {
+                    self.molecule(block, Some(i));
+                    self.emit(Instruction::Store);
+                }
+                Step::Block(i) => self.block(&mut block.children[i]),
+                Step::Simultaneous(range) => {
+                    self.groups.push(block.children[range].to_vec());
+                    self.emit(Instruction::Simultaneous(self.groups.len() - 1));
+                    self.emit(Instruction::Store);
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, block: &mut Block<T>) {
+        self.emit(Instruction::Reset);
+
+        match block.r#box.genus {
+            Genus::Execution => self.body(block),
+            Genus::Condition => {
+                if block.guard.is_some() {
+                    self.molecule(block, None);
+                    let skip = self.emit(Instruction::JumpIfZero(0));
+                    self.body(block);
+                    self.patch(skip);
+                } else {
+                    self.body(block);
+                }
+            }
+            Genus::Loop => {
+                self.emit(Instruction::Enter);
+                let start = self.code.len();
+                if block.guard.is_some() {
+                    self.molecule(block, None);
+                    let exit = self.emit(Instruction::JumpIfZero(0));
+                    self.emit(Instruction::Tick(block.r#box.top_left));
+                    self.body(block);
+                    self.emit(Instruction::Jump(start));
+                    self.patch(exit);
+                } else {
+                    self.emit(Instruction::Tick(block.r#box.top_left));
+                    self.body(block);
+                    self.emit(Instruction::Jump(start));
+                }
+                self.emit(Instruction::Leave);
+            }
+            Genus::NoOp | Genus::Function => {}
+        }
+    }
+}
+
+impl<T: BoxInt> Runnable<T> for Program<T> {
+    fn run(&mut self, context: &mut ExecContext<T>) -> Result<T, BsError> {
+        let mut stack: Vec<T> = Vec::new();
+        let mut loops: Vec<usize> = Vec::new();
+        let mut value = T::zero();
+        let mut next = 0;
+
+        while let Some(instruction) = self.code.get(next) {
+            next += 1;
+
+            match instruction {
+                Instruction::Push(num) => stack.push(*num),
+                Instruction::PollKey(at) => {
+                    stack.push(ast::poll_key(&mut Access::Write(&mut *context), *at)?)
+                }
+                Instruction::Argument(at) => {
+                    stack.push(ast::argument(&mut Access::Write(&mut *context), *at)?)
+                }
+                Instruction::Unary(atom, mode, at) => {
+                    let a = stack.pop().unwrap();
+                    stack.push(ast::unary(
+                        *atom,
+                        a,
+                        &mut Access::Write(&mut *context),
+                        *mode,
+                        *at,
+                    )?);
+                }
+                Instruction::Binary(atom, mode, at) => {
+                    let b = stack.pop().unwrap();
+                    let a = stack.pop().unwrap();
+                    stack.push(ast::binary(
+                        *atom,
+                        a,
+                        b,
+                        &mut Access::Write(&mut *context),
+                        *mode,
+                        *at,
+                    )?);
+                }
+                Instruction::Fail(error) => return Err(error.clone()),
+                Instruction::Evaluated(i) => {
+                    if let Some(hook) = context.hook.as_mut() {
+                        hook.evaluated(&self.molecules[*i], *stack.last().unwrap());
+                    }
+                }
+                Instruction::Simultaneous(i) => {
+                    stack.push(scheduler::run(&mut self.groups[*i], context)?)
+                }
+                Instruction::Spend(atoms, at) => context.spend(*atoms, *at)?,
+                Instruction::Store => value = stack.pop().unwrap(),
+                Instruction::Reset => value = T::zero(),
+                Instruction::Jump(to) => next = *to,
+                Instruction::JumpIfZero(to) => {
+                    if stack.pop().unwrap().is_zero() {
+                        next = *to;
+                    }
+                }
+                Instruction::Enter => loops.push(0),
+                Instruction::Tick(at) => {
+                    let iterations = loops.last_mut().unwrap();
+                    if Some(*iterations) == context.max_iterations {
+                        return Err(BsError::RuntimeError(
+                            format!("Loop exceeded {} iterations", iterations),
+                            Some(*at),
+                        ));
+                    }
+                    *iterations += 1;
+                }
+                Instruction::Leave => {
+                    loops.pop();
+                }
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::input::NoInput;
+    use super::super::trace::ExecHook;
+    use super::*;
+    use std::collections::HashMap;
+
+    type Outcome = Result<(i8, HashMap<i8, i8>, String), BsError>;
+
+    fn run(code: &str, max_iterations: Option<usize>, max_steps: Option<usize>) -> [Outcome; 2] {
+        let mut outcomes = Vec::new();
+
+        for vm in &[false, true] {
+            let mut memory = HashMap::new();
+            let mut stdout = String::new();
+            let mut input = NoInput;
+            let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+            context.max_iterations = max_iterations;
+            context.max_steps = max_steps;
+
+            let mut value = Ok(0);
+            for mut block in Block::<i8>::build(code).unwrap() {
+                value = if *vm {
+                    Program::compile(&mut block).run(&mut context)
+                } else {
+                    block.run(&mut context)
+                };
+                if value.is_err() {
+                    break;
+                }
+            }
+
+            outcomes.push(value.map(|value| (value, memory, stdout)));
+        }
+
+        [outcomes.remove(0), outcomes.remove(0)]
+    }
+
+    #[test]
+    fn it_compiles_blocks() {
+        let mut block = Block::<i8>::build("╔═◇▀▨▀▀▄═╗\n║▀◈◇▀▐▀▀ ║\n╚════════╝")
+            .unwrap()
+            .remove(0);
+        let mode = ArithMode::Checked;
+
+        assert_eq!(
+            Program::compile(&mut block).code,
+            vec![
+                Instruction::Reset,
+                Instruction::Enter,
+                Instruction::Spend(4, Some([0, 2])),
+                Instruction::Push(0),
+                Instruction::Unary(Atom::Memory, mode, Some([0, 2])),
+                Instruction::Push(2),
+                Instruction::Binary(Atom::Less, mode, Some([0, 4])),
+                Instruction::Evaluated(0),
+                Instruction::JumpIfZero(20),
+                Instruction::Tick([0, 0]),
+                Instruction::Spend(6, Some([1, 1])),
+                Instruction::Push(0),
+                Instruction::Push(0),
+                Instruction::Unary(Atom::Memory, mode, Some([1, 3])),
+                Instruction::Push(1),
+                Instruction::Binary(Atom::Add, mode, Some([1, 5])),
+                Instruction::Binary(Atom::Assign, mode, Some([1, 2])),
+                Instruction::Evaluated(1),
+                Instruction::Store,
+                Instruction::Jump(2),
+                Instruction::Leave,
+            ]
+        );
+    }
+
+    #[test]
+    fn it_matches_the_tree_evaluator() {
+        for code in &[
+            "┌──────────┐\n│▀◈▀▀      │\n│┌────────┐│\n││▀◈◇▀▘▀▀▄││\n│└────────┘│\n│▀◈◇▀▐▀▀   │\n└──────────┘",
+            "┏━━━━━━┓\n┃▀▄    ┃\n┃▀◈▀▀  ┃\n┗━━━━━━┛\n┏━━━━━━┓\n┃▀▀    ┃\n┃▀▀◈▀▀ ┃\n┗━━━━━━┛",
+            "╔════════════╗\n║◇▀▨▀▀▄▀     ║\n║▀◈◇▀▐▀▀     ║\n║▭◇▀▐▀▀▀▄▄▄▄ ║\n╚════════════╝",
+            "╔═◇▀▨▀▀▀▀═════╗\n║▀◈◇▀▐▀▀      ║\n║┏━◇▀▖▀▀▄▤▀▄━┓║\n║┃▭◇▀▐▀▀▀▄▄▄▄┃║\n║┗━━━━━━━━━━━┛║\n╚═════════════╝",
+            "┏━▀▄━━━┓\n┃▀◈▀▀  ┃\n┗━━━━━━┛\n╭┄note┄╮\n┆text  ┆\n╰┄┄┄┄┄┄╯",
+            "┌────┐\n│▀▝▀ │\n└────┘",
+            "┏━▀▄━┓\n┃▀▝  ┃\n┗━━━━┛\n┌──┐\n│▀▝│\n└──┘",
+            "┌─────────────────┐\n│▀◈▀▀             │\n│┌─────┐ ┌──────┐ │\n││▭▀▀  │ │▭▀▀▀  │ │\n││▭▀▀▄ │ │▭▀▀▄▄ │ │\n│└─────┘ └──────┘ │\n└─────────────────┘",
+        ] {
+            let [tree, vm] = run(code, None, None);
+            assert_eq!(tree, vm, "{}", code);
+        }
+    }
+
+    #[test]
+    fn it_reports_evaluations() {
+        struct Recorder(Vec<(String, i8)>);
+
+        impl ExecHook<i8> for Recorder {
+            fn evaluated(&mut self, molecule: &Molecule<i8>, value: i8) {
+                self.0.push((molecule.source(), value));
+            }
+        }
+
+        let code = "╔═◇▀▨▀▀▄═╗\n║▀◈◇▀▐▀▀ ║\n╚════════╝";
+        let mut recorded = Vec::new();
+        for vm in &[false, true] {
+            let (mut memory, mut stdout, mut input) = (HashMap::new(), String::new(), NoInput);
+            let mut recorder = Recorder(Vec::new());
+            let mut context = ExecContext::new(&mut memory, &mut stdout, &mut input);
+            context.hook = Some(&mut recorder);
+
+            let mut block = Block::<i8>::build(code).unwrap().remove(0);
+            if *vm {
+                Program::compile(&mut block).run(&mut context).unwrap();
+            } else {
+                block.run(&mut context).unwrap();
+            }
+            recorded.push(recorder.0);
+        }
+
+        assert_eq!(recorded[0], recorded[1]);
+        assert_eq!(recorded[1].len(), 5);
+    }
+
+    #[test]
+    fn it_limits_loops() {
+        let [tree, vm] = run("╔═▀▀══╗\n║▭▀▀▀▀║\n╚═════╝", Some(3), None);
+        assert_eq!(tree, vm);
+        assert_eq!(
+            vm,
+            Err(BsError::RuntimeError(
+                "Loop exceeded 3 iterations".to_string(),
+                Some([0, 0])
+            ))
+        );
+    }
+
+    #[test]
+    fn it_limits_steps() {
+        let code = "╔═◇▀▨▀▀▄═╗\n║▀◈◇▀▐▀▀ ║\n╚════════╝";
+        let [tree, vm] = run(code, None, Some(19));
+        assert_eq!(tree, vm);
+        assert_eq!(
+            vm,
+            Err(BsError::RuntimeError(
+                "Execution budget exceeded after 14 steps".to_string(),
+                Some([1, 1])
+            ))
+        );
+
+        let [tree, vm] = run(code, None, Some(24));
+        assert_eq!(tree, vm);
+        assert!(vm.is_ok());
+    }
+}
@@ -0,0 +1,199 @@
+use super::analysis;
+use super::block::Block;
+use super::datatype::BoxInt;
+use super::error::BsError;
+#[cfg(feature = "json")]
+use super::mi::{location, number};
+use super::r#box::{Box, Genus};
+#[cfg(feature = "json")]
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Reference<T: BoxInt> {
+    pub address: Option<T>,
+    pub readers: Vec<Box>,
+    pub writers: Vec<Box>,
+}
+
+impl<T: BoxInt> Reference<T> {
+    fn new(address: Option<T>) -> Reference<T> {
+        Reference {
+            address,
+            readers: Vec::new(),
+            writers: Vec::new(),
+        }
+    }
+}
+
+pub fn xref<T: BoxInt>(source: &str) -> Result<Vec<Reference<T>>, BsError> {
+    let blocks = Block::<T>::build(source)?;
+    let mut known: BTreeMap<T, Reference<T>> = BTreeMap::new();
+    let mut any = Reference::new(None);
+    for block in Block::walk(&blocks) {
+        if block.r#box.genus == Genus::NoOp {
+            continue;
+        }
+
+        let cells = analysis::shallow_cells(block);
+        for address in cells.reads.iter() {
+            known
+                .entry(*address)
+                .or_insert_with(|| Reference::new(Some(*address)))
+                .readers
+                .push(block.r#box.clone());
+        }
+        for address in cells.writes.iter() {
+            known
+                .entry(*address)
+                .or_insert_with(|| Reference::new(Some(*address)))
+                .writers
+                .push(block.r#box.clone());
+        }
+        if cells.reads_any {
+            any.readers.push(block.r#box.clone());
+        }
+        if cells.writes_any {
+            any.writers.push(block.r#box.clone());
+        }
+    }
+
+    let mut references: Vec<Reference<T>> = known.into_values().collect();
+    if !any.readers.is_empty() || !any.writers.is_empty() {
+        references.push(any);
+    }
+
+    Ok(references)
+}
+
+pub fn name(r#box: &Box) -> String {
+    format!(
+        "{} {}:{}",
+        r#box.genus.name(),
+        r#box.top_left[0] + 1,
+        r#box.top_left[1] + 1
+    )
+}
+
+fn names(boxes: &[Box]) -> String {
+    if boxes.is_empty() {
+        return "-".to_string();
+    }
+
+    boxes.iter().map(name).collect::<Vec<String>>().join(", ")
+}
+
+pub fn table<T: BoxInt>(references: &[Reference<T>]) -> String {
+    let mut rows: Vec<[String; 3]> = vec![[
+        "address".to_string(),
+        "read by".to_string(),
+        "written by".to_string(),
+    ]];
+    for reference in references {
+        rows.push([
+            reference
+                .address
+                .map_or("any".to_string(), |address| address.to_string()),
+            names(&reference.readers),
+            names(&reference.writers),
+        ]);
+    }
+
+    let width = |column: usize| {
+        rows.iter()
+            .map(|row| row[column].chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let (first, second) = (width(0), width(1));
+
+    rows.iter()
+        .map(|row| {
+            format!(
+                "{:first$}  {:second$}  {}\n",
+                row[0],
+                row[1],
+                row[2],
+                first = first,
+                second = second
+            )
+        })
+        .collect()
+}
+
+#[cfg(feature = "json")]
+fn boxes(boxes: &[Box]) -> Vec<Value> {
+    boxes
+        .iter()
+        .map(|r#box| {
+            json!({
+                "genus": r#box.genus.name(),
+                "location": location(r#box.top_left),
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "json")]
+pub fn json<T: BoxInt>(references: &[Reference<T>]) -> String {
+    json!({
+        "cells": references
+            .iter()
+            .map(|reference| json!({
+                "address": reference.address.map(number),
+                "read_by": boxes(&reference.readers),
+                "written_by": boxes(&reference.writers),
+            }))
+            .collect::<Vec<Value>>(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Row = (Option<i8>, Vec<[usize; 2]>, Vec<[usize; 2]>);
+
+    const PROGRAM: &str = "┌──────────┐\n│▀◈▀▀▄     │\n└──────────┘\n╔═◇▀▨▀▀▀═══╗\n║▀◈◇▀▐▀▀   ║\n║┌───────┐ ║\n║│◇▀◈▀▀  │ ║\n║└───────┘ ║\n╚══════════╝";
+
+    #[test]
+    fn it_indexes_cells() {
+        let references = xref::<i8>(PROGRAM).unwrap();
+        let found: Vec<Row> = references
+            .iter()
+            .map(|reference| {
+                (
+                    reference.address,
+                    reference.readers.iter().map(|b| b.top_left).collect(),
+                    reference.writers.iter().map(|b| b.top_left).collect(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (Some(0), vec![[3, 0], [5, 1]], vec![[0, 0], [3, 0]]),
+                (None, vec![], vec![[5, 1]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_prints_tables() {
+        assert_eq!(
+            table(&xref::<i8>(PROGRAM).unwrap()),
+            "address  read by                  written by\n0        loop 4:1, execution 6:2  execution 1:1, loop 4:1\nany      -                        execution 6:2\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "json")]
+    fn it_prints_json() {
+        assert_eq!(
+            json(&xref::<i8>("┌────┐\n│▀◈▀▀│\n└────┘").unwrap()),
+            "{\"cells\":[{\"address\":0,\"read_by\":[],\"written_by\":[{\"genus\":\"execution\",\"location\":{\"column\":1,\"line\":1}}]}]}"
+        );
+    }
+}
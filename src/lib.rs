@@ -0,0 +1,17 @@
+#![allow(dead_code)]
+
+#[cfg(feature = "cli")]
+extern crate ansi_term;
+#[cfg(feature = "json")]
+#[macro_use]
+extern crate serde_json;
+
+#[cfg(feature = "boxscript-ffi")]
+pub mod ffi;
+pub mod lang;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use lang::datatype::BoxInt;
+pub use lang::error::BsError;
+pub use lang::interpreter::{ArithMode, Backend, ExecContext, Interpreter, StateSnapshot};
@@ -1,39 +1,1181 @@
-#![allow(dead_code)]
-
 use std::fs;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
+use std::time::Duration;
 
 extern crate ansi_term;
+extern crate boxscript;
 #[macro_use]
 extern crate clap;
-#[macro_use]
-extern crate lazy_static;
-extern crate regex;
 
-mod lang;
+use ansi_term::Colour::{Red, Yellow};
+use boxscript::lang::ascii;
+use boxscript::lang::block::{Block, Stop};
+use boxscript::lang::canon;
+use boxscript::lang::complexity::Complexity;
+use boxscript::lang::control::{self, Bridge};
+use boxscript::lang::crash::{self, Recent, Recorder};
+use boxscript::lang::debugger::{Command, Debugger, Event};
+use boxscript::lang::diagnostics;
+use boxscript::lang::diff;
+use boxscript::lang::doc;
+use boxscript::lang::dump;
+use boxscript::lang::encode::encode;
+use boxscript::lang::expression::{Dialect, Literal};
+use boxscript::lang::fix;
+use boxscript::lang::function::{CALL_DEPTH_LIMIT, CALL_STACK_SIZE};
+use boxscript::lang::graph;
+#[cfg(unix)]
+use boxscript::lang::input::RawKeyboard;
+use boxscript::lang::input::{InputSource, Reader};
+use boxscript::lang::judge;
+use boxscript::lang::lint;
+use boxscript::lang::memory::{self, FileMemory, Format};
+use boxscript::lang::merge;
+use boxscript::lang::mi;
+use boxscript::lang::output::Stream;
+use boxscript::lang::registry::{self, Registry};
+use boxscript::lang::render::Render;
+use boxscript::lang::replay::Schedule;
+use boxscript::lang::rust;
+use boxscript::lang::selftest;
+use boxscript::lang::trace::{self, ExecHook, Tracer};
+use boxscript::lang::xref;
+use boxscript::{ArithMode, Backend, BoxInt, BsError, Interpreter, StateSnapshot};
+use clap::ArgMatches;
+
+fn fail(message: &str, render: Render) -> ! {
+    eprintln!("{} {}", Red.bold().paint("error:"), render.apply(message));
+    process::exit(1);
+}
+
+fn fail_in(error: &BsError, source: &str, filename: &str, render: Render) -> ! {
+    let filename = if filename == "-" { "<stdin>" } else { filename };
+    eprint!(
+        "{}",
+        diagnostics::report(error, source, filename, render, true)
+    );
+    process::exit(1);
+}
+
+fn read(filename: &str, render: Render) -> String {
+    if filename == "-" {
+        let mut content = String::new();
+        return match io::stdin().read_to_string(&mut content) {
+            Ok(_) => content,
+            Err(_) => fail("<stdin>: Could not read input", render),
+        };
+    }
+
+    match fs::read_to_string(filename) {
+        Ok(content) => content,
+        Err(_) => fail(&format!("{}: No such file or directory", filename), render),
+    }
+}
+
+macro_rules! dispatch {
+    ($width:expr, $function:ident($($argument:expr),*)) => {
+        match $width {
+            "8" => $function::<i8>($($argument),*),
+            "16" => $function::<i16>($($argument),*),
+            "32" => $function::<i32>($($argument),*),
+            "128" => $function::<i128>($($argument),*),
+            _ => $function::<i64>($($argument),*),
+        }
+    };
+}
+
+fn debug<T: BoxInt>(arguments: &ArgMatches, arith_mode: ArithMode, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+
+    let mut interpreter = configure::<T>(filename, arguments, render);
+    interpreter.set_arith_mode(arith_mode);
+
+    if arguments.is_present("mi") {
+        let stdin = io::stdin();
+        let mut commands = stdin
+            .lock()
+            .lines()
+            .map_while(Result::ok)
+            .filter_map(|line| mi::command(&line).transpose());
+        let mut emit = |event: Event<T>| println!("{}", mi::event(&event));
+        let mut debugger = Debugger::new(&mut commands, &mut emit);
+        debugger.set_path(PathBuf::from(filename));
+        debugger.run(&mut interpreter, &content);
+    } else if let Some(script) = arguments.value_of("script") {
+        let script = read(script, render);
+        let mut commands = script
+            .lines()
+            .filter_map(|line| Command::parse(line).transpose());
+        let mut emit = |event: Event<T>| println!("{}", render.apply(&event.describe()));
+        let mut debugger = Debugger::new(&mut commands, &mut emit);
+        debugger.set_path(PathBuf::from(filename));
+        debugger.run(&mut interpreter, &content);
+    } else {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        let mut commands = std::iter::from_fn(|| {
+            print!("(bs) ");
+            let _ = io::stdout().flush();
+            let line = lines.next().and_then(Result::ok);
+            if line.is_none() {
+                println!();
+            }
+            line
+        })
+        .filter_map(|line| Command::parse(&line).transpose());
+        let mut emit = |event: Event<T>| println!("{}", render.apply(&event.describe()));
+        let mut debugger = Debugger::new(&mut commands, &mut emit);
+        debugger.set_path(PathBuf::from(filename));
+        debugger.run(&mut interpreter, &content);
+    }
+}
+
+fn dialect(matches: &ArgMatches) -> Dialect {
+    matches
+        .value_of("dialect")
+        .and_then(Dialect::from_name)
+        .unwrap_or(Dialect::STABLE)
+}
+
+fn configure<T: BoxInt>(filename: &str, matches: &ArgMatches, render: Render) -> Interpreter<T> {
+    let mut interpreter = Interpreter::<T>::new();
+    interpreter.set_dialect(dialect(matches));
+    interpreter.set_silent_assign(matches.is_present("silent_assign"));
+    interpreter.set_complexity(complexity(matches, render));
+    if let Some(dir) = Path::new(filename).parent() {
+        interpreter.set_include_dir(dir.to_path_buf());
+    }
+
+    interpreter
+}
+
+fn complexity(matches: &ArgMatches, render: Render) -> Complexity {
+    let limit = |name: &str, flag: &str| {
+        matches
+            .value_of(name)
+            .map(|value| match value.parse::<usize>() {
+                Ok(limit) => limit,
+                Err(_) => fail(&format!("Invalid limit for --{}: {}", flag, value), render),
+            })
+    };
+
+    Complexity {
+        bytes: limit("max_bytes", "max-bytes"),
+        boxes: limit("max_boxes", "max-boxes"),
+        depth: limit("max_depth", "max-depth"),
+        tokens: limit("max_tokens", "max-tokens"),
+    }
+}
+
+fn compile<T: BoxInt>(
+    arguments: &ArgMatches,
+    complexity: Complexity,
+    arith_mode: ArithMode,
+    render: Render,
+) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+
+    let mut interpreter = Interpreter::<T>::new();
+    interpreter.set_arith_mode(arith_mode);
+    interpreter.set_dialect(dialect(arguments));
+    interpreter.set_silent_assign(arguments.is_present("silent_assign"));
+    interpreter.set_complexity(complexity);
+    interpreter.set_sequential(arguments.is_present("sequential"));
+    if let Some(dir) = Path::new(filename).parent() {
+        interpreter.set_include_dir(dir.to_path_buf());
+    }
+
+    let program = match rust::compile(&interpreter, &content) {
+        Ok(program) => program,
+        Err(error) => fail_in(&error, &content, filename, render),
+    };
+
+    match arguments.value_of("output") {
+        Some(output) => {
+            if fs::write(output, program).is_err() {
+                fail(&format!("{}: Could not write file", output), render);
+            }
+        }
+        None => print!("{}", program),
+    }
+}
+
+fn registry(arguments: &ArgMatches, render: Render) -> Registry {
+    match arguments.value_of("registry") {
+        Some(location) => Registry::parse(location),
+        None => fail(
+            "No registry given, pass --registry or set BOXSCRIPT_REGISTRY",
+            render,
+        ),
+    }
+}
+
+fn add<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let name = arguments.value_of("name").unwrap();
+    let dir = Path::new(arguments.value_of("dir").unwrap_or("."));
+
+    match registry::add::<T>(&registry(arguments, render), name, dir) {
+        Ok((manifest, includes)) => {
+            println!("added {} {}", manifest.name, manifest.version);
+            for include in includes {
+                println!("{}", include.header());
+            }
+        }
+        Err(error) => fail(&error.to_string(), render),
+    }
+}
+
+fn publish<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let dir = Path::new(arguments.value_of("dir").unwrap_or("."));
+
+    match registry::publish::<T>(&registry(arguments, render), dir) {
+        Ok(manifest) => println!("published {} {}", manifest.name, manifest.version),
+        Err(error) => fail(&error.to_string(), render),
+    }
+}
+
+fn judge<T: BoxInt>(
+    arguments: &ArgMatches,
+    complexity: Complexity,
+    arith_mode: ArithMode,
+    render: Render,
+) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+
+    let limits = match arguments.value_of("limits") {
+        Some(path) => match judge::Limits::parse(&read(path, render)) {
+            Ok(limits) => limits,
+            Err(message) => fail(&format!("{}: {}", path, message), render),
+        },
+        None => judge::Limits::default(),
+    };
+    let dir = arguments.value_of("tests").unwrap();
+    let cases = match judge::cases(Path::new(dir)) {
+        Ok(cases) => cases,
+        Err(_) => fail(&format!("{}: Could not read tests", dir), render),
+    };
+
+    let mut interpreter = Interpreter::<T>::new();
+    interpreter.set_arith_mode(arith_mode);
+    interpreter.set_dialect(dialect(arguments));
+    interpreter.set_silent_assign(arguments.is_present("silent_assign"));
+    if let Some(dir) = Path::new(filename).parent() {
+        interpreter.set_include_dir(dir.to_path_buf());
+    }
+
+    interpreter.set_complexity(complexity);
+
+    match judge::judge(&mut interpreter, &content, &cases, &limits) {
+        Ok(reports) => {
+            println!("{}", judge::summary(&reports));
+            if reports
+                .iter()
+                .any(|report| report.verdict != judge::Verdict::Accepted)
+            {
+                process::exit(1);
+            }
+        }
+        Err(error) => fail_in(&error, &content, filename, render),
+    }
+}
+
+fn check<T: BoxInt>(filename: &str, matches: &ArgMatches, json: bool, render: Render) {
+    let content = read(filename, render);
+
+    let errors = configure::<T>(filename, matches, render).check(&content);
+    let name = if filename == "-" { "<stdin>" } else { filename };
+
+    if json {
+        println!("{}", diagnostics::json(&errors));
+    } else {
+        for error in &errors {
+            eprint!(
+                "{}",
+                diagnostics::report(error, &content, name, render, true)
+            );
+        }
+    }
+
+    if !errors.is_empty() {
+        process::exit(1);
+    }
+}
+
+fn dump_ast<T: BoxInt>(filename: &str, json: bool, render: Render) {
+    let content = read(filename, render);
+
+    match Block::<T>::build(&content) {
+        Ok(blocks) if json => println!("{}", dump::json(&blocks)),
+        Ok(blocks) => print!("{}", render.apply(&dump::text(&blocks))),
+        Err(error) if json => {
+            println!("{}", diagnostics::json(&[error]));
+            process::exit(1);
+        }
+        Err(error) => fail_in(&error, &content, filename, render),
+    }
+}
+
+fn supported<T: BoxInt>(content: &str, filename: &str, matches: &ArgMatches, render: Render) {
+    if let Some(error) = configure::<T>(filename, matches, render)
+        .check(content)
+        .first()
+    {
+        fail_in(error, content, filename, render);
+    }
+}
+
+fn lint<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+    supported::<T>(&content, filename, arguments, render);
+
+    let mut config = lint::Config::new();
+    for (name, level) in [
+        ("allow", lint::Level::Allow),
+        ("warn", lint::Level::Warn),
+        ("deny", lint::Level::Deny),
+    ] {
+        for id in arguments.values_of(name).into_iter().flatten() {
+            config.set(lint::Rule::from_id(id).unwrap(), level);
+        }
+    }
+
+    let lints = match lint::lint::<T>(&content, &config) {
+        Ok(lints) => lints,
+        Err(error) => fail_in(&error, &content, filename, render),
+    };
+    for found in &lints {
+        let label = match found.level {
+            lint::Level::Deny => Red.bold().paint(format!("error[{}]:", found.rule.id())),
+            _ => Yellow
+                .bold()
+                .paint(format!("warning[{}]:", found.rule.id())),
+        };
+        eprintln!(
+            "{} {} at {}:{}",
+            label,
+            render.apply(&found.message),
+            found.location[0] + 1,
+            found.location[1] + 1
+        );
+    }
+    if lints.iter().any(|found| found.level == lint::Level::Deny) {
+        process::exit(1);
+    }
+}
+
+fn canon<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+
+    let literal = Literal::from_name(arguments.value_of("literals").unwrap_or("binary")).unwrap();
+
+    let style = canon::Style {
+        literal,
+        dialect: dialect(arguments),
+        fold: arguments.is_present("fold"),
+    };
+
+    match canon::canonicalize_as::<T>(&content, style) {
+        Ok(canonical) => {
+            print!("{}", canonical);
+            println!("{:016x}", canon::hash(&canonical));
+        }
+        Err(error) => fail_in(&error, &content, filename, render),
+    }
+}
+
+fn fmt<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+
+    let style = canon::Style {
+        literal: arguments
+            .value_of("literals")
+            .and_then(Literal::from_name)
+            .unwrap_or(Literal::Original),
+        dialect: dialect(arguments),
+        fold: false,
+    };
+    let formatted = match canon::canonicalize_as::<T>(&content, style) {
+        Ok(formatted) => formatted,
+        Err(error) => fail_in(&error, &content, filename, render),
+    };
+    if arguments.is_present("check") {
+        if formatted != content {
+            fail(&format!("{}: File is not formatted", filename), render);
+        }
+        return;
+    }
+
+    match arguments.value_of("output") {
+        Some(output) => {
+            if fs::write(output, formatted).is_err() {
+                fail(&format!("{}: Could not write file", output), render);
+            }
+        }
+        None => print!("{}", formatted),
+    }
+}
+
+fn transpile<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+
+    let glyphs = content
+        .chars()
+        .any(|c| ('\u{2500}'..='\u{257f}').contains(&c));
+    let result = match arguments.value_of("to") {
+        Some("ascii") => ascii::to_ascii::<T>(&content),
+        Some(_) => ascii::to_glyphs::<T>(&content, dialect(arguments)),
+        None if glyphs => ascii::to_ascii::<T>(&content),
+        None => ascii::to_glyphs::<T>(&content, dialect(arguments)),
+    };
+    let transpiled = match result {
+        Ok(transpiled) => transpiled,
+        Err(error) => fail_in(&error, &content, filename, render),
+    };
+
+    match arguments.value_of("output") {
+        Some(output) => {
+            if fs::write(output, transpiled).is_err() {
+                fail(&format!("{}: Could not write file", output), render);
+            }
+        }
+        None => print!("{}", transpiled),
+    }
+}
+
+fn fix<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+    let (fixed, fixes) = fix::fix::<T>(&content, dialect(arguments));
+
+    for fix in &fixes {
+        eprintln!("{} {}", Yellow.bold().paint("fixed:"), fix.describe());
+    }
+
+    match arguments.value_of("output") {
+        Some(output) => {
+            if fs::write(output, fixed).is_err() {
+                fail(&format!("{}: Could not write file", output), render);
+            }
+        }
+        None => print!("{}", fixed),
+    }
+}
+
+fn doc<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+    supported::<T>(&content, filename, arguments, render);
+    let title = if filename == "-" { "<stdin>" } else { filename };
+
+    match doc::entries::<T>(&content) {
+        Ok(entries) if arguments.value_of("format") == Some("html") => {
+            print!("{}", doc::html(title, &entries))
+        }
+        Ok(entries) => print!("{}", doc::markdown(title, &entries)),
+        Err(error) => fail_in(&error, &content, filename, render),
+    }
+}
+
+fn xref<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+    supported::<T>(&content, filename, arguments, render);
+
+    match xref::xref::<T>(&content) {
+        Ok(references) if arguments.value_of("format") == Some("json") => {
+            println!("{}", xref::json(&references))
+        }
+        Ok(references) => print!("{}", xref::table(&references)),
+        Err(error) => fail_in(&error, &content, filename, render),
+    }
+}
+
+fn graph<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let filename = arguments.value_of("file").unwrap();
+    let content = read(filename, render);
+    supported::<T>(&content, filename, arguments, render);
+
+    let kind = graph::Kind::from_name(arguments.value_of("kind").unwrap_or("nesting")).unwrap();
+    match graph::graph::<T>(&content, kind) {
+        Ok(graph) => print!("{}", graph.dot()),
+        Err(error) => fail_in(&error, &content, filename, render),
+    }
+}
+
+fn diff<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let [old, new] = ["old", "new"].map(|name| {
+        let filename = arguments.value_of(name).unwrap();
+        let content = read(filename, render);
+        supported::<T>(&content, filename, arguments, render);
+        content
+    });
+
+    match diff::diff(&old, &new) {
+        Ok(changes) => {
+            for change in &changes {
+                println!("{}", render.apply(&change.describe()));
+            }
+
+            if !changes.is_empty() {
+                process::exit(1);
+            }
+        }
+        Err(error) => fail(&error.to_string(), render),
+    }
+}
+
+fn merge<T: BoxInt>(arguments: &ArgMatches, render: Render) {
+    let [base, ours, theirs] = ["base", "ours", "theirs"].map(|name| {
+        let filename = arguments.value_of(name).unwrap();
+        let content = read(filename, render);
+        supported::<T>(&content, filename, arguments, render);
+        content
+    });
+
+    match merge::merge(&base, &ours, &theirs) {
+        Ok((merged, conflicts)) => {
+            match arguments.value_of("output") {
+                Some(output) => {
+                    if fs::write(output, merged).is_err() {
+                        fail(&format!("{}: Could not write file", output), render);
+                    }
+                }
+                None => print!("{}", merged),
+            }
+
+            if conflicts > 0 {
+                eprintln!(
+                    "{} {} conflicting box(es)",
+                    Yellow.bold().paint("warning:"),
+                    conflicts
+                );
+                process::exit(1);
+            }
+        }
+        Err(error) => fail(&error.to_string(), render),
+    }
+}
+
+fn tracer<T: BoxInt + 'static>(
+    matches: &ArgMatches,
+    render: Render,
+) -> Option<Box<dyn ExecHook<T>>> {
+    if !matches.is_present("trace") {
+        return None;
+    }
+
+    let range = matches
+        .value_of("trace_range")
+        .map(|text| match trace::range(text) {
+            Some(range) => range,
+            None => fail(&format!("Invalid address range: {}", text), render),
+        });
+    Some(Box::new(Tracer::new(io::stderr(), range)))
+}
+
+fn recorder<T: BoxInt + 'static>(
+    source: &str,
+    matches: &ArgMatches,
+    render: Render,
+) -> Option<Box<dyn ExecHook<T>>> {
+    let recent = Recent::new();
+    crash::install(source.to_string(), recent.clone());
+    Some(Box::new(Recorder::new(recent, tracer(matches, render))))
+}
+
+fn eval<T: BoxInt + 'static>(
+    expr: &str,
+    matches: &ArgMatches,
+    arith_mode: ArithMode,
+    render: Render,
+) {
+    let mut interpreter = Interpreter::<T>::new();
+    interpreter.set_arith_mode(arith_mode);
+    interpreter.set_dialect(dialect(matches));
+    interpreter.set_silent_assign(matches.is_present("silent_assign"));
+    interpreter.set_input(input(matches, render));
+    interpreter.set_exec_hook(recorder(expr, matches, render));
+
+    let result = interpreter.eval(expr);
+    drop(interpreter);
+    match result {
+        Ok((value, stdout)) => {
+            print!("{}", stdout);
+            if !stdout.is_empty() && !stdout.ends_with('\n') {
+                println!();
+            }
+            println!("{}", value);
+        }
+        Err(error) => fail_in(&error, expr, "<eval>", render),
+    }
+}
+
+#[cfg(unix)]
+fn raw_keyboard(render: Render) -> Box<RawKeyboard> {
+    match RawKeyboard::new() {
+        Ok(keyboard) => Box::new(keyboard),
+        Err(_) => fail("Raw key input needs a terminal on stdin", render),
+    }
+}
+
+#[cfg(not(unix))]
+fn raw_keyboard(render: Render) -> ! {
+    fail("Raw key input is not supported on this platform", render)
+}
+
+fn input(matches: &ArgMatches, render: Render) -> Box<dyn InputSource> {
+    if matches.is_present("raw_keys") {
+        return raw_keyboard(render);
+    }
+
+    match matches.value_of("input") {
+        Some(filename) => match fs::File::open(filename) {
+            Ok(file) => Box::new(Reader::new(file)),
+            Err(_) => fail(&format!("{}: No such file or directory", filename), render),
+        },
+        None => Box::new(Reader::new(io::stdin())),
+    }
+}
+
+fn stdout(matches: &ArgMatches, render: Render) -> Box<dyn Write> {
+    if let Some(filename) = matches.value_of("stdout_file") {
+        return match fs::File::create(filename) {
+            Ok(file) => Box::new(file),
+            Err(_) => fail(&format!("{}: Could not create file", filename), render),
+        };
+    }
+
+    match matches.value_of("stdout_socket") {
+        Some(address) => match TcpStream::connect(address) {
+            Ok(stream) => Box::new(stream),
+            Err(_) => fail(&format!("{}: Could not connect", address), render),
+        },
+        None => Box::new(io::stdout()),
+    }
+}
+
+#[cfg(unix)]
+fn control_socket<T: BoxInt + Send + 'static>(path: &str, render: Render) -> Bridge<T> {
+    let (bridge, handle) = Bridge::new();
+    if control::listen(Path::new(path), handle).is_err() {
+        fail(&format!("{}: Could not listen on socket", path), render);
+    }
+    bridge
+}
+
+#[cfg(not(unix))]
+fn control_socket<T: BoxInt>(_: &str, render: Render) -> Bridge<T> {
+    fail("Control sockets are not supported on this platform", render)
+}
+
+fn dump_memory<T: BoxInt>(matches: &ArgMatches, interpreter: &Interpreter<T>, render: Render) {
+    if matches.is_present("dump_memory") {
+        eprint!("{}", memory::table(interpreter.memory()));
+    }
+
+    if let Some(path) = matches.value_of("dump_memory_json") {
+        if fs::write(path, memory::json(interpreter.memory()) + "\n").is_err() {
+            fail(&format!("{}: Could not write file", path), render);
+        }
+    }
+
+    if matches.is_present("mem_stats") {
+        eprint!("{}", interpreter.memory().stats().table());
+    }
+}
+
+fn log_schedule<T: BoxInt>(matches: &ArgMatches, interpreter: &Interpreter<T>, render: Render) {
+    if let (Some(path), Some(schedule)) = (matches.value_of("log_schedule"), interpreter.schedule())
+    {
+        if fs::write(path, schedule.log()).is_err() {
+            fail(&format!("{}: Could not write file", path), render);
+        }
+    }
+}
+
+fn persist<T: BoxInt>(interpreter: &mut Interpreter<T>, render: Render) {
+    if let Err(error) = interpreter.memory_mut().flush() {
+        fail(error.message(), render);
+    }
+}
+
+fn run<T: BoxInt + Send + 'static>(
+    filename: &str,
+    matches: &ArgMatches,
+    arith_mode: ArithMode,
+    render: Render,
+) {
+    let content = read(filename, render);
+
+    let mut interpreter = Interpreter::<T>::new();
+    interpreter.set_arith_mode(arith_mode);
+    interpreter.set_dialect(dialect(matches));
+    interpreter.set_silent_assign(matches.is_present("silent_assign"));
+
+    if let Some(dir) = Path::new(filename).parent() {
+        interpreter.set_include_dir(dir.to_path_buf());
+    }
+
+    interpreter.set_complexity(complexity(matches, render));
+    interpreter
+        .set_backend(Backend::from_name(matches.value_of("backend").unwrap_or("tree")).unwrap());
+    interpreter.set_exec_hook(recorder(&content, matches, render));
+
+    if let Some(limit) = matches.value_of("max_iterations") {
+        match limit.parse::<usize>() {
+            Ok(limit) => interpreter.set_max_iterations(Some(limit)),
+            Err(_) => fail(&format!("Invalid iteration limit: {}", limit), render),
+        }
+    }
+
+    if let Some(limit) = matches.value_of("max_steps") {
+        match limit.parse::<usize>() {
+            Ok(limit) => interpreter.set_max_steps(Some(limit)),
+            Err(_) => fail(&format!("Invalid step limit: {}", limit), render),
+        }
+    }
+
+    if let Some(limit) = matches.value_of("max_call_depth") {
+        match limit.parse::<usize>() {
+            Ok(limit) if limit <= CALL_DEPTH_LIMIT => interpreter.set_max_call_depth(limit),
+            Ok(_) => fail(
+                &format!("Call depth can be at most {}", CALL_DEPTH_LIMIT),
+                render,
+            ),
+            Err(_) => fail(&format!("Invalid call depth: {}", limit), render),
+        }
+    }
+
+    if let Some(timeout) = matches.value_of("timeout") {
+        match timeout.parse::<f64>().map(Duration::try_from_secs_f64) {
+            Ok(Ok(timeout)) => interpreter.set_timeout(Some(timeout)),
+            _ => fail(&format!("Invalid timeout: {}", timeout), render),
+        }
+    }
+
+    if let Some(path) = matches.value_of("persist") {
+        let format =
+            Format::from_name(matches.value_of("persist_format").unwrap_or("json")).unwrap();
+        match FileMemory::<T>::open_as(path, format) {
+            Ok(memory) => interpreter.set_memory(Box::new(memory)),
+            Err(error) => fail(&format!("{}: {}", path, error.message()), render),
+        }
+    }
+
+    if let Some(path) = matches.value_of("load_state") {
+        let state = read(path, render);
+        match StateSnapshot::<T>::from_json(&state) {
+            Ok(snapshot) => interpreter.restore(&snapshot),
+            Err(error) => fail_in(&error, &state, path, render),
+        }
+    }
+    if let Some(path) = matches.value_of("replay_schedule") {
+        let log = read(path, render);
+        match Schedule::replaying(&log) {
+            Ok(schedule) => interpreter.set_schedule(Some(schedule)),
+            Err(error) => fail(&format!("{}: {}", path, error.message()), render),
+        }
+    } else if matches.is_present("log_schedule") {
+        interpreter.set_schedule(Some(Schedule::new()));
+    }
+    interpreter.set_sequential(matches.is_present("sequential"));
+    interpreter.set_checkpoint(matches.value_of("save_state").map(PathBuf::from));
+
+    let socket = matches.value_of("control_socket");
+    let bridge = socket.map(|path| control_socket::<T>(path, render));
+    match &bridge {
+        Some(bridge) => interpreter.set_input(Box::new(bridge.input(input(matches, render)))),
+        None => interpreter.set_input(input(matches, render)),
+    }
+    let mut hook = |stop: Stop<T>| {
+        if let Some(bridge) = &bridge {
+            bridge.serve(stop.memory);
+        }
+        Ok(())
+    };
+
+    if let Some(dir) = matches.value_of("frames") {
+        let frames = interpreter.run_frames_with(&content, &mut hook);
+        dump_memory(matches, &interpreter, render);
+        log_schedule(matches, &interpreter, render);
+        persist(&mut interpreter, render);
+        drop(interpreter);
+        if let Some(path) = socket {
+            let _ = fs::remove_file(path);
+        }
+        let frames = match frames {
+            Ok(frames) => frames,
+            Err(error) => fail_in(&error, &content, filename, render),
+        };
+
+        if fs::create_dir_all(dir).is_err() {
+            fail(&format!("{}: Could not create directory", dir), render);
+        }
+        for (i, frame) in frames.iter().enumerate() {
+            let path = Path::new(dir).join(format!("{:04}.txt", i + 1));
+            if fs::write(&path, frame).is_err() {
+                fail(&format!("{}: Could not write file", path.display()), render);
+            }
+        }
+
+        return;
+    }
+
+    let mut output = Stream::new(stdout(matches, render));
+    let result = interpreter.stream_program(&content, &mut output, &mut hook);
+    dump_memory(matches, &interpreter, render);
+    log_schedule(matches, &interpreter, render);
+    persist(&mut interpreter, render);
+    drop(interpreter);
+    if let Some(path) = socket {
+        let _ = fs::remove_file(path);
+    }
+    if let Err(error) = result {
+        fail_in(&error, &content, filename, render);
+    }
+}
 
 #[cfg(not(tarpaulin_include))]
 fn main() {
+    let worker = thread::Builder::new()
+        .stack_size(CALL_STACK_SIZE)
+        .spawn(cli)
+        .unwrap();
+    if worker.join().is_err() {
+        process::exit(101);
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+fn cli() {
+    let rules = lint::Rule::ids();
     let app = clap_app!(BoxScript =>
         (version: "0.1.0")
         (author: "pyxiis <47072520+pyxiis@users.noreply.github.com>")
         (about: "Runs BoxScript code from a file")
-        (@arg file: +required "Sets the input file to use")
+        (@setting SubcommandsNegateReqs)
+        (@arg file: "Sets the input file to use, or - to read from stdin")
+        (@arg eval: -e --eval +takes_value conflicts_with("file") "Runs a single expression instead of a file")
+        (@arg render: --render +global +takes_value possible_values(&["auto", "unicode", "ascii"])
+            "Sets how glyphs are displayed in diagnostics")
+        (@arg check: --check conflicts_with("eval")
+            "Reports every syntax and structure error in the file without running it")
+        (@arg dump_ast: --("dump-ast") conflicts_with_all(&["eval", "check"])
+            "Prints the boxes and tokenized expressions of the file without running it")
+        (@arg format: --format +takes_value possible_values(&["text", "json"])
+            "Sets whether --check and --dump-ast print text or JSON")
+        (@arg max_iterations: --("max-iterations") +takes_value
+            "Stops any loop that runs more than this many times")
+        (@arg max_steps: --("max-steps") +takes_value
+            "Stops the program once it has evaluated more than this many atoms")
+        (@arg max_call_depth: --("max-call-depth") +takes_value
+            "Stops the program once function calls nest deeper than this")
+        (@arg timeout: --timeout +takes_value
+            "Stops the program once it has run for this many seconds")
+        (@arg max_bytes: --("max-bytes") +global +takes_value
+            "Rejects programs larger than this many bytes before running them")
+        (@arg max_boxes: --("max-boxes") +global +takes_value
+            "Rejects programs with more than this many boxes before running them")
+        (@arg max_depth: --("max-depth") +global +takes_value
+            "Rejects programs with boxes nested deeper than this before running them")
+        (@arg max_tokens: --("max-tokens") +global +takes_value
+            "Rejects programs with an expression longer than this many tokens before running them")
+        (@arg input: -i --input +takes_value conflicts_with("raw_keys")
+            "Reads the program's input from a file instead of stdin")
+        (@arg raw_keys: --("raw-keys")
+            "Reads keys as they are pressed, without echo, so ▮ can poll them")
+        (@arg log_schedule: --("log-schedule") +takes_value conflicts_with("sequential")
+            "Writes every turn the scheduler gives to side-by-side boxes to a file, one JSON object per line")
+        (@arg replay_schedule: --("replay-schedule") +takes_value conflicts_with("sequential")
+            "Runs side-by-side boxes in the order recorded by --log-schedule")
+        (@arg stdout_file: --("stdout-file") +takes_value conflicts_with("frames")
+            "Writes the program's output to a file instead of stdout")
+        (@arg stdout_socket: --("stdout-socket") +takes_value conflicts_with_all(&["stdout_file", "frames"])
+            "Sends the program's output to a TCP address such as 127.0.0.1:4000 instead of stdout")
+        (@arg control_socket: --("control-socket") +takes_value
+            "Lets other processes read and write memory and send input over a Unix socket at this path")
+        (@arg backend: --backend +takes_value possible_values(&["tree", "vm"]) conflicts_with("control_socket")
+            "Sets whether boxes are run by walking expression trees or compiled to bytecode for a stack VM")
+        (@arg trace: --trace
+            "Logs every expression, its value and the memory it writes to stderr as the program runs")
+        (@arg trace_range: --("trace-range") +takes_value requires("trace")
+            "Only logs memory writes to addresses LO..HI, inclusive")
+        (@arg dump_memory: --("dump-memory")
+            "Prints every memory cell with its value and character to stderr when the program ends")
+        (@arg dump_memory_json: --("dump-memory-json") +takes_value
+            "Writes every memory cell to this file as JSON when the program ends")
+        (@arg mem_stats: --("mem-stats")
+            "Prints how many cells the program used, which addresses they span and how many bytes they take to stderr when the program ends")
+        (@arg sequential: --sequential
+            "Runs boxes that sit side by side one after another instead of taking turns")
+        (@arg self_test: --("self-test") +hidden
+            "Runs the built-in conformance programs at every integer width and reports which pass")
+        (@arg save_state: --("save-state") +takes_value
+            "Saves memory to this file after every top-level box so an interrupted run can be resumed")
+        (@arg persist: --persist +takes_value
+            "Loads memory from this file before running and writes it back afterwards, creating it if needed")
+        (@arg persist_format: --("persist-format") +takes_value possible_values(&["json", "binary"])
+            "Sets whether --persist stores memory as JSON or binary")
+        (@arg load_state: --("load-state") +takes_value
+            "Restores memory from a file written by --save-state and skips the boxes that already ran")
+        (@arg frames: --frames +takes_value
+            "Writes the output of each top-level box to its own numbered file in this directory")
+        (@arg overflow: --overflow +global +takes_value possible_values(&["checked", "wrapping", "saturating"])
+            "Sets whether arithmetic overflow is an error, wraps around or saturates")
+        (@arg dialect: --dialect +global +takes_value possible_values(&["1", "2", "3"])
+            "Rejects glyphs introduced after this revision of the language")
+        (@arg silent_assign: --("silent-assign") +global
+            "Makes ◈ write memory without giving a value, so it cannot be used inside a larger expression")
+        (@arg int_width: --("int-width") +global +takes_value possible_values(&["8", "16", "32", "64", "128"])
+            "Sets the width of integers in bits")
+        (@subcommand lint =>
+            (about: "Reports likely mistakes such as boxes that never run and writes that are never read")
+            (@arg file: +required "Sets the input file to use")
+            (@arg allow: --allow +takes_value +multiple number_of_values(1) possible_values(&rules)
+                "Turns off a rule")
+            (@arg warn: --warn +takes_value +multiple number_of_values(1) possible_values(&rules)
+                "Reports a rule as a warning, which is the default")
+            (@arg deny: --deny +takes_value +multiple number_of_values(1) possible_values(&rules)
+                "Reports a rule as an error, so the command exits with status 1"))
+        (@subcommand canon =>
+            (about: "Prints the canonical form of a program and its hash")
+            (@arg file: +required "Sets the input file to use")
+            (@arg literals: --literals +takes_value possible_values(&["binary", "decimal", "hex", "shortest"])
+                "Sets how numbers are written, converting between encodings")
+            (@arg fold: --fold "Replaces constant expressions and parenthesized groups with their value"))
+        (@subcommand fmt =>
+            (about: "Redraws the boxes of a program and trims its padding, keeping how numbers are written")
+            (@arg file: +required "Sets the input file to use")
+            (@arg output: -o --output +takes_value conflicts_with("check") "Writes the result to a file")
+            (@arg check: --check "Exits with status 1 instead of printing if the file is not formatted")
+            (@arg literals: --literals +takes_value possible_values(&["binary", "decimal", "hex", "shortest"])
+                "Converts every number to this encoding instead of keeping how it was written"))
+        (@subcommand encode =>
+            (about: "Prints a program that prints the given text")
+            (@arg text: +required "Sets the text to print")
+            (@arg output: -o --output +takes_value "Writes the result to a file"))
+        (@subcommand transpile =>
+            (about: "Converts between box glyphs and the ASCII syntax")
+            (@arg file: +required "Sets the input file to use")
+            (@arg to: --to +takes_value possible_values(&["ascii", "glyphs"])
+                "Sets which syntax to write, by default the one the file is not in")
+            (@arg output: -o --output +takes_value "Writes the result to a file"))
+        (@subcommand compile =>
+            (about: "Translates a program into a standalone Rust program that builds into a native executable")
+            (@arg file: +required "Sets the input file to use")
+            (@arg target: --target +takes_value possible_values(&["rust"]) "Sets the language to write, which is rust by default")
+            (@arg sequential: --sequential
+                "Runs boxes that sit side by side one after another, which is needed to compile them")
+            (@arg output: -o --output +takes_value "Writes the result to a file"))
+        (@subcommand fix =>
+            (about: "Replaces characters that look like glyphs with the glyphs they were meant to be")
+            (@arg file: +required "Sets the input file to use")
+            (@arg output: -o --output +takes_value "Writes the result to a file"))
+        (@subcommand doc =>
+            (about: "Prints documentation for a program from the comment boxes above its boxes")
+            (@arg file: +required "Sets the input file to use")
+            (@arg format: --format +takes_value possible_values(&["markdown", "html"])
+                "Sets whether Markdown or an HTML page is printed"))
+        (@subcommand xref =>
+            (about: "Lists which boxes read and write each memory cell")
+            (@arg file: +required "Sets the input file to use")
+            (@arg format: --format +takes_value possible_values(&["text", "json"])
+                "Sets whether a table or JSON is printed"))
+        (@subcommand graph =>
+            (about: "Prints a Graphviz graph of how boxes nest or which functions they call")
+            (@arg file: +required "Sets the input file to use")
+            (@arg kind: --kind +takes_value possible_values(&["nesting", "calls"])
+                "Sets whether nesting or calls are graphed"))
+        (@subcommand debug =>
+            (about: "Runs a program under the debugger, reading commands from the terminal")
+            (@arg file: +required "Sets the input file to use")
+            (@arg script: --script +takes_value "Reads debugger commands from a file")
+            (@arg mi: --mi conflicts_with("script") "Reads and writes JSON lines on stdin and stdout"))
+        (@subcommand judge =>
+            (about: "Runs a program against test cases and prints a verdict for each as JSON")
+            (@arg file: +required "Sets the input file to use")
+            (@arg tests: --tests +takes_value +required "Reads NAME.in and NAME.out pairs from this directory")
+            (@arg limits: --limits +takes_value "Reads time_ms, memory_cells and iterations limits from this file"))
+        (@subcommand diff =>
+            (about: "Compares two programs box by box")
+            (@arg old: +required "Sets the original file")
+            (@arg new: +required "Sets the changed file"))
+        (@subcommand merge =>
+            (about: "Merges two versions of a program box by box")
+            (@arg base: +required "Sets the common ancestor")
+            (@arg ours: +required "Sets our version")
+            (@arg theirs: +required "Sets their version")
+            (@arg output: -o --output +takes_value "Writes the result to a file"))
+        (@subcommand add =>
+            (about: "Fetches a library from a registry into the include path and prints its include headers")
+            (@arg name: +required "Sets the library to fetch")
+            (@arg registry: --registry +takes_value env("BOXSCRIPT_REGISTRY")
+                "Sets the registry, a directory or a git URL")
+            (@arg dir: --dir +takes_value "Sets the include path, which is the current directory by default"))
+        (@subcommand publish =>
+            (about: "Pushes the library described by boxscript.json to a registry")
+            (@arg registry: --registry +takes_value env("BOXSCRIPT_REGISTRY")
+                "Sets the registry, a directory or a git URL")
+            (@arg dir: --dir +takes_value "Sets the library directory, which is the current directory by default"))
     );
 
     let matches = app.get_matches();
 
-    let file = matches.value_of("file");
+    let render = Render::from_name(matches.value_of("render").unwrap_or("auto")).unwrap();
+    let width = matches.value_of("int_width").unwrap_or("64");
+    let arith_mode =
+        ArithMode::from_name(matches.value_of("overflow").unwrap_or("checked")).unwrap();
 
-    if let Some(filename) = file {
-        let content = fs::read_to_string(filename);
+    if matches.is_present("self_test") {
+        let outcomes = selftest::run();
+        for outcome in &outcomes {
+            println!("{}", outcome.describe());
+        }
 
-        if content.is_err() {
-            use ansi_term::Colour::Red;
-            eprintln!(
-                "{} {}: No such file or directory",
-                Red.bold().paint("error:"),
-                filename
-            );
+        let failed = outcomes.iter().filter(|outcome| !outcome.passed()).count();
+        println!("{} passed, {} failed", outcomes.len() - failed, failed);
+        if failed > 0 {
+            process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("lint") {
+        dispatch!(width, lint(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("canon") {
+        dispatch!(width, canon(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("fmt") {
+        dispatch!(width, fmt(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("encode") {
+        let program = match dispatch!(width, encode(arguments.value_of("text").unwrap())) {
+            Ok(program) => program,
+            Err(error) => fail(&error.to_string(), render),
+        };
+
+        match arguments.value_of("output") {
+            Some(output) => {
+                if fs::write(output, program).is_err() {
+                    fail(&format!("{}: Could not write file", output), render);
+                }
+            }
+            None => print!("{}", program),
         }
+
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("transpile") {
+        dispatch!(width, transpile(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("compile") {
+        dispatch!(
+            width,
+            compile(arguments, complexity(&matches, render), arith_mode, render)
+        );
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("fix") {
+        dispatch!(width, fix(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("doc") {
+        dispatch!(width, doc(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("xref") {
+        dispatch!(width, xref(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("graph") {
+        dispatch!(width, graph(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("debug") {
+        dispatch!(width, debug(arguments, arith_mode, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("judge") {
+        dispatch!(
+            width,
+            judge(arguments, complexity(&matches, render), arith_mode, render)
+        );
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("diff") {
+        dispatch!(width, diff(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("merge") {
+        dispatch!(width, merge(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("add") {
+        dispatch!(width, add(arguments, render));
+        return;
+    }
+
+    if let Some(arguments) = matches.subcommand_matches("publish") {
+        dispatch!(width, publish(arguments, render));
+        return;
     }
+
+    if let Some(expr) = matches.value_of("eval") {
+        dispatch!(width, eval(expr, &matches, arith_mode, render));
+        return;
+    }
+
+    let filename = match matches.value_of("file") {
+        Some(filename) => filename,
+        None if !io::stdin().is_terminal() => "-",
+        None => clap::Error::with_description(
+            "No input file was given; pass a file, - or --eval",
+            clap::ErrorKind::MissingRequiredArgument,
+        )
+        .exit(),
+    };
+
+    let json = matches.value_of("format") == Some("json");
+    if matches.is_present("dump_ast") {
+        dispatch!(width, dump_ast(filename, json, render));
+        return;
+    }
+
+    if matches.is_present("check") {
+        dispatch!(width, check(filename, &matches, json, render));
+        return;
+    }
+
+    dispatch!(width, run(filename, &matches, arith_mode, render));
 }
@@ -0,0 +1,64 @@
+use crate::lang::error::{json, BsError};
+use crate::lang::expression::Dialect;
+use crate::lang::interpreter::Interpreter;
+use crate::lang::output::Output;
+use js_sys::Function;
+use std::collections::VecDeque;
+use wasm_bindgen::prelude::*;
+
+struct Callback<'a> {
+    on_output: &'a Function,
+}
+
+impl<'a> Output for Callback<'a> {
+    fn write(&mut self, text: &str) -> Result<(), BsError> {
+        self.on_output
+            .call1(&JsValue::NULL, &JsValue::from_str(text))
+            .map(|_| ())
+            .map_err(|_| BsError::IoError("Output callback failed".to_string()))
+    }
+}
+
+fn interpreter(input: &str) -> Interpreter<i64> {
+    let mut interpreter = Interpreter::<i64>::new();
+    interpreter.set_dialect(Dialect::LATEST);
+    interpreter.set_input(Box::new(input.chars().collect::<VecDeque<char>>()));
+    interpreter
+}
+
+fn error(error: BsError) -> JsValue {
+    JsValue::from_str(&json(&[error]))
+}
+
+#[wasm_bindgen]
+pub fn parse(source: &str) -> String {
+    json(&interpreter("").check(source))
+}
+
+#[wasm_bindgen]
+pub fn run(source: &str, input: &str) -> Result<String, JsValue> {
+    interpreter(input).run_program(source).map_err(error)
+}
+
+#[wasm_bindgen(js_name = runStreamed)]
+pub fn run_streamed(source: &str, input: &str, on_output: &Function) -> Result<(), JsValue> {
+    interpreter(input)
+        .stream_program(source, &mut Callback { on_output }, &mut |_| Ok(()))
+        .map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses() {
+        assert_eq!(parse("┌──┐\n│▬▀│\n└──┘"), "{\"errors\":[]}");
+        assert!(parse("┌──┐\n│▬▀").contains("\"kind\""));
+    }
+
+    #[test]
+    fn it_runs() {
+        assert_eq!(run("┌───┐\n│▬◉7│\n└───┘", ""), Ok("7".to_string()));
+    }
+}
@@ -0,0 +1,108 @@
+use boxscript::lang::block::{Block, Hook};
+use boxscript::lang::complexity::Complexity;
+use boxscript::lang::debugger::{Command, Event};
+use boxscript::lang::expression::{Atom, Dialect, Molecule};
+use boxscript::lang::input::InputSource;
+use boxscript::lang::memory::{Memory, SharedMemory};
+use boxscript::lang::output::Output;
+use boxscript::lang::r#box::{Box, Genus};
+use boxscript::lang::session::Session;
+use boxscript::{ArithMode, Backend, BsError, Interpreter, StateSnapshot};
+use std::path::PathBuf;
+use std::time::Duration;
+
+type Runs<T> = fn(&mut Interpreter<T>, &str) -> Result<String, BsError>;
+type Streams<T> =
+    fn(&mut Interpreter<T>, &str, &mut dyn Output, &mut Hook<T>) -> Result<(), BsError>;
+type Frames<T> = fn(&mut Interpreter<T>, &str) -> Result<Vec<String>, BsError>;
+type Evals<T> = fn(&mut Interpreter<T>, &str) -> Result<(T, String), BsError>;
+type Builds<T> = fn(&str) -> Result<Vec<Block<T>>, BsError>;
+type Reads<T> = fn(&str, [usize; 2]) -> Result<Molecule<T>, BsError>;
+type Loads<T> = fn(&mut Session<T>, &str) -> Result<usize, BsError>;
+type SessionRuns<T> = fn(&mut Session<T>, usize, &mut dyn InputSource) -> Result<String, BsError>;
+
+#[test]
+fn it_keeps_interpreter_signatures() {
+    let _: fn() -> Interpreter<i64> = Interpreter::new;
+    let _: Runs<i64> = Interpreter::run_program;
+    let _: Streams<i64> = Interpreter::stream_program;
+    let _: Frames<i64> = Interpreter::run_frames;
+    let _: Evals<i64> = Interpreter::eval;
+    let _: fn(&Interpreter<i64>) -> &dyn Memory<i64> = Interpreter::memory;
+    let _: fn(&mut Interpreter<i64>, std::boxed::Box<dyn Memory<i64>>) = Interpreter::set_memory;
+    let _: fn(&mut Interpreter<i64>, Option<usize>) = Interpreter::set_max_iterations;
+    let _: fn(&mut Interpreter<i64>, Option<usize>) = Interpreter::set_max_steps;
+    let _: fn(&mut Interpreter<i64>, Option<Duration>) = Interpreter::set_timeout;
+    let _: fn(&mut Interpreter<i64>, bool) = Interpreter::set_sequential;
+    let _: fn(&mut Interpreter<i64>, ArithMode) = Interpreter::set_arith_mode;
+    let _: fn(&mut Interpreter<i64>, Backend) = Interpreter::set_backend;
+    let _: fn(&mut Interpreter<i64>, Complexity) = Interpreter::set_complexity;
+    let _: fn(&mut Interpreter<i64>, PathBuf) = Interpreter::set_include_dir;
+    let _: fn(&mut Interpreter<i64>, &StateSnapshot<i64>) = Interpreter::restore;
+}
+
+#[test]
+fn it_keeps_session_and_structure_signatures() {
+    let _: fn() -> Session<i64> = Session::new;
+    let _: Loads<i64> = Session::load;
+    let _: SessionRuns<i64> = Session::run;
+    let _: Builds<i64> = Block::build;
+    let _: Reads<i64> = Molecule::read;
+    let _: fn(&str) -> Result<Vec<Box>, BsError> = Box::find_all;
+    let _: fn(&BsError) -> &str = BsError::message;
+    let _: fn(&BsError) -> &'static str = BsError::kind;
+    let _: fn(&BsError) -> Option<[usize; 2]> = BsError::location;
+    let _: fn(&Atom<i64>) -> Option<char> = Atom::glyph;
+    let _: fn(&Event<i64>) -> String = Event::describe;
+    let _: fn() -> SharedMemory<i64> = SharedMemory::new;
+    let _: fn(&mut SharedMemory<i64>, i64, i64, i64) -> bool = Memory::compare_and_assign;
+}
+
+#[test]
+fn it_calls_host_functions() {
+    let mut interpreter = Interpreter::<i64>::new();
+    interpreter.set_dialect(Dialect::V3);
+    interpreter.register_builtin(9, |context| {
+        let argument = context.argument().unwrap();
+        context.memory.set(argument, 42);
+        Ok(argument * 2)
+    });
+    interpreter.register_builtin(-3, |_| Ok(7));
+
+    assert_eq!(
+        interpreter.run_program("┌─────────┐\n│▬▀▀▄▄▀◵▀▀│\n│▬▄▀▀◵▀   │\n└─────────┘"),
+        Ok("27".to_string())
+    );
+    assert_eq!(interpreter.memory().get(1), 42);
+    assert_eq!(interpreter.eval("▀▀▄▄▀◵▀▀▀"), Ok((6, String::new())));
+
+    let error = interpreter
+        .run_program("┌─────┐\n│▀▀◵▀▀│\n└─────┘")
+        .unwrap_err();
+    assert_eq!(error.message(), "No function numbered 1");
+}
+
+#[test]
+fn it_reads_errors_through_accessors() {
+    let error = Interpreter::<i64>::new().run_program("┌─").unwrap_err();
+
+    assert_eq!(error.kind(), "parse");
+    assert_eq!(error.message(), "Unclosed box");
+    assert_eq!(error.location(), Some([0, 0]));
+}
+
+#[test]
+fn it_matches_growing_enums_with_a_fallback() {
+    let describe = |genus: Genus| match genus {
+        Genus::Execution => "runs once",
+        Genus::Loop => "repeats",
+        _ => "other",
+    };
+    assert_eq!(describe(Genus::Condition), "other");
+
+    assert!(matches!(
+        Command::<i64>::parse("quit"),
+        Ok(Some(Command::Quit))
+    ));
+    assert!(matches!(Atom::<i64>::Data(1), Atom::Data(_)));
+}